@@ -0,0 +1,190 @@
+use burn::{
+    data::{dataloader::DataLoaderBuilder, dataset::Dataset},
+    tensor::backend::Backend,
+};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::data::{MNISTBatcher, MNISTDataset};
+use crate::model::{Architecture, Classifier, ModelConfig};
+use crate::training::{check_image_input, load_classifier};
+use crate::KernelSettings;
+
+/// Inference throughput and per-batch latency over a dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferenceBenchmark {
+    pub batch_size: usize,
+    /// Batches that were timed, after the warmup batches
+    pub batches: usize,
+    /// Samples in the timed batches
+    pub samples: usize,
+    pub samples_per_sec: f64,
+    /// Batch latency percentiles in milliseconds
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    /// Kernel autotuning and fusion the timings were taken with
+    pub kernels: KernelSettings,
+}
+
+/// Time the forward pass of every batch of `dataset` after the first `warmup_batches`
+///
+/// Warmup batches keep one-off setup, such as kernel compilation, out of the timings.
+/// Each timing covers the forward pass and reading the logits back, so backends that
+/// queue work asynchronously are measured to completion.
+pub fn benchmark_model<B: Backend, M: Classifier<B> + ?Sized>(
+    model: &M,
+    device: B::Device,
+    dataset: MNISTDataset,
+    batch_size: usize,
+    warmup_batches: usize,
+    kernels: KernelSettings,
+) -> anyhow::Result<InferenceBenchmark> {
+    let batch_size = batch_size.max(1);
+    let total_batches = dataset.len().div_ceil(batch_size);
+    if total_batches <= warmup_batches {
+        anyhow::bail!(
+            "{} samples make {} batches of {}, leaving none to time after {} warmup batches",
+            dataset.len(),
+            total_batches,
+            batch_size,
+            warmup_batches
+        );
+    }
+
+    let dataloader = DataLoaderBuilder::new(MNISTBatcher::<B>::new(device))
+        .batch_size(batch_size)
+        .build(dataset);
+
+    let mut latencies = Vec::new();
+    let mut samples = 0;
+    for (index, batch) in dataloader.iter().enumerate() {
+        let [size, _] = batch.images.dims();
+        let start = Instant::now();
+        let _ = model.forward(batch.images).into_data();
+        let elapsed = start.elapsed();
+
+        if index >= warmup_batches {
+            latencies.push(elapsed);
+            samples += size;
+        }
+    }
+
+    let total: Duration = latencies.iter().sum();
+    latencies.sort();
+    let as_ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    Ok(InferenceBenchmark {
+        batch_size,
+        batches: latencies.len(),
+        samples,
+        samples_per_sec: samples as f64 / total.as_secs_f64().max(f64::EPSILON),
+        p50_ms: as_ms(percentile(&latencies, 50.0)),
+        p90_ms: as_ms(percentile(&latencies, 90.0)),
+        p99_ms: as_ms(percentile(&latencies, 99.0)),
+        kernels,
+    })
+}
+
+/// Load the trained `arch` model and benchmark it on the test set
+pub fn benchmark_inference<B: Backend>(
+    device: B::Device,
+    arch: Architecture,
+    model_config: &ModelConfig,
+    model_path: &Path,
+    batch_size: usize,
+    warmup_batches: usize,
+    kernels: KernelSettings,
+) -> anyhow::Result<InferenceBenchmark> {
+    check_image_input(arch, model_config)?;
+    let model = load_classifier::<B>(arch, model_config, model_path, &device)?;
+    let benchmark = benchmark_model(
+        model.as_ref(),
+        device,
+        MNISTDataset::test(),
+        batch_size,
+        warmup_batches,
+        kernels,
+    )?;
+    log::info!(
+        "Benchmarked {} batches: {:.1} samples/sec",
+        benchmark.batches,
+        benchmark.samples_per_sec
+    );
+    Ok(benchmark)
+}
+
+/// Nearest-rank `p`th percentile of `sorted`, which must be in ascending order
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl fmt::Display for InferenceBenchmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  Timed: {} batches of {} ({} samples)",
+            self.batches, self.batch_size, self.samples
+        )?;
+        writeln!(f, "  Kernels: {}", self.kernels)?;
+        writeln!(f, "  Throughput: {:.1} samples/sec", self.samples_per_sec)?;
+        write!(
+            f,
+            "  Batch latency: p50 {:.2} ms, p90 {:.2} ms, p99 {:.2} ms",
+            self.p50_ms, self.p90_ms, self.p99_ms
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+
+    type TestBackend = burn_ndarray::NdArray<f32>;
+
+    #[test]
+    fn test_percentile_uses_nearest_rank() {
+        let sorted = (1..=10).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(5));
+        assert_eq!(percentile(&sorted, 90.0), Duration::from_millis(9));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(10));
+        assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_benchmark_reports_throughput_and_ordered_percentiles() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+
+        let kernels = KernelSettings::new("ndarray", false);
+
+        let benchmark =
+            benchmark_model(&model, device, MNISTDataset::test(), 32, 2, kernels).unwrap();
+
+        // 200 test samples make 7 batches, the first 2 of which are not timed
+        assert_eq!(benchmark.batches, 5);
+        assert_eq!(benchmark.samples, 200 - 2 * 32);
+        assert!(benchmark.samples_per_sec > 0.0);
+        assert!(benchmark.p50_ms <= benchmark.p90_ms);
+        assert!(benchmark.p90_ms <= benchmark.p99_ms);
+        assert!(benchmark.to_string().contains("samples/sec"));
+        assert!(benchmark
+            .to_string()
+            .contains("Kernels: autotune off, fusion off"));
+    }
+
+    #[test]
+    fn test_benchmark_needs_a_batch_after_warmup() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+
+        let kernels = KernelSettings::new("ndarray", false);
+
+        assert!(benchmark_model(&model, device, MNISTDataset::test(), 100, 2, kernels).is_err());
+    }
+}
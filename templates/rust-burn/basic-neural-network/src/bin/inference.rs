@@ -1,11 +1,20 @@
 use burn::backend::Backend;
-use burn_neural_network::{evaluate, init_logging, print_banner, Model, ModelConfig};
+use burn::tensor::{Data, Shape, Tensor};
+use burn_neural_network::{
+    benchmark_inference, check_backend, check_image_input, config, configure_kernel_compilation,
+    evaluate, format_backend_list, format_confusion_matrix, generate_model_card, init_logging,
+    load_classifier, load_conv_config, load_image, load_model_config, model_card,
+    parse_hidden_sizes, precision_summary, predict_batch, print_banner, resolve_compile,
+    score_ndjson, scoring, should_show_banner, Architecture, ConfigLayers, Evaluation,
+    InferenceBenchmark, KernelSettings, MNISTBatcher, Model, ModelConfig, ScoreSummary,
+};
 use clap::{Arg, Command};
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 fn main() -> anyhow::Result<()> {
     init_logging();
-    print_banner();
 
     let matches = Command::new("Burn Neural Network Inference")
         .version("1.0")
@@ -14,7 +23,7 @@ fn main() -> anyhow::Result<()> {
             Arg::new("model-path")
                 .long("model-path")
                 .help("Path to the trained model file")
-                .required(true)
+                .required_unless_present("list-backends")
                 .value_parser(clap::value_parser!(std::path::PathBuf)),
         )
         .arg(
@@ -24,18 +33,215 @@ fn main() -> anyhow::Result<()> {
                 .value_parser(["ndarray", "cuda", "metal", "wgpu"])
                 .default_value("ndarray"),
         )
+        .arg(
+            Arg::new("arch")
+                .long("arch")
+                .help("Model architecture: mlp or cnn (must match training)")
+                .value_parser(clap::value_parser!(Architecture))
+                .default_value("mlp"),
+        )
         .arg(
             Arg::new("hidden-size")
                 .long("hidden-size")
-                .help("Hidden layer size (must match training)")
+                .help("Hidden layer sizes, comma-separated; read from the config.json saved next to the model when omitted")
+                .value_parser(parse_hidden_sizes),
+        )
+        .arg(
+            Arg::new("input-size")
+                .long("input-size")
+                .help("Number of input features of the MLP; read from the config.json saved next to the model when omitted")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("num-classes")
+                .long("num-classes")
+                .help("Number of output classes; read from the config.json saved next to the model when omitted")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("mc-samples")
+                .long("mc-samples")
+                .help("Run N stochastic passes with dropout active to estimate uncertainty")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("dropout")
+                .long("dropout")
+                .help("Dropout rate for Monte-Carlo dropout passes [default: the training rate]")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("top-losses")
+                .long("top-losses")
+                .help("Report the N test samples with the highest loss")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("0"),
+        )
+        .arg(
+            Arg::new("model-card")
+                .long("model-card")
+                .help("Write a Markdown model card from the training summary next to the model, then exit")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("input-file")
+                .long("input-file")
+                .help("Score images from an NDJSON file (one {\"id\", \"image\"} object per line; '-' for stdin)")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("input-dir")
+                .long("input-dir")
+                .help("Classify every PNG image in a directory, printing predictions as CSV")
+                .conflicts_with("input-file")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("output-file")
+                .long("output-file")
+                .help("Write NDJSON (or CSV, with --input-dir) predictions here instead of stdout")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("batch-size")
+                .long("batch-size")
+                .help("Images per forward pass when scoring an input file or directory")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("64"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("JSON config file; overridden by BURN_NN_* environment variables and flags")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("compile")
+                .long("compile")
+                .help("Enable kernel autotuning (default for GPU backends)")
+                .action(clap::ArgAction::SetTrue)
+                .overrides_with("no-compile"),
+        )
+        .arg(
+            Arg::new("no-compile")
+                .long("no-compile")
+                .help("Disable kernel autotuning for faster startup (default for ndarray)")
+                .action(clap::ArgAction::SetTrue)
+                .overrides_with("compile"),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .long("benchmark")
+                .help("Also report samples/sec and p50/p90/p99 batch latency over the test set")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("warmup-batches")
+                .long("warmup-batches")
+                .help("Batches run before --benchmark starts timing")
                 .value_parser(clap::value_parser!(usize))
-                .default_value("128"),
+                .default_value("2"),
+        )
+        .arg(
+            Arg::new("demo")
+                .long("demo")
+                .help("Predict one synthetic image after evaluating, as a single-sample example")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-banner")
+                .long("no-banner")
+                .help("Skip the startup banner")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list-backends")
+                .long("list-backends")
+                .help("List the backends compiled into this build and exit")
+                .action(clap::ArgAction::SetTrue),
         )
         .get_matches();
 
+    // NDJSON or CSV predictions on stdout must not be mixed with the banner
+    let machine_output = (matches.contains_id("input-file") || matches.contains_id("input-dir"))
+        && !matches.contains_id("output-file");
+    if should_show_banner(
+        matches.get_flag("no-banner"),
+        machine_output,
+        io::stdout().is_terminal(),
+    ) {
+        print_banner();
+    }
+
+    if matches.get_flag("list-backends") {
+        print!("{}", format_backend_list());
+        return Ok(());
+    }
+
     let model_path = matches.get_one::<std::path::PathBuf>("model-path").unwrap();
-    let backend = matches.get_one::<String>("backend").unwrap();
-    let hidden_size = *matches.get_one::<usize>("hidden-size").unwrap();
+
+    if let Some(card_path) = matches.get_one::<std::path::PathBuf>("model-card") {
+        let summary_path = model_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(model_card::SUMMARY_FILE);
+        generate_model_card(&summary_path, card_path)?;
+        println!("📝 Model card written to {}", card_path.display());
+        return Ok(());
+    }
+    let layers = ConfigLayers::load(
+        matches.get_one::<std::path::PathBuf>("config").map(|p| p.as_path()),
+        config::ENV_PREFIX,
+    )?;
+
+    let backend: String = layers.resolve_arg(&matches, "backend")?;
+    check_backend(&backend)?;
+    let kernels = KernelSettings::new(
+        &backend,
+        resolve_compile(
+            matches.get_flag("compile"),
+            matches.get_flag("no-compile"),
+            &backend,
+        ),
+    );
+    if backend != "ndarray" {
+        configure_kernel_compilation(kernels);
+    }
+    let arch: Architecture = layers.resolve_arg(&matches, "arch")?;
+    let requested_hidden_sizes = layers.resolve_with(
+        "hidden-size",
+        matches.get_one::<Vec<usize>>("hidden-size").cloned().map(Some),
+        None,
+        |value| parse_hidden_sizes(value).map(Some),
+    )?;
+    let requested_input_size = layers.resolve_with(
+        "input-size",
+        matches.get_one::<usize>("input-size").copied().map(Some),
+        None,
+        |value| value.parse().map(Some).map_err(|e| format!("{}", e)),
+    )?;
+    let requested_num_classes = layers.resolve_with(
+        "num-classes",
+        matches.get_one::<usize>("num-classes").copied().map(Some),
+        None,
+        |value| value.parse().map(Some).map_err(|e| format!("{}", e)),
+    )?;
+    let mc_samples = matches.get_one::<usize>("mc-samples").copied();
+    let requested_dropout = layers.resolve_with(
+        "dropout",
+        matches.get_one::<f64>("dropout").copied().map(Some),
+        None,
+        |value| value.parse().map(Some).map_err(|e| format!("{}", e)),
+    )?;
+    let input_file = matches.get_one::<std::path::PathBuf>("input-file");
+    let input_dir = matches.get_one::<std::path::PathBuf>("input-dir");
+    let output_file = matches.get_one::<std::path::PathBuf>("output-file");
+    let batch_size: usize = layers.resolve_arg(&matches, "batch-size")?;
+    let top_losses: usize = layers.resolve_arg(&matches, "top-losses")?;
+    let warmup_batches: usize = layers.resolve_arg(&matches, "warmup-batches")?;
+    let benchmark = matches
+        .get_flag("benchmark")
+        .then_some((batch_size, warmup_batches));
 
     if !model_path.exists() {
         anyhow::bail!("Model file not found: {:?}", model_path);
@@ -44,46 +250,280 @@ fn main() -> anyhow::Result<()> {
     log::info!("Running inference with:");
     log::info!("  Model path: {:?}", model_path);
     log::info!("  Backend: {}", backend);
-    log::info!("  Hidden size: {}", hidden_size);
+    log::info!("  Architecture: {}", arch);
 
+    let saved_config = load_model_config(
+        model_path,
+        requested_hidden_sizes.as_deref(),
+        requested_input_size,
+        requested_num_classes,
+    )?;
     let model_config = ModelConfig {
-        input_size: 784,
-        hidden_size,
-        num_classes: 10,
         dropout: 0.0, // No dropout during inference
+        ..saved_config.clone()
+    };
+    // Reject a bad --dropout before any model is built, not after the evaluation
+    let mc_config = mc_dropout_config(&saved_config, requested_dropout)?;
+    log::info!("  Input size: {}", model_config.input_size);
+    log::info!("  Hidden sizes: {:?}", model_config.hidden_sizes);
+    log::info!("  Classes: {}", model_config.num_classes);
+    let num_params = match arch {
+        Architecture::Mlp => model_config.num_parameters(),
+        Architecture::Cnn => {
+            load_conv_config(model_path, model_config.num_classes)?.num_parameters()
+        }
     };
+    log::info!("  Parameters: {}", num_params);
+
+    let open_output = || -> anyhow::Result<Box<dyn Write>> {
+        Ok(match output_file {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        })
+    };
+
+    if let Some(input_dir) = input_dir {
+        check_image_input(arch, &model_config)?;
+        let images = list_images(input_dir)?;
+        let writer = open_output()?;
+
+        let classified = match backend.as_str() {
+            "ndarray" => {
+                type Backend = burn_ndarray::NdArray<f32>;
+                let device = burn_ndarray::NdArrayDevice::Cpu;
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                classify_images::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    &images,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "cuda")]
+            "cuda" => {
+                type Backend = burn_cuda::Cuda<f32>;
+                let device = burn_cuda::CudaDevice::new(0);
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                classify_images::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    &images,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "metal")]
+            "metal" => {
+                type Backend = burn_metal::Metal<f32>;
+                let device = burn_metal::MetalDevice::new(0);
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                classify_images::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    &images,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "wgpu")]
+            "wgpu" => {
+                type Backend = burn_wgpu::Wgpu<f32>;
+                let device = burn_wgpu::WgpuDevice::default();
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                classify_images::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    &images,
+                    writer,
+                    batch_size,
+                )
+            }
+            _ => {
+                anyhow::bail!("Unsupported backend: {}", backend);
+            }
+        }?;
+
+        log::info!(
+            "Classified {} of {} images in {:?}",
+            classified,
+            images.len(),
+            input_dir
+        );
+        return Ok(());
+    }
+
+    if let Some(input_file) = input_file {
+        let reader: Box<dyn BufRead> = if input_file.as_os_str() == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(BufReader::new(File::open(input_file)?))
+        };
+        let writer = open_output()?;
+
+        let summary = match backend.as_str() {
+            "ndarray" => {
+                type Backend = burn_ndarray::NdArray<f32>;
+                let device = burn_ndarray::NdArrayDevice::Cpu;
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                score_file::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    reader,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "cuda")]
+            "cuda" => {
+                type Backend = burn_cuda::Cuda<f32>;
+                let device = burn_cuda::CudaDevice::new(0);
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                score_file::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    reader,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "metal")]
+            "metal" => {
+                type Backend = burn_metal::Metal<f32>;
+                let device = burn_metal::MetalDevice::new(0);
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                score_file::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    reader,
+                    writer,
+                    batch_size,
+                )
+            }
+            #[cfg(feature = "wgpu")]
+            "wgpu" => {
+                type Backend = burn_wgpu::Wgpu<f32>;
+                let device = burn_wgpu::WgpuDevice::default();
+                log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+                score_file::<Backend>(
+                    device,
+                    arch,
+                    &model_config,
+                    model_path,
+                    reader,
+                    writer,
+                    batch_size,
+                )
+            }
+            _ => {
+                anyhow::bail!("Unsupported backend: {}", backend);
+            }
+        }?;
+
+        log::info!(
+            "Scored {} images, {} lines rejected",
+            summary.scored,
+            summary.failed
+        );
+        return Ok(());
+    }
 
-    let accuracy = match backend.as_str() {
+    let (evaluation, benchmark) = match backend.as_str() {
         "ndarray" => {
             type Backend = burn_ndarray::NdArray<f32>;
             let device = burn_ndarray::NdArrayDevice::Cpu;
-            evaluate::<Backend>(device, model_config, model_path)
+            log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+            evaluate_and_benchmark::<Backend>(
+                device,
+                arch,
+                &model_config,
+                model_path,
+                top_losses,
+                benchmark,
+                kernels,
+            )
         }
         #[cfg(feature = "cuda")]
         "cuda" => {
             type Backend = burn_cuda::Cuda<f32>;
             let device = burn_cuda::CudaDevice::new(0);
-            evaluate::<Backend>(device, model_config, model_path)
+            log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+            evaluate_and_benchmark::<Backend>(
+                device,
+                arch,
+                &model_config,
+                model_path,
+                top_losses,
+                benchmark,
+                kernels,
+            )
         }
         #[cfg(feature = "metal")]
         "metal" => {
             type Backend = burn_metal::Metal<f32>;
             let device = burn_metal::MetalDevice::new(0);
-            evaluate::<Backend>(device, model_config, model_path)
+            log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+            evaluate_and_benchmark::<Backend>(
+                device,
+                arch,
+                &model_config,
+                model_path,
+                top_losses,
+                benchmark,
+                kernels,
+            )
         }
         #[cfg(feature = "wgpu")]
         "wgpu" => {
             type Backend = burn_wgpu::Wgpu<f32>;
             let device = burn_wgpu::WgpuDevice::default();
-            evaluate::<Backend>(device, model_config, model_path)
+            log::info!("{}", precision_summary::<Backend>(&backend, &device, false));
+            evaluate_and_benchmark::<Backend>(
+                device,
+                arch,
+                &model_config,
+                model_path,
+                top_losses,
+                benchmark,
+                kernels,
+            )
         }
         _ => {
             anyhow::bail!("Unsupported backend: {}", backend);
         }
     }?;
 
+    let accuracy = evaluation.accuracy;
     println!("📊 Model Evaluation Results");
     println!("  Test Accuracy: {:.2}%", accuracy * 100.0);
+    println!("🧩 Confusion matrix (rows actual, columns predicted):");
+    print!("{}", format_confusion_matrix(&evaluation.confusion_matrix));
+    println!("📐 Calibration:");
+    println!("{}", evaluation.calibration);
+    if let Some(benchmark) = &benchmark {
+        println!("⏱️  Inference benchmark:");
+        println!("{}", benchmark);
+    }
+    if !evaluation.hardest_samples.is_empty() {
+        println!("🔍 Highest-loss test samples:");
+        for (index, loss) in &evaluation.hardest_samples {
+            println!("  #{:<6} loss {:.4}", index, loss);
+        }
+    }
     
     if accuracy > 0.8 {
         println!("🎉 Excellent performance!");
@@ -93,22 +533,155 @@ fn main() -> anyhow::Result<()> {
         println!("⚠️  Consider retraining with different hyperparameters");
     }
 
-    // Demonstrate single prediction
-    demonstrate_single_prediction(&model_config, model_path, backend)?;
+    if matches.get_flag("demo") {
+        demonstrate_single_prediction(arch, &model_config, model_path, &backend)?;
+    }
+
+    if let Some(samples) = mc_samples {
+        if arch == Architecture::Mlp {
+            demonstrate_mc_dropout(&mc_config, model_path, &backend, samples)?;
+        } else {
+            log::warn!("Monte-Carlo dropout is only available for the MLP");
+        }
+    }
 
     Ok(())
 }
 
+/// Evaluate the model on the test set and, given `(batch size, warmup batches)`, benchmark it
+fn evaluate_and_benchmark<B: Backend>(
+    device: B::Device,
+    arch: Architecture,
+    model_config: &ModelConfig,
+    model_path: &Path,
+    top_losses: usize,
+    benchmark: Option<(usize, usize)>,
+    kernels: KernelSettings,
+) -> anyhow::Result<(Evaluation, Option<InferenceBenchmark>)>
+where
+    B::FloatTensorPrimitive: Send,
+{
+    let evaluation = evaluate::<B>(
+        device.clone(),
+        arch,
+        model_config.clone(),
+        model_path,
+        top_losses,
+    )?;
+    let benchmark = benchmark
+        .map(|(batch_size, warmup_batches)| {
+            benchmark_inference::<B>(
+                device,
+                arch,
+                model_config,
+                model_path,
+                batch_size,
+                warmup_batches,
+                kernels,
+            )
+        })
+        .transpose()?;
+
+    Ok((evaluation, benchmark))
+}
+
+/// Load the model and stream NDJSON predictions for every image in `reader`
+fn score_file<B: Backend>(
+    device: B::Device,
+    arch: Architecture,
+    model_config: &ModelConfig,
+    model_path: &Path,
+    reader: impl BufRead,
+    writer: impl Write,
+    batch_size: usize,
+) -> anyhow::Result<ScoreSummary> {
+    let model = load_classifier::<B>(arch, model_config, model_path, &device)?;
+    let batcher = MNISTBatcher::<B>::new(device);
+
+    score_ndjson(reader, writer, batch_size, input_size(arch, model_config), |items| {
+        Ok(scoring::predict_items(model.as_ref(), &batcher, items))
+    })
+}
+
+/// Values per input the model takes: the CNN always reads a 28x28 image, the MLP what
+/// it was trained on
+fn input_size(arch: Architecture, model_config: &ModelConfig) -> usize {
+    match arch {
+        Architecture::Mlp => model_config.input_size,
+        Architecture::Cnn => scoring::IMAGE_PIXELS,
+    }
+}
+
+/// PNG files directly inside `dir`, sorted by name
+fn list_images(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut images = std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", dir, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect::<Vec<_>>();
+    images.sort();
+    Ok(images)
+}
+
+/// Load the model and write a `file,predicted_class,confidence` CSV row for every image
+///
+/// Images that cannot be read are logged and skipped. Returns how many were classified.
+fn classify_images<B: Backend>(
+    device: B::Device,
+    arch: Architecture,
+    model_config: &ModelConfig,
+    model_path: &Path,
+    images: &[PathBuf],
+    mut writer: impl Write,
+    batch_size: usize,
+) -> anyhow::Result<usize> {
+    let model = load_classifier::<B>(arch, model_config, model_path, &device)?;
+    let mut classified = 0;
+
+    writeln!(writer, "file,predicted_class,confidence")?;
+    for batch in images.chunks(batch_size.max(1)) {
+        let mut names = Vec::new();
+        let mut inputs = Vec::new();
+        for path in batch {
+            match load_image(path) {
+                Ok(pixels) => {
+                    names.push(path.file_name().unwrap_or_default().to_string_lossy());
+                    inputs.push(pixels);
+                }
+                Err(e) => log::warn!("Skipping image: {:#}", e),
+            }
+        }
+
+        let predictions = predict_batch(model.as_ref(), &device, inputs);
+        for (name, (class, confidence)) in names.iter().zip(predictions) {
+            writeln!(writer, "{},{},{:.4}", csv_field(name), class, confidence)?;
+        }
+        classified += names.len();
+        writer.flush()?;
+    }
+
+    Ok(classified)
+}
+
+/// Quote a CSV field when it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 fn demonstrate_single_prediction(
+    arch: Architecture,
     model_config: &ModelConfig,
     model_path: &Path,
     backend: &str,
 ) -> anyhow::Result<()> {
-    use burn::{
-        record::CompactRecorder,
-        tensor::{Data, Shape, Tensor},
-    };
-
     log::info!("Demonstrating single prediction...");
 
     match backend {
@@ -117,41 +690,111 @@ fn demonstrate_single_prediction(
             let device = burn_ndarray::NdArrayDevice::Cpu;
             
             // Load model
+            let model = load_classifier::<Backend>(arch, model_config, model_path, &device)?;
+
+            let input = demo_input::<Backend>(input_size(arch, model_config), &device);
+
+            // Run inference; softmax turns the logits into a probability per class
+            let probabilities = model.probabilities(input).into_data().convert::<f32>().value;
+            let (class, confidence) = probabilities
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, f32::MIN), |best, (class, p)| if p > best.1 { (class, p) } else { best });
+
+            println!("🔮 Single Prediction Demo:");
+            println!("  Predicted class: {}", class);
+            println!("  Confidence: {:.4}", confidence);
+            println!("  Class probabilities:");
+            for (digit, probability) in probabilities.iter().enumerate() {
+                println!("    {}: {:.4}", digit, probability);
+            }
+        }
+        _ => {
+            log::warn!("Single prediction demo only implemented for ndarray backend");
+        }
+    }
+
+    Ok(())
+}
+
+fn demonstrate_mc_dropout(
+    model_config: &ModelConfig,
+    model_path: &Path,
+    backend: &str,
+    samples: usize,
+) -> anyhow::Result<()> {
+    use burn::record::CompactRecorder;
+
+    log::info!("Running Monte-Carlo dropout with {} samples...", samples);
+
+    match backend {
+        "ndarray" => {
+            type Backend = burn_ndarray::NdArray<f32>;
+            let device = burn_ndarray::NdArrayDevice::Cpu;
+
+            // Load model with dropout active at the rate in `model_config`
             let model: Model<Backend> = model_config
                 .init(&device)
                 .load_file(model_path, &CompactRecorder::new(), &device)
                 .map_err(|e| anyhow::anyhow!("Failed to load model: {}", e))?;
 
-            // Create a sample input (synthetic data)
-            let input_data = vec![0.5; 784]; // Dummy input
-            let input = Tensor::<Backend, 2>::from_data(
-                Data::new(input_data, Shape::new([1, 784])),
-                &device,
-            );
-
-            // Run inference
-            let output = model.forward(input);
-            let prediction = output.argmax(1);
-            let confidence = output.max_dim(1);
+            let input = demo_input::<Backend>(model_config.input_size, &device);
+            let prediction = model.predict_mc(input, samples);
+            let class = prediction.predicted_class();
 
-            let pred_value: i32 = prediction.into_scalar();
-            let conf_value: f32 = confidence.into_scalar();
-
-            println!("🔮 Single Prediction Demo:");
-            println!("  Predicted class: {}", pred_value);
-            println!("  Confidence: {:.4}", conf_value);
+            println!("🎲 Monte-Carlo Dropout ({} samples):", prediction.samples);
+            println!("  Predicted class: {}", class);
+            println!(
+                "  Mean probability: {:.4} ± {:.4}",
+                prediction.mean[class],
+                prediction.uncertainty()
+            );
+            for (digit, (mean, std)) in prediction
+                .mean
+                .iter()
+                .zip(&prediction.std_dev)
+                .enumerate()
+            {
+                println!("    {}: {:.4} ± {:.4}", digit, mean, std);
+            }
         }
         _ => {
-            log::warn!("Single prediction demo only implemented for ndarray backend");
+            log::warn!("Monte-Carlo dropout demo only implemented for ndarray backend");
         }
     }
 
     Ok(())
 }
 
+/// Config for Monte-Carlo dropout passes: the saved architecture with dropout at the rate
+/// it was trained with, unless `--dropout` asks for another in [0, 1)
+fn mc_dropout_config(
+    saved: &ModelConfig,
+    requested_dropout: Option<f64>,
+) -> anyhow::Result<ModelConfig> {
+    let config = ModelConfig {
+        dropout: requested_dropout.unwrap_or(saved.dropout),
+        ..saved.clone()
+    };
+    config
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid --dropout: {}", e))?;
+    Ok(config)
+}
+
+/// Synthetic sample for the demos: one row of `input_size` values at 0.5
+fn demo_input<B: Backend>(input_size: usize, device: &B::Device) -> Tensor<B, 2> {
+    Tensor::<B, 2>::from_data(
+        Data::new(vec![0.5; input_size], Shape::new([1, input_size])),
+        device,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use burn::{module::Module, record::CompactRecorder};
 
     #[test]
     fn test_cli_parsing() {
@@ -159,11 +802,33 @@ mod tests {
         let _cmd = Command::new("test");
     }
 
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("seven.png"), "seven.png");
+        assert_eq!(csv_field("a,b.png"), "\"a,b.png\"");
+        assert_eq!(csv_field("say \"hi\".png"), "\"say \"\"hi\"\".png\"");
+    }
+
+    #[test]
+    fn test_list_images_keeps_sorted_pngs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.png", "a.PNG", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+
+        let names = list_images(dir.path())
+            .unwrap()
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.PNG", "b.png"]);
+    }
+
     #[test]
     fn test_model_config_creation() {
         let config = ModelConfig {
             input_size: 784,
-            hidden_size: 128,
+            hidden_sizes: vec![128, 128],
             num_classes: 10,
             dropout: 0.0,
         };
@@ -171,4 +836,51 @@ mod tests {
         assert_eq!(config.input_size, 784);
         assert_eq!(config.dropout, 0.0);
     }
+
+    #[test]
+    fn test_mc_dropout_defaults_to_the_training_rate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let model_path = temp_dir.path().join("final_model");
+        let trained = ModelConfig {
+            dropout: 0.3,
+            ..ModelConfig::new()
+        };
+        burn_neural_network::save_model_config(&trained, temp_dir.path()).unwrap();
+        let saved = load_model_config(&model_path, None, None, None).unwrap();
+
+        assert_eq!(mc_dropout_config(&saved, None).unwrap().dropout, 0.3);
+        assert_eq!(mc_dropout_config(&saved, Some(0.1)).unwrap().dropout, 0.1);
+        assert_eq!(
+            mc_dropout_config(&saved, None).unwrap().hidden_sizes,
+            trained.hidden_sizes
+        );
+
+        // Dropout outside [0, 1) would zero every activation or scale them by a negative
+        for dropout in [1.0, 1.5, -0.1, f64::NAN] {
+            let error = mc_dropout_config(&saved, Some(dropout)).unwrap_err();
+            assert!(error.to_string().contains("--dropout"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_demos_feed_the_model_its_own_input_size() {
+        type Backend = burn_ndarray::NdArray<f32>;
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let model_path = temp_dir.path().join("final_model");
+        let config = ModelConfig {
+            input_size: 20,
+            hidden_sizes: vec![8],
+            num_classes: 3,
+            dropout: 0.2,
+        };
+        config
+            .init::<Backend>(&device)
+            .save_file(model_path.clone(), &CompactRecorder::new())
+            .unwrap();
+
+        assert_eq!(demo_input::<Backend>(20, &device).dims(), [1, 20]);
+        demonstrate_single_prediction(Architecture::Mlp, &config, &model_path, "ndarray").unwrap();
+        demonstrate_mc_dropout(&config, &model_path, "ndarray", 3).unwrap();
+    }
 }
\ No newline at end of file
@@ -1,11 +1,18 @@
 use burn::backend::{Autodiff, Backend};
-use burn_neural_network::{init_logging, print_banner, train, ModelConfig, TrainingConfig};
+use burn::tensor::backend::AutodiffBackend;
+use burn_neural_network::{
+    check_backend, config, configure_kernel_compilation, dry_run, format_backend_list, init_logging,
+    dry_run_cnn, parse_csv_columns, parse_hidden_sizes, parse_shuffle_seed, precision_summary,
+    print_banner, resolve_compile, should_show_banner, train, train_cnn, Architecture,
+    AugmentConfig, ConfigLayers, ConvModelConfig, CsvColumn, CsvConfig, DatasetSource,
+    KernelSettings, ModelConfig, Normalization, Optimizer, Scheduler, TrainingConfig,
+};
 use clap::{Arg, Command};
+use std::io::IsTerminal;
 use std::str::FromStr;
 
 fn main() -> anyhow::Result<()> {
     init_logging();
-    print_banner();
 
     let matches = Command::new("Burn Neural Network Trainer")
         .version("1.0")
@@ -38,12 +45,40 @@ fn main() -> anyhow::Result<()> {
                 .value_parser(clap::value_parser!(f64))
                 .default_value("0.001"),
         )
+        .arg(
+            Arg::new("optimizer")
+                .long("optimizer")
+                .help("Optimizer: adam, adamw, sgd or rmsprop")
+                .value_parser(clap::value_parser!(Optimizer))
+                .default_value("adam"),
+        )
+        .arg(
+            Arg::new("scheduler")
+                .long("scheduler")
+                .help("Learning rate schedule: constant, cosine, step or noam")
+                .value_parser(clap::value_parser!(Scheduler))
+                .default_value("noam"),
+        )
+        .arg(
+            Arg::new("warmup-steps")
+                .long("warmup-steps")
+                .help("Iterations of learning rate warmup for the noam scheduler")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::new("arch")
+                .long("arch")
+                .help("Model architecture: mlp or cnn")
+                .value_parser(clap::value_parser!(Architecture))
+                .default_value("mlp"),
+        )
         .arg(
             Arg::new("hidden-size")
                 .long("hidden-size")
-                .help("Hidden layer size")
-                .value_parser(clap::value_parser!(usize))
-                .default_value("128"),
+                .help("Hidden layer sizes, comma-separated (e.g. 256,128,64)")
+                .value_parser(parse_hidden_sizes)
+                .default_value("128,128"),
         )
         .arg(
             Arg::new("dropout")
@@ -52,70 +87,380 @@ fn main() -> anyhow::Result<()> {
                 .value_parser(clap::value_parser!(f64))
                 .default_value("0.5"),
         )
+        .arg(
+            Arg::new("dataset")
+                .long("dataset")
+                .help("Training data: the synthetic MNIST set, or a CSV file given by --csv-path")
+                .value_parser(["mnist", "csv"])
+                .default_value("mnist"),
+        )
+        .arg(
+            Arg::new("csv-path")
+                .long("csv-path")
+                .help("CSV file of numeric features and an integer class label per row")
+                .value_parser(clap::value_parser!(std::path::PathBuf))
+                .required_if_eq("dataset", "csv"),
+        )
+        .arg(
+            Arg::new("csv-features")
+                .long("csv-features")
+                .help("Feature columns by name or 0-based index (default: all but the label)")
+                .value_parser(parse_csv_columns),
+        )
+        .arg(
+            Arg::new("csv-label")
+                .long("csv-label")
+                .help("Label column by name or 0-based index (default: the last column)")
+                .value_parser(clap::value_parser!(CsvColumn)),
+        )
+        .arg(
+            Arg::new("csv-normalize")
+                .long("csv-normalize")
+                .help("Feature scaling: none, minmax or zscore")
+                .value_parser(clap::value_parser!(Normalization))
+                .default_value("zscore"),
+        )
+        .arg(
+            Arg::new("csv-test-fraction")
+                .long("csv-test-fraction")
+                .help("Share of the CSV rows held out for validation")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.2"),
+        )
+        .arg(
+            Arg::new("input-size")
+                .long("input-size")
+                .help("Number of input features of the MLP (784 for MNIST)")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("784"),
+        )
+        .arg(
+            Arg::new("num-classes")
+                .long("num-classes")
+                .help("Number of output classes")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Validate config and run a single forward/backward pass without training")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output-dir")
+                .long("output-dir")
+                .help("Directory for checkpoints and the final model")
+                .value_parser(clap::value_parser!(std::path::PathBuf))
+                .default_value("./burn-models"),
+        )
+        .arg(
+            Arg::new("shuffle-seed")
+                .long("shuffle-seed")
+                .help("Seed for shuffling training batches, or 'none' to keep dataset order")
+                .value_parser(parse_shuffle_seed)
+                .default_value("1234"),
+        )
+        .arg(
+            Arg::new("augment")
+                .long("augment")
+                .help("Randomly rotate, shift and add noise to training images")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("keep-last-n")
+                .long("keep-last-n")
+                .visible_alias("keep-checkpoints")
+                .help("Keep only the N most recent epoch checkpoints plus the best (0 keeps all)")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("log-layer-stats-every")
+                .long("log-layer-stats-every")
+                .help("Log each MLP layer's weight norm and mean activation every N steps")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .help("Show a within-epoch progress bar with ETA (disabled when stdout is not a TTY)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("export-onnx")
+                .long("export-onnx")
+                .help("Also write the trained model to <output-dir>/model.onnx")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("JSON config file; overridden by BURN_NN_* environment variables and flags")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("compile")
+                .long("compile")
+                .help("Enable kernel autotuning (default for GPU backends)")
+                .action(clap::ArgAction::SetTrue)
+                .overrides_with("no-compile"),
+        )
+        .arg(
+            Arg::new("no-compile")
+                .long("no-compile")
+                .help("Disable kernel autotuning for faster startup (default for ndarray)")
+                .action(clap::ArgAction::SetTrue)
+                .overrides_with("compile"),
+        )
+        .arg(
+            Arg::new("no-banner")
+                .long("no-banner")
+                .help("Skip the startup banner")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("list-backends")
+                .long("list-backends")
+                .help("List the backends compiled into this build and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
         .get_matches();
 
-    let backend = matches.get_one::<String>("backend").unwrap();
-    let epochs = *matches.get_one::<usize>("epochs").unwrap();
-    let batch_size = *matches.get_one::<usize>("batch-size").unwrap();
-    let learning_rate = *matches.get_one::<f64>("learning-rate").unwrap();
-    let hidden_size = *matches.get_one::<usize>("hidden-size").unwrap();
-    let dropout = *matches.get_one::<f64>("dropout").unwrap();
+    if should_show_banner(matches.get_flag("no-banner"), false, std::io::stdout().is_terminal()) {
+        print_banner();
+    }
+
+    if matches.get_flag("list-backends") {
+        print!("{}", format_backend_list());
+        return Ok(());
+    }
+
+    let layers = ConfigLayers::load(
+        matches.get_one::<std::path::PathBuf>("config").map(|p| p.as_path()),
+        config::ENV_PREFIX,
+    )?;
+
+    let backend: String = layers.resolve_arg(&matches, "backend")?;
+    check_backend(&backend)?;
+    let kernels = KernelSettings::new(
+        &backend,
+        resolve_compile(
+            matches.get_flag("compile"),
+            matches.get_flag("no-compile"),
+            &backend,
+        ),
+    );
+    if backend != "ndarray" {
+        configure_kernel_compilation(kernels);
+    }
+    let epochs: usize = layers.resolve_arg(&matches, "epochs")?;
+    let batch_size: usize = layers.resolve_arg(&matches, "batch-size")?;
+    let learning_rate: f64 = layers.resolve_arg(&matches, "learning-rate")?;
+    let optimizer: Optimizer = layers.resolve_arg(&matches, "optimizer")?;
+    let scheduler: Scheduler = layers.resolve_arg(&matches, "scheduler")?;
+    let warmup_steps: usize = layers.resolve_arg(&matches, "warmup-steps")?;
+    let arch: Architecture = layers.resolve_arg(&matches, "arch")?;
+    let hidden_sizes: Vec<usize> =
+        layers.resolve_arg_with(&matches, "hidden-size", parse_hidden_sizes)?;
+    let dropout: f64 = layers.resolve_arg(&matches, "dropout")?;
+    let input_size: usize = layers.resolve_arg(&matches, "input-size")?;
+    let num_classes: usize = layers.resolve_arg(&matches, "num-classes")?;
+    let dataset_name: String = layers.resolve_arg(&matches, "dataset")?;
+    let dataset = match dataset_name.as_str() {
+        "mnist" => DatasetSource::Mnist,
+        "csv" => {
+            let path = matches
+                .get_one::<std::path::PathBuf>("csv-path")
+                .ok_or_else(|| anyhow::anyhow!("--dataset csv needs --csv-path"))?;
+            DatasetSource::Csv(CsvConfig {
+                feature_columns: matches
+                    .get_one::<Vec<CsvColumn>>("csv-features")
+                    .cloned()
+                    .unwrap_or_default(),
+                label_column: matches.get_one::<CsvColumn>("csv-label").cloned(),
+                normalization: layers.resolve_arg(&matches, "csv-normalize")?,
+                test_fraction: layers.resolve_arg(&matches, "csv-test-fraction")?,
+                ..CsvConfig::new(path)
+            })
+        }
+        other => anyhow::bail!("Unknown dataset '{}' (expected mnist or csv)", other),
+    };
+    if dataset != DatasetSource::Mnist && arch == Architecture::Cnn {
+        anyhow::bail!("The CNN reads 28x28 images; train CSV datasets with --arch mlp");
+    }
+    let dry_run = matches.get_flag("dry-run");
+    let output_dir: std::path::PathBuf = layers.resolve_arg(&matches, "output-dir")?;
+    let progress = matches.get_flag("progress") && std::io::stdout().is_terminal();
+    let keep_last_n: usize = layers.resolve_arg(&matches, "keep-last-n")?;
+    let export_onnx = matches.get_flag("export-onnx");
+    let log_layer_stats_every = layers.resolve_with(
+        "log-layer-stats-every",
+        matches.get_one::<usize>("log-layer-stats-every").copied().map(Some),
+        None,
+        |value| value.parse().map(Some).map_err(|e| format!("{}", e)),
+    )?;
+    let shuffle_seed: Option<u64> =
+        layers.resolve_arg_with(&matches, "shuffle-seed", parse_shuffle_seed)?;
+    let augment = if matches.get_flag("augment") {
+        AugmentConfig::standard(shuffle_seed.unwrap_or_default())
+    } else {
+        AugmentConfig::default()
+    };
 
     log::info!("Training configuration:");
     log::info!("  Backend: {}", backend);
+    log::info!("  Kernels: {}", kernels);
     log::info!("  Epochs: {}", epochs);
     log::info!("  Batch size: {}", batch_size);
     log::info!("  Learning rate: {}", learning_rate);
-    log::info!("  Hidden size: {}", hidden_size);
+    log::info!("  Optimizer: {}", optimizer);
+    log::info!("  Scheduler: {}", scheduler);
+    if scheduler == Scheduler::Noam {
+        log::info!("  Warmup steps: {}", warmup_steps);
+    }
+    log::info!("  Architecture: {}", arch);
+    log::info!("  Hidden sizes: {:?}", hidden_sizes);
     log::info!("  Dropout: {}", dropout);
+    log::info!("  Dataset: {}", dataset);
+    log::info!("  Input size: {}", input_size);
+    log::info!("  Classes: {}", num_classes);
+    log::info!("  Dry run: {}", dry_run);
+    log::info!("  Output dir: {:?}", output_dir);
+    log::info!("  Progress bar: {}", progress);
+    log::info!("  Shuffle seed: {:?}", shuffle_seed);
+    log::info!("  Augmentation: {}", !augment.is_identity());
+    log::info!("  Checkpoints kept: {}", keep_last_n);
+    log::info!("  Export ONNX: {}", export_onnx);
+    if let Some(every) = log_layer_stats_every {
+        log::info!("  Layer stats every: {} steps", every);
+    }
 
     let training_config = TrainingConfig {
         epochs,
         batch_size,
         learning_rate,
         weight_decay: 1e-4,
+        optimizer,
+        scheduler,
+        warmup_steps,
         early_stopping_patience: 5,
         save_every: 5,
+        output_dir: output_dir.clone(),
+        progress,
+        shuffle_seed,
+        keep_last_n,
+        export_onnx,
+        augment,
+        dataset,
+        log_layer_stats_every,
     };
 
     let model_config = ModelConfig {
-        input_size: 784,
-        hidden_size,
-        num_classes: 10,
+        input_size,
+        hidden_sizes,
+        num_classes,
+        dropout,
+    };
+    let conv_config = ConvModelConfig {
         dropout,
+        num_classes,
+        ..ConvModelConfig::new()
+    };
+    let num_params = match arch {
+        Architecture::Mlp => model_config.num_parameters(),
+        Architecture::Cnn => conv_config.num_parameters(),
+    };
+    log::info!("  Parameters: {}", num_params);
+    let model = ArchConfig {
+        arch,
+        mlp: model_config,
+        cnn: conv_config,
     };
 
     match backend.as_str() {
         "ndarray" => {
             type Backend = Autodiff<burn_ndarray::NdArray<f32>>;
             let device = burn_ndarray::NdArrayDevice::Cpu;
-            train::<Backend>(device, training_config, model_config)
+            run::<Backend>(&backend, device, training_config, model, dry_run)
         }
         #[cfg(feature = "cuda")]
         "cuda" => {
             type Backend = Autodiff<burn_cuda::Cuda<f32>>;
             let device = burn_cuda::CudaDevice::new(0);
-            train::<Backend>(device, training_config, model_config)
+            run::<Backend>(&backend, device, training_config, model, dry_run)
         }
         #[cfg(feature = "metal")]
         "metal" => {
             type Backend = Autodiff<burn_metal::Metal<f32>>;
             let device = burn_metal::MetalDevice::new(0);
-            train::<Backend>(device, training_config, model_config)
+            run::<Backend>(&backend, device, training_config, model, dry_run)
         }
         #[cfg(feature = "wgpu")]
         "wgpu" => {
             type Backend = Autodiff<burn_wgpu::Wgpu<f32>>;
             let device = burn_wgpu::WgpuDevice::default();
-            train::<Backend>(device, training_config, model_config)
+            run::<Backend>(&backend, device, training_config, model, dry_run)
         }
         _ => {
             anyhow::bail!("Unsupported backend: {}", backend);
         }
     }?;
 
+    if dry_run {
+        println!("✅ Dry run passed! Configuration is ready for training.");
+        return Ok(());
+    }
+
     log::info!("Training completed successfully!");
-    println!("🎉 Training finished! Check './burn-models/' for saved models.");
+    println!(
+        "🎉 Training finished! Check '{}' for saved models.",
+        output_dir.display()
+    );
+    println!("🧮 Model parameters: {}", num_params);
+
+    Ok(())
+}
+
+/// The selected architecture and the configuration of each
+struct ArchConfig {
+    arch: Architecture,
+    mlp: ModelConfig,
+    cnn: ConvModelConfig,
+}
+
+/// Run either a full training or a dry run on the selected backend
+fn run<B: AutodiffBackend>(
+    backend: &str,
+    device: B::Device,
+    training_config: TrainingConfig,
+    model: ArchConfig,
+    dry_run_only: bool,
+) -> anyhow::Result<()>
+where
+    B::FloatTensorPrimitive: Send,
+    B::Device: Clone,
+    B::InnerBackend: Send,
+{
+    log::info!("{}", precision_summary::<B>(backend, &device, true));
+
+    if !dry_run_only {
+        return match model.arch {
+            Architecture::Mlp => train::<B>(device, training_config, model.mlp),
+            Architecture::Cnn => train_cnn::<B>(device, training_config, model.cnn),
+        };
+    }
+
+    let report = match model.arch {
+        Architecture::Mlp => dry_run::<B>(device, &training_config, &model.mlp)?,
+        Architecture::Cnn => dry_run_cnn::<B>(device, &training_config, &model.cnn)?,
+    };
+
+    println!("🧪 Dry Run Summary:");
+    println!("  Parameters: {}", report.num_params);
+    println!("  Batch size: {}", report.batch_size);
+    println!("  Output shape: {:?}", report.output_shape);
+    println!("  Initial loss: {:.4}", report.loss);
 
     Ok(())
 }
@@ -0,0 +1,115 @@
+use std::fmt;
+
+/// Default number of equal-width confidence bins
+pub const DEFAULT_CALIBRATION_BINS: usize = 10;
+
+/// Predictions whose confidence falls in `[lower, upper)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationBin {
+    pub lower: f32,
+    pub upper: f32,
+    pub count: usize,
+    /// Fraction of predictions in the bin that were correct
+    pub accuracy: f32,
+    pub avg_confidence: f32,
+}
+
+/// Reliability diagram data and Expected Calibration Error
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationReport {
+    pub bins: Vec<CalibrationBin>,
+    /// Count-weighted mean of |accuracy - confidence| over the bins
+    pub ece: f32,
+}
+
+/// Bin predictions by confidence and compare each bin's accuracy to its mean confidence
+///
+/// `confidences[i]` is the top softmax probability of prediction `i` and `correct[i]`
+/// whether it matched the label. A confidence of exactly 1.0 goes in the last bin.
+pub fn calibration_report(
+    confidences: &[f32],
+    correct: &[bool],
+    num_bins: usize,
+) -> CalibrationReport {
+    let num_bins = num_bins.max(1);
+    let mut counts = vec![0usize; num_bins];
+    let mut hits = vec![0usize; num_bins];
+    let mut confidence_sums = vec![0.0f32; num_bins];
+
+    for (&confidence, &is_correct) in confidences.iter().zip(correct) {
+        let bin = ((confidence.clamp(0.0, 1.0) * num_bins as f32) as usize).min(num_bins - 1);
+        counts[bin] += 1;
+        hits[bin] += is_correct as usize;
+        confidence_sums[bin] += confidence;
+    }
+
+    let total = counts.iter().sum::<usize>().max(1) as f32;
+    let mut ece = 0.0;
+    let bins = (0..num_bins)
+        .map(|bin| {
+            let count = counts[bin];
+            let (accuracy, avg_confidence) = if count == 0 {
+                (0.0, 0.0)
+            } else {
+                (hits[bin] as f32 / count as f32, confidence_sums[bin] / count as f32)
+            };
+            ece += count as f32 / total * (accuracy - avg_confidence).abs();
+
+            CalibrationBin {
+                lower: bin as f32 / num_bins as f32,
+                upper: (bin + 1) as f32 / num_bins as f32,
+                count,
+                accuracy,
+                avg_confidence,
+            }
+        })
+        .collect();
+
+    CalibrationReport { bins, ece }
+}
+
+impl fmt::Display for CalibrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  Confidence    Count  Accuracy  Avg conf")?;
+        for bin in self.bins.iter().filter(|bin| bin.count > 0) {
+            writeln!(
+                f,
+                "  {:.1} - {:.1}  {:>7}  {:>8.3}  {:>8.3}",
+                bin.lower, bin.upper, bin.count, bin.accuracy, bin.avg_confidence
+            )?;
+        }
+        write!(f, "  Expected Calibration Error: {:.4}", self.ece)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perfect_calibration_has_zero_ece() {
+        // In each bin the fraction of correct predictions equals the stated confidence
+        let mut confidences = Vec::new();
+        let mut correct = Vec::new();
+        for (confidence, hits, total) in [(0.25f32, 1, 4), (0.55, 11, 20), (0.95, 19, 20)] {
+            for i in 0..total {
+                confidences.push(confidence);
+                correct.push(i < hits);
+            }
+        }
+
+        let report = calibration_report(&confidences, &correct, DEFAULT_CALIBRATION_BINS);
+        assert!(report.ece.abs() < 1e-6, "ece = {}", report.ece);
+        assert_eq!(report.bins.len(), 10);
+        assert_eq!(report.bins[9].count, 20);
+    }
+
+    #[test]
+    fn test_overconfident_predictions() {
+        let report = calibration_report(&[0.9, 0.9, 1.0, 0.9], &[false, true, false, false], 10);
+
+        assert_eq!(report.bins[9].count, 4);
+        assert!((report.bins[9].accuracy - 0.25).abs() < 1e-6);
+        assert!((report.ece - 0.675).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,185 @@
+use burn::{
+    config::Config,
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        loss::{CrossEntropyLoss, Reduction},
+        pool::{MaxPool2d, MaxPool2dConfig},
+        Dropout, DropoutConfig, Linear, LinearConfig, PaddingConfig2d, Relu,
+    },
+    tensor::{backend::Backend, Tensor},
+    train::{ClassificationOutput, TrainOutput, TrainStep, ValidStep},
+};
+
+use crate::model::{Classifier, MNISTBatch};
+
+/// Side length of the square MNIST images
+pub const IMAGE_SIZE: usize = 28;
+
+/// Convolution kernel size of both conv blocks
+const KERNEL_SIZE: usize = 3;
+
+/// Convolutional model configuration
+#[derive(Config, Debug)]
+pub struct ConvModelConfig {
+    /// Output channels of the first conv block
+    pub conv1_channels: usize,
+    /// Output channels of the second conv block
+    pub conv2_channels: usize,
+    pub num_classes: usize,
+    pub dropout: f64,
+}
+
+impl ConvModelConfig {
+    /// Initialize with default values for MNIST
+    pub fn new() -> Self {
+        Self {
+            conv1_channels: 16,
+            conv2_channels: 32,
+            num_classes: 10,
+            dropout: 0.25,
+        }
+    }
+
+    /// Returns the initialized model
+    pub fn init<B: Backend>(&self, device: &B::Device) -> ConvModel<B> {
+        let conv = |channels_in: usize, channels_out: usize| {
+            Conv2dConfig::new([channels_in, channels_out], [KERNEL_SIZE, KERNEL_SIZE])
+                .with_padding(PaddingConfig2d::Same)
+                .init(device)
+        };
+
+        ConvModel {
+            conv1: conv(1, self.conv1_channels),
+            conv2: conv(self.conv1_channels, self.conv2_channels),
+            pool: MaxPool2dConfig::new([2, 2]).with_strides([2, 2]).init(),
+            classifier: LinearConfig::new(self.classifier_inputs(), self.num_classes).init(device),
+            dropout: DropoutConfig::new(self.dropout).init(),
+            activation: Relu::new(),
+        }
+    }
+
+    /// Flattened feature count after both blocks, each halving the image side
+    fn classifier_inputs(&self) -> usize {
+        let side = IMAGE_SIZE / 4;
+        self.conv2_channels * side * side
+    }
+
+    /// Number of parameters the configured architecture will have
+    pub fn num_parameters(&self) -> usize {
+        let conv = |c_in: usize, c_out: usize| c_in * c_out * KERNEL_SIZE * KERNEL_SIZE + c_out;
+
+        conv(1, self.conv1_channels)
+            + conv(self.conv1_channels, self.conv2_channels)
+            + self.classifier_inputs() * self.num_classes
+            + self.num_classes
+    }
+
+    /// Validate the architecture parameters
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.conv1_channels == 0 || self.conv2_channels == 0 || self.num_classes == 0 {
+            anyhow::bail!(
+                "conv1_channels, conv2_channels and num_classes must be non-zero (got {}, {}, {})",
+                self.conv1_channels,
+                self.conv2_channels,
+                self.num_classes
+            );
+        }
+        if !(0.0..1.0).contains(&self.dropout) {
+            anyhow::bail!("dropout must be in [0.0, 1.0), got {}", self.dropout);
+        }
+        Ok(())
+    }
+}
+
+/// Two conv + max-pool blocks followed by a linear classifier
+#[derive(Module, Debug)]
+pub struct ConvModel<B: Backend> {
+    conv1: Conv2d<B>,
+    conv2: Conv2d<B>,
+    pool: MaxPool2d,
+    classifier: Linear<B>,
+    dropout: Dropout,
+    activation: Relu,
+}
+
+impl<B: Backend> ConvModel<B> {
+    /// Forward pass over flattened `[batch_size, 784]` images
+    pub fn forward(&self, images: Tensor<B, 2>) -> Tensor<B, 2> {
+        let [batch_size, _] = images.dims();
+        let x = images.reshape([batch_size, 1, IMAGE_SIZE, IMAGE_SIZE]);
+
+        let x = self.activation.forward(self.conv1.forward(x));
+        let x = self.pool.forward(x);
+        let x = self.activation.forward(self.conv2.forward(x));
+        let x = self.pool.forward(x);
+
+        let x: Tensor<B, 2> = x.flatten(1, 3);
+        x.apply(&self.dropout).apply(&self.classifier)
+    }
+
+    /// Number of output classes, read from the classifier
+    pub fn num_classes(&self) -> usize {
+        self.classifier.weight.val().dims()[1]
+    }
+
+    /// Forward pass with classification output for training
+    pub fn forward_classification(&self, item: MNISTBatch<B>) -> ClassificationOutput<B> {
+        let targets = item.targets;
+        let output = self.forward(item.images);
+
+        ClassificationOutput::new(output, targets)
+    }
+}
+
+impl<B: Backend> Classifier<B> for ConvModel<B> {
+    fn forward(&self, images: Tensor<B, 2>) -> Tensor<B, 2> {
+        ConvModel::forward(self, images)
+    }
+
+    fn num_classes(&self) -> usize {
+        ConvModel::num_classes(self)
+    }
+}
+
+impl<B: Backend> TrainStep<MNISTBatch<B>, ClassificationOutput<B>> for ConvModel<B> {
+    fn step(&self, batch: MNISTBatch<B>) -> TrainOutput<ClassificationOutput<B>> {
+        let item = self.forward_classification(batch);
+        let loss = CrossEntropyLoss::new(None, &Reduction::Auto)
+            .forward(item.output.clone(), item.targets.clone());
+
+        TrainOutput::new(self, loss.backward(), item)
+    }
+}
+
+impl<B: Backend> ValidStep<MNISTBatch<B>, ClassificationOutput<B>> for ConvModel<B> {
+    fn step(&self, batch: MNISTBatch<B>) -> ClassificationOutput<B> {
+        self.forward_classification(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_ndarray::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    fn test_conv_forward_shape() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let config = ConvModelConfig::new();
+        let model: ConvModel<TestBackend> = config.init(&device);
+
+        let images = Tensor::<TestBackend, 2>::random(
+            [4, IMAGE_SIZE * IMAGE_SIZE],
+            burn::tensor::Distribution::Normal(0.0, 1.0),
+            &device,
+        );
+        let output = model.forward(images);
+
+        assert_eq!(output.dims(), [4, config.num_classes]);
+        assert_eq!(model.num_classes(), config.num_classes);
+        assert_eq!(model.num_params(), config.num_parameters());
+    }
+}
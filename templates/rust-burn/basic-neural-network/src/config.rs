@@ -0,0 +1,14 @@
+/*!
+Layered configuration shared by the binaries
+
+Settings resolve as built-in defaults < config file < environment variables <
+command-line flags through the `config-layers` crate shared with the other Burn
+templates. The config file is a flat JSON object whose keys match the flag names
+(`batch-size` and `batch_size` are equivalent); environment variables use the
+upper-cased key with the `BURN_NN_` prefix, e.g. `BURN_NN_BATCH_SIZE`.
+*/
+
+pub use config_layers::ConfigLayers;
+
+/// Environment variable prefix used by the train and inference binaries
+pub const ENV_PREFIX: &str = "BURN_NN_";
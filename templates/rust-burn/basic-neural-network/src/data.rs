@@ -2,10 +2,103 @@ use burn::{
     data::{dataloader::batcher::Batcher, dataset::Dataset},
     tensor::{backend::Backend, Data, ElementConversion, Int, Shape, Tensor},
 };
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
+use crate::cnn::IMAGE_SIZE;
 use crate::model::MNISTBatch;
 
+/// Random image transforms applied by a training [`MNISTBatcher`]
+///
+/// Each image is rotated by up to `max_rotation_degrees` either way, shifted by up to
+/// `max_shift` pixels on each axis and given gaussian noise with standard deviation
+/// `noise_std`. A zero magnitude disables that transform; the default disables all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct AugmentConfig {
+    pub max_rotation_degrees: f32,
+    pub max_shift: usize,
+    pub noise_std: f32,
+    /// Seed for the transforms, so the same batches are augmented the same way every run
+    pub seed: u64,
+}
+
+impl AugmentConfig {
+    /// ±15° rotation, ±2 pixel shifts and light noise
+    pub fn standard(seed: u64) -> Self {
+        Self {
+            max_rotation_degrees: 15.0,
+            max_shift: 2,
+            noise_std: 0.05,
+            seed,
+        }
+    }
+
+    /// Whether every transform is disabled
+    pub fn is_identity(&self) -> bool {
+        self.max_rotation_degrees == 0.0 && self.max_shift == 0 && self.noise_std == 0.0
+    }
+
+    /// Return a transformed copy of a flattened 28x28 `image`
+    pub fn apply(&self, image: &[f32], rng: &mut fastrand::Rng) -> Vec<f32> {
+        let mut output = image.to_vec();
+
+        if self.max_rotation_degrees > 0.0 || self.max_shift > 0 {
+            let angle = (rng.f32() * 2.0 - 1.0) * self.max_rotation_degrees.to_radians();
+            let max_shift = self.max_shift as i64;
+            let shift_x = rng.i64(-max_shift..=max_shift) as f32;
+            let shift_y = rng.i64(-max_shift..=max_shift) as f32;
+            let (sin, cos) = angle.sin_cos();
+            let center = (IMAGE_SIZE as f32 - 1.0) / 2.0;
+
+            // Map each output pixel back to its source position and sample bilinearly
+            for row in 0..IMAGE_SIZE {
+                for col in 0..IMAGE_SIZE {
+                    let x = col as f32 - center - shift_x;
+                    let y = row as f32 - center - shift_y;
+                    let source_x = cos * x + sin * y + center;
+                    let source_y = -sin * x + cos * y + center;
+                    output[row * IMAGE_SIZE + col] = sample_bilinear(image, source_x, source_y);
+                }
+            }
+        }
+
+        if self.noise_std > 0.0 {
+            for pixel in output.iter_mut() {
+                *pixel += gaussian(rng) * self.noise_std;
+            }
+        }
+
+        output
+    }
+}
+
+/// Bilinear interpolation of a 28x28 image, treating pixels outside it as 0
+fn sample_bilinear(image: &[f32], x: f32, y: f32) -> f32 {
+    let bounds = 0.0..IMAGE_SIZE as f32;
+    let pixel = |row: f32, col: f32| {
+        if bounds.contains(&row) && bounds.contains(&col) {
+            image[row as usize * IMAGE_SIZE + col as usize]
+        } else {
+            0.0
+        }
+    };
+
+    let (x0, y0) = (x.floor(), y.floor());
+    let (dx, dy) = (x - x0, y - y0);
+    let top = pixel(y0, x0) * (1.0 - dx) + pixel(y0, x0 + 1.0) * dx;
+    let bottom = pixel(y0 + 1.0, x0) * (1.0 - dx) + pixel(y0 + 1.0, x0 + 1.0) * dx;
+    top * (1.0 - dy) + bottom * dy
+}
+
+/// Standard normal sample via the Box-Muller transform
+fn gaussian(rng: &mut fastrand::Rng) -> f32 {
+    let u1 = rng.f32().max(f32::MIN_POSITIVE);
+    let u2 = rng.f32();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
 /// MNIST dataset item
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct MNISTItem {
@@ -14,6 +107,7 @@ pub struct MNISTItem {
 }
 
 /// MNIST dataset wrapper
+#[derive(Clone)]
 pub struct MNISTDataset {
     dataset: Vec<MNISTItem>,
 }
@@ -84,6 +178,24 @@ impl MNISTDataset {
         
         Self { dataset }
     }
+
+    /// Dataset of the given items, e.g. rows read from a file
+    pub fn from_items(items: Vec<MNISTItem>) -> Self {
+        Self { dataset: items }
+    }
+
+    /// Append the items of `other` after the items of this dataset
+    pub fn concat(mut self, other: Self) -> Self {
+        self.dataset.extend(other.dataset);
+        self
+    }
+
+    /// Merge several sources, e.g. synthetic and real data, in the given order
+    pub fn from_many(datasets: Vec<Self>) -> Self {
+        datasets
+            .into_iter()
+            .fold(Self { dataset: Vec::new() }, Self::concat)
+    }
 }
 
 impl Dataset<MNISTItem> for MNISTDataset {
@@ -96,15 +208,419 @@ impl Dataset<MNISTItem> for MNISTDataset {
     }
 }
 
+/// The items of a shared dataset at a list of indices, in list order
+pub struct SubsetDataset<D> {
+    dataset: Arc<D>,
+    indices: Vec<usize>,
+}
+
+impl<D> SubsetDataset<D> {
+    pub fn new(dataset: Arc<D>, indices: Vec<usize>) -> Self {
+        Self { dataset, indices }
+    }
+
+    /// Indices into the wrapped dataset, in the order items are returned
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+impl<D, I> Dataset<I> for SubsetDataset<D>
+where
+    D: Dataset<I>,
+{
+    fn get(&self, index: usize) -> Option<I> {
+        self.dataset.get(*self.indices.get(index)?)
+    }
+
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+}
+
+/// Split `dataset` into a train and a test subset holding `ratio` and `1 - ratio` of it
+///
+/// The indices are shuffled with `seed`, so the same seed always gives the same split,
+/// and every item lands in exactly one subset. The train size is rounded to the nearest
+/// item.
+///
+/// # Panics
+///
+/// If `ratio` is not within `0.0..=1.0`.
+pub fn split<D, I>(dataset: D, ratio: f64, seed: u64) -> (SubsetDataset<D>, SubsetDataset<D>)
+where
+    D: Dataset<I>,
+{
+    assert!(
+        (0.0..=1.0).contains(&ratio),
+        "split ratio must be between 0.0 and 1.0, got {}",
+        ratio
+    );
+    let mut indices: Vec<usize> = (0..dataset.len()).collect();
+    fastrand::Rng::with_seed(seed).shuffle(&mut indices);
+    let test = indices.split_off((indices.len() as f64 * ratio).round() as usize);
+
+    let dataset = Arc::new(dataset);
+    (
+        SubsetDataset::new(dataset.clone(), indices),
+        SubsetDataset::new(dataset, test),
+    )
+}
+
+/// How [`CsvDataset`] scales each feature column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Use the values as written
+    None,
+    /// Scale each column to `[0, 1]`
+    MinMax,
+    /// Shift each column to mean 0 and scale it to standard deviation 1
+    #[default]
+    ZScore,
+}
+
+impl std::str::FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "minmax" => Ok(Self::MinMax),
+            "zscore" => Ok(Self::ZScore),
+            _ => Err(format!(
+                "unknown normalization '{}' (expected none, minmax or zscore)",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::MinMax => "minmax",
+            Self::ZScore => "zscore",
+        })
+    }
+}
+
+/// A CSV column, by 0-based position or by header name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+impl std::str::FromStr for CsvColumn {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err("column must not be empty".to_string());
+        }
+        Ok(value
+            .parse()
+            .map(Self::Index)
+            .unwrap_or_else(|_| Self::Name(value.to_string())))
+    }
+}
+
+impl std::fmt::Display for CsvColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{}", index),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Parse a comma-separated list of CSV columns, e.g. `sepal_length,2,petal_width`
+pub fn parse_csv_columns(value: &str) -> Result<Vec<CsvColumn>, String> {
+    value.split(',').map(str::parse).collect()
+}
+
+/// Where and how to read a [`CsvDataset`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvConfig {
+    pub path: PathBuf,
+    /// Feature columns in input order; empty uses every column except the label
+    pub feature_columns: Vec<CsvColumn>,
+    /// Column of integer class labels; `None` uses the last column
+    pub label_column: Option<CsvColumn>,
+    pub normalization: Normalization,
+    /// Share of the rows held out for validation and testing
+    pub test_fraction: f64,
+}
+
+impl CsvConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            feature_columns: Vec::new(),
+            label_column: None,
+            normalization: Normalization::default(),
+            test_fraction: 0.2,
+        }
+    }
+}
+
+/// Tabular dataset read from a CSV file, one [`MNISTItem`] per row
+///
+/// The first row is taken as a header when any of its fields is not a number; columns
+/// can only be selected by name when there is one. Fields are split on commas without
+/// quoting, and blank lines are skipped. Normalization statistics are computed over
+/// every row of the file.
+pub struct CsvDataset {
+    items: Vec<MNISTItem>,
+}
+
+impl CsvDataset {
+    /// Read and parse the file at `config.path`
+    pub fn load(config: &CsvConfig) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(&config.path)
+            .with_context(|| format!("Failed to read CSV dataset {:?}", config.path))?;
+        Self::parse(&text, config)
+            .with_context(|| format!("Invalid CSV dataset {:?}", config.path))
+    }
+
+    /// Parse CSV `text`, selecting and normalizing columns as set in `config`
+    pub fn parse(text: &str, config: &CsvConfig) -> anyhow::Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (index + 1, line.split(',').map(str::trim).collect::<Vec<_>>()))
+            .peekable();
+
+        let header = match lines.peek() {
+            Some((_, fields)) if fields.iter().any(|field| field.parse::<f32>().is_err()) => {
+                lines.next().map(|(_, fields)| fields)
+            }
+            Some(_) => None,
+            None => anyhow::bail!("no rows"),
+        };
+        let width = match (&header, lines.peek()) {
+            (Some(header), _) => header.len(),
+            (None, Some((_, fields))) => fields.len(),
+            (None, None) => unreachable!("a file without a header has a first row"),
+        };
+
+        let resolve = |column: &CsvColumn| -> anyhow::Result<usize> {
+            let index = match column {
+                CsvColumn::Index(index) => *index,
+                CsvColumn::Name(name) => header
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("column '{}' needs a header row", name))?
+                    .iter()
+                    .position(|field| field == name)
+                    .ok_or_else(|| anyhow::anyhow!("no column named '{}'", name))?,
+            };
+            if index >= width {
+                anyhow::bail!("column {} is out of range for {} columns", index, width);
+            }
+            Ok(index)
+        };
+        let label_index = match &config.label_column {
+            Some(column) => resolve(column)?,
+            None => width - 1,
+        };
+        let feature_indices = if config.feature_columns.is_empty() {
+            (0..width).filter(|&index| index != label_index).collect()
+        } else {
+            config
+                .feature_columns
+                .iter()
+                .map(resolve)
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        if feature_indices.is_empty() {
+            anyhow::bail!("no feature columns");
+        }
+        if feature_indices.contains(&label_index) {
+            anyhow::bail!("label column {} is also a feature column", label_index);
+        }
+
+        let mut items = Vec::new();
+        for (line, fields) in lines {
+            if fields.len() != width {
+                anyhow::bail!("line {}: expected {} fields, found {}", line, width, fields.len());
+            }
+            let image = feature_indices
+                .iter()
+                .map(|&index| {
+                    fields[index].parse::<f32>().map_err(|_| {
+                        anyhow::anyhow!("line {}: '{}' is not a number", line, fields[index])
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let label = fields[label_index].parse().map_err(|_| {
+                anyhow::anyhow!(
+                    "line {}: label '{}' is not a class index",
+                    line,
+                    fields[label_index]
+                )
+            })?;
+            items.push(MNISTItem { image, label });
+        }
+        if items.is_empty() {
+            anyhow::bail!("no data rows");
+        }
+
+        normalize(&mut items, config.normalization);
+        Ok(Self { items })
+    }
+
+    /// Number of features in each item
+    pub fn num_features(&self) -> usize {
+        self.items.first().map_or(0, |item| item.image.len())
+    }
+
+    /// Split into training and test sets, holding out `test_fraction` of the rows
+    ///
+    /// Held-out rows are spread evenly through the file rather than taken from its end,
+    /// so a file sorted by label still tests every class.
+    pub fn split(self, test_fraction: f64) -> (MNISTDataset, MNISTDataset) {
+        let test_fraction = test_fraction.clamp(0.0, 1.0);
+        let (test, train): (Vec<_>, Vec<_>) =
+            self.items.into_iter().enumerate().partition(|(index, _)| {
+                let held_out = |row: usize| (row as f64 * test_fraction).floor();
+                held_out(index + 1) > held_out(*index)
+            });
+        let items = |rows: Vec<(usize, MNISTItem)>| rows.into_iter().map(|(_, item)| item);
+        (
+            MNISTDataset::from_items(items(train).collect()),
+            MNISTDataset::from_items(items(test).collect()),
+        )
+    }
+}
+
+impl Dataset<MNISTItem> for CsvDataset {
+    fn get(&self, index: usize) -> Option<MNISTItem> {
+        self.items.get(index).cloned()
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// Scale every feature column of `items` in place
+fn normalize(items: &mut [MNISTItem], normalization: Normalization) {
+    let count = items.len() as f32;
+    for column in 0..items.first().map_or(0, |item| item.image.len()) {
+        let values = || items.iter().map(|item| item.image[column]);
+        let (offset, scale) = match normalization {
+            Normalization::None => continue,
+            Normalization::MinMax => {
+                let min = values().fold(f32::INFINITY, f32::min);
+                let max = values().fold(f32::NEG_INFINITY, f32::max);
+                (min, max - min)
+            }
+            Normalization::ZScore => {
+                let mean = values().sum::<f32>() / count;
+                let variance = values().map(|value| (value - mean).powi(2)).sum::<f32>() / count;
+                (mean, variance.sqrt())
+            }
+        };
+        // A constant column carries no information; center it and leave it at that
+        let scale = if scale > f32::EPSILON { scale } else { 1.0 };
+        for item in items.iter_mut() {
+            item.image[column] = (item.image[column] - offset) / scale;
+        }
+    }
+}
+
+/// Data the training binaries learn from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DatasetSource {
+    /// The synthetic 28x28 MNIST-like images
+    #[default]
+    Mnist,
+    /// Rows of a CSV file, split into training and test sets
+    Csv(CsvConfig),
+}
+
+impl DatasetSource {
+    /// Training and test sets of this source
+    pub fn load(&self) -> anyhow::Result<(MNISTDataset, MNISTDataset)> {
+        match self {
+            Self::Mnist => Ok((MNISTDataset::train(), MNISTDataset::test())),
+            Self::Csv(config) => {
+                if !(config.test_fraction > 0.0 && config.test_fraction < 1.0) {
+                    anyhow::bail!(
+                        "test fraction must be between 0 and 1, got {}",
+                        config.test_fraction
+                    );
+                }
+                let dataset = CsvDataset::load(config)?;
+                log::info!(
+                    "Loaded {} rows of {} features from {:?}",
+                    dataset.len(),
+                    dataset.num_features(),
+                    config.path
+                );
+                let (train, test) = dataset.split(config.test_fraction);
+                if train.is_empty() || test.is_empty() {
+                    anyhow::bail!(
+                        "{:?} has too few rows to hold out {} for testing",
+                        config.path,
+                        config.test_fraction
+                    );
+                }
+                Ok((train, test))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for DatasetSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mnist => f.write_str("mnist"),
+            Self::Csv(config) => write!(f, "csv ({})", config.path.display()),
+        }
+    }
+}
+
 /// Batcher for MNIST dataset
+///
+/// Images pass through unchanged unless augmentation is set with
+/// [`MNISTBatcher::with_augmentation`], which only the training batcher should use.
+/// Items of any feature length are batched as rows, so tabular data shares the batcher
+/// as long as it is not augmented.
 #[derive(Clone)]
 pub struct MNISTBatcher<B: Backend> {
     device: B::Device,
+    augment: AugmentConfig,
+    /// Shared by clones so successive batches keep drawing from one seeded sequence
+    rng: Arc<Mutex<fastrand::Rng>>,
 }
 
 impl<B: Backend> MNISTBatcher<B> {
     pub fn new(device: B::Device) -> Self {
-        Self { device }
+        Self {
+            device,
+            augment: AugmentConfig::default(),
+            rng: Arc::new(Mutex::new(fastrand::Rng::with_seed(0))),
+        }
+    }
+
+    /// Randomly transform every image batched from now on
+    pub fn with_augmentation(mut self, augment: AugmentConfig) -> Self {
+        self.rng = Arc::new(Mutex::new(fastrand::Rng::with_seed(augment.seed)));
+        self.augment = augment;
+        self
+    }
+
+    /// Image data of `item`, augmented when configured
+    fn image(&self, item: &MNISTItem) -> Vec<f32> {
+        if self.augment.is_identity() {
+            return item.image.clone();
+        }
+        let mut rng = self.rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.augment.apply(&item.image, &mut rng)
     }
 }
 
@@ -113,8 +629,10 @@ impl<B: Backend> Batcher<MNISTItem, MNISTBatch<B>> for MNISTBatcher<B> {
         let images = items
             .iter()
             .map(|item| {
-                let data = Data::new(item.image.clone(), Shape::new([28, 28]));
-                Tensor::<B, 2>::from_data(data, &self.device)
+                let image = self.image(item);
+                let len = image.len();
+                let data = Data::new(image, Shape::new([len]));
+                Tensor::<B, 1>::from_data(data, &self.device)
             })
             .collect::<Vec<_>>();
 
@@ -123,7 +641,7 @@ impl<B: Backend> Batcher<MNISTItem, MNISTBatch<B>> for MNISTBatcher<B> {
             .map(|item| item.label.elem::<Int>())
             .collect::<Vec<_>>();
 
-        let images = Tensor::stack(images, 0).flatten(1, 2); // [batch_size, 784]
+        let images = Tensor::stack(images, 0); // [batch_size, 784] for MNIST
         let targets = Tensor::from_ints(targets.as_slice(), &self.device);
 
         MNISTBatch { images, targets }
@@ -162,6 +680,50 @@ mod tests {
         assert_eq!(batch.targets.shape(), [2]);
     }
 
+    #[test]
+    fn test_concat_indexes_across_boundary() {
+        let train = MNISTDataset::train();
+        let test = MNISTDataset::test();
+        let expected = test.get(50).unwrap();
+
+        let combined = train.concat(test);
+        assert_eq!(combined.len(), 1200);
+
+        let item = combined.get(1050).unwrap();
+        assert_eq!(item.image, expected.image);
+        assert_eq!(item.label, expected.label);
+        assert!(combined.get(1200).is_none());
+
+        let merged = MNISTDataset::from_many(vec![MNISTDataset::test(), MNISTDataset::test()]);
+        assert_eq!(merged.len(), 400);
+        assert!(MNISTDataset::from_many(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_split_is_disjoint_and_reproducible() {
+        let items = (0..100)
+            .map(|label| MNISTItem { image: vec![0.0; 4], label })
+            .collect();
+        let dataset = MNISTDataset::from_items(items);
+
+        let (train, test) = split(dataset.clone(), 0.8, 42);
+        assert_eq!(train.len(), 80);
+        assert_eq!(test.len(), 20);
+
+        let mut labels: Vec<usize> = (0..train.len())
+            .map(|i| train.get(i).unwrap().label)
+            .chain((0..test.len()).map(|i| test.get(i).unwrap().label))
+            .collect();
+        labels.sort_unstable();
+        assert_eq!(labels, (0..100).collect::<Vec<_>>());
+        assert!(train.get(80).is_none());
+
+        let (again, _) = split(dataset.clone(), 0.8, 42);
+        assert_eq!(again.indices(), train.indices());
+        let (reseeded, _) = split(dataset, 0.8, 43);
+        assert_ne!(reseeded.indices(), train.indices());
+    }
+
     #[test]
     fn test_dataset_consistency() {
         let train_dataset = MNISTDataset::train();
@@ -176,4 +738,98 @@ mod tests {
             assert_eq!(item.image.len(), 784);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_augmentation() {
+        let image: Vec<f32> = (0..784).map(|i| (i % 28) as f32 / 28.0).collect();
+        let mut rng = fastrand::Rng::with_seed(7);
+
+        let unchanged = AugmentConfig { seed: 7, ..Default::default() };
+        assert!(unchanged.is_identity());
+        assert_eq!(unchanged.apply(&image, &mut rng), image);
+
+        let noise = AugmentConfig { noise_std: 0.1, seed: 7, ..Default::default() };
+        let noisy = noise.apply(&image, &mut rng);
+        assert_eq!(noisy.len(), image.len());
+        assert!(noisy.iter().zip(&image).any(|(a, b)| a != b));
+
+        // The same seed augments the same batch identically
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let batch = |seed| {
+            let config = AugmentConfig::standard(seed);
+            let batcher = MNISTBatcher::<TestBackend>::new(device).with_augmentation(config);
+            let item = MNISTItem { image: image.clone(), label: 3 };
+            batcher.batch(vec![item]).images.into_data().convert::<f32>().value
+        };
+        assert_eq!(batch(1), batch(1));
+        assert_ne!(batch(1), image);
+    }
+
+    #[test]
+    fn test_csv_dataset_parses_features_and_label() {
+        let csv = "width,height,class\n1,10,0\n\n3,30,1\n5,50,2\n";
+        let config = CsvConfig {
+            feature_columns: vec![CsvColumn::Name("height".into()), CsvColumn::Index(0)],
+            label_column: Some(CsvColumn::Name("class".into())),
+            normalization: Normalization::None,
+            ..CsvConfig::new("in-memory.csv")
+        };
+
+        let dataset = CsvDataset::parse(csv, &config).unwrap();
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(dataset.num_features(), 2);
+        let item = dataset.get(1).unwrap();
+        assert_eq!(item.image, vec![30.0, 3.0]);
+        assert_eq!(item.label, 1);
+
+        // Without a header the label defaults to the last column
+        let minmax = CsvConfig {
+            normalization: Normalization::MinMax,
+            ..CsvConfig::new("in-memory.csv")
+        };
+        let dataset = CsvDataset::parse("1,10,0\n3,30,1\n5,50,1\n", &minmax).unwrap();
+        let columns = (0..3).map(|i| dataset.get(i).unwrap().image).collect::<Vec<_>>();
+        assert_eq!(columns, vec![vec![0.0, 0.0], vec![0.5, 0.5], vec![1.0, 1.0]]);
+
+        let zscore = CsvDataset::parse(csv, &CsvConfig::new("in-memory.csv")).unwrap();
+        let mean = (0..3).map(|i| zscore.get(i).unwrap().image[0]).sum::<f32>() / 3.0;
+        assert!(mean.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_csv_dataset_rejects_malformed_rows() {
+        let config = CsvConfig::new("in-memory.csv");
+        assert!(CsvDataset::parse("", &config).is_err());
+        assert!(CsvDataset::parse("a,label\n", &config).is_err());
+        assert!(CsvDataset::parse("1,0\n2,x\n", &config).is_err());
+        assert!(CsvDataset::parse("1,0\n2\n", &config).is_err());
+
+        let by_name = CsvConfig {
+            label_column: Some(CsvColumn::Name("label".into())),
+            ..CsvConfig::new("in-memory.csv")
+        };
+        assert!(CsvDataset::parse("1,0\n2,1\n", &by_name).is_err());
+    }
+
+    #[test]
+    fn test_csv_split_holds_out_rows_throughout_the_file() {
+        let csv = (0..10).map(|i| format!("{},{}\n", i, i / 5)).collect::<String>();
+        let config = CsvConfig {
+            normalization: Normalization::None,
+            ..CsvConfig::new("in-memory.csv")
+        };
+
+        let (train, test) = CsvDataset::parse(&csv, &config).unwrap().split(0.2);
+        assert_eq!(train.len(), 8);
+        let held_out = (0..test.len()).map(|i| test.get(i).unwrap().label).collect::<Vec<_>>();
+        assert_eq!(held_out, vec![0, 1]);
+
+        // Tabular rows are batched at their own width
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let batch = MNISTBatcher::<TestBackend>::new(device).batch(vec![
+            train.get(0).unwrap(),
+            train.get(1).unwrap(),
+        ]);
+        assert_eq!(batch.images.shape(), [2, 1]);
+    }
+}
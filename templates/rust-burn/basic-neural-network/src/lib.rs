@@ -7,6 +7,7 @@ Burn is a modern, type-safe, and performant deep learning framework written in R
 ## Features
 
 - **Multi-layer Perceptron (MLP)**: A simple feedforward neural network
+- **Convolutional Network (CNN)**: Two conv + max-pool blocks, selected with `--arch cnn`
 - **Type Safety**: Leverages Rust's type system for compile-time guarantees
 - **Backend Agnostic**: Supports multiple compute backends (CPU, CUDA, Metal, WebGPU)
 - **Training Loop**: Complete training pipeline with metrics and early stopping
@@ -17,9 +18,16 @@ Burn is a modern, type-safe, and performant deep learning framework written in R
 
 The template consists of:
 
+- `calibration.rs`: Confidence calibration (reliability diagram and ECE)
+- `cnn.rs`: Convolutional network variant
+- `config.rs`: Environment prefix for the shared `config-layers` resolution
 - `model.rs`: Neural network architecture definition
-- `data.rs`: Dataset handling and data loading utilities
+- `model_card.rs`: Training summary and Markdown model card generation
+- `onnx.rs`: ONNX export of the trained model
+- `data.rs`: Dataset handling, CSV loading and data loading utilities
 - `training.rs`: Training loop and evaluation functions
+- `progress.rs`: Within-epoch progress bar and ETA estimation
+- `scoring.rs`: Streaming NDJSON batch scoring
 - `bin/train.rs`: Training executable
 - `bin/inference.rs`: Inference executable
 
@@ -28,13 +36,91 @@ The template consists of:
 ### Training
 ```bash
 cargo run --bin train
+
+# Keep artifacts of each experiment separate
+cargo run --bin train -- --output-dir ./experiments/run-1
+
+# Fixed batch order for deterministic debugging
+cargo run --bin train -- --shuffle-seed none
+
+# Convolutional network instead of the MLP (pass the same --arch to inference)
+cargo run --bin train -- --arch cnn
+```
+
+### Custom CSV Data
+Train the MLP on your own tabular data: each row holds numeric features and an integer
+class label, with an optional header row. Features are z-score normalized by default
+(`--csv-normalize none|minmax|zscore`) and every fifth row is held out for validation
+(`--csv-test-fraction`):
+```bash
+cargo run --bin train -- --dataset csv --csv-path iris.csv --input-size 4 --num-classes 3
+
+# Pick columns by header name or 0-based index
+cargo run --bin train -- --dataset csv --csv-path iris.csv --input-size 2 --num-classes 3 \
+    --csv-features petal_length,petal_width --csv-label species
+```
+Inference and the model card still evaluate on MNIST.
+
+For datasets built in code, [`data::split`] partitions any `Dataset` into reproducible,
+disjoint train and test subsets, e.g. `split(dataset, 0.8, 42)` for an 80/20 split.
+
+### ONNX Export
+Write the final model as `model.onnx` next to the Burn checkpoint, for serving with
+onnxruntime or other ONNX tooling:
+```bash
+cargo run --bin train -- --export-onnx
+```
+
+### Configuration
+Settings resolve as defaults < `--config` JSON file < `BURN_NN_*` environment variables < flags:
+```bash
+BURN_NN_EPOCHS=20 cargo run --bin train -- --config train.json --batch-size 64
+```
+
+### Dry Run
+```bash
+cargo run --bin train -- --dry-run
 ```
 
 ### Inference
+Training saves the architecture as `config.json` next to the model, so inference rebuilds
+it without repeating `--hidden-size`, `--input-size` or `--num-classes`. Those flags only
+matter for models saved without a config, and weights that don't match the sizes fail with
+a description of the mismatch:
 ```bash
 cargo run --bin inference -- --model-path ./burn-models/final_model
 ```
 
+### Model Card
+Training writes `training_summary.json` next to the final model; render it as Markdown with:
+```bash
+cargo run --bin inference -- --model-path ./burn-models/final_model --model-card MODEL_CARD.md
+```
+
+### Inference Benchmark
+`--benchmark` times the forward pass of every `--batch-size` batch of the test set after
+`--warmup-batches` untimed ones, and reports samples/sec and p50/p90/p99 batch latency
+alongside the accuracy:
+```bash
+cargo run --release --bin inference -- --model-path ./burn-models/final_model --benchmark
+```
+
+### Batch Scoring
+Input is newline-delimited JSON (`{"id": "img-1", "image": [784 floats]}` per line, or
+as many floats as the model's `input_size`); predictions are streamed out as NDJSON in
+the same order:
+```bash
+cargo run --bin inference -- --model-path ./burn-models/final_model \
+    --input-file images.ndjson --output-file predictions.ndjson
+```
+
+A directory of PNG images is classified with `--input-dir`. Each image is converted to
+grayscale, resized to 28x28 and scaled to `[0, 1]`, and one
+`file,predicted_class,confidence` CSV row is printed per image:
+```bash
+cargo run --bin inference -- --model-path ./burn-models/final_model --input-dir ./digits
+```
+
 ### With GPU Support
 ```bash
 # CUDA
@@ -47,6 +133,21 @@ cargo run --features metal --bin train
 cargo run --features wgpu --bin train
 ```
 
+### Kernel Compilation
+GPU backends autotune their kernels on first use, which slows the first batches but
+speeds up the rest of the run. Autotuning is on by default for cuda/metal/wgpu and off
+for ndarray; `--compile` / `--no-compile` override it per run through CubeCL's runtime
+configuration. Kernel fusion wraps the GPU backend when built with the `fusion` feature.
+`--benchmark` reports both settings next to its timings:
+```bash
+# Faster startup for short jobs
+cargo run --features wgpu --bin inference -- --backend wgpu --no-compile --model-path ...
+
+# Fused kernels, timed with and without autotuning
+cargo run --features wgpu,fusion --bin inference -- --backend wgpu --benchmark --model-path ...
+cargo run --features wgpu,fusion --bin inference -- --backend wgpu --benchmark --no-compile ...
+```
+
 ## Key Concepts
 
 ### Burn Framework Benefits
@@ -59,23 +160,31 @@ cargo run --features wgpu --bin train
 ### Model Design
 The neural network uses:
 - Input layer: 784 neurons (28x28 flattened images)
-- Hidden layers: 2 layers with 128 neurons each
+- Hidden layers: 2 layers with 128 neurons each by default; set the depth and widths with
+  `--hidden-size 256,128,64`
 - Output layer: 10 neurons (classification classes)
 - Activation: ReLU
 - Regularization: Dropout (0.5)
 
 ### Training Features
-- Adam optimizer with weight decay
-- Learning rate scheduling (Noam scheduler)
+- Adam, AdamW, SGD or RMSprop with weight decay, chosen with `--optimizer` (default: adam)
+- Learning rate scheduling chosen with `--scheduler`: constant, cosine annealing, step
+  decay or Noam (default, with `--warmup-steps` iterations of warmup)
+- Optional augmentation of training images with `--augment` (±15° rotation, ±2 pixel
+  shifts, gaussian noise; seeded by `--shuffle-seed`)
 - Early stopping based on validation loss
 - Accuracy and loss metrics tracking
-- Model checkpointing
+- Model checkpointing, bounded with `--keep-last-n`/`--keep-checkpoints` (latest N epochs,
+  3 by default, plus the best; 0 keeps every epoch)
+- Optional per-layer weight norm and mean activation logging with
+  `--log-layer-stats-every N`, for spotting dead ReLUs and exploding weights (MLP only)
 
 ## Extending the Template
 
 You can extend this template by:
 
-1. **Adding more complex architectures**: CNNs, RNNs, Transformers
+1. **Adding more complex architectures**: RNNs, Transformers (see `cnn.rs` for a second
+   architecture plugged into the same training loop)
 2. **Using real datasets**: MNIST, CIFAR-10, custom datasets
 3. **Implementing custom layers**: Attention, normalization, custom activations
 4. **Adding data augmentation**: Random transforms, noise injection
@@ -98,14 +207,40 @@ Compared to PyTorch/TensorFlow:
 - **Ecosystem**: Growing but still maturing compared to Python frameworks
 */
 
+pub mod benchmark;
+pub mod calibration;
+pub mod cnn;
+pub mod config;
 pub mod data;
 pub mod model;
+pub mod model_card;
+pub mod onnx;
+pub mod progress;
+pub mod scoring;
 pub mod training;
 
 // Re-export commonly used types
-pub use data::{MNISTBatch, MNISTBatcher, MNISTDataset, MNISTItem};
-pub use model::{Model, ModelConfig};
-pub use training::{evaluate, train, TrainingConfig};
+pub use benchmark::{benchmark_inference, benchmark_model, InferenceBenchmark};
+pub use calibration::{calibration_report, CalibrationReport};
+pub use cnn::{ConvModel, ConvModelConfig};
+pub use config::ConfigLayers;
+pub use data::{
+    parse_csv_columns, split, AugmentConfig, CsvColumn, CsvConfig, CsvDataset, DatasetSource,
+    MNISTBatch, MNISTBatcher, MNISTDataset, MNISTItem, Normalization, SubsetDataset,
+};
+pub use model::{
+    parse_hidden_sizes, Architecture, Classifier, LayerStats, LayerStatsSchedule, LossReduction,
+    McPrediction, Model, ModelConfig,
+};
+pub use model_card::{format_confusion_matrix, generate_model_card, ClassMetrics, TrainingSummary};
+pub use progress::{estimate_progress, ProgressEstimate, ProgressRenderer};
+pub use scoring::{load_image, predict_batch, score_ndjson, Prediction, ScoreRecord, ScoreSummary};
+pub use training::{
+    check_image_input, dry_run, dry_run_cnn, evaluate, evaluate_model, export_onnx,
+    load_classifier, load_conv_config, load_model_config, parse_shuffle_seed, save_conv_config,
+    save_model_config, train, train_cnn, DryRunReport, Evaluation, Optimizer, Scheduler,
+    TrainingConfig,
+};
 
 // Version and metadata
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -128,10 +263,248 @@ pub fn print_banner() {
     println!();
 }
 
+/// Whether to print the startup banner
+///
+/// The banner is for people at a terminal; it is skipped when asked to, when output is
+/// machine-readable JSON, or when stdout is piped or redirected.
+pub fn should_show_banner(no_banner: bool, json_output: bool, stdout_is_tty: bool) -> bool {
+    !no_banner && !json_output && stdout_is_tty
+}
+
+/// Whether kernel autotuning should be on for `backend` unless overridden
+///
+/// GPU backends pay a one-off tuning cost for faster steady-state kernels; ndarray has
+/// nothing to tune.
+pub fn default_compile(backend: &str) -> bool {
+    backend != "ndarray"
+}
+
+/// Combine `--compile` / `--no-compile` with the per-backend default
+pub fn resolve_compile(compile: bool, no_compile: bool, backend: &str) -> bool {
+    if compile {
+        true
+    } else if no_compile {
+        false
+    } else {
+        default_compile(backend)
+    }
+}
+
+/// Kernel settings a run uses on the cuda/metal/wgpu backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelSettings {
+    /// CubeCL autotuning at its balanced level rather than its minimal one
+    pub autotune: bool,
+    /// Operation fusion, which wraps the backend in builds with the `fusion` feature
+    pub fusion: bool,
+}
+
+impl KernelSettings {
+    /// Settings for `backend` given the resolved `--compile` / `--no-compile` choice
+    pub fn new(backend: &str, compile: bool) -> Self {
+        let gpu = backend != "ndarray";
+        Self {
+            autotune: gpu && compile,
+            fusion: gpu && cfg!(feature = "fusion"),
+        }
+    }
+}
+
+impl std::fmt::Display for KernelSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+        write!(
+            f,
+            "autotune {}, fusion {}",
+            on_off(self.autotune),
+            on_off(self.fusion)
+        )
+    }
+}
+
+/// CubeCL configuration that tunes kernels at the balanced level when `autotune` is set
+#[cfg(any(feature = "cuda", feature = "metal", feature = "wgpu"))]
+pub fn kernel_config(autotune: bool) -> cubecl::config::GlobalConfig {
+    use cubecl::config::autotune::AutotuneLevel;
+
+    let mut config = cubecl::config::GlobalConfig::default();
+    config.autotune.level = if autotune {
+        AutotuneLevel::Balanced
+    } else {
+        AutotuneLevel::Minimal
+    };
+    config
+}
+
+/// Apply `settings` to the CubeCL runtime behind the cuda/metal/wgpu backends
+///
+/// Call once, before the first device is created: CubeCL panics if its configuration is
+/// set after a runtime has read it. Minimal autotuning shortens the first batches at the
+/// cost of throughput.
+pub fn configure_kernel_compilation(settings: KernelSettings) {
+    #[cfg(any(feature = "cuda", feature = "metal", feature = "wgpu"))]
+    cubecl::config::GlobalConfig::set(kernel_config(settings.autotune));
+    log::info!("Kernels: {}", settings);
+}
+
+/// One-line summary of the backend, float element type, device and autodiff state
+pub fn format_precision_summary(
+    backend: &str,
+    element: &str,
+    device: &str,
+    autodiff: bool,
+) -> String {
+    format!(
+        "Backend: {} | precision: {} | device: {} | autodiff: {}",
+        backend,
+        element,
+        device,
+        if autodiff { "on" } else { "off" }
+    )
+}
+
+/// Precision summary for backend type `B` running on `device`
+pub fn precision_summary<B: burn::tensor::backend::Backend>(
+    backend: &str,
+    device: &B::Device,
+    autodiff: bool,
+) -> String {
+    // `type_name` yields a path such as `half::binary16::f16`; keep the last segment
+    let element = std::any::type_name::<B::FloatElem>();
+    let element = element.rsplit("::").next().unwrap_or(element);
+    format_precision_summary(backend, element, &format!("{:?}", device), autodiff)
+}
+
+/// Backends this crate knows about, paired with whether each is compiled into this build
+pub fn compiled_backends() -> Vec<(&'static str, bool)> {
+    vec![
+        ("ndarray", true),
+        ("cuda", cfg!(feature = "cuda")),
+        ("metal", cfg!(feature = "metal")),
+        ("wgpu", cfg!(feature = "wgpu")),
+    ]
+}
+
+/// Render the `--list-backends` report
+pub fn format_backend_list() -> String {
+    let mut output = String::from("Backends:\n");
+    for (name, compiled) in compiled_backends() {
+        let status = if compiled {
+            "available".to_string()
+        } else {
+            format!("not compiled (build with --features {})", name)
+        };
+        output.push_str(&format!("  {:<8} {}\n", name, status));
+    }
+    output
+}
+
+/// Check that `name` is a backend compiled into this build
+///
+/// A known backend that was left out of the build and an unknown name fail with
+/// different messages, both listing the backends that are available.
+pub fn check_backend(name: &str) -> anyhow::Result<()> {
+    let backends = compiled_backends();
+    let available: Vec<&str> = backends
+        .iter()
+        .filter(|(_, compiled)| *compiled)
+        .map(|(name, _)| *name)
+        .collect();
+
+    match backends.iter().find(|(known, _)| *known == name) {
+        Some((_, true)) => Ok(()),
+        Some((_, false)) => anyhow::bail!(
+            "{} backend requires building with --features {} (available in this build: {})",
+            name,
+            name,
+            available.join(", ")
+        ),
+        None => anyhow::bail!(
+            "Unknown backend '{}' (expected one of: {}; available in this build: {})",
+            name,
+            backends.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "),
+            available.join(", ")
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_banner_decision() {
+        assert!(should_show_banner(false, false, true));
+        assert!(!should_show_banner(false, true, true));
+        assert!(!should_show_banner(true, false, true));
+        assert!(!should_show_banner(false, false, false));
+    }
+
+    #[test]
+    fn test_backend_check_distinguishes_disabled_from_unknown() {
+        assert!(check_backend("ndarray").is_ok());
+
+        let unknown = check_backend("tpu").unwrap_err().to_string();
+        assert!(unknown.starts_with("Unknown backend 'tpu'"));
+        assert!(unknown.contains("available in this build: ndarray"));
+
+        if let Some((disabled, _)) = compiled_backends().into_iter().find(|(_, on)| !on) {
+            let message = check_backend(disabled).unwrap_err().to_string();
+            assert!(message.starts_with(&format!(
+                "{} backend requires building with --features {}",
+                disabled, disabled
+            )));
+            assert_ne!(message, unknown);
+        }
+    }
+
+    #[test]
+    fn test_precision_summary() {
+        assert_eq!(
+            format_precision_summary("ndarray", "f32", "Cpu", false),
+            "Backend: ndarray | precision: f32 | device: Cpu | autodiff: off"
+        );
+        assert_eq!(
+            precision_summary::<burn_ndarray::NdArray<f32>>(
+                "ndarray",
+                &burn_ndarray::NdArrayDevice::Cpu,
+                false
+            ),
+            "Backend: ndarray | precision: f32 | device: Cpu | autodiff: off"
+        );
+    }
+
+    #[test]
+    fn test_default_compile() {
+        assert!(!default_compile("ndarray"));
+        assert!(default_compile("cuda"));
+        assert!(default_compile("wgpu"));
+
+        assert!(resolve_compile(true, false, "ndarray"));
+        assert!(!resolve_compile(false, true, "wgpu"));
+        assert!(resolve_compile(false, false, "metal"));
+    }
+
+    #[test]
+    fn test_kernel_settings() {
+        let ndarray = KernelSettings::new("ndarray", true);
+        assert_eq!(ndarray.to_string(), "autotune off, fusion off");
+
+        let wgpu = KernelSettings::new("wgpu", false);
+        assert!(!wgpu.autotune);
+        assert_eq!(wgpu.fusion, cfg!(feature = "fusion"));
+        assert!(KernelSettings::new("wgpu", true).autotune);
+    }
+
+    #[cfg(any(feature = "cuda", feature = "metal", feature = "wgpu"))]
+    #[test]
+    fn test_kernel_config_sets_the_autotune_level() {
+        use cubecl::config::autotune::AutotuneLevel;
+
+        assert!(matches!(kernel_config(true).autotune.level, AutotuneLevel::Balanced));
+        assert!(matches!(kernel_config(false).autotune.level, AutotuneLevel::Minimal));
+    }
+
     #[test]
     fn test_version_info() {
         assert!(!VERSION.is_empty());
@@ -139,6 +512,15 @@ mod tests {
         assert!(!DESCRIPTION.is_empty());
     }
 
+    #[test]
+    fn test_backend_list() {
+        let output = format_backend_list();
+        for (name, _) in compiled_backends() {
+            assert!(output.contains(name));
+        }
+        assert!(output.contains("ndarray  available"));
+    }
+
     #[test]
     fn test_banner() {
         // Just ensure it doesn't panic
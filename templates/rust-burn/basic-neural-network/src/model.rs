@@ -1,20 +1,27 @@
 use burn::{
     config::Config,
-    module::Module,
+    module::{Ignored, Module},
     nn::{
         self,
         loss::{CrossEntropyLoss, Reduction},
         Dropout, DropoutConfig, Linear, LinearConfig, Relu,
     },
-    tensor::{backend::Backend, Tensor},
+    tensor::{
+        activation::{log_softmax, softmax},
+        backend::Backend,
+        Distribution, ElementConversion, Tensor,
+    },
     train::{ClassificationOutput, TrainOutput, TrainStep, ValidStep},
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 /// Multi-layer perceptron model configuration
 #[derive(Config, Debug)]
 pub struct ModelConfig {
     pub input_size: usize,
-    pub hidden_size: usize,
+    /// Width of each hidden layer, input side first
+    pub hidden_sizes: Vec<usize>,
     pub num_classes: usize,
     pub dropout: f64,
 }
@@ -23,50 +30,315 @@ impl ModelConfig {
     /// Returns the initialized model using the autodiff backend
     pub fn init<B: Backend>(&self, device: &B::Device) -> Model<B> {
         Model {
-            linear1: LinearConfig::new(self.input_size, self.hidden_size).init(device),
-            linear2: LinearConfig::new(self.hidden_size, self.hidden_size).init(device),
-            linear3: LinearConfig::new(self.hidden_size, self.num_classes).init(device),
+            layers: self
+                .layer_dims()
+                .into_iter()
+                .map(|(d_in, d_out)| LinearConfig::new(d_in, d_out).init(device))
+                .collect(),
             dropout: DropoutConfig::new(self.dropout).init(),
             activation: Relu::new(),
+            stats_schedule: Ignored(None),
+        }
+    }
+
+    /// `(d_input, d_output)` of every linear layer in forward order
+    pub(crate) fn layer_dims(&self) -> Vec<(usize, usize)> {
+        let sizes = std::iter::once(self.input_size)
+            .chain(self.hidden_sizes.iter().copied())
+            .chain(std::iter::once(self.num_classes))
+            .collect::<Vec<_>>();
+        sizes.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    }
+
+    /// Number of parameters the configured architecture will have
+    pub fn num_parameters(&self) -> usize {
+        self.layer_dims()
+            .into_iter()
+            .map(|(d_in, d_out)| d_in * d_out + d_out)
+            .sum()
+    }
+
+    /// Validate the architecture parameters
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.hidden_sizes.is_empty() {
+            anyhow::bail!("at least one hidden layer is required");
+        }
+        if self.input_size == 0 || self.hidden_sizes.contains(&0) || self.num_classes == 0 {
+            anyhow::bail!(
+                "input_size, hidden_sizes and num_classes must be non-zero (got {}, {:?}, {})",
+                self.input_size,
+                self.hidden_sizes,
+                self.num_classes
+            );
+        }
+        if !(0.0..1.0).contains(&self.dropout) {
+            anyhow::bail!("dropout must be in [0.0, 1.0), got {}", self.dropout);
         }
+        Ok(())
     }
 
     /// Initialize with default values for MNIST-like data
     pub fn new() -> Self {
         Self {
             input_size: 784, // 28x28 images
-            hidden_size: 128,
+            hidden_sizes: vec![128, 128],
             num_classes: 10,
             dropout: 0.5,
         }
     }
 }
 
+/// Model architectures selectable with `--arch`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Architecture {
+    /// Multi-layer perceptron over flattened pixels ([`Model`])
+    #[default]
+    Mlp,
+    /// Convolutional network over the 28x28 image ([`crate::cnn::ConvModel`])
+    Cnn,
+}
+
+impl std::str::FromStr for Architecture {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "mlp" => Ok(Self::Mlp),
+            "cnn" => Ok(Self::Cnn),
+            _ => Err(format!("unknown architecture '{}' (expected mlp or cnn)", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Mlp => "mlp",
+            Self::Cnn => "cnn",
+        })
+    }
+}
+
+/// Inference interface shared by every architecture
+///
+/// Takes flattened `[batch_size, input_size]` inputs, as produced by
+/// [`crate::MNISTBatcher`], so evaluation and scoring work with any model. That is 784 for
+/// 28x28 images, which the CNN always reads, and the feature count for an MLP trained on
+/// CSV rows.
+pub trait Classifier<B: Backend> {
+    /// Class logits, shape `[batch_size, num_classes]`
+    fn forward(&self, images: Tensor<B, 2>) -> Tensor<B, 2>;
+
+    /// Number of output classes
+    fn num_classes(&self) -> usize;
+
+    /// Class probabilities, the softmax of the logits, shape `[batch_size, num_classes]`
+    fn probabilities(&self, images: Tensor<B, 2>) -> Tensor<B, 2> {
+        softmax(self.forward(images), 1)
+    }
+
+    /// Unreduced cross-entropy loss of every sample in the batch, shape `[batch_size]`
+    fn forward_losses(&self, batch: MNISTBatch<B>) -> Tensor<B, 1> {
+        let [batch_size] = batch.targets.dims();
+        let log_probs = log_softmax(self.forward(batch.images), 1);
+
+        log_probs
+            .gather(1, batch.targets.reshape([batch_size, 1]))
+            .reshape([batch_size])
+            .neg()
+    }
+}
+
+/// Parse a comma-separated list of hidden layer sizes such as `256,128,64`
+pub fn parse_hidden_sizes(value: &str) -> Result<Vec<usize>, String> {
+    value
+        .split(',')
+        .map(|size| match size.trim().parse() {
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(format!(
+                "invalid hidden sizes '{}' (expected positive integers separated by commas)",
+                value
+            )),
+        })
+        .collect()
+}
+
 /// Multi-layer perceptron model
 #[derive(Module, Debug)]
 pub struct Model<B: Backend> {
-    linear1: Linear<B>,
-    linear2: Linear<B>,
-    linear3: Linear<B>,
+    /// Hidden layers followed by the output layer
+    layers: Vec<Linear<B>>,
     dropout: Dropout,
     activation: Relu,
+    /// When set, training steps log [`Model::layer_stats`] on this schedule
+    stats_schedule: Ignored<Option<LayerStatsSchedule>>,
+}
+
+/// Which training steps log per-layer statistics: the first and every `every`th after it
+///
+/// Clones share one step counter, so the schedule carries over as each optimizer update
+/// replaces the model.
+#[derive(Debug, Clone)]
+pub struct LayerStatsSchedule {
+    every: usize,
+    steps: Arc<AtomicUsize>,
+}
+
+impl LayerStatsSchedule {
+    pub fn new(every: usize) -> Self {
+        Self {
+            every: every.max(1),
+            steps: Arc::default(),
+        }
+    }
+
+    /// Count a training step, returning its 1-based number if statistics are due on it
+    fn tick(&self) -> Option<usize> {
+        let step = self.steps.fetch_add(1, Ordering::Relaxed);
+        (step % self.every == 0).then_some(step + 1)
+    }
+}
+
+/// Weight norm and mean activation of one linear layer for a batch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStats {
+    /// Frobenius norm of the weight matrix
+    pub weight_norm: f32,
+    /// Mean output of the layer: after the ReLU for hidden layers, the logits for the last
+    pub mean_activation: f32,
 }
 
 impl<B: Backend> Model<B> {
     /// Forward pass of the model
     pub fn forward(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
-        let x = input
-            .flatten(1, 2) // Flatten input to [batch_size, features]
-            .apply(&self.linear1)
-            .apply(&self.activation)
-            .apply(&self.dropout);
+        let (output, hidden) = self.layers.split_last().expect("model has an output layer");
+        let x = hidden.iter().fold(
+            input.flatten(1, 2), // Flatten input to [batch_size, features]
+            |x, linear| {
+                x.apply(linear)
+                    .apply(&self.activation)
+                    .apply(&self.dropout)
+            },
+        );
+
+        x.apply(output)
+    }
+
+    /// Forward pass that keeps dropout active on every backend
+    ///
+    /// Burn only applies `Dropout` when autodiff is enabled, so inference backends would
+    /// otherwise ignore it. Used for Monte-Carlo dropout uncertainty estimation.
+    pub fn forward_stochastic(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
+        let (output, hidden) = self.layers.split_last().expect("model has an output layer");
+        let x = hidden.iter().fold(input, |x, linear| {
+            self.mc_dropout(x.apply(linear).apply(&self.activation))
+        });
+
+        x.apply(output)
+    }
+
+    /// Run `samples` stochastic passes over a single input and summarize the class probabilities
+    pub fn predict_mc(&self, input: Tensor<B, 2>, samples: usize) -> McPrediction {
+        let passes = (0..samples.max(1))
+            .map(|_| {
+                let probabilities = softmax(self.forward_stochastic(input.clone()), 1);
+                probabilities.into_data().convert::<f32>().value
+            })
+            .collect::<Vec<_>>();
+
+        McPrediction::from_samples(&passes)
+    }
+
+    fn mc_dropout(&self, x: Tensor<B, 2>) -> Tensor<B, 2> {
+        let prob = self.dropout.prob;
+        if prob == 0.0 {
+            return x;
+        }
+
+        let keep = 1.0 - prob;
+        let mask = x.random_like(Distribution::Bernoulli(keep));
+        x.mul(mask).div_scalar(keep)
+    }
 
-        let x = x
-            .apply(&self.linear2)
-            .apply(&self.activation)
-            .apply(&self.dropout);
+    /// Log [`Model::layer_stats`] of the first training step and of every `every`th after it
+    pub fn with_layer_stats(mut self, every: usize) -> Self {
+        self.stats_schedule = Ignored(Some(LayerStatsSchedule::new(every)));
+        self
+    }
 
-        x.apply(&self.linear3)
+    /// Weight norm and mean activation of every linear layer on `input`, without dropout
+    ///
+    /// Dead ReLUs show up as hidden layers with a mean activation near zero, exploding
+    /// weights as a growing weight norm.
+    pub fn layer_stats(&self, input: Tensor<B, 2>) -> Vec<LayerStats> {
+        let last = self.layers.len().saturating_sub(1);
+        let mut x = input.detach();
+        self.layers
+            .iter()
+            .enumerate()
+            .map(|(i, linear)| {
+                x = x.clone().apply(linear);
+                if i < last {
+                    x = x.clone().apply(&self.activation);
+                }
+                let weight = linear.weight.val().detach();
+                LayerStats {
+                    weight_norm: weight.powf_scalar(2.0).sum().sqrt().into_scalar().elem(),
+                    mean_activation: x.clone().mean().into_scalar().elem(),
+                }
+            })
+            .collect()
+    }
+
+    fn log_layer_stats(&self, step: usize, input: Tensor<B, 2>) {
+        for (i, stats) in self.layer_stats(input).iter().enumerate() {
+            log::info!(
+                "Step {} layer {}: weight norm {:.4}, mean activation {:.4}",
+                step,
+                i + 1,
+                stats.weight_norm,
+                stats.mean_activation
+            );
+        }
+    }
+
+    /// Linear layers in forward order
+    pub(crate) fn layers(&self) -> &[Linear<B>] {
+        &self.layers
+    }
+
+    /// Total number of parameters, summed over the element counts of all layer tensors
+    pub fn num_parameters(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|linear| {
+                let weight = linear.weight.val().shape().num_elements();
+                let bias = linear
+                    .bias
+                    .as_ref()
+                    .map_or(0, |bias| bias.val().shape().num_elements());
+                weight + bias
+            })
+            .sum()
+    }
+
+    /// Number of output classes, read from the last layer
+    pub fn num_classes(&self) -> usize {
+        self.layers
+            .last()
+            .map_or(0, |output| output.weight.val().dims()[1])
+    }
+
+    /// Cross-entropy loss of the batch with the given reduction
+    ///
+    /// `Mean` and `Sum` return a single-element tensor, `None` the per-sample losses.
+    pub fn forward_loss(&self, batch: MNISTBatch<B>, reduction: LossReduction) -> Tensor<B, 1> {
+        let losses = self.forward_losses(batch);
+        match reduction {
+            LossReduction::Mean => losses.mean(),
+            LossReduction::Sum => losses.sum(),
+            LossReduction::None => losses,
+        }
     }
 
     /// Forward pass with classification output for training
@@ -78,6 +350,79 @@ impl<B: Backend> Model<B> {
     }
 }
 
+impl<B: Backend> Classifier<B> for Model<B> {
+    fn forward(&self, images: Tensor<B, 2>) -> Tensor<B, 2> {
+        Model::forward(self, images)
+    }
+
+    fn num_classes(&self) -> usize {
+        Model::num_classes(self)
+    }
+}
+
+/// Monte-Carlo dropout prediction for a single input
+#[derive(Debug, Clone)]
+pub struct McPrediction {
+    /// Mean probability per class over all passes
+    pub mean: Vec<f32>,
+    /// Standard deviation of the probability per class over all passes
+    pub std_dev: Vec<f32>,
+    pub samples: usize,
+}
+
+impl McPrediction {
+    /// Aggregate per-pass class probabilities into mean and standard deviation
+    pub fn from_samples(passes: &[Vec<f32>]) -> Self {
+        let samples = passes.len();
+        let num_classes = passes.first().map_or(0, |pass| pass.len());
+
+        let mut mean = vec![0.0; num_classes];
+        for pass in passes {
+            for (acc, p) in mean.iter_mut().zip(pass) {
+                *acc += p / samples as f32;
+            }
+        }
+
+        let mut std_dev = vec![0.0; num_classes];
+        for pass in passes {
+            for ((acc, p), m) in std_dev.iter_mut().zip(pass).zip(&mean) {
+                *acc += (p - m).powi(2) / samples as f32;
+            }
+        }
+        std_dev.iter_mut().for_each(|v| *v = v.sqrt());
+
+        Self {
+            mean,
+            std_dev,
+            samples,
+        }
+    }
+
+    /// Class with the highest mean probability
+    pub fn predicted_class(&self) -> usize {
+        self.mean
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(class, _)| class)
+    }
+
+    /// Standard deviation of the predicted class, used as the uncertainty estimate
+    pub fn uncertainty(&self) -> f32 {
+        self.std_dev[self.predicted_class()]
+    }
+}
+
+/// How per-sample losses are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LossReduction {
+    #[default]
+    Mean,
+    Sum,
+    /// Keep one loss per sample, e.g. for hard-example mining
+    None,
+}
+
 /// MNIST batch structure
 #[derive(Clone, Debug)]
 pub struct MNISTBatch<B: Backend> {
@@ -87,6 +432,9 @@ pub struct MNISTBatch<B: Backend> {
 
 impl<B: Backend> TrainStep<MNISTBatch<B>, ClassificationOutput<B>> for Model<B> {
     fn step(&self, batch: MNISTBatch<B>) -> TrainOutput<ClassificationOutput<B>> {
+        if let Some(step) = self.stats_schedule.0.as_ref().and_then(LayerStatsSchedule::tick) {
+            self.log_layer_stats(step, batch.images.clone());
+        }
         let item = self.forward_classification(batch);
         let loss = CrossEntropyLoss::new(None, &Reduction::Auto).forward(
             item.output.clone(),
@@ -130,18 +478,157 @@ mod tests {
         assert_eq!(output.shape(), [batch_size, config.num_classes]);
     }
 
+    #[test]
+    fn test_probabilities_are_softmax_of_logits() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+        let input = Tensor::<TestBackend, 2>::random(
+            [4, 784],
+            burn::tensor::Distribution::Normal(0.0, 1.0),
+            &device,
+        );
+
+        let probabilities = model.probabilities(input.clone());
+        assert_eq!(probabilities.dims(), [4, 10]);
+
+        let values = probabilities.clone().into_data().convert::<f32>().value;
+        for row in values.chunks(10) {
+            assert!((row.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+            assert!(row.iter().all(|p| (0.0..=1.0).contains(p)));
+        }
+
+        let from_probabilities = probabilities.argmax(1).into_data().convert::<i64>().value;
+        let from_logits = model.forward(input).argmax(1).into_data().convert::<i64>().value;
+        assert_eq!(from_probabilities, from_logits);
+    }
+
+    #[test]
+    fn test_forward_losses_match_reduced_loss() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+        let batch_size = 4;
+        let batch = MNISTBatch {
+            images: Tensor::<TestBackend, 2>::random(
+                [batch_size, 784],
+                burn::tensor::Distribution::Normal(0.0, 1.0),
+                &device,
+            ),
+            targets: Tensor::from_ints([0, 3, 7, 9], &device),
+        };
+
+        let losses = model.forward_losses(batch.clone());
+        assert_eq!(losses.dims(), [batch_size]);
+
+        let output = model.forward(batch.images.clone());
+        let reduced: f32 = CrossEntropyLoss::new(None, &Reduction::Auto)
+            .forward(output, batch.targets.clone())
+            .into_scalar();
+        let mean: f32 = losses.mean().into_scalar();
+        assert!((mean - reduced).abs() < 1e-4);
+
+        let sum: f32 = model.forward_loss(batch, LossReduction::Sum).into_scalar();
+        assert!((sum - reduced * batch_size as f32).abs() < 1e-3);
+    }
+
     #[test]
     fn test_model_config() {
         let config = ModelConfig {
             input_size: 784,
-            hidden_size: 256,
+            hidden_sizes: vec![256],
             num_classes: 10,
             dropout: 0.3,
         };
         
         assert_eq!(config.input_size, 784);
-        assert_eq!(config.hidden_size, 256);
+        assert_eq!(config.hidden_sizes, [256]);
         assert_eq!(config.num_classes, 10);
         assert_eq!(config.dropout, 0.3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_mc_single_sample_matches_stochastic_pass() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+        let input = Tensor::<TestBackend, 2>::random(
+            [1, 784],
+            burn::tensor::Distribution::Normal(0.0, 1.0),
+            &device,
+        );
+
+        TestBackend::seed(42);
+        let expected = softmax(model.forward_stochastic(input.clone()), 1)
+            .into_data()
+            .convert::<f32>()
+            .value;
+
+        TestBackend::seed(42);
+        let prediction = model.predict_mc(input, 1);
+
+        assert_eq!(prediction.samples, 1);
+        for (mean, expected) in prediction.mean.iter().zip(&expected) {
+            assert!((mean - expected).abs() < 1e-6);
+        }
+        assert!(prediction.std_dev.iter().all(|std| *std == 0.0));
+    }
+
+    #[test]
+    fn test_mc_multiple_samples_report_variance() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model: Model<TestBackend> = ModelConfig::new().init(&device);
+        let input = Tensor::<TestBackend, 2>::random(
+            [1, 784],
+            burn::tensor::Distribution::Normal(0.0, 1.0),
+            &device,
+        );
+
+        let prediction = model.predict_mc(input, 20);
+
+        assert_eq!(prediction.samples, 20);
+        assert_eq!(prediction.mean.len(), 10);
+        assert!((prediction.mean.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(prediction.std_dev.iter().any(|std| *std > 0.0));
+    }
+
+    #[test]
+    fn test_num_parameters() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let config = ModelConfig::new();
+        let model: Model<TestBackend> = config.init(&device);
+
+        let expected = (784 * 128 + 128) + (128 * 128 + 128) + (128 * 10 + 10);
+        assert_eq!(model.num_parameters(), expected);
+        assert_eq!(config.num_parameters(), expected);
+    }
+
+    #[test]
+    fn test_deeper_model() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let config = ModelConfig {
+            hidden_sizes: parse_hidden_sizes("256,128,64").unwrap(),
+            ..ModelConfig::new()
+        };
+        let model: Model<TestBackend> = config.init(&device);
+
+        let output = model.forward(Tensor::zeros([3, 784], &device));
+        assert_eq!(output.dims(), [3, config.num_classes]);
+        assert_eq!(model.num_parameters(), config.num_parameters());
+        assert_eq!(model.layers().len(), 4);
+    }
+
+    #[test]
+    fn test_parse_hidden_sizes() {
+        assert_eq!(parse_hidden_sizes("128"), Ok(vec![128]));
+        assert_eq!(parse_hidden_sizes("256, 128,64"), Ok(vec![256, 128, 64]));
+        assert!(parse_hidden_sizes("128,0").is_err());
+        assert!(parse_hidden_sizes("").is_err());
+        assert!(parse_hidden_sizes("128,,64").is_err());
+    }
+
+    #[test]
+    fn test_parse_architecture() {
+        assert_eq!("mlp".parse(), Ok(Architecture::Mlp));
+        assert_eq!("CNN".parse(), Ok(Architecture::Cnn));
+        assert!("rnn".parse::<Architecture>().is_err());
+        assert_eq!(Architecture::Cnn.to_string(), "cnn");
+    }
+}
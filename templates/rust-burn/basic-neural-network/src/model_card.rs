@@ -0,0 +1,403 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::cnn::{ConvModelConfig, IMAGE_SIZE};
+use crate::model::ModelConfig;
+use crate::training::{Evaluation, TrainingConfig};
+
+/// File name of the training summary written next to the final model
+pub const SUMMARY_FILE: &str = "training_summary.json";
+
+/// Everything known about a finished training run, persisted as JSON
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrainingSummary {
+    pub architecture: ArchitectureSummary,
+    pub training: HyperparameterSummary,
+    pub metrics: MetricsSummary,
+    /// `confusion_matrix[actual][predicted]` counts on the test set
+    pub confusion_matrix: Vec<Vec<usize>>,
+    pub dataset: DatasetSummary,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchitectureSummary {
+    pub input_size: usize,
+    pub hidden_sizes: Vec<usize>,
+    pub num_classes: usize,
+    pub dropout: f64,
+    pub num_parameters: usize,
+    /// Output channels of the two conv blocks of a CNN; `None` for an MLP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conv_channels: Option<[usize; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HyperparameterSummary {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    pub weight_decay: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MetricsSummary {
+    pub test_accuracy: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatasetSummary {
+    pub train_samples: usize,
+    pub test_samples: usize,
+}
+
+impl TrainingSummary {
+    pub fn new(
+        training_config: &TrainingConfig,
+        model_config: &ModelConfig,
+        evaluation: &Evaluation,
+        train_samples: usize,
+        test_samples: usize,
+    ) -> Self {
+        let architecture = ArchitectureSummary {
+            input_size: model_config.input_size,
+            hidden_sizes: model_config.hidden_sizes.clone(),
+            num_classes: model_config.num_classes,
+            dropout: model_config.dropout,
+            num_parameters: model_config.num_parameters(),
+            conv_channels: None,
+        };
+        Self::with_architecture(
+            architecture,
+            training_config,
+            evaluation,
+            train_samples,
+            test_samples,
+        )
+    }
+
+    /// Summary of a CNN training run
+    pub fn new_cnn(
+        training_config: &TrainingConfig,
+        conv_config: &ConvModelConfig,
+        evaluation: &Evaluation,
+        train_samples: usize,
+        test_samples: usize,
+    ) -> Self {
+        let architecture = ArchitectureSummary {
+            input_size: IMAGE_SIZE * IMAGE_SIZE,
+            hidden_sizes: Vec::new(),
+            num_classes: conv_config.num_classes,
+            dropout: conv_config.dropout,
+            num_parameters: conv_config.num_parameters(),
+            conv_channels: Some([conv_config.conv1_channels, conv_config.conv2_channels]),
+        };
+        Self::with_architecture(
+            architecture,
+            training_config,
+            evaluation,
+            train_samples,
+            test_samples,
+        )
+    }
+
+    fn with_architecture(
+        architecture: ArchitectureSummary,
+        training_config: &TrainingConfig,
+        evaluation: &Evaluation,
+        train_samples: usize,
+        test_samples: usize,
+    ) -> Self {
+        Self {
+            architecture,
+            training: HyperparameterSummary {
+                epochs: training_config.epochs,
+                batch_size: training_config.batch_size,
+                learning_rate: training_config.learning_rate,
+                weight_decay: training_config.weight_decay,
+            },
+            metrics: MetricsSummary {
+                test_accuracy: evaluation.accuracy,
+            },
+            confusion_matrix: evaluation.confusion_matrix.clone(),
+            dataset: DatasetSummary {
+                train_samples,
+                test_samples,
+            },
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write training summary to {:?}", path))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read training summary from {:?}", path))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Invalid training summary in {:?}", path))
+    }
+}
+
+/// Count `(actual, predicted)` pairs into a `num_classes` x `num_classes` matrix
+///
+/// Labels outside `0..num_classes` are ignored.
+pub fn confusion_matrix(
+    targets: &[usize],
+    predictions: &[usize],
+    num_classes: usize,
+) -> Vec<Vec<usize>> {
+    let mut matrix = vec![vec![0; num_classes]; num_classes];
+    for (&actual, &predicted) in targets.iter().zip(predictions) {
+        if actual < num_classes && predicted < num_classes {
+            matrix[actual][predicted] += 1;
+        }
+    }
+    matrix
+}
+
+/// Precision and recall of one class
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassMetrics {
+    /// Fraction of predictions of this class that were correct
+    pub precision: f64,
+    /// Fraction of samples of this class that were predicted correctly
+    pub recall: f64,
+}
+
+/// Per-class precision and recall from a `matrix[actual][predicted]` confusion matrix
+///
+/// A class that is never predicted (or never present) gets a precision (or recall) of 0.
+pub fn class_metrics(matrix: &[Vec<usize>]) -> Vec<ClassMetrics> {
+    let ratio = |hits: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    };
+
+    (0..matrix.len())
+        .map(|class| {
+            let hits = matrix[class][class];
+            let predicted = matrix.iter().map(|row| row[class]).sum();
+            let actual = matrix[class].iter().sum();
+            ClassMetrics {
+                precision: ratio(hits, predicted),
+                recall: ratio(hits, actual),
+            }
+        })
+        .collect()
+}
+
+/// Render a confusion matrix with per-class precision and recall for the terminal
+pub fn format_confusion_matrix(matrix: &[Vec<usize>]) -> String {
+    let width = matrix
+        .iter()
+        .flatten()
+        .map(|count| count.to_string().len())
+        .max()
+        .unwrap_or(1)
+        .max(3);
+    let mut output = String::new();
+
+    let _ = write!(output, "  actual\\pred");
+    for class in 0..matrix.len() {
+        let _ = write!(output, " {:>width$}", class, width = width);
+    }
+    let _ = writeln!(output, "  precision  recall");
+
+    for (actual, (row, metrics)) in matrix.iter().zip(class_metrics(matrix)).enumerate() {
+        let _ = write!(output, "  {:>11}", actual);
+        for count in row {
+            let _ = write!(output, " {:>width$}", count, width = width);
+        }
+        let _ = writeln!(
+            output,
+            "  {:>9.3}  {:>6.3}",
+            metrics.precision, metrics.recall
+        );
+    }
+    output
+}
+
+/// Render a training summary as a Markdown model card
+pub fn render_model_card(summary: &TrainingSummary) -> String {
+    let arch = &summary.architecture;
+    let mut card = String::new();
+
+    let kind = if arch.conv_channels.is_some() { "CNN" } else { "MLP" };
+    let _ = writeln!(card, "# Model Card: Burn {} Classifier\n", kind);
+
+    let _ = writeln!(card, "## Architecture\n");
+    let _ = writeln!(card, "| Layer | Input | Output |");
+    let _ = writeln!(card, "|-------|-------|--------|");
+    if let Some([conv1, conv2]) = arch.conv_channels {
+        let (side, half, quarter) = (IMAGE_SIZE, IMAGE_SIZE / 2, IMAGE_SIZE / 4);
+        let block = "3x3 conv + ReLU + max-pool";
+        let _ = writeln!(card, "| {} | 1x{}x{} | {}x{}x{} |", block, side, side, conv1, half, half);
+        let _ = writeln!(
+            card,
+            "| {} | {}x{}x{} | {}x{}x{} |",
+            block, conv1, half, half, conv2, quarter, quarter
+        );
+        let _ = writeln!(
+            card,
+            "| dropout + linear | {} | {} |",
+            conv2 * quarter * quarter,
+            arch.num_classes
+        );
+    } else {
+        let mut d_in = arch.input_size;
+        for (index, &d_out) in arch.hidden_sizes.iter().enumerate() {
+            let layer = format!("linear{} + ReLU + dropout", index + 1);
+            let _ = writeln!(card, "| {} | {} | {} |", layer, d_in, d_out);
+            d_in = d_out;
+        }
+        let _ = writeln!(
+            card,
+            "| linear{} | {} | {} |",
+            arch.hidden_sizes.len() + 1,
+            d_in,
+            arch.num_classes
+        );
+    }
+    let _ = writeln!(card);
+    let _ = writeln!(card, "- Parameters: {}", arch.num_parameters);
+    let _ = writeln!(card, "- Dropout: {}\n", arch.dropout);
+
+    let training = &summary.training;
+    let _ = writeln!(card, "## Training Configuration\n");
+    let _ = writeln!(card, "- Epochs: {}", training.epochs);
+    let _ = writeln!(card, "- Batch size: {}", training.batch_size);
+    let _ = writeln!(card, "- Learning rate: {}", training.learning_rate);
+    let _ = writeln!(card, "- Weight decay: {}\n", training.weight_decay);
+
+    let _ = writeln!(card, "## Metrics\n");
+    let _ = writeln!(
+        card,
+        "- Test accuracy: {:.2}%\n",
+        summary.metrics.test_accuracy * 100.0
+    );
+
+    let _ = writeln!(card, "## Confusion Matrix\n");
+    let _ = writeln!(card, "Rows are actual classes, columns are predicted classes.\n");
+    let classes = summary.confusion_matrix.len();
+    let header = (0..classes).map(|c| c.to_string()).collect::<Vec<_>>();
+    let _ = writeln!(card, "| actual \\ predicted | {} |", header.join(" | "));
+    let _ = writeln!(card, "|---{}|", "|---".repeat(classes));
+    for (actual, row) in summary.confusion_matrix.iter().enumerate() {
+        let cells = row.iter().map(|count| count.to_string()).collect::<Vec<_>>();
+        let _ = writeln!(card, "| **{}** | {} |", actual, cells.join(" | "));
+    }
+    let _ = writeln!(card);
+
+    let _ = writeln!(card, "## Dataset\n");
+    let _ = writeln!(card, "- Training samples: {}", summary.dataset.train_samples);
+    let _ = writeln!(card, "- Test samples: {}", summary.dataset.test_samples);
+    let _ = writeln!(card, "- Classes: {}", arch.num_classes);
+
+    card
+}
+
+/// Render the training summary at `summary_json` into a Markdown model card at `out`
+pub fn generate_model_card(summary_json: &Path, out: &Path) -> anyhow::Result<()> {
+    let summary = TrainingSummary::load(summary_json)?;
+    std::fs::write(out, render_model_card(&summary))
+        .with_context(|| format!("Failed to write model card to {:?}", out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confusion_matrix() {
+        let matrix = confusion_matrix(&[0, 1, 1, 2], &[0, 1, 2, 2], 3);
+        assert_eq!(matrix, vec![vec![1, 0, 0], vec![0, 1, 1], vec![0, 0, 1]]);
+    }
+
+    #[test]
+    fn test_class_metrics() {
+        // Class 0: 2 of 3 predictions right, 2 of 2 samples found
+        // Class 1: 1 of 1 prediction right, 1 of 2 samples found
+        // Class 2: never predicted, never present
+        let matrix = confusion_matrix(&[0, 0, 1, 1], &[0, 0, 0, 1], 3);
+        assert_eq!(matrix[1][0], 1);
+        assert_eq!(matrix[0][0], 2);
+        assert_eq!(matrix[2], vec![0, 0, 0]);
+
+        let metrics = class_metrics(&matrix);
+        assert!((metrics[0].precision - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(metrics[0].recall, 1.0);
+        assert_eq!(metrics[1].precision, 1.0);
+        assert_eq!(metrics[1].recall, 0.5);
+        assert_eq!(metrics[2], ClassMetrics { precision: 0.0, recall: 0.0 });
+
+        let table = format_confusion_matrix(&matrix);
+        assert_eq!(table.lines().count(), 4);
+        assert_eq!(
+            table.lines().nth(2).unwrap(),
+            "            1   1   1   0      1.000   0.500"
+        );
+    }
+
+    #[test]
+    fn test_generate_model_card() {
+        let dir = tempfile::tempdir().unwrap();
+        let summary_path = dir.path().join(SUMMARY_FILE);
+        let card_path = dir.path().join("MODEL_CARD.md");
+
+        let evaluation = Evaluation {
+            accuracy: 0.925,
+            confusion_matrix: confusion_matrix(&[0, 1, 1], &[0, 1, 0], 2),
+            calibration: crate::calibration::calibration_report(&[], &[], 10),
+            hardest_samples: Vec::new(),
+        };
+        let model_config = ModelConfig {
+            input_size: 784,
+            hidden_sizes: vec![128, 64],
+            num_classes: 2,
+            dropout: 0.5,
+        };
+        TrainingSummary::new(&TrainingConfig::default(), &model_config, &evaluation, 1000, 200)
+            .save(&summary_path)
+            .unwrap();
+
+        generate_model_card(&summary_path, &card_path).unwrap();
+        let card = std::fs::read_to_string(card_path).unwrap();
+
+        assert!(card.contains("Test accuracy: 92.50%"));
+        assert!(card.contains(&format!("Parameters: {}", model_config.num_parameters())));
+        assert!(card.contains("| linear2 + ReLU + dropout | 128 | 64 |"));
+        assert!(card.contains("| linear3 | 64 | 2 |"));
+        assert!(card.contains("| actual \\ predicted | 0 | 1 |"));
+        assert!(card.contains("| **1** | 1 | 1 |"));
+    }
+
+    #[test]
+    fn test_cnn_model_card() {
+        let evaluation = Evaluation {
+            accuracy: 0.5,
+            confusion_matrix: confusion_matrix(&[0, 1], &[0, 0], 2),
+            calibration: crate::calibration::calibration_report(&[], &[], 10),
+            hardest_samples: Vec::new(),
+        };
+        let conv_config = ConvModelConfig {
+            num_classes: 2,
+            ..ConvModelConfig::new()
+        };
+        let summary =
+            TrainingSummary::new_cnn(&TrainingConfig::default(), &conv_config, &evaluation, 10, 2);
+        assert_eq!(summary.architecture.conv_channels, Some([16, 32]));
+
+        let card = render_model_card(&summary);
+        assert!(card.starts_with("# Model Card: Burn CNN Classifier"));
+        assert!(card.contains("| 3x3 conv + ReLU + max-pool | 1x28x28 | 16x14x14 |"));
+        assert!(card.contains("| dropout + linear | 1568 | 2 |"));
+        assert!(card.contains(&format!("Parameters: {}", conv_config.num_parameters())));
+    }
+}
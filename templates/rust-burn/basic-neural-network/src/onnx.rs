@@ -0,0 +1,266 @@
+/*!
+ONNX export of the MLP
+
+Burn can import ONNX but not export it, so the model is written out directly as an ONNX
+`ModelProto`. Only the messages and fields the MLP needs are declared below; their tags
+match `onnx.proto`, so the output loads in onnxruntime, Netron and the phi ONNX tooling.
+
+Each `Linear` layer becomes a `Gemm` node (Burn stores weights as `[d_input, d_output]`,
+which is Gemm's untransposed `B`), followed by `Relu` between hidden layers. Dropout is
+an identity at inference and is left out. The batch dimension is symbolic.
+*/
+
+use crate::model::Model;
+use burn::{nn::Linear, tensor::backend::Backend};
+use prost::Message;
+use std::path::Path;
+
+/// ONNX IR version written to the model (ONNX 1.13+)
+pub const IR_VERSION: i64 = 8;
+
+/// Default-domain operator set the graph is expressed in
+pub const OPSET_VERSION: i64 = 13;
+
+/// Name of the graph input, shape `[batch, input_size]`
+pub const INPUT_NAME: &str = "input";
+
+/// Name of the graph output, shape `[batch, num_classes]` (unnormalized logits)
+pub const OUTPUT_NAME: &str = "logits";
+
+/// `TensorProto.DataType.FLOAT`
+const FLOAT: i32 = 1;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ModelProto {
+    #[prost(int64, tag = "1")]
+    pub ir_version: i64,
+    #[prost(string, tag = "2")]
+    pub producer_name: String,
+    #[prost(string, tag = "3")]
+    pub producer_version: String,
+    #[prost(message, optional, tag = "7")]
+    pub graph: Option<GraphProto>,
+    #[prost(message, repeated, tag = "8")]
+    pub opset_import: Vec<OperatorSetIdProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    pub domain: String,
+    #[prost(int64, tag = "2")]
+    pub version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    pub node: Vec<NodeProto>,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, repeated, tag = "5")]
+    pub initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    pub input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    pub output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    pub input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub output: Vec<String>,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(string, tag = "4")]
+    pub op_type: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    pub dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    pub data_type: i32,
+    #[prost(string, tag = "8")]
+    pub name: String,
+    /// Little-endian element bytes
+    #[prost(bytes = "vec", tag = "9")]
+    pub raw_data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(message, optional, tag = "2")]
+    pub r#type: Option<TypeProto>,
+}
+
+/// `TypeProto` restricted to its `tensor_type` variant
+#[derive(Clone, PartialEq, Message)]
+pub struct TypeProto {
+    #[prost(message, optional, tag = "1")]
+    pub tensor_type: Option<TensorTypeProto>,
+}
+
+/// `TypeProto.Tensor`
+#[derive(Clone, PartialEq, Message)]
+pub struct TensorTypeProto {
+    #[prost(int32, tag = "1")]
+    pub elem_type: i32,
+    #[prost(message, optional, tag = "2")]
+    pub shape: Option<TensorShapeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TensorShapeProto {
+    #[prost(message, repeated, tag = "1")]
+    pub dim: Vec<Dimension>,
+}
+
+/// `TensorShapeProto.Dimension`: a fixed size or a symbolic name
+#[derive(Clone, PartialEq, Message)]
+pub struct Dimension {
+    #[prost(int64, optional, tag = "1")]
+    pub dim_value: Option<i64>,
+    #[prost(string, optional, tag = "2")]
+    pub dim_param: Option<String>,
+}
+
+impl Dimension {
+    fn fixed(size: usize) -> Self {
+        Self {
+            dim_value: Some(size as i64),
+            dim_param: None,
+        }
+    }
+
+    fn symbolic(name: &str) -> Self {
+        Self {
+            dim_value: None,
+            dim_param: Some(name.to_string()),
+        }
+    }
+}
+
+/// `[batch, features]` float tensor value
+fn batch_value(name: &str, features: usize) -> ValueInfoProto {
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            tensor_type: Some(TensorTypeProto {
+                elem_type: FLOAT,
+                shape: Some(TensorShapeProto {
+                    dim: vec![Dimension::symbolic("batch"), Dimension::fixed(features)],
+                }),
+            }),
+        }),
+    }
+}
+
+fn float_tensor(name: String, dims: &[usize], values: &[f32]) -> TensorProto {
+    TensorProto {
+        dims: dims.iter().map(|&dim| dim as i64).collect(),
+        data_type: FLOAT,
+        name,
+        raw_data: values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect(),
+    }
+}
+
+fn node(op_type: &str, name: String, input: Vec<String>, output: String) -> NodeProto {
+    NodeProto {
+        input,
+        output: vec![output],
+        name,
+        op_type: op_type.to_string(),
+    }
+}
+
+/// Gemm node and initializers for one linear layer
+fn linear_layer<B: Backend>(
+    linear: &Linear<B>,
+    name: &str,
+    input: String,
+    output: String,
+) -> (NodeProto, Vec<TensorProto>) {
+    let weight = linear.weight.val();
+    let weight_name = format!("{}.weight", name);
+    let mut initializers = vec![float_tensor(
+        weight_name.clone(),
+        &weight.dims(),
+        &weight.into_data().convert::<f32>().value,
+    )];
+    let mut inputs = vec![input, weight_name];
+
+    if let Some(bias) = &linear.bias {
+        let bias = bias.val();
+        let bias_name = format!("{}.bias", name);
+        initializers.push(float_tensor(
+            bias_name.clone(),
+            &bias.dims(),
+            &bias.into_data().convert::<f32>().value,
+        ));
+        inputs.push(bias_name);
+    }
+
+    (node("Gemm", name.to_string(), inputs, output), initializers)
+}
+
+/// Convert `model` to an ONNX model computing the same logits as [`Model::forward`]
+pub fn model_proto<B: Backend>(model: &Model<B>) -> ModelProto {
+    let layers = model.layers();
+    let input_size = layers[0].weight.val().dims()[0];
+    let mut nodes = Vec::new();
+    let mut initializer = Vec::new();
+    let mut current = INPUT_NAME.to_string();
+
+    for (index, linear) in layers.iter().enumerate() {
+        let name = format!("fc{}", index + 1);
+        let is_last = index + 1 == layers.len();
+        let output = if is_last {
+            OUTPUT_NAME.to_string()
+        } else {
+            name.clone()
+        };
+
+        let (gemm, tensors) = linear_layer(linear, &name, current, output.clone());
+        nodes.push(gemm);
+        initializer.extend(tensors);
+        current = output;
+
+        if !is_last {
+            let relu = format!("relu{}", index + 1);
+            nodes.push(node("Relu", relu.clone(), vec![current], relu.clone()));
+            current = relu;
+        }
+    }
+
+    ModelProto {
+        ir_version: IR_VERSION,
+        producer_name: crate::NAME.to_string(),
+        producer_version: crate::VERSION.to_string(),
+        graph: Some(GraphProto {
+            node: nodes,
+            name: "mnist_mlp".to_string(),
+            initializer,
+            input: vec![batch_value(INPUT_NAME, input_size)],
+            output: vec![batch_value(OUTPUT_NAME, model.num_classes())],
+        }),
+        opset_import: vec![OperatorSetIdProto {
+            domain: String::new(),
+            version: OPSET_VERSION,
+        }],
+    }
+}
+
+/// Write `model` to `path` as an ONNX file
+pub fn export_model<B: Backend>(model: &Model<B>, path: &Path) -> anyhow::Result<()> {
+    std::fs::write(path, model_proto(model).encode_to_vec())
+        .map_err(|e| anyhow::anyhow!("Failed to write ONNX model {:?}: {}", path, e))
+}
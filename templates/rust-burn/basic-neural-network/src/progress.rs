@@ -0,0 +1,141 @@
+use burn::train::renderer::{MetricState, MetricsRenderer, TrainingProgress};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::{Duration, Instant};
+
+/// Estimated time remaining for the current epoch and the whole run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressEstimate {
+    pub epoch_fraction: f64,
+    pub epoch_eta: Duration,
+    pub total_eta: Duration,
+}
+
+/// Estimate remaining time from the progress within the current epoch
+///
+/// `epoch` is 1-based like Burn's learner. Returns `None` until at least one batch has
+/// completed, since there is nothing to extrapolate from yet.
+pub fn estimate_progress(
+    batches_done: usize,
+    batches_total: usize,
+    epoch: usize,
+    epochs_total: usize,
+    elapsed_in_epoch: Duration,
+) -> Option<ProgressEstimate> {
+    if batches_done == 0 || batches_total == 0 {
+        return None;
+    }
+
+    let epoch_fraction = (batches_done as f64 / batches_total as f64).min(1.0);
+    let epoch_duration = elapsed_in_epoch.as_secs_f64() / epoch_fraction;
+    let epoch_eta = epoch_duration - elapsed_in_epoch.as_secs_f64();
+    let remaining_epochs = epochs_total.saturating_sub(epoch) as f64;
+
+    Some(ProgressEstimate {
+        epoch_fraction,
+        epoch_eta: Duration::from_secs_f64(epoch_eta.max(0.0)),
+        total_eta: Duration::from_secs_f64((epoch_eta + remaining_epochs * epoch_duration).max(0.0)),
+    })
+}
+
+/// Learner renderer that shows a within-epoch progress bar with ETAs
+pub struct ProgressRenderer {
+    bar: ProgressBar,
+    epoch: usize,
+    epoch_start: Instant,
+}
+
+impl ProgressRenderer {
+    pub fn new() -> Self {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::with_template("{prefix} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+
+        Self {
+            bar,
+            epoch: 0,
+            epoch_start: Instant::now(),
+        }
+    }
+
+    fn render(&mut self, phase: &str, item: TrainingProgress) {
+        if item.epoch != self.epoch {
+            self.epoch = item.epoch;
+            self.epoch_start = Instant::now();
+        }
+
+        self.bar.set_prefix(format!("{} epoch {}/{}", phase, item.epoch, item.epoch_total));
+        self.bar.set_length(item.progress.items_total as u64);
+        self.bar.set_position(item.progress.items_processed as u64);
+
+        if let Some(estimate) = estimate_progress(
+            item.progress.items_processed,
+            item.progress.items_total,
+            item.epoch,
+            item.epoch_total,
+            self.epoch_start.elapsed(),
+        ) {
+            self.bar.set_message(format!(
+                "epoch ETA {}s, run ETA {}s",
+                estimate.epoch_eta.as_secs(),
+                estimate.total_eta.as_secs()
+            ));
+        }
+    }
+}
+
+impl Default for ProgressRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsRenderer for ProgressRenderer {
+    fn update_train(&mut self, _state: MetricState) {}
+
+    fn update_valid(&mut self, _state: MetricState) {}
+
+    fn render_train(&mut self, item: TrainingProgress) {
+        self.render("train", item);
+    }
+
+    fn render_valid(&mut self, item: TrainingProgress) {
+        self.render("valid", item);
+    }
+}
+
+impl Drop for ProgressRenderer {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_progress() {
+        let estimate = estimate_progress(50, 100, 1, 3, Duration::from_secs(10)).unwrap();
+
+        assert_eq!(estimate.epoch_fraction, 0.5);
+        assert_eq!(estimate.epoch_eta, Duration::from_secs(10));
+        // 10s left in this epoch + 2 more epochs of 20s each
+        assert_eq!(estimate.total_eta, Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_estimate_progress_last_batch() {
+        let estimate = estimate_progress(100, 100, 3, 3, Duration::from_secs(20)).unwrap();
+
+        assert_eq!(estimate.epoch_eta, Duration::ZERO);
+        assert_eq!(estimate.total_eta, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_estimate_progress_before_first_batch() {
+        assert!(estimate_progress(0, 100, 1, 3, Duration::from_secs(1)).is_none());
+    }
+}
@@ -0,0 +1,380 @@
+use burn::{data::dataloader::batcher::Batcher, tensor::backend::Backend};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use crate::cnn::IMAGE_SIZE;
+use crate::data::{MNISTBatcher, MNISTItem};
+use crate::model::Classifier;
+
+/// Number of pixels in a flattened 28x28 input image
+pub const IMAGE_PIXELS: usize = 784;
+
+/// One line of NDJSON scoring input
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScoreRequest {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub image: Vec<f32>,
+}
+
+/// Predicted class and its softmax probability
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Prediction {
+    pub class: usize,
+    pub confidence: f32,
+}
+
+/// One line of NDJSON scoring output, in the same order as the input
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ScoreRecord {
+    Prediction {
+        line: usize,
+        id: Option<String>,
+        predicted_class: usize,
+        confidence: f32,
+    },
+    Error {
+        line: usize,
+        error: String,
+    },
+}
+
+/// Counts of scored and rejected input lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScoreSummary {
+    pub scored: usize,
+    pub failed: usize,
+}
+
+/// Parse and validate a single NDJSON input line for a model taking `input_size` values
+pub fn parse_request(line: &str, input_size: usize) -> Result<ScoreRequest, String> {
+    let request: ScoreRequest =
+        serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+
+    if request.image.len() != input_size {
+        return Err(format!(
+            "expected {} pixels, got {}",
+            input_size,
+            request.image.len()
+        ));
+    }
+    if request.image.iter().any(|pixel| !pixel.is_finite()) {
+        return Err("image contains non-finite pixel values".to_string());
+    }
+    Ok(request)
+}
+
+/// Stream NDJSON images from `reader` and write NDJSON predictions to `writer`
+///
+/// Each image must hold `input_size` values. At most `batch_size` lines, images and error
+/// records together, are held in memory at a time, so the input can be arbitrarily large.
+/// Malformed lines are logged, reported as error records and skipped; blank lines are
+/// ignored. Output is flushed after every batch.
+pub fn score_ndjson<R, W, F>(
+    reader: R,
+    mut writer: W,
+    batch_size: usize,
+    input_size: usize,
+    mut predict: F,
+) -> anyhow::Result<ScoreSummary>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(Vec<MNISTItem>) -> anyhow::Result<Vec<Prediction>>,
+{
+    let batch_size = batch_size.max(1);
+    let mut summary = ScoreSummary::default();
+    let mut pending: Vec<Result<(usize, ScoreRequest), ScoreRecord>> = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_request(&line, input_size) {
+            Ok(request) => pending.push(Ok((line_number, request))),
+            Err(error) => {
+                log::warn!("Skipping line {}: {}", line_number, error);
+                pending.push(Err(ScoreRecord::Error {
+                    line: line_number,
+                    error,
+                }));
+            }
+        }
+
+        if pending.len() >= batch_size {
+            flush_batch(&mut pending, &mut writer, &mut predict, &mut summary)?;
+        }
+    }
+    flush_batch(&mut pending, &mut writer, &mut predict, &mut summary)?;
+
+    Ok(summary)
+}
+
+fn flush_batch<W, F>(
+    pending: &mut Vec<Result<(usize, ScoreRequest), ScoreRecord>>,
+    writer: &mut W,
+    predict: &mut F,
+    summary: &mut ScoreSummary,
+) -> anyhow::Result<()>
+where
+    W: Write,
+    F: FnMut(Vec<MNISTItem>) -> anyhow::Result<Vec<Prediction>>,
+{
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let items = pending
+        .iter()
+        .filter_map(|entry| entry.as_ref().ok())
+        .map(|(_, request)| MNISTItem {
+            image: request.image.clone(),
+            label: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut predictions = if items.is_empty() {
+        Vec::new()
+    } else {
+        predict(items)?
+    }
+    .into_iter();
+
+    for entry in pending.drain(..) {
+        let record = match entry {
+            Ok((line, request)) => {
+                let prediction = predictions
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Predictor returned too few results"))?;
+                summary.scored += 1;
+                ScoreRecord::Prediction {
+                    line,
+                    id: request.id,
+                    predicted_class: prediction.class,
+                    confidence: prediction.confidence,
+                }
+            }
+            Err(record) => {
+                summary.failed += 1;
+                record
+            }
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Predict a batch of items with `model`, batching them the same way as the dataloader
+pub fn predict_items<B: Backend, M: Classifier<B> + ?Sized>(
+    model: &M,
+    batcher: &MNISTBatcher<B>,
+    items: Vec<MNISTItem>,
+) -> Vec<Prediction> {
+    let batch = batcher.batch(items);
+    let probabilities = model
+        .probabilities(batch.images)
+        .into_data()
+        .convert::<f32>()
+        .value;
+
+    let num_classes = probabilities.len() / batch.targets.dims()[0];
+    probabilities
+        .chunks(num_classes)
+        .map(|row| {
+            let (class, confidence) = row
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, f32::MIN), |best, (class, p)| if p > best.1 { (class, p) } else { best });
+            Prediction { class, confidence }
+        })
+        .collect()
+}
+
+/// Predicted `(class, confidence)` of every flattened 28x28 image in `inputs`, in order
+pub fn predict_batch<B: Backend, M: Classifier<B> + ?Sized>(
+    model: &M,
+    device: &B::Device,
+    inputs: Vec<Vec<f32>>,
+) -> Vec<(usize, f32)> {
+    if inputs.is_empty() {
+        return Vec::new();
+    }
+
+    let items = inputs
+        .into_iter()
+        .map(|image| MNISTItem { image, label: 0 })
+        .collect();
+    predict_items(model, &MNISTBatcher::new(device.clone()), items)
+        .into_iter()
+        .map(|prediction| (prediction.class, prediction.confidence))
+        .collect()
+}
+
+/// Load an image file as a flattened 28x28 grayscale input with pixels in `[0, 1]`
+///
+/// Color images are converted to grayscale and other sizes are resized, so the image
+/// should already look like MNIST: a light digit on a dark background.
+pub fn load_image(path: &Path) -> anyhow::Result<Vec<f32>> {
+    let image = image::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read image {:?}: {}", path, e))?
+        .into_luma8();
+    let size = IMAGE_SIZE as u32;
+    let image = if image.dimensions() == (size, size) {
+        image
+    } else {
+        image::imageops::resize(&image, size, size, FilterType::Triangle)
+    };
+
+    Ok(image.pixels().map(|pixel| pixel.0[0] as f32 / 255.0).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn::tensor::Tensor;
+    use std::io::Cursor;
+
+    type TestBackend = burn_ndarray::NdArray<f32>;
+
+    /// Classifier that scores each image by its mean pixel, favouring class 3 when bright
+    struct DummyClassifier;
+
+    impl Classifier<TestBackend> for DummyClassifier {
+        fn forward(&self, images: Tensor<TestBackend, 2>) -> Tensor<TestBackend, 2> {
+            let [batch_size, _] = images.dims();
+            let device = images.device();
+            let brightness = images.mean_dim(1).mul_scalar(10.0);
+            Tensor::cat(
+                vec![
+                    Tensor::zeros([batch_size, 3], &device),
+                    brightness,
+                    Tensor::zeros([batch_size, 6], &device),
+                ],
+                1,
+            )
+        }
+
+        fn num_classes(&self) -> usize {
+            10
+        }
+    }
+
+    #[test]
+    fn test_predict_batch_returns_one_prediction_per_input() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let inputs = vec![
+            vec![1.0; IMAGE_PIXELS],
+            vec![0.0; IMAGE_PIXELS],
+            vec![0.9; IMAGE_PIXELS],
+        ];
+
+        let predictions = predict_batch(&DummyClassifier, &device, inputs);
+
+        assert_eq!(predictions.len(), 3);
+        assert_eq!(predictions[0].0, 3);
+        assert_eq!(predictions[2].0, 3);
+        assert!(predictions[1].1 < predictions[0].1);
+        assert!(predictions
+            .iter()
+            .all(|&(class, confidence)| class < 10 && (0.0..=1.0).contains(&confidence)));
+        assert!(predict_batch(&DummyClassifier, &device, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_load_image_resizes_and_normalizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("digit.png");
+        image::GrayImage::from_pixel(56, 56, image::Luma([255])).save(&path).unwrap();
+
+        let pixels = load_image(&path).unwrap();
+        assert_eq!(pixels.len(), IMAGE_PIXELS);
+        assert!(pixels.iter().all(|&pixel| (pixel - 1.0).abs() < 1e-6));
+        assert!(load_image(&dir.path().join("missing.png")).is_err());
+    }
+
+    fn image_line(id: &str, value: f32) -> String {
+        serde_json::json!({ "id": id, "image": vec![value; IMAGE_PIXELS] }).to_string()
+    }
+
+    #[test]
+    fn test_score_ndjson_skips_malformed_line() {
+        let input = format!(
+            "{}\n{{\"id\": \"broken\", \"image\": [0.1, 0.2\n{}\n",
+            image_line("a", 0.0),
+            image_line("c", 1.0)
+        );
+        let mut output = Vec::new();
+        let mut batch_sizes = Vec::new();
+
+        let summary = score_ndjson(Cursor::new(input), &mut output, 1, IMAGE_PIXELS, |items| {
+            batch_sizes.push(items.len());
+            Ok(items
+                .iter()
+                .map(|item| Prediction {
+                    class: item.image[0] as usize,
+                    confidence: 0.9,
+                })
+                .collect())
+        })
+        .unwrap();
+
+        assert_eq!(summary, ScoreSummary { scored: 2, failed: 1 });
+        // The malformed line fills a batch of its own
+        assert_eq!(batch_sizes, vec![1, 1]);
+
+        let records = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["id"], "a");
+        assert_eq!(records[0]["predicted_class"], 0);
+        assert_eq!(records[1]["line"], 2);
+        assert!(records[1]["error"].as_str().unwrap().contains("invalid JSON"));
+        assert_eq!(records[2]["id"], "c");
+        assert_eq!(records[2]["predicted_class"], 1);
+    }
+
+    #[test]
+    fn test_error_records_count_towards_the_batch() {
+        let bad_lines = "{\"image\": [0.5]}\n".repeat(5);
+        let input = format!("{}{}\n{}\n", bad_lines, image_line("a", 0.0), image_line("b", 1.0));
+        let mut output = Vec::new();
+        let mut batch_sizes = Vec::new();
+
+        let summary = score_ndjson(Cursor::new(input), &mut output, 2, IMAGE_PIXELS, |items| {
+            batch_sizes.push(items.len());
+            Ok(vec![Prediction { class: 0, confidence: 1.0 }; items.len()])
+        })
+        .unwrap();
+
+        assert_eq!(summary, ScoreSummary { scored: 2, failed: 5 });
+        // Batches of two lines: two of errors only, then an error with `a`, then `b`
+        assert_eq!(batch_sizes, vec![1, 1]);
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), 7);
+    }
+
+    #[test]
+    fn test_parse_request_checks_pixel_count() {
+        assert!(parse_request(&image_line("ok", 0.5), IMAGE_PIXELS).is_ok());
+        assert!(parse_request(r#"{"image": [0.5, 0.5]}"#, IMAGE_PIXELS)
+            .unwrap_err()
+            .contains("expected 784 pixels"));
+
+        // Models trained on other inputs take their own size
+        assert!(parse_request(r#"{"image": [0.5, 0.5]}"#, 2).is_ok());
+        assert!(parse_request(&image_line("mnist", 0.5), 2)
+            .unwrap_err()
+            .contains("expected 2 pixels, got 784"));
+    }
+}
@@ -1,18 +1,149 @@
-use crate::{data::MNISTBatcher, model::ModelConfig};
+use crate::{
+    calibration::{calibration_report, CalibrationReport, DEFAULT_CALIBRATION_BINS},
+    cnn::{ConvModelConfig, ConvModelRecord},
+    cnn::IMAGE_SIZE,
+    data::{AugmentConfig, DatasetSource, MNISTBatcher, MNISTDataset},
+    model::{Architecture, Classifier, MNISTBatch, Model, ModelConfig, ModelRecord},
+    model_card::{class_metrics, confusion_matrix, ClassMetrics, TrainingSummary, SUMMARY_FILE},
+    progress::ProgressRenderer,
+};
 use burn::{
     backend::{Autodiff, Backend},
-    data::dataloader::DataLoaderBuilder,
-    lr_scheduler::noam::NoamLrSchedulerConfig,
+    config::Config,
+    data::{
+        dataloader::{batcher::Batcher, DataLoader, DataLoaderBuilder},
+        dataset::Dataset,
+    },
+    lr_scheduler::{
+        constant::ConstantLr,
+        cosine::{CosineAnnealingLrScheduler, CosineAnnealingLrSchedulerConfig},
+        noam::{NoamLrScheduler, NoamLrSchedulerConfig},
+        step::{StepLrScheduler, StepLrSchedulerConfig},
+        LrScheduler,
+    },
     nn::loss::CrossEntropyLoss,
-    optim::AdamConfig,
-    record::CompactRecorder,
-    tensor::backend::AutodiffBackend,
+    optim::{decay::WeightDecayConfig, AdamConfig, AdamWConfig, RmsPropConfig, SgdConfig},
+    module::{AutodiffModule, Module},
+    record::{CompactRecorder, Recorder},
+    tensor::{activation::softmax, backend::AutodiffBackend, ElementConversion},
     train::{
-        metric::{AccuracyMetric, LossMetric},
-        LearnerBuilder, MetricEarlyStoppingStrategy, StoppingCondition,
+        checkpoint::{
+            ComposedCheckpointingStrategy, KeepLastNCheckpoints, MetricCheckpointingStrategy,
+        },
+        metric::{
+            store::{Aggregate, Direction, Split},
+            AccuracyMetric, LossMetric,
+        },
+        ClassificationOutput, LearnerBuilder, MetricEarlyStoppingStrategy, StoppingCondition,
+        TrainStep, ValidStep,
     },
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// File name of the ONNX export inside the output directory
+pub const ONNX_FILE: &str = "model.onnx";
+
+/// File name of the saved `ModelConfig` inside the output directory
+pub const MODEL_CONFIG_FILE: &str = "config.json";
+
+/// File name of the saved `ConvModelConfig` inside the output directory, kept apart from
+/// [`MODEL_CONFIG_FILE`] so an MLP and a CNN can be trained into the same directory
+pub const CONV_CONFIG_FILE: &str = "conv_config.json";
+
+/// Optimizers selectable with `--optimizer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Optimizer {
+    #[default]
+    Adam,
+    /// Adam with decoupled weight decay
+    AdamW,
+    Sgd,
+    RmsProp,
+}
+
+impl Optimizer {
+    /// Every optimizer, in the order they are listed in `--help`
+    pub const ALL: [Optimizer; 4] = [Self::Adam, Self::AdamW, Self::Sgd, Self::RmsProp];
+}
+
+impl std::str::FromStr for Optimizer {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "adam" => Ok(Self::Adam),
+            "adamw" => Ok(Self::AdamW),
+            "sgd" => Ok(Self::Sgd),
+            "rmsprop" => Ok(Self::RmsProp),
+            _ => Err(format!(
+                "unknown optimizer '{}' (expected adam, adamw, sgd or rmsprop)",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Optimizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Adam => "adam",
+            Self::AdamW => "adamw",
+            Self::Sgd => "sgd",
+            Self::RmsProp => "rmsprop",
+        })
+    }
+}
+
+/// Learning rate schedules selectable with `--scheduler`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheduler {
+    /// `learning_rate` throughout
+    Constant,
+    /// Cosine annealing from `learning_rate` down to zero over the whole run
+    Cosine,
+    /// `learning_rate` divided by 10 after each third of the epochs
+    Step,
+    /// Linear warmup over `warmup_steps`, then inverse square root decay
+    #[default]
+    Noam,
+}
+
+impl Scheduler {
+    /// Every scheduler, in the order they are listed in `--help`
+    pub const ALL: [Scheduler; 4] = [Self::Constant, Self::Cosine, Self::Step, Self::Noam];
+}
+
+impl std::str::FromStr for Scheduler {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "constant" => Ok(Self::Constant),
+            "cosine" => Ok(Self::Cosine),
+            "step" => Ok(Self::Step),
+            "noam" => Ok(Self::Noam),
+            _ => Err(format!(
+                "unknown scheduler '{}' (expected constant, cosine, step or noam)",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Constant => "constant",
+            Self::Cosine => "cosine",
+            Self::Step => "step",
+            Self::Noam => "noam",
+        })
+    }
+}
+
+/// Factor the step scheduler multiplies the learning rate by at each decay
+const STEP_DECAY_GAMMA: f64 = 0.1;
 
 /// Training configuration
 #[derive(Debug)]
@@ -21,8 +152,26 @@ pub struct TrainingConfig {
     pub batch_size: usize,
     pub learning_rate: f64,
     pub weight_decay: f64,
+    pub optimizer: Optimizer,
+    pub scheduler: Scheduler,
+    /// Iterations of linear learning rate warmup for the Noam scheduler
+    pub warmup_steps: usize,
     pub early_stopping_patience: usize,
     pub save_every: usize,
+    pub output_dir: PathBuf,
+    pub progress: bool,
+    /// Seed for shuffling the training set each epoch; `None` keeps dataset order
+    pub shuffle_seed: Option<u64>,
+    /// Number of most recent epoch checkpoints to keep besides the best one; 0 keeps all
+    pub keep_last_n: usize,
+    /// Also write the final model as `model.onnx` for serving outside Burn
+    pub export_onnx: bool,
+    /// Random transforms applied to training images; validation images are left as-is
+    pub augment: AugmentConfig,
+    /// Data to train and evaluate on
+    pub dataset: DatasetSource,
+    /// Log each MLP layer's weight norm and mean activation every this many steps
+    pub log_layer_stats_every: Option<usize>,
 }
 
 impl Default for TrainingConfig {
@@ -32,12 +181,182 @@ impl Default for TrainingConfig {
             batch_size: 32,
             learning_rate: 1e-3,
             weight_decay: 1e-4,
+            optimizer: Optimizer::Adam,
+            scheduler: Scheduler::Noam,
+            warmup_steps: 1000,
             early_stopping_patience: 5,
             save_every: 5,
+            output_dir: PathBuf::from("./burn-models"),
+            progress: false,
+            shuffle_seed: Some(1234),
+            keep_last_n: 3,
+            export_onnx: false,
+            augment: AugmentConfig::default(),
+            dataset: DatasetSource::default(),
+            log_layer_stats_every: None,
         }
     }
 }
 
+impl TrainingConfig {
+    /// Validate the training hyperparameters before any work is done
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.epochs == 0 {
+            anyhow::bail!("epochs must be at least 1");
+        }
+        if self.batch_size == 0 {
+            anyhow::bail!("batch_size must be at least 1");
+        }
+        if !(self.learning_rate > 0.0 && self.learning_rate.is_finite()) {
+            anyhow::bail!("learning_rate must be a positive number, got {}", self.learning_rate);
+        }
+        if self.weight_decay < 0.0 {
+            anyhow::bail!("weight_decay must not be negative, got {}", self.weight_decay);
+        }
+        if self.scheduler == Scheduler::Noam && self.warmup_steps == 0 {
+            anyhow::bail!("warmup_steps must be at least 1 for the noam scheduler");
+        }
+        if self.dataset != DatasetSource::Mnist && !self.augment.is_identity() {
+            anyhow::bail!("augmentation transforms 28x28 images and only applies to MNIST");
+        }
+        if self.log_layer_stats_every == Some(0) {
+            anyhow::bail!("log_layer_stats_every must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// `weight_decay` as the penalty shared by Adam, SGD and RMSprop
+    fn weight_decay_config(&self) -> Option<WeightDecayConfig> {
+        Some(WeightDecayConfig::new(self.weight_decay as f32))
+    }
+
+    fn constant_scheduler(&self) -> ConstantLr {
+        ConstantLr::new(self.learning_rate)
+    }
+
+    /// Cosine annealing over every iteration of the run
+    fn cosine_scheduler(&self, steps_per_epoch: usize) -> CosineAnnealingLrScheduler {
+        let num_iters = (self.epochs * steps_per_epoch).max(1);
+        CosineAnnealingLrSchedulerConfig::new(self.learning_rate, num_iters).init()
+    }
+
+    /// Step decay after each third of the epochs
+    fn step_scheduler(&self, steps_per_epoch: usize) -> StepLrScheduler {
+        let step_size = ((self.epochs / 3).max(1) * steps_per_epoch).max(1);
+        StepLrSchedulerConfig::new(self.learning_rate, step_size)
+            .with_gamma(STEP_DECAY_GAMMA)
+            .init()
+    }
+
+    /// Noam schedule scaled by `model_size`
+    fn noam_scheduler(&self, model_size: usize) -> NoamLrScheduler {
+        NoamLrSchedulerConfig::new(self.learning_rate)
+            .with_warmup_steps(self.warmup_steps)
+            .with_model_size(model_size)
+            .init()
+    }
+}
+
+/// Checkpoint retention: the `keep_last_n` latest epochs plus the lowest validation loss
+fn checkpoint_retention<B: Backend>(keep_last_n: usize) -> ComposedCheckpointingStrategy {
+    let keep = if keep_last_n == 0 {
+        usize::MAX
+    } else {
+        keep_last_n
+    };
+
+    ComposedCheckpointingStrategy::builder()
+        .add(KeepLastNCheckpoints::new(keep))
+        .add(MetricCheckpointingStrategy::new(
+            &LossMetric::<B>::new(),
+            Aggregate::Mean,
+            Direction::Lowest,
+            Split::Valid,
+        ))
+        .build()
+}
+
+/// Parse a shuffle seed, where `none` disables shuffling
+pub fn parse_shuffle_seed(value: &str) -> Result<Option<u64>, String> {
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(|_| {
+        format!("invalid shuffle seed '{}' (expected an integer or 'none')", value)
+    })
+}
+
+/// Build the training dataloader, shuffled with `shuffle_seed` when one is set and
+/// augmenting images with `augment`
+pub fn train_dataloader<B: Backend>(
+    device: B::Device,
+    batch_size: usize,
+    shuffle_seed: Option<u64>,
+    augment: AugmentConfig,
+    dataset: MNISTDataset,
+) -> Arc<dyn DataLoader<MNISTBatch<B>>> {
+    let batcher = MNISTBatcher::<B>::new(device).with_augmentation(augment);
+    let mut builder = DataLoaderBuilder::new(batcher).batch_size(batch_size);
+    if let Some(seed) = shuffle_seed {
+        builder = builder.shuffle(seed);
+    }
+    builder.build(dataset)
+}
+
+/// Check that every item of `dataset` fits a model of `input_size` inputs and
+/// `num_classes` outputs
+fn check_dataset(
+    dataset: &MNISTDataset,
+    input_size: usize,
+    num_classes: usize,
+) -> anyhow::Result<()> {
+    for item in (0..dataset.len()).filter_map(|index| dataset.get(index)) {
+        if item.image.len() != input_size {
+            anyhow::bail!(
+                "dataset items have {} features but the model expects {} inputs",
+                item.image.len(),
+                input_size
+            );
+        }
+        if item.label >= num_classes {
+            anyhow::bail!(
+                "dataset label {} is out of range for {} classes",
+                item.label,
+                num_classes
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Result of a dry run: everything a real training run needs, minus the fit loop
+#[derive(Debug)]
+pub struct DryRunReport {
+    pub num_params: usize,
+    pub batch_size: usize,
+    pub output_shape: [usize; 2],
+    pub loss: f32,
+}
+
+/// Test-set results of a trained model
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub accuracy: f64,
+    /// `confusion_matrix[actual][predicted]` counts
+    pub confusion_matrix: Vec<Vec<usize>>,
+    /// Accuracy versus softmax confidence
+    pub calibration: CalibrationReport,
+    /// `(test index, loss)` of the highest-loss samples, hardest first
+    pub hardest_samples: Vec<(usize, f32)>,
+}
+
+impl Evaluation {
+    /// Precision and recall of each class, derived from the confusion matrix
+    pub fn class_metrics(&self) -> Vec<ClassMetrics> {
+        class_metrics(&self.confusion_matrix)
+    }
+}
+
 /// Training function
 pub fn train<B: AutodiffBackend>(
     device: B::Device,
@@ -49,127 +368,713 @@ where
     B::Device: Clone,
     B::InnerBackend: Send,
 {
+    training_config.validate()?;
     log::info!("Starting training with config: {:?}", training_config);
     log::info!("Model config: {:?}", model_config);
 
-    // Create datasets
-    let train_dataset = crate::data::MNISTDataset::train();
-    let test_dataset = crate::data::MNISTDataset::test();
+    let (train_dataset, test_dataset) = training_config.dataset.load()?;
+    check_dataset(&train_dataset, model_config.input_size, model_config.num_classes)?;
+    check_dataset(&test_dataset, model_config.input_size, model_config.num_classes)?;
+    let train_samples = train_dataset.len();
+
+    // Initialize model
+    let mut model = model_config.init::<B>(&device);
+    if let Some(every) = training_config.log_layer_stats_every {
+        model = model.with_layer_stats(every);
+    }
+    let num_params = model.num_parameters();
+    log::info!("Model parameters: {}", num_params);
+
+    // Scale the learning rate schedule by the width of the first hidden layer
+    let model_size = model_config
+        .hidden_sizes
+        .first()
+        .copied()
+        .unwrap_or(model_config.input_size);
+    let trained_model = fit::<B, _>(
+        device.clone(),
+        &training_config,
+        model,
+        model_size,
+        train_dataset,
+        test_dataset.clone(),
+    )?;
+    let output_dir = training_config.output_dir.as_path();
+    save_model_config(&model_config, output_dir)?;
+
+    if training_config.export_onnx {
+        let onnx_path = export_onnx(&trained_model, output_dir)?;
+        log::info!("ONNX model exported to: {:?}", onnx_path);
+    }
+    log::info!("Trained model parameters: {}", num_params);
+
+    // Record the run so `inference --model-card` can describe it later
+    let test_samples = test_dataset.len();
+    let evaluation = evaluate_model(&trained_model.valid(), device, test_dataset, 0);
+    let summary = TrainingSummary::new(
+        &training_config,
+        &model_config,
+        &evaluation,
+        train_samples,
+        test_samples,
+    );
+    summary.save(&output_dir.join(SUMMARY_FILE))?;
+    log::info!("Test accuracy: {:.4}", evaluation.accuracy);
+
+    Ok(())
+}
+
+/// Train the convolutional model, saving it like [`train`] does
+///
+/// Its config is saved as [`CONV_CONFIG_FILE`]. The ONNX export describes the MLP only
+/// and is skipped.
+pub fn train_cnn<B: AutodiffBackend>(
+    device: B::Device,
+    training_config: TrainingConfig,
+    conv_config: ConvModelConfig,
+) -> anyhow::Result<()>
+where
+    B::FloatTensorPrimitive: Send,
+    B::Device: Clone,
+    B::InnerBackend: Send,
+{
+    training_config.validate()?;
+    log::info!("Starting CNN training with config: {:?}", training_config);
+    log::info!("Model config: {:?}", conv_config);
+    log::info!("Model parameters: {}", conv_config.num_parameters());
+
+    if training_config.export_onnx {
+        log::warn!("ONNX export is only available for the MLP; skipping it for the CNN");
+    }
+    if training_config.log_layer_stats_every.is_some() {
+        log::warn!("Layer statistics are only logged for the MLP; skipping them for the CNN");
+    }
+
+    let (train_dataset, test_dataset) = training_config.dataset.load()?;
+    check_dataset(&train_dataset, IMAGE_SIZE * IMAGE_SIZE, conv_config.num_classes)?;
+    check_dataset(&test_dataset, IMAGE_SIZE * IMAGE_SIZE, conv_config.num_classes)?;
+    let train_samples = train_dataset.len();
 
+    let model = conv_config.init::<B>(&device);
+    let trained_model = fit::<B, _>(
+        device.clone(),
+        &training_config,
+        model,
+        conv_config.conv2_channels,
+        train_dataset,
+        test_dataset.clone(),
+    )?;
+    let output_dir = training_config.output_dir.as_path();
+    save_conv_config(&conv_config, output_dir)?;
+
+    // Record the run so `inference --model-card` can describe it later
+    let test_samples = test_dataset.len();
+    let evaluation = evaluate_model(&trained_model.valid(), device, test_dataset, 0);
+    let summary = TrainingSummary::new_cnn(
+        &training_config,
+        &conv_config,
+        &evaluation,
+        train_samples,
+        test_samples,
+    );
+    summary.save(&output_dir.join(SUMMARY_FILE))?;
+    log::info!("Test accuracy: {:.4}", evaluation.accuracy);
+
+    Ok(())
+}
+
+/// Fit `model` on `train_dataset` and save it as `final_model` in the output directory
+///
+/// `model_size` scales the Noam learning rate schedule; `test_dataset` is the validation
+/// set that drives early stopping and checkpoint selection.
+fn fit<B, M>(
+    device: B::Device,
+    training_config: &TrainingConfig,
+    model: M,
+    model_size: usize,
+    train_dataset: MNISTDataset,
+    test_dataset: MNISTDataset,
+) -> anyhow::Result<M>
+where
+    B: AutodiffBackend,
+    B::FloatTensorPrimitive: Send,
+    B::Device: Clone,
+    B::InnerBackend: Send,
+    M: AutodiffModule<B>
+        + TrainStep<MNISTBatch<B>, ClassificationOutput<B>>
+        + std::fmt::Display
+        + 'static,
+    M::InnerModule: ValidStep<MNISTBatch<B::InnerBackend>, ClassificationOutput<B::InnerBackend>>,
+{
     log::info!("Train dataset size: {}", train_dataset.len());
     log::info!("Test dataset size: {}", test_dataset.len());
+    let steps_per_epoch = train_dataset.len().div_ceil(training_config.batch_size);
 
-    // Create data loaders
-    let batcher_train = MNISTBatcher::<B>::new(device.clone());
-    let batcher_test = MNISTBatcher::<B::InnerBackend>::new(device.clone());
-
-    let dataloader_train = DataLoaderBuilder::new(batcher_train)
-        .batch_size(training_config.batch_size)
-        .shuffle(1234)
-        .build(train_dataset);
+    // Create data loaders; validation order does not affect its metrics, so only the
+    // training set is shuffled
+    let dataloader_train = train_dataloader::<B>(
+        device.clone(),
+        training_config.batch_size,
+        training_config.shuffle_seed,
+        training_config.augment,
+        train_dataset,
+    );
 
+    let batcher_test = MNISTBatcher::<B::InnerBackend>::new(device.clone());
     let dataloader_test = DataLoaderBuilder::new(batcher_test)
         .batch_size(training_config.batch_size)
-        .shuffle(1234)
         .build(test_dataset);
 
-    // Initialize model
-    let model = model_config.init::<B>(&device);
+    // Create output directory
+    let output_dir = training_config.output_dir.as_path();
+    std::fs::create_dir_all(output_dir)?;
+    log::info!("Writing training artifacts to: {:?}", output_dir);
+
+    // Like the optimizers, each scheduler is its own type and gets its own learner
+    log::info!("Learning rate scheduler: {}", training_config.scheduler);
+    let (train, test) = (dataloader_train, dataloader_test);
+    let trained_model = match training_config.scheduler {
+        Scheduler::Constant => {
+            let lr_scheduler = training_config.constant_scheduler();
+            run_learner(device, training_config, model, lr_scheduler, train, test)
+        }
+        Scheduler::Cosine => {
+            let lr_scheduler = training_config.cosine_scheduler(steps_per_epoch);
+            run_learner(device, training_config, model, lr_scheduler, train, test)
+        }
+        Scheduler::Step => {
+            let lr_scheduler = training_config.step_scheduler(steps_per_epoch);
+            run_learner(device, training_config, model, lr_scheduler, train, test)
+        }
+        Scheduler::Noam => {
+            let lr_scheduler = training_config.noam_scheduler(model_size);
+            run_learner(device, training_config, model, lr_scheduler, train, test)
+        }
+    };
 
-    // Initialize optimizer
-    let optimizer = AdamConfig::new()
-        .with_weight_decay(Some(training_config.weight_decay))
-        .init();
+    // Save final model
+    let final_model_path = output_dir.join("final_model");
+    trained_model
+        .clone()
+        .save_file(final_model_path.clone(), &CompactRecorder::new())
+        .map_err(|e| anyhow::anyhow!("Failed to save model: {}", e))?;
 
-    // Initialize learning rate scheduler
-    let lr_scheduler = NoamLrSchedulerConfig::new(training_config.learning_rate)
-        .with_warmup_steps(1000)
-        .with_model_size(model_config.hidden_size)
-        .init();
+    log::info!("Training completed! Model saved to: {:?}", final_model_path);
 
-    // Create output directory
-    let output_dir = Path::new("./burn-models");
-    std::fs::create_dir_all(output_dir)?;
+    Ok(trained_model)
+}
+
+/// Build a learner around `model` with the configured optimizer and `lr_scheduler`, and
+/// run it to completion
+fn run_learner<B, M, S>(
+    device: B::Device,
+    training_config: &TrainingConfig,
+    model: M,
+    lr_scheduler: S,
+    dataloader_train: Arc<dyn DataLoader<MNISTBatch<B>>>,
+    dataloader_test: Arc<dyn DataLoader<MNISTBatch<B::InnerBackend>>>,
+) -> M
+where
+    B: AutodiffBackend,
+    B::FloatTensorPrimitive: Send,
+    B::Device: Clone,
+    B::InnerBackend: Send,
+    M: AutodiffModule<B>
+        + TrainStep<MNISTBatch<B>, ClassificationOutput<B>>
+        + std::fmt::Display
+        + 'static,
+    M::InnerModule: ValidStep<MNISTBatch<B::InnerBackend>, ClassificationOutput<B::InnerBackend>>,
+    S: LrScheduler + 'static,
+{
+    let output_dir = training_config.output_dir.as_path();
 
     // Create learner
-    let learner = LearnerBuilder::new(output_dir)
+    let mut builder = LearnerBuilder::new(output_dir)
         .metric_train_numeric(AccuracyMetric::new())
         .metric_valid_numeric(AccuracyMetric::new())
         .metric_train_numeric(LossMetric::new())
         .metric_valid_numeric(LossMetric::new())
         .with_file_checkpointer(CompactRecorder::new())
+        .with_checkpointing_strategy(checkpoint_retention::<B>(training_config.keep_last_n))
         .early_stopping(MetricEarlyStoppingStrategy::new::<LossMetric<B>>(
             StoppingCondition::NoImprovementSince {
                 n_epochs: training_config.early_stopping_patience,
             },
-        ))
+        ));
+
+    if training_config.progress {
+        builder = builder.renderer(ProgressRenderer::new());
+    }
+
+    let builder = builder
         .devices(vec![device])
         .num_epochs(training_config.epochs)
-        .summary()
-        .build(model, optimizer, lr_scheduler);
+        .summary();
 
-    // Start training
-    log::info!("Starting training loop...");
-    let trained_model = learner.fit(dataloader_train, dataloader_test);
+    // Each optimizer is its own type, so each arm builds and runs its own learner
+    log::info!("Starting training loop with {}...", training_config.optimizer);
+    let weight_decay = training_config.weight_decay_config();
+    match training_config.optimizer {
+        Optimizer::Adam => builder
+            .build(
+                model,
+                AdamConfig::new().with_weight_decay(weight_decay).init(),
+                lr_scheduler,
+            )
+            .fit(dataloader_train, dataloader_test),
+        Optimizer::AdamW => builder
+            .build(
+                model,
+                AdamWConfig::new()
+                    .with_weight_decay(training_config.weight_decay as f32)
+                    .init(),
+                lr_scheduler,
+            )
+            .fit(dataloader_train, dataloader_test),
+        Optimizer::Sgd => builder
+            .build(
+                model,
+                SgdConfig::new().with_weight_decay(weight_decay).init(),
+                lr_scheduler,
+            )
+            .fit(dataloader_train, dataloader_test),
+        Optimizer::RmsProp => builder
+            .build(
+                model,
+                RmsPropConfig::new().with_weight_decay(weight_decay).init(),
+                lr_scheduler,
+            )
+            .fit(dataloader_train, dataloader_test),
+    }
+}
 
-    // Save final model
-    let final_model_path = output_dir.join("final_model");
-    trained_model
-        .save_file(final_model_path.clone(), &CompactRecorder::new())
-        .map_err(|e| anyhow::anyhow!("Failed to save model: {}", e))?;
+/// Write `model_config` as `config.json` in `output_dir` so inference can rebuild the model
+pub fn save_model_config(
+    model_config: &ModelConfig,
+    output_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let path = output_dir.join(MODEL_CONFIG_FILE);
+    model_config
+        .save(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to save model config to {:?}: {}", path, e))?;
+    Ok(path)
+}
 
-    log::info!("Training completed! Model saved to: {:?}", final_model_path);
+/// Write `conv_config` as [`CONV_CONFIG_FILE`] in `output_dir` so inference can rebuild
+/// the CNN
+pub fn save_conv_config(
+    conv_config: &ConvModelConfig,
+    output_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let path = output_dir.join(CONV_CONFIG_FILE);
+    conv_config
+        .save(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to save CNN config to {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+/// Architecture of the CNN saved at `model_path`
+///
+/// CNNs trained before their config was saved fall back to the default architecture with
+/// `num_classes` classes.
+pub fn load_conv_config(model_path: &Path, num_classes: usize) -> anyhow::Result<ConvModelConfig> {
+    let path = model_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(CONV_CONFIG_FILE);
+
+    if !path.exists() {
+        return Ok(ConvModelConfig {
+            num_classes,
+            ..ConvModelConfig::new()
+        });
+    }
+    ConvModelConfig::load(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read CNN config {:?}: {}", path, e))
+}
+
+/// Architecture of the model saved at `model_path`
+///
+/// The config saved next to the model at training time wins; sizes requested by the
+/// caller only log a warning when they disagree. Models trained before configs were
+/// saved fall back to the requested sizes, or the default architecture.
+pub fn load_model_config(
+    model_path: &Path,
+    hidden_sizes: Option<&[usize]>,
+    input_size: Option<usize>,
+    num_classes: Option<usize>,
+) -> anyhow::Result<ModelConfig> {
+    let path = model_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(MODEL_CONFIG_FILE);
+
+    if !path.exists() {
+        let mut config = ModelConfig::new();
+        if let Some(hidden_sizes) = hidden_sizes {
+            config.hidden_sizes = hidden_sizes.to_vec();
+        }
+        if let Some(input_size) = input_size {
+            config.input_size = input_size;
+        }
+        if let Some(num_classes) = num_classes {
+            config.num_classes = num_classes;
+        }
+        return Ok(config);
+    }
+
+    let config = ModelConfig::load(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read model config {:?}: {}", path, e))?;
+    if let Some(requested) = hidden_sizes.filter(|&sizes| sizes != config.hidden_sizes) {
+        log::warn!(
+            "--hidden-size {:?} does not match the saved config {:?}; using the saved config",
+            requested,
+            config.hidden_sizes
+        );
+    }
+    if let Some(requested) = input_size.filter(|&size| size != config.input_size) {
+        log::warn!(
+            "--input-size {} does not match the saved config {}; using the saved config",
+            requested,
+            config.input_size
+        );
+    }
+    if let Some(requested) = num_classes.filter(|&classes| classes != config.num_classes) {
+        log::warn!(
+            "--num-classes {} does not match the saved config {}; using the saved config",
+            requested,
+            config.num_classes
+        );
+    }
+    Ok(config)
+}
+
+/// Write `model` as `model.onnx` in `output_dir` and return the file path
+pub fn export_onnx<B: Backend>(model: &Model<B>, output_dir: &Path) -> anyhow::Result<PathBuf> {
+    let path = output_dir.join(ONNX_FILE);
+    crate::onnx::export_model(model, &path)?;
+    Ok(path)
+}
+
+/// Validate the configuration and run a single forward/backward pass without training
+///
+/// Builds the model and one batch from the training set so that shape mismatches and
+/// backend initialization problems surface in seconds. Nothing is written to disk.
+pub fn dry_run<B: AutodiffBackend>(
+    device: B::Device,
+    training_config: &TrainingConfig,
+    model_config: &ModelConfig,
+) -> anyhow::Result<DryRunReport> {
+    training_config.validate()?;
+    model_config.validate()?;
+
+    log::info!("Dry run with config: {:?}", training_config);
+    log::info!("Model config: {:?}", model_config);
+
+    let (train_dataset, _) = training_config.dataset.load()?;
+    check_dataset(&train_dataset, model_config.input_size, model_config.num_classes)?;
 
+    let model = model_config.init::<B>(&device);
+    let num_params = model.num_parameters();
+    dry_run_step(device, training_config, &model, num_params, train_dataset)
+}
+
+/// [`dry_run`] for the convolutional model
+pub fn dry_run_cnn<B: AutodiffBackend>(
+    device: B::Device,
+    training_config: &TrainingConfig,
+    conv_config: &ConvModelConfig,
+) -> anyhow::Result<DryRunReport> {
+    training_config.validate()?;
+    conv_config.validate()?;
+
+    log::info!("Dry run with config: {:?}", training_config);
+    log::info!("Model config: {:?}", conv_config);
+
+    let (train_dataset, _) = training_config.dataset.load()?;
+    check_dataset(&train_dataset, IMAGE_SIZE * IMAGE_SIZE, conv_config.num_classes)?;
+
+    let model = conv_config.init::<B>(&device);
+    let num_params = conv_config.num_parameters();
+    dry_run_step(device, training_config, &model, num_params, train_dataset)
+}
+
+/// Run one training step of `model` on the head of `train_dataset`
+fn dry_run_step<B, M>(
+    device: B::Device,
+    training_config: &TrainingConfig,
+    model: &M,
+    num_params: usize,
+    train_dataset: MNISTDataset,
+) -> anyhow::Result<DryRunReport>
+where
+    B: AutodiffBackend,
+    M: TrainStep<MNISTBatch<B>, ClassificationOutput<B>>,
+{
+    // Build a single batch from the head of the training set
+    let items = (0..training_config.batch_size.min(train_dataset.len()))
+        .filter_map(|index| train_dataset.get(index))
+        .collect::<Vec<_>>();
+    let batch = MNISTBatcher::<B>::new(device).batch(items);
+    let batch_size = batch.targets.dims()[0];
+
+    // One forward + backward pass exercises the autodiff graph end to end
+    let output = TrainStep::step(model, batch);
+    let output_shape = output.item.output.dims();
+    let loss = output.item.loss.into_scalar().elem::<f32>();
+
+    if !loss.is_finite() {
+        anyhow::bail!("Dry run produced a non-finite loss: {}", loss);
+    }
+
+    log::info!("Dry run completed: loss {:.4}, {} parameters", loss, num_params);
+
+    Ok(DryRunReport {
+        num_params,
+        batch_size,
+        output_shape,
+        loss,
+    })
+}
+
+/// Build the `arch` model and load its trained weights from `model_path`
+///
+/// The CNN uses the [`ConvModelConfig`] saved next to it, or the default one with the class
+/// count of `model_config`; see [`load_conv_config`].
+/// The saved weight shapes are checked against the config before they are applied, so a
+/// model trained with other sizes fails with a description of the mismatch.
+pub fn load_classifier<B: Backend>(
+    arch: Architecture,
+    model_config: &ModelConfig,
+    model_path: &Path,
+    device: &B::Device,
+) -> anyhow::Result<Box<dyn Classifier<B>>> {
+    log::info!("Loading {} model from: {:?}", arch, model_path);
+    let recorder = CompactRecorder::new();
+    let load_error = |e| anyhow::anyhow!("Failed to load model: {}", e);
+
+    Ok(match arch {
+        Architecture::Mlp => {
+            let record: ModelRecord<B> = Recorder::<B>::load(
+                &recorder,
+                model_path.to_path_buf(),
+                device,
+            )
+            .map_err(load_error)?;
+            let found = record
+                .layers
+                .iter()
+                .map(|linear| linear.weight.val().dims())
+                .collect::<Vec<_>>();
+            check_layer_shapes(model_path, model_config, &found)?;
+            Box::new(model_config.init::<B>(device).load_record(record))
+        }
+        Architecture::Cnn => {
+            let config = load_conv_config(model_path, model_config.num_classes)?;
+            let record: ConvModelRecord<B> = Recorder::<B>::load(
+                &recorder,
+                model_path.to_path_buf(),
+                device,
+            )
+            .map_err(load_error)?;
+            let [_, classes] = record.classifier.weight.val().dims();
+            if classes != config.num_classes {
+                anyhow::bail!(
+                    "Model weights in {:?} don't match the model config: the saved model has {} \
+                     classes, but num_classes is {}. Pass the --num-classes it was trained with",
+                    model_path,
+                    classes,
+                    config.num_classes
+                );
+            }
+            Box::new(config.init::<B>(device).load_record(record))
+        }
+    })
+}
+
+/// Fail with a description of the first saved layer whose `[d_input, d_output]` weight
+/// shape doesn't match `config`
+fn check_layer_shapes(
+    model_path: &Path,
+    config: &ModelConfig,
+    found: &[[usize; 2]],
+) -> anyhow::Result<()> {
+    let expected = config.layer_dims();
+    let last = expected.len().saturating_sub(1);
+    let mismatch = if found.len() != expected.len() {
+        Some(format!(
+            "the saved model has {} linear layers, but hidden_sizes {:?} needs {}",
+            found.len(),
+            config.hidden_sizes,
+            expected.len()
+        ))
+    } else {
+        expected
+            .iter()
+            .zip(found)
+            .enumerate()
+            .find(|(_, (&(d_input, d_output), &[f_input, f_output]))| {
+                (d_input, d_output) != (f_input, f_output)
+            })
+            .map(|(i, (&(d_input, d_output), &[f_input, f_output]))| {
+                if i == 0 && f_input != d_input {
+                    format!(
+                        "the saved model takes {} inputs, but input_size is {}",
+                        f_input, d_input
+                    )
+                } else if i == last && f_output != d_output {
+                    format!(
+                        "the saved model has {} classes, but num_classes is {}",
+                        f_output, d_output
+                    )
+                } else {
+                    format!(
+                        "layer {} of the saved model is {}x{}, but hidden_sizes {:?} expects {}x{}",
+                        i + 1,
+                        f_input,
+                        f_output,
+                        config.hidden_sizes,
+                        d_input,
+                        d_output
+                    )
+                }
+            })
+    };
+
+    match mismatch {
+        Some(mismatch) => anyhow::bail!(
+            "Model weights in {:?} don't match the model config: {}. Pass the --input-size, \
+             --hidden-size and --num-classes it was trained with, or keep the config.json \
+             saved next to it",
+            model_path,
+            mismatch
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Fail unless the model reads flattened 28x28 images
+///
+/// Evaluation, benchmarks and image classification feed the model MNIST-sized images, so
+/// an MLP trained on CSV rows of another width can only score its own rows.
+pub fn check_image_input(arch: Architecture, model_config: &ModelConfig) -> anyhow::Result<()> {
+    let pixels = IMAGE_SIZE * IMAGE_SIZE;
+    if arch == Architecture::Mlp && model_config.input_size != pixels {
+        anyhow::bail!(
+            "the model takes {} inputs, but evaluation, benchmarks and --input-dir feed it \
+             {}x{} images ({} values); score rows of its own dataset with --input-file instead",
+            model_config.input_size,
+            IMAGE_SIZE,
+            IMAGE_SIZE,
+            pixels
+        );
+    }
     Ok(())
 }
 
-/// Evaluation function
+/// Evaluate `model_path` on the MNIST test set
+///
+/// Fails with [`check_image_input`]'s error for an MLP trained on other data.
 pub fn evaluate<B: Backend>(
     device: B::Device,
+    arch: Architecture,
     model_config: ModelConfig,
     model_path: &Path,
-) -> anyhow::Result<f64>
+    top_losses: usize,
+) -> anyhow::Result<Evaluation>
 where
     B::FloatTensorPrimitive: Send,
 {
-    log::info!("Loading model from: {:?}", model_path);
+    check_image_input(arch, &model_config)?;
+    let model = load_classifier::<B>(arch, &model_config, model_path, &device)?;
 
-    // Load model
-    let model = model_config
-        .init::<B>(&device)
-        .load_file(model_path, &CompactRecorder::new(), &device)
-        .map_err(|e| anyhow::anyhow!("Failed to load model: {}", e))?;
-
-    // Create test dataset and dataloader
     let test_dataset = crate::data::MNISTDataset::test();
-    let batcher_test = MNISTBatcher::<B>::new(device);
-    let dataloader_test = DataLoaderBuilder::new(batcher_test)
+    let evaluation = evaluate_model(model.as_ref(), device, test_dataset, top_losses);
+    log::info!("Test accuracy: {:.4}", evaluation.accuracy);
+    log::info!("Expected calibration error: {:.4}", evaluation.calibration.ece);
+
+    Ok(evaluation)
+}
+
+/// Compute accuracy, the confusion matrix and calibration of `model` over `dataset`
+///
+/// The `top_losses` samples with the highest cross-entropy loss are kept for inspection.
+pub fn evaluate_model<B: Backend, M: Classifier<B> + ?Sized>(
+    model: &M,
+    device: B::Device,
+    dataset: crate::data::MNISTDataset,
+    top_losses: usize,
+) -> Evaluation {
+    let num_classes = model.num_classes();
+    let batcher = MNISTBatcher::<B>::new(device);
+    let dataloader = DataLoaderBuilder::new(batcher)
         .batch_size(32)
-        .build(test_dataset);
+        .build(dataset);
 
-    // Evaluate
-    let mut correct = 0;
-    let mut total = 0;
+    let mut targets = Vec::new();
+    let mut predictions = Vec::new();
+    let mut confidences = Vec::new();
+    let mut losses = Vec::new();
 
-    for batch in dataloader_test {
+    for batch in dataloader.iter() {
+        if top_losses > 0 {
+            losses.extend(
+                model
+                    .forward_losses(batch.clone())
+                    .into_data()
+                    .convert::<f32>()
+                    .value,
+            );
+        }
         let output = model.forward(batch.images);
-        let predictions = output.argmax(1);
-        let targets = batch.targets;
+        confidences.extend(
+            softmax(output.clone(), 1)
+                .max_dim(1)
+                .into_data()
+                .convert::<f32>()
+                .value,
+        );
+        predictions.extend(
+            output
+                .argmax(1)
+                .into_data()
+                .convert::<i64>()
+                .value
+                .into_iter()
+                .map(|class| class as usize),
+        );
+        targets.extend(
+            batch
+                .targets
+                .into_data()
+                .convert::<i64>()
+                .value
+                .into_iter()
+                .map(|class| class as usize),
+        );
+    }
 
-        let batch_correct = predictions
-            .equal(targets)
-            .int()
-            .sum()
-            .into_scalar();
+    let correct = targets
+        .iter()
+        .zip(&predictions)
+        .map(|(actual, predicted)| actual == predicted)
+        .collect::<Vec<_>>();
+    let num_correct = correct.iter().filter(|&&is_correct| is_correct).count();
 
-        correct += batch_correct as i32;
-        total += batch.targets.shape()[0];
+    Evaluation {
+        accuracy: num_correct as f64 / targets.len().max(1) as f64,
+        confusion_matrix: confusion_matrix(&targets, &predictions, num_classes),
+        calibration: calibration_report(&confidences, &correct, DEFAULT_CALIBRATION_BINS),
+        hardest_samples: hardest_samples(&losses, top_losses),
     }
+}
 
-    let accuracy = correct as f64 / total as f64;
-    log::info!("Test accuracy: {:.4}", accuracy);
-
-    Ok(accuracy)
+/// Indices and values of the `n` largest losses, largest first
+pub fn hardest_samples(losses: &[f32], n: usize) -> Vec<(usize, f32)> {
+    let mut ranked = losses.iter().copied().enumerate().collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(n);
+    ranked
 }
 
 #[cfg(test)]
@@ -187,10 +1092,173 @@ mod tests {
         assert!(config.learning_rate > 0.0);
     }
 
+    #[test]
+    fn test_optimizer_parsing() {
+        for optimizer in Optimizer::ALL {
+            assert_eq!(optimizer.to_string().parse::<Optimizer>(), Ok(optimizer));
+        }
+        assert_eq!("AdamW".parse::<Optimizer>(), Ok(Optimizer::AdamW));
+        assert!("lbfgs".parse::<Optimizer>().is_err());
+    }
+
+    /// Apply one update from `optimizer` to `model` on a random batch
+    fn optimizer_step<O>(mut optimizer: O, model: Model<TestBackend>) -> Model<TestBackend>
+    where
+        O: burn::optim::Optimizer<Model<TestBackend>, TestBackend>,
+    {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let batch = MNISTBatch {
+            images: burn::tensor::Tensor::random(
+                [8, 784],
+                burn::tensor::Distribution::Normal(0.0, 1.0),
+                &device,
+            ),
+            targets: burn::tensor::Tensor::from_ints([0, 1, 2, 3, 4, 5, 6, 7], &device),
+        };
+        let output = TrainStep::step(&model, batch);
+        optimizer.step(1e-2, model, output.grads)
+    }
+
+    /// Logger that keeps every message so tests can assert on what was logged
+    struct CaptureLogger(std::sync::Mutex<Vec<String>>);
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGS: CaptureLogger = CaptureLogger(std::sync::Mutex::new(Vec::new()));
+
+    #[test]
+    fn test_layer_stats_are_logged_on_the_first_step() {
+        let _ = log::set_logger(&LOGS);
+        log::set_max_level(log::LevelFilter::Info);
+
+        let model = ModelConfig {
+            hidden_sizes: vec![16],
+            ..ModelConfig::new()
+        }
+        .init::<TestBackend>(&burn_ndarray::NdArrayDevice::Cpu)
+        .with_layer_stats(100);
+        let step = |model: &Model<TestBackend>| {
+            let device = burn_ndarray::NdArrayDevice::Cpu;
+            let batch = MNISTBatch {
+                images: burn::tensor::Tensor::random(
+                    [4, 784],
+                    burn::tensor::Distribution::Normal(0.0, 1.0),
+                    &device,
+                ),
+                targets: burn::tensor::Tensor::from_ints([0, 1, 2, 3], &device),
+            };
+            TrainStep::step(model, batch);
+        };
+        let stats_lines = || {
+            LOGS.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|line| line.contains(" layer ") && line.contains("weight norm"))
+                .count()
+        };
+
+        step(&model);
+        // One line per layer: the hidden layer and the output layer
+        assert_eq!(stats_lines(), 2);
+        // The next 99 steps are not due
+        step(&model.clone());
+        assert_eq!(stats_lines(), 2);
+    }
+
+    #[test]
+    fn test_each_optimizer_steps() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let config = TrainingConfig::default();
+        let weight_decay = config.weight_decay_config();
+
+        for optimizer in Optimizer::ALL {
+            let model = ModelConfig::new().init::<TestBackend>(&device);
+            let before = model.layers()[0].weight.val().into_data();
+
+            let model = match optimizer {
+                Optimizer::Adam => optimizer_step(
+                    AdamConfig::new().with_weight_decay(weight_decay.clone()).init(),
+                    model,
+                ),
+                Optimizer::AdamW => optimizer_step(
+                    AdamWConfig::new()
+                        .with_weight_decay(config.weight_decay as f32)
+                        .init(),
+                    model,
+                ),
+                Optimizer::Sgd => optimizer_step(
+                    SgdConfig::new().with_weight_decay(weight_decay.clone()).init(),
+                    model,
+                ),
+                Optimizer::RmsProp => optimizer_step(
+                    RmsPropConfig::new().with_weight_decay(weight_decay.clone()).init(),
+                    model,
+                ),
+            };
+
+            let after = model.layers()[0].weight.val().into_data();
+            assert_ne!(before, after, "{} did not update the weights", optimizer);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_parsing() {
+        for scheduler in Scheduler::ALL {
+            assert_eq!(scheduler.to_string().parse::<Scheduler>(), Ok(scheduler));
+        }
+        assert_eq!("Cosine".parse::<Scheduler>(), Ok(Scheduler::Cosine));
+        assert!("cyclic".parse::<Scheduler>().is_err());
+    }
+
+    #[test]
+    fn test_each_scheduler_yields_positive_initial_lr() {
+        let config = TrainingConfig {
+            epochs: 3,
+            warmup_steps: 10,
+            ..Default::default()
+        };
+        let steps_per_epoch = 4;
+
+        for scheduler in Scheduler::ALL {
+            let initial_lr = match scheduler {
+                Scheduler::Constant => config.constant_scheduler().step(),
+                Scheduler::Cosine => config.cosine_scheduler(steps_per_epoch).step(),
+                Scheduler::Step => config.step_scheduler(steps_per_epoch).step(),
+                Scheduler::Noam => config.noam_scheduler(128).step(),
+            };
+            assert!(initial_lr > 0.0, "{} starts at {}", scheduler, initial_lr);
+        }
+
+        assert_eq!(config.constant_scheduler().step(), config.learning_rate);
+
+        // The step scheduler decays after one third of the 3 epochs
+        let mut step = config.step_scheduler(steps_per_epoch);
+        let rates = (0..=steps_per_epoch).map(|_| step.step()).collect::<Vec<_>>();
+        assert_eq!(rates[0], config.learning_rate);
+        assert!((rates[steps_per_epoch] - config.learning_rate * STEP_DECAY_GAMMA).abs() < 1e-12);
+
+        let no_warmup = TrainingConfig {
+            warmup_steps: 0,
+            ..Default::default()
+        };
+        assert!(no_warmup.validate().is_err());
+    }
+
     #[test]
     #[ignore] // This is a longer running test
     fn test_training_integration() {
-        env_logger::init();
+        let _ = env_logger::try_init();
         
         let device = burn_ndarray::NdArrayDevice::Cpu;
         let model_config = ModelConfig::new();
@@ -203,4 +1271,255 @@ mod tests {
         let result = train::<TestBackend>(device, training_config, model_config);
         assert!(result.is_ok());
     }
+
+    #[test]
+    #[ignore] // This is a longer running test
+    fn test_training_output_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_dir = temp_dir.path().join("experiment");
+        let default_model = TrainingConfig::default().output_dir.join("final_model.mpk");
+        let default_existed = default_model.exists();
+
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let training_config = TrainingConfig {
+            epochs: 1,
+            batch_size: 64,
+            output_dir: output_dir.clone(),
+            ..Default::default()
+        };
+
+        train::<TestBackend>(device, training_config, ModelConfig::new()).unwrap();
+
+        assert!(output_dir.join("final_model.mpk").exists());
+        assert_eq!(default_model.exists(), default_existed);
+    }
+
+    #[test]
+    #[ignore] // This is a longer running test
+    fn test_checkpoint_retention() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output_dir = temp_dir.path().join("retention");
+
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let training_config = TrainingConfig {
+            epochs: 5,
+            batch_size: 64,
+            output_dir: output_dir.clone(),
+            keep_last_n: 2,
+            ..Default::default()
+        };
+
+        train::<TestBackend>(device, training_config, ModelConfig::new()).unwrap();
+
+        let mut epochs = std::fs::read_dir(output_dir.join("checkpoint"))
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                name.strip_prefix("model-")?.split('.').next()?.parse::<usize>().ok()
+            })
+            .collect::<Vec<_>>();
+        epochs.sort_unstable();
+        // The two latest epochs, plus the best epoch when it is older than those
+        match epochs.as_slice() {
+            [4, 5] => {}
+            [best, 4, 5] => assert!(*best < 4),
+            kept => panic!("kept checkpoints of epochs {:?}", kept),
+        }
+    }
+
+    #[test]
+    fn test_model_config_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let model_path = temp_dir.path().join("final_model");
+        let saved = ModelConfig {
+            input_size: 784,
+            hidden_sizes: vec![256, 64],
+            num_classes: 10,
+            dropout: 0.3,
+        };
+
+        // Without a saved config the requested sizes are used
+        let fallback = load_model_config(&model_path, Some(&[32]), None, Some(5)).unwrap();
+        assert_eq!(fallback.hidden_sizes, [32]);
+        assert_eq!(fallback.num_classes, 5);
+
+        save_model_config(&saved, temp_dir.path()).unwrap();
+        let loaded = load_model_config(&model_path, Some(&[128, 128]), Some(28), Some(5)).unwrap();
+
+        assert_eq!(loaded.input_size, saved.input_size);
+        assert_eq!(loaded.hidden_sizes, saved.hidden_sizes);
+        assert_eq!(loaded.num_classes, saved.num_classes);
+        assert_eq!(loaded.dropout, saved.dropout);
+    }
+
+    #[test]
+    fn test_conv_config_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let model_path = temp_dir.path().join("final_model");
+        let saved = ConvModelConfig {
+            conv1_channels: 8,
+            conv2_channels: 12,
+            num_classes: 4,
+            dropout: 0.1,
+        };
+
+        // Without a saved config the default architecture is used
+        let fallback = load_conv_config(&model_path, 5).unwrap();
+        assert_eq!(fallback.conv2_channels, ConvModelConfig::new().conv2_channels);
+        assert_eq!(fallback.num_classes, 5);
+
+        save_conv_config(&saved, temp_dir.path()).unwrap();
+        let loaded = load_conv_config(&model_path, 5).unwrap();
+        assert_eq!(loaded.conv1_channels, saved.conv1_channels);
+        assert_eq!(loaded.conv2_channels, saved.conv2_channels);
+        assert_eq!(loaded.num_classes, saved.num_classes);
+
+        // The MLP config next to it is untouched
+        assert!(!temp_dir.path().join(MODEL_CONFIG_FILE).exists());
+    }
+
+    #[test]
+    fn test_mismatched_num_classes_is_a_descriptive_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let model_path = temp_dir.path().join("final_model");
+        let device = Default::default();
+        ModelConfig::new()
+            .init::<NdArray<f32>>(&device)
+            .save_file(model_path.clone(), &CompactRecorder::new())
+            .unwrap();
+
+        let config = ModelConfig {
+            num_classes: 5,
+            ..ModelConfig::new()
+        };
+        let error =
+            match load_classifier::<NdArray<f32>>(Architecture::Mlp, &config, &model_path, &device)
+            {
+                Ok(_) => panic!("loading 10-class weights as 5 classes should fail"),
+                Err(error) => error.to_string(),
+            };
+        assert!(
+            error.contains("the saved model has 10 classes, but num_classes is 5"),
+            "{}",
+            error
+        );
+        assert!(error.contains("--num-classes"), "{}", error);
+
+        let config = ModelConfig::new();
+        let loaded = load_classifier::<NdArray<f32>>(Architecture::Mlp, &config, &model_path, &device);
+        assert!(loaded.is_ok());
+    }
+
+    #[test]
+    fn test_export_onnx() {
+        use crate::onnx::{ModelProto, INPUT_NAME, OUTPUT_NAME};
+        use prost::Message;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model = ModelConfig::new().init::<NdArray<f32>>(&device);
+
+        let path = export_onnx(&model, temp_dir.path()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(path, temp_dir.path().join(ONNX_FILE));
+        assert!(!bytes.is_empty());
+
+        let onnx = ModelProto::decode(bytes.as_slice()).unwrap();
+        let graph = onnx.graph.unwrap();
+        let ops = graph.node.iter().map(|node| node.op_type.as_str()).collect::<Vec<_>>();
+        assert_eq!(ops, ["Gemm", "Relu", "Gemm", "Relu", "Gemm"]);
+        assert_eq!(graph.input[0].name, INPUT_NAME);
+        assert_eq!(graph.output[0].name, OUTPUT_NAME);
+
+        let weights = graph
+            .initializer
+            .iter()
+            .map(|tensor| tensor.raw_data.len() / 4)
+            .sum::<usize>();
+        assert_eq!(weights, model.num_parameters());
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model_config = ModelConfig::new();
+        let training_config = TrainingConfig {
+            batch_size: 8,
+            ..Default::default()
+        };
+
+        let final_model = training_config.output_dir.join("final_model.mpk");
+        let existed_before = final_model.exists();
+
+        let start = std::time::Instant::now();
+        let report = dry_run::<TestBackend>(device, &training_config, &model_config).unwrap();
+
+        assert!(start.elapsed().as_secs() < 30);
+        assert_eq!(report.batch_size, 8);
+        assert_eq!(report.output_shape, [8, model_config.num_classes]);
+        assert!(report.num_params > 0);
+        assert!(report.loss.is_finite());
+        assert_eq!(final_model.exists(), existed_before);
+    }
+
+    #[test]
+    fn test_dry_run_on_csv_dataset() {
+        use crate::data::{CsvConfig, Normalization};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("points.csv");
+        let rows = (0..20)
+            .map(|i| format!("{},{},{}\n", i, 20 - i, i % 3))
+            .collect::<String>();
+        std::fs::write(&path, format!("x,y,label\n{}", rows)).unwrap();
+
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let model_config = ModelConfig {
+            input_size: 2,
+            hidden_sizes: vec![8],
+            num_classes: 3,
+            dropout: 0.0,
+        };
+        let training_config = TrainingConfig {
+            batch_size: 4,
+            dataset: DatasetSource::Csv(CsvConfig {
+                normalization: Normalization::MinMax,
+                ..CsvConfig::new(&path)
+            }),
+            ..Default::default()
+        };
+
+        let report = dry_run::<TestBackend>(device, &training_config, &model_config).unwrap();
+        assert_eq!(report.output_shape, [4, 3]);
+
+        // The default model expects 784 inputs, not the 2 columns of the file
+        let mismatched = dry_run::<TestBackend>(device, &training_config, &ModelConfig::new());
+        assert!(mismatched.is_err());
+    }
+
+    #[test]
+    fn test_image_input_check() {
+        let csv_model = ModelConfig {
+            input_size: 2,
+            ..ModelConfig::new()
+        };
+        let error = check_image_input(Architecture::Mlp, &csv_model).unwrap_err();
+        assert!(error.to_string().contains("--input-file"), "{}", error);
+        assert!(check_image_input(Architecture::Mlp, &ModelConfig::new()).is_ok());
+        // The CNN reads 28x28 images whatever the MLP config says
+        assert!(check_image_input(Architecture::Cnn, &csv_model).is_ok());
+    }
+
+    #[test]
+    fn test_dry_run_rejects_invalid_config() {
+        let device = burn_ndarray::NdArrayDevice::Cpu;
+        let training_config = TrainingConfig {
+            batch_size: 0,
+            ..Default::default()
+        };
+
+        let result = dry_run::<TestBackend>(device, &training_config, &ModelConfig::new());
+        assert!(result.is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,350 @@
+/*!
+Layered configuration shared by the template binaries
+
+Every setting resolves through the same precedence chain:
+built-in defaults < config file < environment variables < command-line flags.
+
+The config file is a flat TOML table, or a JSON object when its name ends in
+`.json` or its contents start with `{`, whose keys match the flag names
+(`batch-size` and `batch_size` are equivalent); environment variables use the
+upper-cased key with a per-binary prefix, e.g. `PHI_MAX_TOKENS`.
+*/
+
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Config-file and environment layers that sit between defaults and CLI flags
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    file: HashMap<String, String>,
+    env: HashMap<String, String>,
+    env_prefix: String,
+}
+
+impl ConfigLayers {
+    /// Build layers from explicit values; `env` is keyed by full variable name
+    pub fn new(
+        file: HashMap<String, String>,
+        env: HashMap<String, String>,
+        env_prefix: &str,
+    ) -> Self {
+        Self {
+            file: file
+                .into_iter()
+                .map(|(k, v)| (normalize_key(&k), v))
+                .collect(),
+            env,
+            env_prefix: env_prefix.to_string(),
+        }
+    }
+
+    /// Read the optional config file and snapshot the prefixed environment variables
+    pub fn load(config_path: Option<&Path>, env_prefix: &str) -> Result<Self> {
+        let file = match config_path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config file {:?}", path))?;
+                let is_json = path.extension().is_some_and(|ext| ext == "json")
+                    || contents.trim_start().starts_with('{');
+                let parsed = if is_json {
+                    parse_config_file(&contents)
+                } else {
+                    parse_toml_config_file(&contents)
+                };
+                parsed.with_context(|| format!("Invalid config file {:?}", path))?
+            }
+            None => HashMap::new(),
+        };
+        let env = std::env::vars()
+            .filter(|(key, _)| key.starts_with(env_prefix))
+            .collect();
+
+        Ok(Self::new(file, env, env_prefix))
+    }
+
+    /// Fail on config-file keys that are not in `known`, which are most likely typos
+    pub fn check_keys<'a>(&self, known: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let known: Vec<String> = known.into_iter().map(normalize_key).collect();
+        let mut unknown: Vec<&str> = self
+            .file
+            .keys()
+            .filter(|key| !known.contains(key))
+            .map(String::as_str)
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+
+        unknown.sort_unstable();
+        bail!(
+            "Unknown config key{} {} (expected one of: {})",
+            if unknown.len() == 1 { "" } else { "s" },
+            unknown
+                .iter()
+                .map(|key| format!("'{}'", key))
+                .collect::<Vec<_>>()
+                .join(", "),
+            known.join(", ")
+        )
+    }
+
+    /// Resolve `key` using its `FromStr` implementation for file and environment values
+    pub fn resolve<T>(&self, key: &str, cli: Option<T>, default: T) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.resolve_with(key, cli, default, |value| {
+            value.parse().map_err(|e: T::Err| e.to_string())
+        })
+    }
+
+    /// Resolve `key`, validating file and environment values with `parse`
+    ///
+    /// CLI values are taken as-is since clap has already validated them.
+    pub fn resolve_with<T>(
+        &self,
+        key: &str,
+        cli: Option<T>,
+        default: T,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T> {
+        if let Some(value) = cli {
+            return Ok(value);
+        }
+
+        let env_var = self.env_var(key);
+        if let Some(value) = self.env.get(&env_var) {
+            return parse(value).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid value '{}' for {} from {}: {}",
+                    value,
+                    key,
+                    env_var,
+                    e
+                )
+            });
+        }
+
+        if let Some(value) = self.file.get(&normalize_key(key)) {
+            return parse(value).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid value '{}' for {} from config file: {}",
+                    value,
+                    key,
+                    e
+                )
+            });
+        }
+
+        Ok(default)
+    }
+
+    /// Resolve a clap argument, using its `default_value` as the bottom layer
+    pub fn resolve_arg<T>(&self, matches: &ArgMatches, id: &str) -> Result<T>
+    where
+        T: FromStr + Clone + Send + Sync + 'static,
+        T::Err: Display,
+    {
+        let (cli, default) = split_arg::<T>(matches, id)?;
+        self.resolve(id, cli, default)
+    }
+
+    /// Like [`ConfigLayers::resolve_arg`] with a custom parser for file and environment values
+    pub fn resolve_arg_with<T>(
+        &self,
+        matches: &ArgMatches,
+        id: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let (cli, default) = split_arg::<T>(matches, id)?;
+        self.resolve_with(id, cli, default, parse)
+    }
+
+    /// Resolve a clap argument without a default, which stays `None` unless some layer sets it
+    pub fn resolve_optional_arg<T>(&self, matches: &ArgMatches, id: &str) -> Result<Option<T>>
+    where
+        T: FromStr + Clone + Send + Sync + 'static,
+        T::Err: Display,
+    {
+        let cli = matches.get_one::<T>(id).cloned().map(Some);
+        self.resolve_with(id, cli, None, |value| {
+            value.parse().map(Some).map_err(|e: T::Err| e.to_string())
+        })
+    }
+
+    fn env_var(&self, key: &str) -> String {
+        format!("{}{}", self.env_prefix, normalize_key(key).to_uppercase())
+    }
+}
+
+/// Separate an explicitly passed flag from clap's default value
+fn split_arg<T>(matches: &ArgMatches, id: &str) -> Result<(Option<T>, T)>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let value = matches
+        .get_one::<T>(id)
+        .cloned()
+        .with_context(|| format!("Argument '{}' has no default value", id))?;
+
+    if matches.value_source(id) == Some(ValueSource::CommandLine) {
+        Ok((Some(value.clone()), value))
+    } else {
+        Ok((None, value))
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.trim().replace('-', "_").to_lowercase()
+}
+
+/// Parse a flat JSON object into string values
+fn parse_config_file(contents: &str) -> Result<HashMap<String, String>> {
+    let object: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(contents).context("Config file must be a JSON object")?;
+
+    let mut values = HashMap::new();
+    for (key, value) in object {
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            _ => bail!("Config key '{}' must be a string, number or boolean", key),
+        };
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+/// Parse a flat TOML table into string values
+fn parse_toml_config_file(contents: &str) -> Result<HashMap<String, String>> {
+    let table: toml::Table = toml::from_str(contents).context("Config file must be valid TOML")?;
+
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(n) => n.to_string(),
+            toml::Value::Float(n) => n.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            _ => bail!("Config key '{}' must be a string, number or boolean", key),
+        };
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const PREFIX: &str = "APP_";
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_precedence() {
+        let file = values(&[("batch-size", "16")]);
+        let env = values(&[("APP_BATCH_SIZE", "64")]);
+
+        let layers = ConfigLayers::new(file.clone(), env, PREFIX);
+        assert_eq!(
+            layers.resolve("batch-size", Some(128usize), 32).unwrap(),
+            128
+        );
+        assert_eq!(layers.resolve("batch-size", None, 32usize).unwrap(), 64);
+
+        let layers = ConfigLayers::new(file, HashMap::new(), PREFIX);
+        assert_eq!(layers.resolve("batch-size", None, 32usize).unwrap(), 16);
+
+        let layers = ConfigLayers::default();
+        assert_eq!(layers.resolve("batch-size", None, 32usize).unwrap(), 32);
+    }
+
+    #[test]
+    fn test_invalid_layer_values_are_reported() {
+        let layers = ConfigLayers::new(
+            values(&[("max-tokens", "lots")]),
+            values(&[("APP_EPOCHS", "-3")]),
+            PREFIX,
+        );
+
+        let err = layers
+            .resolve::<usize>("max_tokens", None, 512)
+            .unwrap_err();
+        assert!(err.to_string().contains("config file"));
+
+        let err = layers.resolve::<usize>("epochs", None, 10).unwrap_err();
+        assert!(err.to_string().contains("APP_EPOCHS"));
+    }
+
+    #[test]
+    fn test_load_json_config_file() {
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        write!(
+            file,
+            r#"{{"max-tokens": 128, "backend": "wgpu", "coding_mode": true}}"#
+        )
+        .unwrap();
+
+        let layers = ConfigLayers::load(Some(file.path()), "APP_TEST_UNSET_PREFIX_").unwrap();
+        assert_eq!(layers.resolve("max_tokens", None, 512usize).unwrap(), 128);
+        assert_eq!(
+            layers
+                .resolve("backend", None, "ndarray".to_string())
+                .unwrap(),
+            "wgpu"
+        );
+
+        // JSON is recognised by its contents when the name has no `.json` extension
+        let mut unnamed = tempfile::NamedTempFile::new().unwrap();
+        write!(unnamed, r#"{{"epochs": 3, "learning_rate": 0.01}}"#).unwrap();
+        let layers = ConfigLayers::load(Some(unnamed.path()), "APP_TEST_UNSET_PREFIX_").unwrap();
+        assert_eq!(layers.resolve("epochs", None, 10usize).unwrap(), 3);
+        assert_eq!(
+            layers.resolve("learning-rate", None, 0.001f64).unwrap(),
+            0.01
+        );
+
+        let mut nested = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        write!(nested, r#"{{"sampling": {{"top_p": 0.9}}}}"#).unwrap();
+        assert!(ConfigLayers::load(Some(nested.path()), PREFIX).is_err());
+    }
+
+    #[test]
+    fn test_load_toml_config_file() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            "temperature = 0.2\nmax-tokens = 128\nsystem = \"Be brief.\""
+        )
+        .unwrap();
+
+        let layers = ConfigLayers::load(Some(file.path()), "APP_TEST_UNSET_PREFIX_").unwrap();
+        assert_eq!(layers.resolve("temperature", None, 0.7f32).unwrap(), 0.2);
+        assert_eq!(layers.resolve("max_tokens", None, 512usize).unwrap(), 128);
+        assert!(layers
+            .check_keys(["temperature", "max_tokens", "system"])
+            .is_ok());
+
+        let err = layers
+            .check_keys(["temperature", "max_tokens"])
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown config key 'system'"));
+    }
+}
@@ -0,0 +1,277 @@
+/*!
+Generation Benchmark for Phi Models
+
+Loads a Phi model through the model cache, streams a fixed prompt for a number of
+iterations and reports throughput (tokens/sec), time-to-first-token and the peak
+resident memory of the process. Warmup iterations run first and are not measured.
+*/
+
+use anyhow::{bail, Context, Result};
+use burn_phi_local_llm::telemetry::LogFormat;
+use burn_phi_local_llm::{
+    compiled_backends, format_bytes, ChatSession, GenerationTiming, PhiError, PhiInference,
+    PhiModel, PhiModelManager, SamplingConfig,
+};
+use clap::Parser;
+use futures::StreamExt;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Prompt generated in every iteration, so runs are comparable across models and backends
+const BENCHMARK_PROMPT: &str = "Write a function that checks whether a number is prime.";
+
+#[derive(Parser)]
+#[command(name = "phi-benchmark")]
+#[command(about = "Measure generation throughput and latency of a Phi model")]
+#[command(version = "1.0.0")]
+struct Args {
+    /// Which Phi model to benchmark (phi1, phi15, phi2, phi3, phi35, phi4, phi4-mini)
+    #[arg(short, long, default_value = "phi3", value_parser = parse_model)]
+    model: PhiModel,
+
+    /// Backend to run inference on
+    #[arg(short, long, default_value = "ndarray", value_parser = ["ndarray", "cuda", "metal", "wgpu"])]
+    backend: String,
+
+    /// Measured generations
+    #[arg(short, long, default_value = "5", value_parser = clap::value_parser!(u32).range(1..))]
+    iterations: u32,
+
+    /// Unmeasured generations run before the measured ones
+    #[arg(short, long, default_value = "1")]
+    warmup: u32,
+
+    /// Tokens generated per iteration
+    #[arg(long, default_value = "128", value_parser = burn_phi_local_llm::sampling::parse_max_tokens)]
+    max_tokens: usize,
+
+    /// Print the summary as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+
+    /// Log line format (text, or json for log ingestion)
+    #[arg(long, default_value = "text")]
+    log_format: LogFormat,
+}
+
+fn parse_model(name: &str) -> Result<PhiModel, String> {
+    PhiModel::from_short_name(name).ok_or_else(|| {
+        format!(
+            "unknown model '{}' (expected phi1, phi15, phi2, phi3, phi35, phi4 or phi4-mini)",
+            name
+        )
+    })
+}
+
+/// Timing of one measured generation
+#[derive(Debug, Clone, Serialize)]
+struct Sample {
+    tokens: usize,
+    ttft_ms: f64,
+    total_ms: f64,
+}
+
+impl Sample {
+    fn tokens_per_sec(&self) -> f64 {
+        self.tokens as f64 / (self.total_ms / 1000.0)
+    }
+}
+
+/// Aggregated results of a benchmark run
+#[derive(Debug, Serialize)]
+struct BenchmarkReport {
+    model: String,
+    backend: String,
+    iterations: usize,
+    tokens_per_iteration: f64,
+    tokens_per_sec: f64,
+    ttft_ms: f64,
+    total_ms: f64,
+    /// Peak resident memory of the process in bytes, when the platform reports it
+    peak_memory: Option<u64>,
+    samples: Vec<Sample>,
+}
+
+impl BenchmarkReport {
+    fn new(model: &PhiModel, backend: &str, samples: Vec<Sample>) -> Self {
+        let count = samples.len().max(1) as f64;
+        let mean = |value: fn(&Sample) -> f64| samples.iter().map(value).sum::<f64>() / count;
+
+        Self {
+            model: model.model_name().to_string(),
+            backend: backend.to_string(),
+            iterations: samples.len(),
+            tokens_per_iteration: mean(|sample| sample.tokens as f64),
+            tokens_per_sec: mean(Sample::tokens_per_sec),
+            ttft_ms: mean(|sample| sample.ttft_ms),
+            total_ms: mean(|sample| sample.total_ms),
+            peak_memory: peak_memory(),
+            samples,
+        }
+    }
+
+    /// Summary table printed without `--json`
+    fn format_table(&self) -> String {
+        let peak_memory = self
+            .peak_memory
+            .map(format_bytes)
+            .unwrap_or_else(|| "unknown".to_string());
+        let rows = [
+            ("Model", self.model.clone()),
+            ("Backend", self.backend.clone()),
+            ("Iterations", self.iterations.to_string()),
+            (
+                "Tokens/iteration",
+                format!("{:.1}", self.tokens_per_iteration),
+            ),
+            ("Tokens/sec", format!("{:.2}", self.tokens_per_sec)),
+            ("Time to first token", format!("{:.1} ms", self.ttft_ms)),
+            ("Total time", format!("{:.1} ms", self.total_ms)),
+            ("Peak memory", peak_memory),
+        ];
+
+        rows.iter()
+            .map(|(name, value)| format!("{:<20} {}\n", name, value))
+            .collect()
+    }
+}
+
+/// Stream one generation of at most `max_tokens` chunks and time it
+async fn run_iteration(session: &mut ChatSession, max_tokens: usize) -> Result<Sample> {
+    let mut timing = GenerationTiming::start();
+    let mut stream = Box::pin(session.generate_stream(BENCHMARK_PROMPT).take(max_tokens));
+    while let Some(chunk) = stream.next().await {
+        chunk?;
+        timing.record_chunk();
+    }
+
+    let (Some(ttft), Some(total)) = (timing.ttft(), timing.total()) else {
+        bail!("generation produced no tokens");
+    };
+    let as_ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    Ok(Sample {
+        tokens: timing.chunks(),
+        ttft_ms: as_ms(ttft),
+        total_ms: as_ms(total),
+    })
+}
+
+/// Run `warmup` unmeasured and then `iterations` measured generations
+async fn run_benchmark(
+    session: &mut ChatSession,
+    backend: &str,
+    iterations: u32,
+    warmup: u32,
+) -> Result<BenchmarkReport> {
+    let max_tokens = session.sampling.max_tokens;
+
+    for _ in 0..warmup {
+        run_iteration(session, max_tokens).await?;
+    }
+
+    let mut samples = Vec::new();
+    for iteration in 0..iterations {
+        let sample = run_iteration(session, max_tokens)
+            .await
+            .with_context(|| format!("Iteration {} failed", iteration + 1))?;
+        tracing::info!(
+            "Iteration {}: {} tokens, {:.2} tokens/sec",
+            iteration + 1,
+            sample.tokens,
+            sample.tokens_per_sec()
+        );
+        samples.push(sample);
+    }
+
+    Ok(BenchmarkReport::new(&session.model, backend, samples))
+}
+
+/// Peak resident set size of this process
+#[cfg(target_os = "linux")]
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_memory() -> Option<u64> {
+    None
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    burn_phi_local_llm::init_tracing_with_format(args.log_format);
+
+    let compiled = compiled_backends()
+        .into_iter()
+        .any(|(name, compiled)| compiled && name == args.backend);
+    if !compiled {
+        return Err(PhiError::BackendUnavailable {
+            backend: args.backend.clone(),
+            reason: format!(
+                "not compiled into this build (build with --features {})",
+                args.backend
+            ),
+        }
+        .into());
+    }
+
+    let manager = PhiModelManager::default()?;
+    let inference = manager
+        .load_with_repair(&args.model, |path| async move {
+            PhiInference::load(&path).await
+        })
+        .await
+        .context("Failed to load model")?;
+    tracing::info!("Model ready at: {:?}", inference.model_path());
+
+    // History would grow the prompt between iterations, so none is kept
+    let mut session = ChatSession::new(args.model, None, false, false)
+        .with_history_turns(0)
+        .with_sampling(SamplingConfig {
+            max_tokens: args.max_tokens,
+            ..SamplingConfig::default()
+        });
+
+    let report = run_benchmark(&mut session, &args.backend, args.iterations, args.warmup).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.format_table());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_benchmark_reports_throughput() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_history_turns(0)
+            .with_sampling(SamplingConfig {
+                max_tokens: 8,
+                ..SamplingConfig::default()
+            });
+
+        let report = run_benchmark(&mut session, "ndarray", 2, 0).await.unwrap();
+
+        assert_eq!(report.iterations, 2);
+        assert!(report.tokens_per_sec.is_finite() && report.tokens_per_sec > 0.0);
+        assert!(report.samples.iter().all(|sample| sample.tokens <= 8));
+        assert!(report.ttft_ms <= report.total_ms);
+        assert!(report.format_table().contains("Tokens/sec"));
+    }
+}
@@ -6,28 +6,71 @@ Microsoft Phi models, showcasing the power of small language models for on-devic
 */
 
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
-use std::io::{self, Write};
-use tracing::{info, warn};
-use burn_phi_local_llm::{PhiModel, PhiModelManager, PhiInference};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use futures::{Stream, StreamExt};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use burn_phi_local_llm::metrics::{self, MetricsBackend};
+use burn_phi_local_llm::server::ApiState;
+use burn_phi_local_llm::telemetry::LogFormat;
+use burn_phi_local_llm::{
+    check_system_requirements, config, format_backend_list, format_model_list,
+    format_model_status, sampling, server, should_show_banner, stop, telemetry, ChatSession,
+    ConfigLayers, ContentBlocked, ContentFilter, Generation, GenerationTiming, PhiError,
+    PhiInference, PhiModel, PhiModelManager, Quantization, RegexDenyFilter, SamplingConfig,
+    SystemInfo,
+};
 
 #[derive(Parser)]
 #[command(name = "phi-chat")]
 #[command(about = "Interactive chat with Microsoft Phi models")]
 #[command(version = "1.0.0")]
 struct Args {
-    /// Which Phi model to use
-    #[arg(short, long, default_value = "phi3")]
-    model: PhiModelChoice,
+    /// Which Phi model to use; when omitted, a terminal session asks with a menu and
+    /// anything else uses phi3
+    #[arg(short, long)]
+    model: Option<PhiModelChoice>,
+
+    /// Also answer every prompt with this model, printing both replies labeled by model
+    #[arg(long, value_name = "MODEL")]
+    compare: Option<PhiModelChoice>,
 
     /// Maximum tokens to generate
-    #[arg(short, long, default_value = "512")]
+    #[arg(long, default_value = "512", value_parser = sampling::parse_max_tokens)]
     max_tokens: usize,
 
-    /// Temperature for sampling (0.0 to 1.0)
-    #[arg(short, long, default_value = "0.7")]
+    /// Temperature for sampling (0.0 to 2.0)
+    #[arg(short, long, default_value = "0.7", value_parser = sampling::parse_temperature)]
     temperature: f32,
 
+    /// Nucleus sampling cutoff (0.0 exclusive to 1.0)
+    #[arg(long, default_value = "0.9", value_parser = sampling::parse_top_p)]
+    top_p: f32,
+
+    /// Only sample from the K most likely tokens (at least 1)
+    #[arg(long, default_value = "40", value_parser = sampling::parse_top_k)]
+    top_k: usize,
+
+    /// Divide the logits of recently generated tokens by this (1.0 disables it)
+    #[arg(long, default_value = "1.1", value_parser = sampling::parse_repeat_penalty)]
+    repeat_penalty: f32,
+
+    /// Never repeat an n-gram of this many tokens (0 disables it)
+    #[arg(long, default_value = "0")]
+    no_repeat_ngram_size: usize,
+
+    /// Seed for the sampling RNG; the same prompt, seed and settings give the same reply
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Stop generating when this sequence is produced; repeatable, `\n` and `\t` are expanded
+    #[arg(long, value_name = "SEQ", value_parser = stop::parse_stop_sequence)]
+    stop: Vec<String>,
+
     /// System prompt to set context
     #[arg(short, long)]
     system: Option<String>,
@@ -36,6 +79,10 @@ struct Args {
     #[arg(short, long, default_value = "ndarray")]
     backend: String,
 
+    /// Precision of the model weights (f32, f16, int8, int4)
+    #[arg(long, default_value = "f16")]
+    quantization: Quantization,
+
     /// Enable coding assistant mode
     #[arg(long)]
     coding_mode: bool,
@@ -43,10 +90,193 @@ struct Args {
     /// Enable math assistant mode
     #[arg(long)]
     math_mode: bool,
+
+    /// Give up on a generation that runs longer than this many seconds
+    #[arg(long, default_value = "120")]
+    timeout_secs: u64,
+
+    /// Number of past conversation turns to keep in the prompt
+    #[arg(long, default_value = "10")]
+    history_turns: usize,
+
+    /// Where to send inference metrics (datadog, prometheus, log)
+    #[arg(long, default_value = "log")]
+    metrics_backend: MetricsBackend,
+
+    /// OTLP/HTTP collector endpoint for exporting trace spans (e.g. http://localhost:4318/v1/traces)
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Log line format (text, or json for log ingestion)
+    #[arg(long, default_value = "text")]
+    log_format: LogFormat,
+
+    /// List the compiled-in and hardware-available backends, then exit
+    #[arg(long)]
+    list_backends: bool,
+
+    /// List every Phi model with its cached size and whether it fits this machine, then exit
+    #[arg(long, visible_alias = "list")]
+    list_models: bool,
+
+    /// Show cache status, size, validity (hashes checked against the download manifest) and
+    /// fit of every model, then exit
+    #[arg(long)]
+    status: bool,
+
+    /// Skip the throwaway warmup generation after a model loads, preloaded ones included
+    #[arg(long)]
+    no_warmup: bool,
+
+    /// Check that the model fits this machine and is cached, print a readiness report and
+    /// exit (nonzero if not ready) without downloading, loading or generating
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Check this machine, fetch the smallest model if needed and run one short generation,
+    /// printing pass/fail per step; exits nonzero if any step fails
+    #[arg(long)]
+    selftest: bool,
+
+    /// Load the model even if it looks too large for this machine's memory or disk
+    #[arg(long)]
+    force: bool,
+
+    /// Run prompts from a file (one per line, or blocks separated by `---`) and exit; use `-` for stdin
+    #[arg(long)]
+    prompt_file: Option<PathBuf>,
+
+    /// Include per-token log-probabilities in the output
+    #[arg(long)]
+    logprobs: bool,
+
+    /// Print each response as a JSON object instead of plain text
+    #[arg(long)]
+    json: bool,
+
+    /// Skip the startup banner (also skipped with --json or when stdout is not a terminal)
+    #[arg(long)]
+    no_banner: bool,
+
+    /// Hide the live tokens/sec indicator and the stats printed after each response
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Serve the REST API instead of the interactive chat
+    #[arg(long)]
+    api_mode: bool,
+
+    /// Address the API server binds to
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port the API server listens on
+    #[arg(long, default_value = "8080")]
+    port: u16,
+
+    /// Seconds the API server lets in-flight requests finish after SIGTERM before exiting
+    #[arg(long, default_value = "30")]
+    shutdown_grace_secs: u64,
+
+    /// Download, load and warm up these models before the API server reports ready
+    #[arg(long, value_name = "MODEL", num_args = 1.., requires = "api_mode")]
+    preload: Vec<PhiModelChoice>,
+
+    /// File of regex patterns, one per line, that block matching prompts and replies
+    #[arg(long)]
+    deny_file: Option<PathBuf>,
+
+    /// Save the conversation as a Markdown transcript here when the chat ends
+    #[arg(long, value_name = "PATH")]
+    export_md: Option<PathBuf>,
+
+    /// Config file (TOML, or JSON when named *.json; default ~/.config/vibecode/phi-chat.toml);
+    /// its values are overridden by PHI_* environment variables and flags
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+/// Config file read from `~/.config/vibecode/` when `--config` is not given
+const CONFIG_FILE_NAME: &str = "phi-chat.toml";
+
+/// Model used when none is given and none is picked from the menu
+const DEFAULT_MODEL: &str = "phi3";
+
+/// Transcript written by `/export` without a path when `--export-md` is not given
+const DEFAULT_EXPORT_PATH: &str = "phi-chat.md";
+
+/// Prompt answered by the `--selftest` generation
+const SELFTEST_PROMPT: &str = "Say hello.";
+
+/// Token budget of the `--selftest` generation
+const SELFTEST_MAX_TOKENS: usize = 16;
+
+impl Args {
+    /// Parse flags, then layer the config file and environment underneath them
+    fn load() -> Result<Self> {
+        Self::from_matches(&Args::command().get_matches())
+    }
+
+    /// Resolve every layered setting from already-parsed flags
+    fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let mut args = Args::from_arg_matches(matches)?;
+        let config_path = args.config.clone().or_else(|| {
+            config::user_config_path(CONFIG_FILE_NAME).filter(|path| path.exists())
+        });
+        let layers = ConfigLayers::load(config_path.as_deref(), config::ENV_PREFIX)?;
+        layers
+            .check_keys(Args::command().get_arguments().map(|arg| arg.get_id().as_str()))
+            .with_context(|| format!("Invalid config file {:?}", config_path.unwrap_or_default()))?;
+
+        let cli_model = matches.get_one::<PhiModelChoice>("model").cloned().map(Some);
+        args.model = layers.resolve_with("model", cli_model, None, |value| {
+            PhiModelChoice::from_str(value, true).map(Some)
+        })?;
+        let cli_compare = matches.get_one::<PhiModelChoice>("compare").cloned().map(Some);
+        args.compare = layers.resolve_with("compare", cli_compare, None, |value| {
+            PhiModelChoice::from_str(value, true).map(Some)
+        })?;
+        args.max_tokens =
+            layers.resolve_arg_with(matches, "max_tokens", sampling::parse_max_tokens)?;
+        args.temperature =
+            layers.resolve_arg_with(matches, "temperature", sampling::parse_temperature)?;
+        args.top_p = layers.resolve_arg_with(matches, "top_p", sampling::parse_top_p)?;
+        args.top_k = layers.resolve_arg_with(matches, "top_k", sampling::parse_top_k)?;
+        args.repeat_penalty =
+            layers.resolve_arg_with(matches, "repeat_penalty", sampling::parse_repeat_penalty)?;
+        args.no_repeat_ngram_size = layers.resolve_arg(matches, "no_repeat_ngram_size")?;
+        args.seed = layers.resolve_optional_arg(matches, "seed")?;
+        args.backend = layers.resolve_arg(matches, "backend")?;
+        args.quantization = layers.resolve_arg(matches, "quantization")?;
+        args.history_turns = layers.resolve_arg(matches, "history_turns")?;
+        args.timeout_secs = layers.resolve_arg(matches, "timeout_secs")?;
+        args.system = layers.resolve_optional_arg(matches, "system")?;
+        args.coding_mode = layers.resolve_arg(matches, "coding_mode")?;
+        args.math_mode = layers.resolve_arg(matches, "math_mode")?;
+        args.logprobs = layers.resolve_arg(matches, "logprobs")?;
+        args.no_warmup = layers.resolve_arg(matches, "no_warmup")?;
+        args.dry_run = layers.resolve_arg(matches, "dry_run")?;
+        args.selftest = layers.resolve_arg(matches, "selftest")?;
+        args.force = layers.resolve_arg(matches, "force")?;
+        args.no_banner = layers.resolve_arg(matches, "no_banner")?;
+        args.quiet = layers.resolve_arg(matches, "quiet")?;
+        args.metrics_backend = layers.resolve_arg(matches, "metrics_backend")?;
+        args.otlp_endpoint = layers.resolve_optional_arg(matches, "otlp_endpoint")?;
+        args.deny_file = layers.resolve_optional_arg(matches, "deny_file")?;
+        args.export_md = layers.resolve_optional_arg(matches, "export_md")?;
+        args.log_format = layers.resolve_arg(matches, "log_format")?;
+        args.host = layers.resolve_arg(matches, "host")?;
+        args.port = layers.resolve_arg(matches, "port")?;
+        args.shutdown_grace_secs = layers.resolve_arg(matches, "shutdown_grace_secs")?;
+
+        Ok(args)
+    }
 }
 
 #[derive(Clone, ValueEnum)]
 enum PhiModelChoice {
+    Phi1,
+    Phi15,
     Phi2,
     Phi3,
     Phi35,
@@ -56,84 +286,223 @@ enum PhiModelChoice {
 
 impl From<PhiModelChoice> for PhiModel {
     fn from(choice: PhiModelChoice) -> Self {
-        match choice {
-            PhiModelChoice::Phi2 => PhiModel::Phi2 {
-                parameters: "2.7B".to_string(),
-                context_length: 2048,
-                specialization: vec![
-                    "language comprehension".to_string(),
-                    "reasoning".to_string(),
-                ],
-            },
-            PhiModelChoice::Phi3 => PhiModel::Phi3 {
-                parameters: "3.8B".to_string(),
-                context_length: 4096,
-                specialization: vec![
-                    "coding".to_string(),
-                    "math".to_string(),
-                    "reasoning".to_string(),
-                ],
-            },
-            PhiModelChoice::Phi35 => PhiModel::Phi3_5 {
-                parameters: "3.8B".to_string(),
-                context_length: 131072,
-                specialization: vec![
-                    "multilingual".to_string(),
-                    "general performance".to_string(),
-                ],
-            },
-            PhiModelChoice::Phi4 => PhiModel::Phi4 {
-                parameters: "14B".to_string(),
-                context_length: 16384,
-                specialization: vec![
-                    "complex reasoning".to_string(),
-                    "mathematics".to_string(),
-                    "logic".to_string(),
-                ],
-            },
-            PhiModelChoice::Phi4Mini => PhiModel::Phi4Mini {
-                parameters: "3.8B".to_string(),
-                context_length: 8192,
-                specialization: vec![
-                    "instruction following".to_string(),
-                    "reasoning".to_string(),
-                ],
-            },
-        }
+        // Every choice is named after the short name of the model it selects
+        let value = choice.to_possible_value().expect("no model choice is skipped");
+        PhiModel::from_short_name(value.get_name())
+            .unwrap_or_else(|| panic!("no model is named {}", value.get_name()))
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter("info")
-        .init();
+    let args = Args::load()?;
+    let _telemetry = telemetry::init("info", args.otlp_endpoint.as_deref(), args.log_format)?;
 
-    let args = Args::parse();
-    let model: PhiModel = args.model.into();
+    // Diagnostics short-circuit before any model is resolved or downloaded
+    if args.list_backends || args.list_models || args.status {
+        let system = check_system_requirements()?;
+        if args.list_backends {
+            print!("{}", format_backend_list(&system));
+        }
+        let statuses = if args.list_models || args.status {
+            PhiModelManager::default()?
+                .with_quantization(args.quantization)
+                .status(&system)
+                .await
+        } else {
+            Vec::new()
+        };
+        if args.list_models {
+            print!("{}", format_model_list(&statuses));
+        }
+        if args.status {
+            print!("{}", format_model_status(&statuses));
+        }
+        return Ok(());
+    }
 
-    println!("🔥 VibeCode Phi Chat Interface");
-    println!("================================================");
-    println!("{}", model.display_info());
-    println!("================================================");
+    if args.selftest {
+        let manager = PhiModelManager::default()?.with_quantization(args.quantization);
+        let steps = run_selftest(check_system_requirements(), &manager).await;
+        let (report, passed) = format_selftest(&steps);
+        print!("{}", report);
+        if !passed {
+            anyhow::bail!("Self-test failed");
+        }
+        return Ok(());
+    }
+
+    let timeout = Duration::from_secs(args.timeout_secs);
+    let filter: Option<Arc<dyn ContentFilter>> = match &args.deny_file {
+        Some(path) => {
+            let filter = RegexDenyFilter::from_file(path)?;
+            info!("Loaded {} deny patterns from {:?}", filter.len(), path);
+            Some(Arc::new(filter))
+        }
+        None => None,
+    };
+
+    // A dry run of the API server checks its preloaded models below instead of serving
+    if args.api_mode && !args.dry_run {
+        let preload = preload_models(&args)?;
+        let listener = tokio::net::TcpListener::bind((args.host.as_str(), args.port))
+            .await
+            .with_context(|| format!("Failed to bind {}:{}", args.host, args.port))?;
+        let state = ApiState {
+            sampling: SamplingConfig {
+                temperature: args.temperature,
+                top_p: args.top_p,
+                top_k: args.top_k,
+                max_tokens: args.max_tokens,
+                return_logprobs: args.logprobs,
+                repeat_penalty: args.repeat_penalty,
+                no_repeat_ngram_size: args.no_repeat_ngram_size,
+            },
+            stop_sequences: args.stop,
+            timeout: Some(timeout),
+            filter,
+            seed: args.seed,
+            skip_warmup: args.no_warmup,
+            ..ApiState::default()
+        };
+        // Bind before preloading so health and readiness probes get 503 instead of a
+        // refused connection
+        state.preloading.store(!preload.is_empty(), Ordering::SeqCst);
+        let serving = tokio::spawn(server::serve_with_shutdown(
+            listener,
+            state.clone(),
+            server::shutdown_signal(),
+            Duration::from_secs(args.shutdown_grace_secs),
+        ));
 
-    if args.coding_mode {
-        println!("💻 Coding Assistant Mode Enabled");
+        if !preload.is_empty() {
+            let manager = PhiModelManager::default()?.with_quantization(args.quantization);
+            if let Err(e) = server::preload(&state, &manager, &preload).await {
+                error!("Preload failed, shutting down: {:#}", e);
+                serving.abort();
+                return Err(e);
+            }
+        }
+        info!("API server ready");
+        return serving.await?;
     }
-    if args.math_mode {
-        println!("🧮 Math Assistant Mode Enabled");
+
+    let system = check_system_requirements()?;
+    let model: PhiModel = match args.model.clone() {
+        Some(choice) => choice.into(),
+        None if io::stdin().is_terminal() && args.prompt_file.is_none() && !args.dry_run => {
+            let options: Vec<_> = PhiModel::available_models()
+                .into_iter()
+                .map(|model| {
+                    let (fits, _) = system.can_run_model(&model, args.quantization);
+                    (model, fits)
+                })
+                .collect();
+            pick_model(&mut io::stdin().lock(), &mut io::stdout(), &options)?
+        }
+        None => PhiModel::from_short_name(DEFAULT_MODEL).context("Unknown default model")?,
+    };
+
+    let mut models = vec![model];
+    if let Some(compare) = args.compare.clone() {
+        models.push(compare.into());
+    }
+    if args.api_mode {
+        // Only a dry run gets here, which checks the models the server would preload
+        models = preload_models(&args)?;
+    } else {
+        check_max_tokens(args.max_tokens, &models)?;
     }
-    println!();
 
-    // Initialize model manager and ensure model is available
-    let model_manager = PhiModelManager::default();
-    let model_path = model_manager.ensure_model(&model).await
-        .context("Failed to ensure model availability")?;
+    if args.dry_run {
+        let manager = PhiModelManager::default()?.with_quantization(args.quantization);
+        let mut checks = Vec::with_capacity(models.len());
+        for model in models {
+            checks.push(dry_run_check(&system, &manager, model).await);
+        }
+        let (report, ready) = format_dry_run(&checks, args.quantization);
+        print!("{}", report);
+        if !ready {
+            anyhow::bail!("Not ready: the dry run found blocking issues");
+        }
+        return Ok(());
+    }
+
+    if should_show_banner(args.no_banner, args.json, io::stdout().is_terminal()) {
+        println!("🔥 VibeCode Phi Chat Interface");
+        println!("================================================");
+        for model in &models {
+            println!("{}", model.display_info());
+            println!("================================================");
+        }
+
+        if args.coding_mode {
+            println!("💻 Coding Assistant Mode Enabled");
+        }
+        if args.math_mode {
+            println!("🧮 Math Assistant Mode Enabled");
+        }
+        println!();
+    }
+
+    // Initialize model manager and ensure every model is available
+    let model_manager = PhiModelManager::default()?.with_quantization(args.quantization);
+    let mut sessions = Vec::with_capacity(models.len());
+    for model in models {
+        let issues = guard_model_fits(&system, &model, args.quantization, args.force)?;
+        if !issues.is_empty() {
+            warn!("Loading {} despite: {}", model.model_name(), issues.join("; "));
+            eprintln!(
+                "\n⚠️  WARNING: --force is loading {}, which may not fit here:\n{}\n",
+                model.model_name(),
+                format_issues(&issues)
+            );
+        }
+
+        let inference = model_manager
+            .load_with_repair(&model, |path| async move { PhiInference::open(&path).await })
+            .await
+            .with_context(|| format!("Failed to load model {}", model.model_name()))?;
+
+        info!("Model ready at: {:?}", inference.model_path());
+
+        let mut session =
+            new_session(model, &args, filter.clone(), timeout)?.with_inference(inference);
+
+        // Run one throwaway generation so the first real request doesn't pay for lazy
+        // initialization
+        if !args.no_warmup {
+            let elapsed = session.warmup().await.context("Warmup generation failed")?;
+            info!("Warmup generation completed in {:?}", elapsed);
+        }
+        sessions.push(session);
+    }
+    let compare = sessions.len() > 1;
 
-    info!("Model ready at: {:?}", model_path);
+    if let Some(prompt_file) = &args.prompt_file {
+        let prompts = parse_prompts(&read_prompt_source(prompt_file)?);
+        info!("Running {} prompts from {:?}", prompts.len(), prompt_file);
 
-    // Initialize inference engine (placeholder - would integrate with actual Burn inference)
-    let mut chat_session = ChatSession::new(model, args.system, args.coding_mode, args.math_mode);
+        let mut responses = Vec::with_capacity(sessions.len());
+        for session in &mut sessions {
+            responses.push(run_batch(session, &prompts).await?);
+        }
+        for (index, prompt) in prompts.iter().enumerate() {
+            if !args.json {
+                println!("[{}/{}] You: {}", index + 1, prompts.len(), prompt);
+            }
+            for (session, responses) in sessions.iter().zip(&responses) {
+                let response = &responses[index];
+                if args.json {
+                    let model = compare.then(|| session.model.short_name());
+                    println!("{}", format_json_response(prompt, response, model)?);
+                } else {
+                    println!("{}: {}\n", reply_label(session, compare), response.text);
+                }
+            }
+        }
+        return Ok(());
+    }
 
     println!("Type 'exit' to quit, 'help' for commands, or start chatting!");
     println!();
@@ -143,8 +512,14 @@ async fn main() -> Result<()> {
         print!("You: ");
         io::stdout().flush()?;
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        let Some(input) = read_input_line().await? else {
+            if let Some(path) = &args.export_md {
+                export_transcripts(&sessions, path)?;
+            }
+            println!("\nGoodbye! 👋");
+            // The blocked stdin reader cannot be cancelled, so don't wait for it on shutdown
+            std::process::exit(0);
+        };
         let input = input.trim();
 
         if input.is_empty() {
@@ -162,209 +537,1020 @@ async fn main() -> Result<()> {
             }
             "clear" => {
                 print!("\x1B[2J\x1B[1;1H"); // Clear screen
+                for session in &mut sessions {
+                    session.reset_cache();
+                }
                 continue;
             }
             "info" => {
-                println!("\n{}\n", chat_session.model.display_info());
+                for session in &sessions {
+                    let usage = session.context_usage();
+                    println!("\n{}", session.model.display_info());
+                    println!("⚙️  Sampling: {}", session.sampling);
+                    println!(
+                        "🧠 Context: ~{} of {} tokens used, {} reserved for the response\n",
+                        usage.prompt_tokens, usage.context_length, usage.max_tokens
+                    );
+                }
+                continue;
+            }
+            "params" => {
+                println!("\n⚙️  Sampling: {}\n", sessions[0].sampling);
                 continue;
             }
             _ => {}
         }
 
-        // Generate response (placeholder implementation)
-        print!("Phi: ");
-        let response = chat_session.generate_response(input, args.max_tokens, args.temperature).await?;
-        println!("{}\n", response);
+        if let Some(path) = input.strip_prefix("/export") {
+            let path = match path.trim() {
+                "" => args.export_md.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_EXPORT_PATH)),
+                path => PathBuf::from(path),
+            };
+            match export_transcripts(&sessions, &path) {
+                Ok(()) => println!("📝 Transcript saved to {:?}\n", path),
+                Err(e) => println!("❌ {:#}\n", e),
+            }
+            continue;
+        }
+
+        if input.starts_with("set ") {
+            // Sessions share their sampling settings, so the change applies to all of them
+            let mut sampling = sessions[0].sampling.clone();
+            match apply_set_command(&mut sampling, input) {
+                Ok(()) => {
+                    println!("✅ Sampling: {}\n", sampling);
+                    for session in &mut sessions {
+                        session.sampling = sampling.clone();
+                    }
+                }
+                Err(e) => println!("❌ {}\n", e),
+            }
+            continue;
+        }
+
+        // A blocked prompt or reply is reported and the chat carries on
+        if args.json {
+            for session in &mut sessions {
+                let model = compare.then(|| session.model.short_name());
+                match session.generate(input).await {
+                    Ok(response) => {
+                        println!("{}\n", format_json_response(input, &response, model)?)
+                    }
+                    Err(e) if e.is::<ContentBlocked>() => println!("🚫 {}\n", e),
+                    Err(e) => return Err(e),
+                }
+            }
+        } else {
+            let stats = if args.quiet {
+                StreamStats::Hidden
+            } else if io::stdout().is_terminal() {
+                StreamStats::Live
+            } else {
+                StreamStats::Summary
+            };
+            print_turn(&mut sessions, input, &mut io::stdout(), stats).await?;
+        }
     }
 
+    if let Some(path) = &args.export_md {
+        export_transcripts(&sessions, path)?;
+    }
     Ok(())
 }
 
-fn print_help() {
-    println!("\n📚 Available Commands:");
-    println!("  exit/quit  - Exit the chat");
-    println!("  help       - Show this help message");
-    println!("  clear      - Clear the screen");
-    println!("  info       - Show model information");
-    println!("\n💡 Tips:");
-    println!("  - Use specific prompts for better results");
-    println!("  - Coding mode: Ask for code examples, debugging help");
-    println!("  - Math mode: Ask for mathematical problem solving");
-    println!("  - Try: 'Explain this code:', 'Solve this equation:', etc.");
-    println!();
+/// Save the transcript of every session to `path`, one after another
+fn export_transcripts(sessions: &[ChatSession], path: &Path) -> Result<()> {
+    if let [session] = sessions {
+        return session.export_markdown(path);
+    }
+    let markdown = sessions
+        .iter()
+        .map(ChatSession::to_markdown)
+        .collect::<Vec<_>>()
+        .join("\n---\n\n");
+    std::fs::write(path, markdown).with_context(|| format!("Failed to write transcript {:?}", path))
 }
 
-/// Chat session management
-struct ChatSession {
-    model: PhiModel,
-    conversation_history: Vec<(String, String)>, // (user, assistant) pairs
-    system_prompt: Option<String>,
-    coding_mode: bool,
-    math_mode: bool,
+/// Models the API server preloads, once `--max-tokens` is known to fit each one's context
+fn preload_models(args: &Args) -> Result<Vec<PhiModel>> {
+    let models: Vec<PhiModel> = args.preload.iter().cloned().map(Into::into).collect();
+    check_max_tokens(args.max_tokens, &models)?;
+    Ok(models)
 }
 
-impl ChatSession {
-    fn new(model: PhiModel, system_prompt: Option<String>, coding_mode: bool, math_mode: bool) -> Self {
-        let enhanced_system = if let Some(base) = system_prompt {
-            Some(Self::enhance_system_prompt(base, coding_mode, math_mode))
-        } else {
-            Some(Self::default_system_prompt(coding_mode, math_mode))
-        };
+/// Refuse a `--max-tokens` budget larger than the context window of any of `models`
+fn check_max_tokens(max_tokens: usize, models: &[PhiModel]) -> Result<()> {
+    match models.iter().find(|model| max_tokens > model.context_length()) {
+        Some(model) => anyhow::bail!(
+            "--max-tokens {} exceeds the {} token context of {}",
+            max_tokens,
+            model.context_length(),
+            model.model_name()
+        ),
+        None => Ok(()),
+    }
+}
 
-        Self {
-            model,
-            conversation_history: Vec::new(),
-            system_prompt: enhanced_system,
-            coding_mode,
-            math_mode,
-        }
+/// Refuse to load `model` if `system` can't run it at `quantization`, unless `force` is set
+///
+/// Returns the reasons it won't fit, which `force` turns into warnings, or nothing when
+/// it fits.
+fn guard_model_fits(
+    system: &SystemInfo,
+    model: &PhiModel,
+    quantization: Quantization,
+    force: bool,
+) -> Result<Vec<String>> {
+    let (can_run, issues) = system.can_run_model(model, quantization);
+    if can_run {
+        return Ok(Vec::new());
+    }
+    if !force {
+        let error = PhiError::InsufficientResources {
+            model: model.model_name().to_string(),
+            quantization,
+            issues,
+        };
+        anyhow::bail!(
+            "{}\nTry a smaller model or --quantization int4, or pass --force to load it anyway",
+            error
+        );
     }
+    Ok(issues)
+}
 
-    fn default_system_prompt(coding_mode: bool, math_mode: bool) -> String {
-        let mut prompt = "You are Phi, a helpful AI assistant created by Microsoft.".to_string();
-        
-        if coding_mode {
-            prompt.push_str(" You specialize in helping with programming tasks, code generation, debugging, and software development best practices.");
-        }
-        
-        if math_mode {
-            prompt.push_str(" You excel at mathematical reasoning, problem solving, and explaining complex mathematical concepts clearly.");
+/// What `--dry-run` found for one model
+struct DryRunCheck {
+    model: PhiModel,
+    /// Problems that keep the model from being served
+    blocking: Vec<String>,
+    /// Problems that only make it slower
+    warnings: Vec<String>,
+}
+
+/// Check that `model` fits `system` and is cached by `manager`, without downloading it
+async fn dry_run_check(
+    system: &SystemInfo,
+    manager: &PhiModelManager,
+    model: PhiModel,
+) -> DryRunCheck {
+    let (can_run, issues) = system.can_run_model(&model, manager.quantization());
+    let (mut blocking, warnings) = if can_run {
+        (Vec::new(), issues)
+    } else {
+        (issues, Vec::new())
+    };
+    match manager.validate_model_file(&model).await {
+        Ok(()) => {}
+        Err(PhiError::ModelNotCached { path }) => {
+            blocking.push(format!("Not cached at {:?}; fetch it with download-phi", path));
         }
-        
-        prompt.push_str(" You provide accurate, helpful, and concise responses.");
-        prompt
+        Err(e) => blocking.push(format!("Cached model is unusable: {}", e)),
+    }
+    DryRunCheck {
+        model,
+        blocking,
+        warnings,
     }
+}
 
-    fn enhance_system_prompt(base: String, coding_mode: bool, math_mode: bool) -> String {
-        let mut enhanced = base;
-        
-        if coding_mode {
-            enhanced.push_str("\n\nCoding Assistant Mode: Focus on programming tasks, code quality, and best practices.");
+/// Readiness report for `--dry-run`, and whether every model is ready
+///
+/// Only the API server has no models to check, when nothing is given to `--preload`.
+fn format_dry_run(checks: &[DryRunCheck], quantization: Quantization) -> (String, bool) {
+    if checks.is_empty() {
+        let report = "Nothing checked: the API server loads no models without --preload\n";
+        return (report.to_string(), true);
+    }
+    let mut report = String::new();
+    for check in checks {
+        let status = if check.blocking.is_empty() { "ready" } else { "not ready" };
+        report.push_str(&format!("{} at {}: {}\n", check.model.model_name(), quantization, status));
+        for issue in &check.blocking {
+            report.push_str(&format!("  ❌ {}\n", issue));
         }
-        
-        if math_mode {
-            enhanced.push_str("\n\nMath Assistant Mode: Emphasize mathematical accuracy and clear step-by-step explanations.");
+        for warning in &check.warnings {
+            report.push_str(&format!("  ⚠️  {}\n", warning));
         }
-        
-        enhanced
     }
+    let ready = checks.iter().all(|check| check.blocking.is_empty());
+    (report, ready)
+}
 
-    async fn generate_response(&mut self, input: &str, max_tokens: usize, temperature: f32) -> Result<String> {
-        // Add to conversation history
-        let enhanced_input = self.enhance_input(input);
-        
-        // In a real implementation, this would:
-        // 1. Format the conversation with system prompt
-        // 2. Tokenize the input using the appropriate tokenizer
-        // 3. Run inference using Burn with the loaded model
-        // 4. Decode the output tokens back to text
-        // 5. Apply post-processing and safety filters
+/// Outcome of one `--selftest` step: what it found, or why it failed
+struct SelfTestStep {
+    name: &'static str,
+    result: Result<String, String>,
+}
 
-        // For now, provide a demonstration response
-        let response = self.generate_demo_response(input).await;
-        
-        self.conversation_history.push((input.to_string(), response.clone()));
-        
-        // Keep conversation history manageable
-        if self.conversation_history.len() > 10 {
-            self.conversation_history.remove(0);
-        }
+/// Check the system, cache the smallest model and generate a short reply with it
+///
+/// Steps run in order and stop at the first failure, since each depends on the last.
+async fn run_selftest(
+    system: Result<SystemInfo>,
+    manager: &PhiModelManager,
+) -> Vec<SelfTestStep> {
+    let model = PhiModel::available_models()
+        .into_iter()
+        .min_by(|a, b| a.parameter_count().total_cmp(&b.parameter_count()))
+        .expect("at least one model is available");
+    let mut steps = Vec::with_capacity(3);
 
-        Ok(response)
+    let fits = system.map_err(|e| format!("{:#}", e)).and_then(|system| {
+        match system.can_run_model(&model, manager.quantization()) {
+            (true, _) => Ok(format!("{} fits this machine", model.model_name())),
+            (false, issues) => Err(issues.join("; ")),
+        }
+    });
+    let failed = fits.is_err();
+    steps.push(SelfTestStep { name: "System requirements", result: fits });
+    if failed {
+        return steps;
     }
 
-    fn enhance_input(&self, input: &str) -> String {
-        let mut enhanced = input.to_string();
+    let path = match manager.ensure_model(&model).await {
+        Ok(path) => path,
+        Err(e) => {
+            steps.push(SelfTestStep { name: "Model cache", result: Err(e.to_string()) });
+            return steps;
+        }
+    };
+    steps.push(SelfTestStep {
+        name: "Model cache",
+        result: Ok(format!("{} at {:?}", model.model_name(), path)),
+    });
 
-        if self.coding_mode && (input.contains("code") || input.contains("function") || input.contains("bug")) {
-            enhanced = format!("[CODING TASK] {}", enhanced);
+    let generation = async {
+        let inference = PhiInference::open(&path).await?;
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_sampling(SamplingConfig {
+                max_tokens: SELFTEST_MAX_TOKENS,
+                ..SamplingConfig::default()
+            })
+            .with_inference(inference);
+        let start = Instant::now();
+        let reply = session.generate_response(SELFTEST_PROMPT).await?;
+        if reply.trim().is_empty() {
+            anyhow::bail!("the reply was empty");
         }
+        Ok(format!(
+            "{} words in {:.1}s",
+            reply.split_whitespace().count(),
+            start.elapsed().as_secs_f64()
+        ))
+    };
+    steps.push(SelfTestStep {
+        name: "Generation",
+        result: generation.await.map_err(|e: anyhow::Error| format!("{:#}", e)),
+    });
+    steps
+}
+
+/// Pass/fail report for `--selftest`, and whether every step passed
+///
+/// Steps that never ran because an earlier one failed are listed as skipped.
+fn format_selftest(steps: &[SelfTestStep]) -> (String, bool) {
+    const STEPS: [&str; 3] = ["System requirements", "Model cache", "Generation"];
 
-        if self.math_mode && (input.contains("solve") || input.contains("calculate") || input.contains("equation")) {
-            enhanced = format!("[MATH PROBLEM] {}", enhanced);
+    let mut report = String::new();
+    for step in steps {
+        match &step.result {
+            Ok(detail) => report.push_str(&format!("✅ {}: {}\n", step.name, detail)),
+            Err(reason) => report.push_str(&format!("❌ {}: {}\n", step.name, reason)),
         }
+    }
+    for name in &STEPS[steps.len().min(STEPS.len())..] {
+        report.push_str(&format!("⏭️  {}: skipped\n", name));
+    }
+    let passed = steps.len() == STEPS.len() && steps.iter().all(|step| step.result.is_ok());
+    report.push_str(if passed { "Self-test passed\n" } else { "Self-test failed\n" });
+    (report, passed)
+}
 
-        enhanced
+/// One indented bullet per issue
+fn format_issues(issues: &[String]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  - {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Build a chat session for `model` from the command-line settings
+fn new_session(
+    model: PhiModel,
+    args: &Args,
+    filter: Option<Arc<dyn ContentFilter>>,
+    timeout: Duration,
+) -> Result<ChatSession> {
+    let mut session =
+        ChatSession::new(model, args.system.clone(), args.coding_mode, args.math_mode)
+            .with_history_turns(args.history_turns)
+            .with_sampling(SamplingConfig {
+                temperature: args.temperature,
+                top_p: args.top_p,
+                top_k: args.top_k,
+                max_tokens: args.max_tokens,
+                return_logprobs: args.logprobs,
+                repeat_penalty: args.repeat_penalty,
+                no_repeat_ngram_size: args.no_repeat_ngram_size,
+            })
+            .with_stop_sequences(args.stop.clone())
+            .with_timeout(timeout)
+            .with_metrics(metrics::create_sink(args.metrics_backend)?);
+    if let Some(filter) = filter {
+        session = session.with_filter(filter);
+    }
+    if let Some(seed) = args.seed {
+        session = session.with_seed(seed);
     }
+    Ok(session)
+}
 
-    async fn generate_demo_response(&self, input: &str) -> String {
-        // Simulate processing time
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Name a session's replies are printed under: the model when comparing, else "Phi"
+fn reply_label(session: &ChatSession, compare: bool) -> &'static str {
+    if compare {
+        session.model.model_name()
+    } else {
+        "Phi"
+    }
+}
 
-        // Generate contextual demo responses based on the model and input
-        match &self.model {
-            PhiModel::Phi2 { .. } => {
-                if input.to_lowercase().contains("code") {
-                    "I'd be happy to help with coding! As Phi-2, I can assist with code generation, explanation, and basic debugging. What specific programming task are you working on?".to_string()
-                } else if input.to_lowercase().contains("math") {
-                    "I can help with mathematical problems! Please share the specific math question or equation you'd like me to work on.".to_string()
-                } else {
-                    format!("Thank you for your question about '{}'. As Phi-2, I'm designed to help with language comprehension and reasoning tasks. How can I assist you further?", input)
-                }
+/// How much generation speed `print_turn` shows alongside streamed replies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamStats {
+    /// Reply text only (`--quiet`)
+    Hidden,
+    /// A stats line after each reply
+    Summary,
+    /// A status line redrawn after the text as chunks arrive, then the stats line
+    Live,
+}
+
+/// How often the live status line is redrawn while no chunk arrives
+const STATUS_REFRESH: Duration = Duration::from_millis(250);
+
+/// Stream every session's reply to `input` into `out`, each under its label
+///
+/// Sessions answer one after another, so compared replies never interleave. Ctrl-C stops
+/// the reply being streamed and skips the sessions after it.
+async fn print_turn(
+    sessions: &mut [ChatSession],
+    input: &str,
+    out: &mut impl Write,
+    stats: StreamStats,
+) -> Result<()> {
+    let compare = sessions.len() > 1;
+    for session in sessions {
+        write!(out, "{}: ", reply_label(session, compare))?;
+        out.flush()?;
+        let mut timing = GenerationTiming::start();
+        let live = stats == StreamStats::Live;
+        let result = print_stream(session.generate_stream(input), out, &mut timing, live).await;
+        let cancelled = match result {
+            Ok(finished) => !finished,
+            Err(e) if e.is::<ContentBlocked>() => {
+                write!(out, "🚫 {}", e)?;
+                false
             }
-            PhiModel::Phi3 { .. } => {
-                if self.coding_mode && input.to_lowercase().contains("code") {
-                    "As Phi-3 in coding mode, I'm optimized for programming tasks! I can help with:\n• Code generation and completion\n• Debugging and error analysis\n• Algorithm design\n• Best practices\n\nWhat would you like to work on?".to_string()
-                } else if self.math_mode && input.to_lowercase().contains("math") {
-                    "Phi-3 excels at mathematical reasoning! I can help with:\n• Problem solving step-by-step\n• Equation solving\n• Mathematical proofs\n• Concept explanation\n\nWhat math problem shall we tackle?".to_string()
-                } else {
-                    format!("I'm Phi-3, designed for coding, math, and reasoning tasks. Regarding '{}', I can provide detailed analysis and solutions. What specific aspect would you like me to focus on?", input)
+            Err(e) => return Err(e),
+        };
+        if cancelled {
+            write!(out, " ⏹️  (cancelled)")?;
+        }
+        if stats != StreamStats::Hidden && timing.chunks() > 0 {
+            write!(out, "\n{}", format_stream_stats(&timing))?;
+        }
+        writeln!(out, "\n")?;
+        if cancelled {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read one line from stdin, or `None` on Ctrl-C or end of input
+async fn read_input_line() -> Result<Option<String>> {
+    let read = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map(|read| (read > 0).then_some(line))
+    });
+
+    tokio::select! {
+        line = read => Ok(line??),
+        _ = tokio::signal::ctrl_c() => Ok(None),
+    }
+}
+
+/// Write chunks to `out` as they arrive, recording each in `timing`; returns `false` if
+/// Ctrl-C cancelled the stream
+///
+/// With `live`, a status line follows the text and is redrawn on every chunk and every
+/// [`STATUS_REFRESH`]; it is erased again before the stream ends.
+async fn print_stream(
+    stream: impl Stream<Item = Result<String>>,
+    out: &mut impl Write,
+    timing: &mut GenerationTiming,
+    live: bool,
+) -> Result<bool> {
+    tokio::pin!(stream);
+    let mut refresh = tokio::time::interval(STATUS_REFRESH);
+    let result = loop {
+        tokio::select! {
+            chunk = stream.next() => match chunk {
+                Some(Ok(chunk)) => {
+                    timing.record_chunk();
+                    if live {
+                        clear_status(out)?;
+                    }
+                    write!(out, "{}", chunk)?;
+                    if live {
+                        draw_status(out, timing)?;
+                    }
+                    out.flush()?;
                 }
+                Some(Err(e)) => break Err(e),
+                None => break Ok(true),
+            },
+            _ = refresh.tick(), if live => {
+                clear_status(out)?;
+                draw_status(out, timing)?;
+                out.flush()?;
             }
-            PhiModel::Phi4 { .. } => {
-                "As Phi-4, I excel at complex reasoning and mathematical problem solving. I can provide sophisticated analysis with step-by-step reasoning. What challenging problem would you like me to work on?".to_string()
-            }
-            _ => {
-                format!("I understand you're asking about '{}'. How can I help you with this?", input)
-            }
+            _ = tokio::signal::ctrl_c() => break Ok(false),
+        }
+    };
+    if live {
+        clear_status(out)?;
+        out.flush()?;
+    }
+    result
+}
+
+/// Save the cursor at the end of the text and draw the dimmed status line after it
+fn draw_status(out: &mut impl Write, timing: &GenerationTiming) -> io::Result<()> {
+    let now = Instant::now();
+    let rate = timing.tokens_per_second_at(now).unwrap_or(0.0);
+    write!(
+        out,
+        "\x1b7  \x1b[2m[{} tokens · {:.1} tok/s · {:.1}s]\x1b[0m",
+        timing.chunks(),
+        rate,
+        timing.elapsed_at(now).as_secs_f64()
+    )
+}
+
+/// Return to the saved cursor and erase the status line drawn after it
+fn clear_status(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "\x1b8\x1b[K")
+}
+
+/// One-line summary of a finished reply: tokens, tokens/sec and time-to-first-token
+fn format_stream_stats(timing: &GenerationTiming) -> String {
+    let rate = timing.tokens_per_second().unwrap_or(0.0);
+    let ttft = timing.ttft().unwrap_or_default();
+    format!(
+        "📊 {} tokens · {:.1} tok/s · first token in {:.2}s",
+        timing.chunks(),
+        rate,
+        ttft.as_secs_f64()
+    )
+}
+
+/// Read the prompt file, treating `-` as stdin
+fn read_prompt_source(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read prompt file {:?}", path))
+    }
+}
+
+/// Split a prompt file into prompts: `---` lines separate multi-line blocks, otherwise one prompt per line
+fn parse_prompts(content: &str) -> Vec<String> {
+    let has_separators = content.lines().any(|line| line.trim() == "---");
+
+    let prompts: Vec<String> = if has_separators {
+        content
+            .split('\n')
+            .collect::<Vec<_>>()
+            .split(|line| line.trim() == "---")
+            .map(|block| block.join("\n").trim().to_string())
+            .collect()
+    } else {
+        content.lines().map(|line| line.trim().to_string()).collect()
+    };
+
+    prompts.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// Run each prompt independently (no shared history) and collect the responses in order
+async fn run_batch(session: &mut ChatSession, prompts: &[String]) -> Result<Vec<Generation>> {
+    let mut responses = Vec::with_capacity(prompts.len());
+    for prompt in prompts {
+        session.clear_history();
+        responses.push(session.generate(prompt).await?);
+    }
+    Ok(responses)
+}
+
+/// Render a prompt and its generation as a single-line JSON object for `--json`, naming
+/// the model that generated it when comparing models
+fn format_json_response(
+    prompt: &str,
+    generation: &Generation,
+    model: Option<&str>,
+) -> Result<String> {
+    let mut value = serde_json::to_value(generation)?;
+    value["prompt"] = serde_json::Value::String(prompt.to_string());
+    if let Some(model) = model {
+        value["model"] = serde_json::Value::String(model.to_string());
+    }
+    Ok(serde_json::to_string(&value)?)
+}
+
+/// Apply a `set <parameter> <value>` command to the sampling config
+fn apply_set_command(config: &mut SamplingConfig, input: &str) -> Result<(), String> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    match parts.as_slice() {
+        ["set", name, value] => config.set(&name.to_lowercase(), value),
+        _ => Err("usage: set <temperature|top-p|top-k|max-tokens|repeat-penalty|\
+                  no-repeat-ngram-size|logprobs> <value>"
+            .to_string()),
+    }
+}
+
+/// Ask which of `options` to use with a numbered menu, dimming the models that won't fit
+/// on this machine
+///
+/// Models that won't fit can still be picked, with a warning. An empty answer or the end
+/// of input picks [`DEFAULT_MODEL`].
+fn pick_model(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    options: &[(PhiModel, bool)],
+) -> Result<PhiModel> {
+    let models: Vec<PhiModel> = options.iter().map(|(model, _)| model.clone()).collect();
+    let default = models
+        .iter()
+        .position(|model| model.short_name() == DEFAULT_MODEL)
+        .unwrap_or(0);
+
+    writeln!(output, "Choose a Phi model:\n")?;
+    for (index, (model, fits)) in options.iter().enumerate() {
+        let info = model.display_info().replace('\n', "\n    ");
+        if *fits {
+            writeln!(output, "{:>2}) {}\n", index + 1, info)?;
+        } else {
+            writeln!(
+                output,
+                "\x1B[2m{:>2}) {}\n    ⚠️  Won't fit on this machine\x1B[0m\n",
+                index + 1,
+                info
+            )?;
+        }
+    }
+
+    let index = loop {
+        write!(
+            output,
+            "Model [1-{}, Enter for {}]: ",
+            models.len(),
+            models[default].short_name()
+        )?;
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(output)?;
+            break default;
+        }
+        match parse_model_selection(&answer, &models, default) {
+            Ok(index) => break index,
+            Err(e) => writeln!(output, "{}", e)?,
         }
+    };
+
+    if !options[index].1 {
+        writeln!(
+            output,
+            "⚠️  {} may not fit on this machine; try a smaller model or --quantization int4",
+            models[index].model_name()
+        )?;
     }
+    Ok(models[index].clone())
 }
 
-// Placeholder struct for future Burn integration
-pub struct PhiInference {
-    // Would contain Burn model, tokenizer, etc.
+/// Index of the model chosen by `answer`: a menu number from 1, a short model name, or
+/// nothing for `default`
+fn parse_model_selection(
+    answer: &str,
+    models: &[PhiModel],
+    default: usize,
+) -> Result<usize, String> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(default);
+    }
+    if let Ok(number) = answer.parse::<usize>() {
+        if !(1..=models.len()).contains(&number) {
+            return Err(format!("Pick a number from 1 to {}", models.len()));
+        }
+        return Ok(number - 1);
+    }
+    models
+        .iter()
+        .position(|model| model.short_name().eq_ignore_ascii_case(answer))
+        .ok_or_else(|| format!("Unknown model '{}'", answer))
+}
+
+fn print_help() {
+    println!("\n📚 Available Commands:");
+    println!("  exit/quit  - Exit the chat");
+    println!("  help       - Show this help message");
+    println!("  clear      - Clear the screen and the model's cached prompt");
+    println!("  info       - Show model information and context window usage");
+    println!("  params     - Show sampling parameters");
+    println!("  set <p> <v> - Change a sampling parameter (temperature, top-p, top-k, max-tokens,");
+    println!("                repeat-penalty, no-repeat-ngram-size, logprobs)");
+    println!(
+        "  /export [path] - Save the conversation as Markdown (default: --export-md or {})",
+        DEFAULT_EXPORT_PATH
+    );
+    println!("\n💡 Tips:");
+    println!("  - Press Ctrl-C while Phi is answering to stop the response");
+    println!("  - Use specific prompts for better results");
+    println!("  - Coding mode: Ask for code examples, debugging help");
+    println!("  - Math mode: Ask for mathematical problem solving");
+    println!("  - Try: 'Explain this code:', 'Solve this equation:', etc.");
+    println!();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    /// CPU-only machine with `memory` bytes of RAM available and plenty of disk
+    fn system_with_memory(memory: u64) -> SystemInfo {
+        use burn_phi_local_llm::{DiskInfo, GpuInfo, MemoryInfo};
+
+        SystemInfo {
+            memory: MemoryInfo { total: 2 * memory, available: memory },
+            disk: DiskInfo { total: 500 * GB, available: 100 * GB },
+            cpu_cores: 4,
+            gpu: GpuInfo {
+                has_cuda: false,
+                has_metal: false,
+                has_vulkan: false,
+                device_count: 0,
+                vram_total: 0,
+                vram_available: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_guard_refuses_models_that_do_not_fit() {
+        let tiny = system_with_memory(GB);
+        let phi4 = PhiModel::from_short_name("phi4").unwrap();
+
+        let error = guard_model_fits(&tiny, &phi4, Quantization::F16, false).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("microsoft/Phi-4 won't fit on this machine"), "{}", message);
+        assert!(message.contains("  - Insufficient memory: need ~"), "{}", message);
+        assert!(message.contains("--force"), "{}", message);
+
+        let issues = guard_model_fits(&tiny, &phi4, Quantization::F16, true).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("Insufficient memory"));
+
+        let phi1 = PhiModel::from_short_name("phi1").unwrap();
+        assert!(guard_model_fits(&tiny, &phi1, Quantization::Int4, false).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_is_ready_only_for_cached_models_that_fit() {
+        let cache = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(cache.path());
+        let phi3 = PhiModel::from_short_name("phi3").unwrap();
+        let mut onnx = vec![0x08, 0x07];
+        onnx.resize(1024, 0);
+        std::fs::write(manager.model_path(&phi3), onnx).unwrap();
+
+        let roomy = system_with_memory(64 * GB);
+        let checks = [dry_run_check(&roomy, &manager, phi3.clone()).await];
+        let (report, ready) = format_dry_run(&checks, Quantization::F16);
+        assert!(ready, "{}", report);
+        assert_eq!(report, "microsoft/Phi-3-mini-4k-instruct at f16: ready\n");
+
+        // Too little memory for Phi-3, and Phi-4 was never downloaded
+        let tiny = system_with_memory(GB);
+        let phi4 = PhiModel::from_short_name("phi4").unwrap();
+        let checks = [
+            dry_run_check(&tiny, &manager, phi3).await,
+            dry_run_check(&roomy, &manager, phi4).await,
+        ];
+        let (report, ready) = format_dry_run(&checks, Quantization::F16);
+        assert!(!ready);
+        let phi3_report = "microsoft/Phi-3-mini-4k-instruct at f16: not ready\n  ❌ Insufficient memory";
+        assert!(report.contains(phi3_report), "{}", report);
+        let phi4_report = "microsoft/Phi-4 at f16: not ready\n  ❌ Not cached at";
+        assert!(report.contains(phi4_report), "{}", report);
+
+        // An API server dry run without --preload has nothing to check, and says so
+        let (report, _) = format_dry_run(&[], Quantization::F16);
+        assert!(report.starts_with("Nothing checked"), "{}", report);
+        assert!(!report.contains("ready"), "{}", report);
+    }
+
+    #[tokio::test]
+    async fn test_selftest_passes_with_a_cached_model() {
+        use burn_phi_local_llm::RetryPolicy;
+
+        let cache = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9")
+            .with_retry(RetryPolicy { max_retries: 0, ..RetryPolicy::default() });
+        let phi1 = PhiModel::from_short_name("phi1").unwrap();
+
+        // Nothing cached and no hub to download from
+        let steps = run_selftest(Ok(system_with_memory(64 * GB)), &manager).await;
+        let (report, passed) = format_selftest(&steps);
+        assert!(!passed);
+        assert!(report.contains("✅ System requirements: microsoft/phi-1 fits"), "{}", report);
+        assert!(report.contains("❌ Model cache"), "{}", report);
+        assert!(report.contains("⏭️  Generation: skipped"), "{}", report);
+
+        let mut onnx = vec![0x08, 0x07];
+        onnx.resize(1024, 0);
+        std::fs::write(manager.model_path(&phi1), onnx).unwrap();
+        std::fs::write(
+            manager.tokenizer_path(&phi1),
+            r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+                "normalizer": null, "pre_tokenizer": {"type": "Whitespace"},
+                "post_processor": null, "decoder": null,
+                "model": {"type": "WordLevel", "vocab": {"[UNK]": 0}, "unk_token": "[UNK]"}}"#,
+        )
+        .unwrap();
+
+        let steps = run_selftest(Ok(system_with_memory(64 * GB)), &manager).await;
+        let (report, passed) = format_selftest(&steps);
+        assert!(passed, "{}", report);
+        assert_eq!(report.matches("✅").count(), 3, "{}", report);
+        assert!(report.ends_with("Self-test passed\n"));
+    }
+
+    #[test]
+    fn test_model_selection_parsing() {
+        let models = PhiModel::available_models();
+        let phi3 = models.iter().position(|model| model.short_name() == "phi3").unwrap();
+
+        assert_eq!(parse_model_selection("\n", &models, phi3), Ok(phi3));
+        assert_eq!(parse_model_selection(" 1 \n", &models, phi3), Ok(0));
+        let by_name = parse_model_selection("PHI4-mini", &models, phi3).unwrap();
+        assert_eq!(models[by_name].short_name(), "phi4-mini");
+        assert!(parse_model_selection("0", &models, phi3).is_err());
+        assert!(parse_model_selection(&(models.len() + 1).to_string(), &models, phi3).is_err());
+        assert!(parse_model_selection("gpt-4", &models, phi3).is_err());
+    }
+
     #[test]
-    fn test_chat_session_creation() {
-        let model = PhiModel::Phi3 {
-            parameters: "3.8B".to_string(),
-            context_length: 4096,
-            specialization: vec!["coding".to_string()],
+    fn test_model_picker_retries_until_a_valid_choice() {
+        let options: Vec<_> = PhiModel::available_models()
+            .into_iter()
+            .map(|model| {
+                let fits = model.short_name() != "phi4";
+                (model, fits)
+            })
+            .collect();
+        let phi4 = options.iter().position(|(model, _)| model.short_name() == "phi4").unwrap();
+
+        let mut output = Vec::new();
+        let answers = format!("99\nnope\n{}\n", phi4 + 1);
+        let model = pick_model(&mut answers.as_bytes(), &mut output, &options).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(model.short_name(), "phi4");
+        assert!(output.contains("Won't fit on this machine"));
+        assert!(output.contains("Unknown model 'nope'"));
+        assert!(output.contains("may not fit"));
+
+        let model = pick_model(&mut "".as_bytes(), &mut Vec::new(), &options).unwrap();
+        assert_eq!(model.short_name(), DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn test_set_command_parser() {
+        let mut config = SamplingConfig::default();
+
+        apply_set_command(&mut config, "set temperature 0.2").unwrap();
+        apply_set_command(&mut config, "set top-p 0.5").unwrap();
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.top_p, 0.5);
+
+        let before = config.clone();
+        assert!(apply_set_command(&mut config, "set temperature 5").is_err());
+        assert!(apply_set_command(&mut config, "set top-p").is_err());
+        assert!(apply_set_command(&mut config, "set bogus 1").is_err());
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn test_config_file_seeds_defaults_under_flags() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(file, "model = \"phi4\"\ntemperature = 0.2\nsystem = \"Be brief.\"").unwrap();
+        let config = file.path().to_str().unwrap().to_string();
+        let load = |flags: &[&str]| {
+            let argv = ["phi-chat", "--config", &config].into_iter().chain(flags.iter().copied());
+            Args::from_matches(&Args::command().try_get_matches_from(argv).unwrap())
         };
 
-        let session = ChatSession::new(model, None, true, false);
-        assert!(session.coding_mode);
-        assert!(!session.math_mode);
-        assert!(session.system_prompt.is_some());
+        let args = load(&[]).unwrap();
+        assert_eq!(args.temperature, 0.2);
+        assert_eq!(args.system.as_deref(), Some("Be brief."));
+        assert!(matches!(args.model, Some(PhiModelChoice::Phi4)));
+
+        let args = load(&["--temperature", "0.5"]).unwrap();
+        assert_eq!(args.temperature, 0.5);
+
+        writeln!(file, "temprature = 0.9").unwrap();
+        let err = load(&[]).err().unwrap();
+        assert!(format!("{:#}", err).contains("Unknown config key 'temprature'"));
     }
 
     #[test]
-    fn test_system_prompt_enhancement() {
-        let base = "You are an AI assistant.".to_string();
-        let enhanced = ChatSession::enhance_system_prompt(base, true, true);
-        
-        assert!(enhanced.contains("Coding Assistant Mode"));
-        assert!(enhanced.contains("Math Assistant Mode"));
+    fn test_sampling_flags() {
+        let args =
+            Args::try_parse_from(["phi-chat", "--top-p", "0.5", "--top-k", "8", "--seed", "42"])
+                .unwrap();
+        assert_eq!(args.top_p, 0.5);
+        assert_eq!(args.top_k, 8);
+        assert_eq!(args.seed, Some(42));
+
+        let defaults = Args::try_parse_from(["phi-chat"]).unwrap();
+        assert_eq!(defaults.top_p, 0.9);
+        assert_eq!(defaults.top_k, 40);
+        assert_eq!(defaults.repeat_penalty, 1.1);
+        assert_eq!(defaults.no_repeat_ngram_size, 0);
+        assert_eq!(defaults.seed, None);
+    }
+
+    #[test]
+    fn test_sampling_flags_reject_out_of_range_values() {
+        let rejected = [
+            "--top-p=0",
+            "--top-p=1.5",
+            "--top-k=0",
+            "--top-k=-3",
+            "--temperature=-0.5",
+            "--temperature=3.0",
+            "--temperature=NaN",
+            "--max-tokens=0",
+            "--repeat-penalty=0.5",
+        ];
+        for arg in rejected {
+            let err = Args::try_parse_from(["phi-chat", arg]).err().unwrap();
+            assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation, "{}", arg);
+        }
+
+        let args = Args::try_parse_from(["phi-chat", "--temperature=0.7"]).unwrap();
+        assert_eq!(args.temperature, 0.7);
+    }
+
+    #[test]
+    fn test_max_tokens_must_fit_every_context() {
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        let phi3 = PhiModel::from_short_name("phi3").unwrap();
+
+        assert!(check_max_tokens(2048, &[phi2.clone(), phi3.clone()]).is_ok());
+        let err = check_max_tokens(4096, &[phi3, phi2]).unwrap_err().to_string();
+        assert!(err.contains("exceeds the 2048 token context"), "{}", err);
+    }
+
+    #[test]
+    fn test_every_model_choice_selects_its_model() {
+        let models: Vec<PhiModel> =
+            PhiModelChoice::value_variants().iter().cloned().map(Into::into).collect();
+        let names: Vec<_> = models.iter().map(PhiModel::short_name).collect();
+        let available = PhiModel::available_models();
+        let available: Vec<_> = available.iter().map(PhiModel::short_name).collect();
+        assert_eq!(names, available);
+    }
+
+    #[test]
+    fn test_preloaded_models_must_fit_max_tokens() {
+        let args = |preload| {
+            Args::try_parse_from([
+                "phi-chat", "--api-mode", "--max-tokens", "4096", "--preload", preload,
+            ])
+            .unwrap()
+        };
+
+        let models = preload_models(&args("phi3")).unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].short_name(), "phi3");
+        let err = preload_models(&args("phi2")).unwrap_err().to_string();
+        assert!(err.contains("exceeds the 2048 token context of"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_prompts_lines_and_blocks() {
+        assert_eq!(parse_prompts("first\n\nsecond\n"), vec!["first", "second"]);
+
+        let blocks = parse_prompts("line one\nline two\n---\nsecond block\n---\n");
+        assert_eq!(blocks, vec!["line one\nline two", "second block"]);
     }
 
     #[tokio::test]
-    async fn test_demo_response_generation() {
-        let model = PhiModel::Phi3 {
-            parameters: "3.8B".to_string(),
-            context_length: 4096,
-            specialization: vec!["coding".to_string()],
+    async fn test_batch_prompt_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("prompts.txt");
+        std::fs::write(&path, "write some code\nwhat is math?\n").unwrap();
+
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
         };
+        let mut session = ChatSession::new(model, None, false, false);
 
-        let session = ChatSession::new(model, None, true, false);
-        let response = session.generate_demo_response("help me write code").await;
-        
-        assert!(!response.is_empty());
-        assert!(response.to_lowercase().contains("code") || response.to_lowercase().contains("coding"));
+        let prompts = parse_prompts(&read_prompt_source(&path).unwrap());
+        let responses = run_batch(&mut session, &prompts).await.unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0].text.contains("coding"));
+        assert!(responses[1].text.contains("math"));
+        assert!(responses[0].logprobs.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_json_output_includes_logprobs() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false).with_sampling(SamplingConfig {
+            return_logprobs: true,
+            ..SamplingConfig::default()
+        });
+
+        let generation = session.generate("hello").await.unwrap();
+        let logprobs = generation.logprobs.as_ref().unwrap();
+        assert_eq!(logprobs.len(), generation.text.split_whitespace().count());
+
+        let json = format_json_response("hello", &generation, None).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["prompt"], "hello");
+        assert_eq!(json["logprobs"].as_array().unwrap().len(), logprobs.len());
+        assert!(json.get("model").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compare_prints_a_labeled_reply_per_model() {
+        let mut sessions: Vec<_> = ["phi2", "phi3"]
+            .into_iter()
+            .map(|name| {
+                let model = PhiModel::from_short_name(name).unwrap();
+                ChatSession::new(model, None, false, false)
+            })
+            .collect();
+
+        let mut out = Vec::new();
+        print_turn(&mut sessions, "hello", &mut out, StreamStats::Hidden).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let phi2 = out.find("microsoft/phi-2: ").unwrap();
+        let phi3 = out.find("microsoft/Phi-3-mini-4k-instruct: ").unwrap();
+        assert!(phi2 < phi3, "{}", out);
+        assert!(out[phi2..phi3].contains("As Phi-2"), "{}", out);
+        assert!(out[phi3..].contains("I'm Phi-3"), "{}", out);
+        assert!(!out.contains("Phi: "));
+        assert!(!out.contains("tok/s"));
+
+        // Each session keeps its own reply, answering the same input
+        for session in &sessions {
+            assert_eq!(session.history().len(), 1);
+            assert_eq!(session.history()[0].0, "hello");
+        }
+        assert_ne!(sessions[0].history()[0].1, sessions[1].history()[0].1);
+    }
+
+    #[tokio::test]
+    async fn test_reply_is_followed_by_stats_unless_hidden() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut sessions = vec![ChatSession::new(model, None, false, false)];
+
+        let mut out = Vec::new();
+        print_turn(&mut sessions, "hello", &mut out, StreamStats::Summary).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let stats = out.lines().find(|line| line.starts_with("📊 ")).unwrap();
+        assert!(stats.contains(" tok/s · first token in "), "{}", stats);
+        assert!(!out.contains('\x1b'), "only the live status line uses escapes: {:?}", out);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,230 @@
+/*!
+Code Assistant for Phi Models
+
+Completes code at a `<CURSOR>` marker using a fill-in-the-middle (FIM) prompt: the code
+before the marker is the prefix, the code after it the suffix, and the model generates
+what goes between them. With `--explain` the file is summarized instead.
+*/
+
+use anyhow::{bail, Context, Result};
+use burn_phi_local_llm::telemetry::LogFormat;
+use burn_phi_local_llm::{
+    sampling, ChatSession, PhiInference, PhiModel, PhiModelManager, SamplingConfig, MAX_INPUT_BYTES,
+};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+/// Marks where the completion is inserted
+const CURSOR_MARKER: &str = "<CURSOR>";
+
+/// FIM sentinels, in prefix-suffix-middle order
+const FIM_PREFIX: &str = "<|fim_prefix|>";
+const FIM_SUFFIX: &str = "<|fim_suffix|>";
+const FIM_MIDDLE: &str = "<|fim_middle|>";
+
+/// Bytes kept on each side of the cursor, so the prompt stays under the input limit
+const FIM_CONTEXT_BYTES: usize = MAX_INPUT_BYTES / 2 - 256;
+
+const CODE_SYSTEM_PROMPT: &str = "You are Phi, a code completion engine. Given the code \
+before and after the cursor, reply with only the code that belongs at the cursor: no \
+explanations, no markdown fences, and nothing that repeats the surrounding code.";
+
+#[derive(Parser)]
+#[command(name = "code-assistant")]
+#[command(about = "Complete or explain code with Microsoft Phi models")]
+#[command(version = "1.0.0")]
+struct Args {
+    /// Source file; completions are inserted at its <CURSOR> marker
+    #[arg(short, long)]
+    file: PathBuf,
+
+    /// Language of the file (inferred from its extension when omitted)
+    #[arg(short, long)]
+    language: Option<String>,
+
+    /// Which Phi model to use (phi1, phi15, phi2, phi3, phi35, phi4, phi4-mini)
+    #[arg(short, long, default_value = "phi4-mini", value_parser = parse_model)]
+    model: PhiModel,
+
+    /// Maximum tokens to generate
+    #[arg(long, default_value = "256", value_parser = sampling::parse_max_tokens)]
+    max_tokens: usize,
+
+    /// Summarize what the file does instead of completing it
+    #[arg(long)]
+    explain: bool,
+
+    /// Log line format (text, or json for log ingestion)
+    #[arg(long, default_value = "text")]
+    log_format: LogFormat,
+}
+
+fn parse_model(name: &str) -> Result<PhiModel, String> {
+    PhiModel::from_short_name(name).ok_or_else(|| {
+        format!(
+            "unknown model '{}' (expected phi1, phi15, phi2, phi3, phi35, phi4 or phi4-mini)",
+            name
+        )
+    })
+}
+
+/// Code on either side of the cursor
+#[derive(Debug, PartialEq)]
+struct FimPrompt<'a> {
+    prefix: &'a str,
+    suffix: &'a str,
+}
+
+impl<'a> FimPrompt<'a> {
+    /// Split `source` at its single cursor marker
+    fn from_source(source: &'a str) -> Result<Self> {
+        let Some((prefix, suffix)) = source.split_once(CURSOR_MARKER) else {
+            bail!(
+                "no {} marker found; put one where the completion should go",
+                CURSOR_MARKER
+            );
+        };
+        if suffix.contains(CURSOR_MARKER) {
+            bail!("found more than one {} marker", CURSOR_MARKER);
+        }
+        Ok(Self { prefix, suffix })
+    }
+
+    /// Prompt with the code nearest the cursor on each side, in prefix-suffix-middle order
+    fn render(&self, language: &str) -> String {
+        format!(
+            "Language: {}\n{}{}{}{}{}",
+            language,
+            FIM_PREFIX,
+            tail(self.prefix, FIM_CONTEXT_BYTES),
+            FIM_SUFFIX,
+            head(self.suffix, FIM_CONTEXT_BYTES),
+            FIM_MIDDLE
+        )
+    }
+}
+
+/// Prompt asking for a summary of `source`
+fn explain_prompt(source: &str, language: &str) -> String {
+    format!(
+        "Summarize what this {} file does, then list its main functions and anything that \
+         looks like a bug.\n\n```{}\n{}\n```",
+        language,
+        language,
+        head(
+            &source.replace(CURSOR_MARKER, ""),
+            FIM_CONTEXT_BYTES * 2 - 256
+        )
+    )
+}
+
+/// Last `max_bytes` of `s`, starting on a character boundary
+fn tail(s: &str, max_bytes: usize) -> &str {
+    let mut start = s.len().saturating_sub(max_bytes);
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    &s[start..]
+}
+
+/// First `max_bytes` of `s`, ending on a character boundary
+fn head(s: &str, max_bytes: usize) -> &str {
+    let mut end = s.len().min(max_bytes);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Language name for a file extension, for the prompt
+fn language_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).unwrap_or("") {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "rb" => "ruby",
+        "sh" => "bash",
+        _ => "text",
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    burn_phi_local_llm::init_tracing_with_format(args.log_format);
+
+    let source = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {:?}", args.file))?;
+    let language = args
+        .language
+        .clone()
+        .unwrap_or_else(|| language_for(&args.file).to_string());
+
+    let (prompt, system_prompt) = if args.explain {
+        (explain_prompt(&source, &language), None)
+    } else {
+        let fim = FimPrompt::from_source(&source)
+            .with_context(|| format!("Cannot complete {:?}", args.file))?;
+        (fim.render(&language), Some(CODE_SYSTEM_PROMPT.to_string()))
+    };
+
+    let manager = PhiModelManager::default()?;
+    let inference = manager
+        .load_with_repair(&args.model, |path| async move {
+            PhiInference::load(&path).await
+        })
+        .await
+        .context("Failed to load model")?;
+    tracing::info!("Model ready at: {:?}", inference.model_path());
+
+    let mut session = ChatSession::new(args.model, system_prompt, true, false)
+        .with_history_turns(0)
+        .with_sampling(SamplingConfig {
+            temperature: 0.2,
+            max_tokens: args.max_tokens,
+            ..SamplingConfig::default()
+        });
+
+    println!("{}", session.generate_response(&prompt).await?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fim_prompt_splits_at_cursor() {
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    <CURSOR>\n}\n";
+        let fim = FimPrompt::from_source(source).unwrap();
+
+        assert_eq!(fim.prefix, "fn add(a: i32, b: i32) -> i32 {\n    ");
+        assert_eq!(fim.suffix, "\n}\n");
+        assert_eq!(
+            fim.render("rust"),
+            "Language: rust\n<|fim_prefix|>fn add(a: i32, b: i32) -> i32 {\n    \
+             <|fim_suffix|>\n}\n<|fim_middle|>"
+        );
+    }
+
+    #[test]
+    fn test_fim_prompt_requires_one_cursor() {
+        assert!(FimPrompt::from_source("no marker here").is_err());
+        assert!(FimPrompt::from_source("a <CURSOR> b <CURSOR> c").is_err());
+    }
+
+    #[test]
+    fn test_fim_context_is_cut_on_char_boundaries() {
+        let prefix = "é".repeat(FIM_CONTEXT_BYTES);
+        let source = format!("{}<CURSOR>{}", prefix, prefix);
+        let prompt = FimPrompt::from_source(&source).unwrap().render("text");
+
+        assert!(prompt.len() < MAX_INPUT_BYTES);
+        assert_eq!(language_for(Path::new("src/main.rs")), "rust");
+    }
+}
@@ -0,0 +1,163 @@
+/*!
+Phi Model Downloader
+
+Fetches a Phi model's ONNX weights from Hugging Face into the local cache so the chat
+and code assistant binaries can start without a download, and inspects or clears that
+cache. Without a subcommand the `--model` given is pulled.
+*/
+
+use anyhow::Result;
+use burn_phi_local_llm::{
+    format_bytes, parse_bytes, PhiModel, PhiModelManager, Quantization, RetryPolicy,
+};
+use clap::{Parser, Subcommand};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "download-phi")]
+#[command(about = "Download Microsoft Phi models into the local cache")]
+#[command(version = "1.0.0")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<CacheCommand>,
+
+    /// Which Phi model to download when no subcommand is given (phi1, phi15, phi2, phi3, phi35, phi4, phi4-mini)
+    #[arg(short, long, default_value = "phi3", value_parser = parse_model)]
+    model: PhiModel,
+
+    /// Cache directory (defaults to the platform cache dir)
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Precision of the weights to pull (f32, f16, int8, int4); cached apart from the others
+    #[arg(long, global = true, default_value = "f16")]
+    quantization: Quantization,
+
+    /// Times to retry a download after a timeout, dropped connection or server error
+    #[arg(long, global = true, default_value = "3")]
+    max_retries: u32,
+
+    /// Parallel connections per file when the server supports range requests
+    #[arg(long, global = true, default_value = "4", value_parser = clap::value_parser!(u32).range(1..=16))]
+    connections: u32,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Download a model into the cache and print its path
+    Pull {
+        /// Model to download (phi1, phi15, phi2, phi3, phi35, phi4, phi4-mini)
+        #[arg(value_parser = parse_model)]
+        model: PhiModel,
+    },
+    /// List the cached models
+    List {
+        /// Also show the repository, commit, files and sizes each model was downloaded with
+        #[arg(long)]
+        details: bool,
+    },
+    /// Print the total size of the cache
+    Size,
+    /// Delete the cache directory and every model in it
+    Clean {
+        /// Only delete least recently used models until the cache fits this size (e.g. 20GB)
+        #[arg(long, value_parser = parse_bytes)]
+        max_cache_size: Option<u64>,
+    },
+}
+
+fn parse_model(name: &str) -> Result<PhiModel, String> {
+    PhiModel::from_short_name(name).ok_or_else(|| {
+        format!(
+            "unknown model '{}' (expected phi1, phi15, phi2, phi3, phi35, phi4 or phi4-mini)",
+            name
+        )
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let manager = match &args.cache_dir {
+        Some(dir) => PhiModelManager::new(dir),
+        None => PhiModelManager::default()?,
+    }
+    .with_quantization(args.quantization)
+    .with_retry(RetryPolicy {
+        max_retries: args.max_retries,
+        ..RetryPolicy::default()
+    })
+    .with_connections(args.connections);
+
+    match args.command {
+        None => pull(&manager, &args.model).await,
+        Some(CacheCommand::Pull { model }) => pull(&manager, &model).await,
+        Some(CacheCommand::List { details }) => {
+            let models = manager.list_cached_models_with_manifests().await?;
+            if models.is_empty() {
+                println!("No models cached");
+            }
+            for model in models {
+                println!("{}", model.label);
+                if !details {
+                    continue;
+                }
+                match model.manifest {
+                    Some(manifest) => {
+                        println!("  {}", manifest);
+                        for file in &manifest.files {
+                            println!("    {} ({})", file.name, format_bytes(file.size));
+                        }
+                    }
+                    None => println!("  no manifest"),
+                }
+            }
+            Ok(())
+        }
+        Some(CacheCommand::Size) => {
+            println!("{}", format_bytes(manager.cache_size().await?));
+            Ok(())
+        }
+        Some(CacheCommand::Clean { max_cache_size: None }) => {
+            manager.clear_cache().await?;
+            println!("🧹 Model cache cleared");
+            Ok(())
+        }
+        Some(CacheCommand::Clean {
+            max_cache_size: Some(max_bytes),
+        }) => {
+            for model in manager.evict_to(max_bytes).await? {
+                println!("🧹 Evicted {}", model);
+            }
+            println!("Cache size: {}", format_bytes(manager.cache_size().await?));
+            Ok(())
+        }
+    }
+}
+
+/// Download `model` with a progress line and print where it was cached
+async fn pull(manager: &PhiModelManager, model: &PhiModel) -> Result<()> {
+    println!("📥 {}", model.display_info());
+
+    let mut last_percent = None;
+    let path = manager
+        .ensure_model_with_progress(model, |done, total| {
+            let Some(total) = total.filter(|&total| total > 0) else {
+                return;
+            };
+            let percent = done * 100 / total;
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                print!("\r  {:>3}% of {:.1} MB", percent, total as f64 / (1024.0 * 1024.0));
+                let _ = io::stdout().flush();
+            }
+        })
+        .await?;
+
+    if last_percent.is_some() {
+        println!();
+    }
+    println!("✅ Model ready at {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,38 @@
+/*!
+System Requirements Report
+
+Prints this machine's memory, disk, CPU and GPU resources, the recommended Burn backend,
+and which Phi models are cached and can run. `--json` prints the same report as a single JSON object
+for orchestration scripts.
+*/
+
+use anyhow::Result;
+use burn_phi_local_llm::{check_system_requirements, format_model_list, PhiModelManager};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "system-info")]
+#[command(about = "Report whether this system can run Phi models locally")]
+#[command(version = "1.0.0")]
+struct Args {
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let system = check_system_requirements()?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&system.to_json())?);
+    } else {
+        system.display();
+        println!();
+        let statuses = PhiModelManager::default()?.status(&system).await;
+        print!("{}", format_model_list(&statuses));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+/*!
+Token Counter for Context Planning
+
+Reports how many tokens a piece of text uses for a Phi model and whether it leaves room
+for the requested response within the model's context window.
+
+Tokens are counted with the model's tokenizer when `phi-download` has cached it, at any
+quantization, and estimated otherwise.
+*/
+
+use anyhow::{Context, Result};
+use burn_phi_local_llm::{
+    count_tokens_with, phi_models, sampling, ContextFit, PhiModel, PhiModelManager,
+    Quantization, Tokenizer,
+};
+use clap::Parser;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "phi-tokens")]
+#[command(about = "Count tokens and check that text fits a Phi model's context window")]
+#[command(version = "1.0.0")]
+struct Args {
+    /// Which Phi model to plan for (phi1, phi15, phi2, phi3, phi35, phi4, phi4-mini)
+    #[arg(short, long, default_value = "phi3", value_parser = parse_model)]
+    model: PhiModel,
+
+    /// Read text from this file instead of stdin
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Tokens to reserve for the response
+    #[arg(long, default_value = "512", value_parser = sampling::parse_max_tokens)]
+    max_tokens: usize,
+
+    /// Cache directory to find the model's tokenizer in (defaults to the platform cache dir)
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+}
+
+fn parse_model(name: &str) -> Result<PhiModel, String> {
+    PhiModel::from_short_name(name).ok_or_else(|| {
+        format!(
+            "unknown model '{}' (expected phi1, phi15, phi2, phi3, phi35, phi4 or phi4-mini)",
+            name
+        )
+    })
+}
+
+/// The model's tokenizer from the cache, whichever quantization it was downloaded with
+fn cached_tokenizer(cache_dir: &Path, model: &PhiModel) -> Result<Option<Tokenizer>> {
+    let path = Quantization::ALL
+        .into_iter()
+        .map(|quantization| {
+            PhiModelManager::new(cache_dir)
+                .with_quantization(quantization)
+                .tokenizer_path(model)
+        })
+        .find(|path| path.exists());
+    path.map(|path| Tokenizer::from_file(&path)).transpose()
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let text = match &args.file {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {:?}", path))?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+
+    let cache_dir = args.cache_dir.clone().unwrap_or_else(|| {
+        phi_models::default_cache_dir(std::env::var_os(phi_models::CACHE_DIR_ENV))
+    });
+    let tokenizer = cached_tokenizer(&cache_dir, &args.model)?;
+    let fit = ContextFit::new(
+        count_tokens_with(tokenizer.as_ref(), &text),
+        args.max_tokens,
+        args.model.context_length(),
+    );
+
+    println!("Model: {}", args.model.model_name());
+    if tokenizer.is_some() {
+        println!("Tokens: {}", fit.prompt_tokens);
+    } else {
+        println!(
+            "Tokens: ~{} (estimated; download the model for an exact count)",
+            fit.prompt_tokens
+        );
+    }
+    println!("Context length: {}", fit.context_length);
+    println!("Response budget: {}", fit.max_tokens);
+
+    if fit.fits() {
+        println!("Verdict: fits ({} tokens of headroom)", fit.headroom());
+        Ok(())
+    } else {
+        println!("Verdict: does not fit ({} tokens over)", -fit.headroom());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,1569 @@
+/*!
+Chat sessions for Phi models
+
+A [`ChatSession`] holds the conversation history, sampling settings and stop sequences
+for one conversation and produces replies either whole or streamed chunk by chunk. The
+interactive `chat-phi` binary keeps one session for its lifetime; the API server builds
+a fresh one for every request.
+
+The prompt given to the model is the system prompt, history and new input rendered with
+the model's [`ChatTemplate`](crate::ChatTemplate); see [`ChatSession::build_prompt`].
+
+History is bounded twice: by a number of turns, and by the model's context window. Before
+each generation the oldest turns are dropped until the rendered prompt and the
+`max_tokens` response budget fit in `context_length()` tokens, as counted by
+[`ChatSession::count_tokens`]: with the model's tokenizer when it has one, otherwise
+estimated.
+
+A session can also be given a timeout: a generation still running when it expires is
+dropped, which cancels it, and fails with [`GenerationTimeout`].
+
+Prompts and replies pass through the session's [`ContentFilter`]; a blocked one fails
+the generation with [`ContentBlocked`](crate::filter::ContentBlocked) and leaves the
+history untouched.
+
+Sampling draws its random numbers from the session's RNG. Sessions given the same seed
+with [`ChatSession::with_seed`] produce the same replies to the same prompts and
+sampling settings; unseeded sessions are seeded from the system.
+
+A session given a [`PhiInference`] with a runnable graph and tokenizer through
+[`ChatSession::with_inference`] samples its replies token by token from the model; other
+sessions answer with canned demo replies. The model's key/value cache carries over between
+turns, so a follow-up only runs the tokens after the prefix it shares with the previous
+prompt; [`ChatSession::reset_cache`] empties it.
+
+A user turn can also be given as a list of [`Message`] parts with
+[`ChatSession::generate_messages`]. Text parts are joined into the prompt; image parts
+are rejected with [`ImageInputUnsupported`] by models that only read text, which today is
+every Phi model.
+
+[`ChatSession::export_markdown`] saves the system prompt and every turn as a Markdown
+transcript headed by the model name and the time of export.
+*/
+
+use anyhow::{Context, Result};
+use futures::Stream;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::filter::{self, ContentFilter, FilterStage, NoopFilter};
+use crate::metrics::{LogSink, MetricsSink};
+use crate::sampling::{sample_next_token, REPEAT_PENALTY_WINDOW};
+use crate::stop::{self, StopDetector};
+use crate::{
+    sanitize_input, ContextFit, Generation, GenerationTiming, PhiInference, PhiModel,
+    SamplingConfig, MAX_INPUT_BYTES,
+};
+
+/// Default number of conversation turns kept in history
+pub const DEFAULT_HISTORY_TURNS: usize = 10;
+
+/// Short fixed prompt used to warm up the model after loading
+const WARMUP_PROMPT: &str = "Hello";
+
+/// Token budget for the warmup generation
+const WARMUP_MAX_TOKENS: usize = 8;
+
+/// Pause between words when streaming a demo response
+const DEMO_CHUNK_DELAY: Duration = Duration::from_millis(20);
+
+/// Word groups the demo response's closing sentence is sampled from, one group after
+/// another, each with the logits of its alternatives
+const DEMO_CLOSING: [&[(&str, f32)]; 2] = [
+    &[
+        ("Let me know", 2.0),
+        ("Feel free to ask", 1.6),
+        ("Just say so", 1.2),
+        ("Tell me", 0.8),
+    ],
+    &[
+        ("if you'd like more detail.", 2.0),
+        ("if anything is unclear.", 1.6),
+        ("if an example would help.", 1.2),
+        ("if you have a follow-up.", 0.8),
+    ],
+];
+
+/// Error returned when a generation does not finish within the session's timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationTimeout(pub Duration);
+
+impl fmt::Display for GenerationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "generation timed out after {}s", self.0.as_secs_f64())
+    }
+}
+
+impl std::error::Error for GenerationTimeout {}
+
+/// One part of a user turn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    /// Encoded image bytes, such as a PNG or JPEG file
+    Image(Vec<u8>),
+}
+
+/// Error returned when an image is sent to a model that only reads text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInputUnsupported {
+    /// Hugging Face name of the model
+    pub model: String,
+}
+
+impl fmt::Display for ImageInputUnsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "this model does not support image input ({})", self.model)
+    }
+}
+
+impl std::error::Error for ImageInputUnsupported {}
+
+/// Append a `## heading` followed by `content` in a fenced block
+fn push_markdown_section(markdown: &mut String, heading: &str, content: &str) {
+    let mut longest_run = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    markdown.push_str(&format!("\n## {}\n\n{}\n{}\n{}\n", heading, fence, content, fence));
+}
+
+/// `secs` since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`
+fn format_utc(secs: u64) -> String {
+    // Days to a civil date, from Howard Hinnant's `civil_from_days`
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// A reply being sampled token by token from a session's model
+struct Sampler {
+    inference: Arc<PhiInference>,
+    /// End-of-text and end-of-turn tokens, which end the reply
+    eos: Vec<u32>,
+    /// Prompt tokens followed by the tokens sampled so far
+    ids: Vec<u32>,
+    prompt_len: usize,
+    /// The sampled tokens decoded together
+    text: String,
+}
+
+/// One token added to a reply by [`ChatSession::sample_token`]
+struct SampledChunk {
+    /// Text the token adds to the decoded reply
+    chunk: String,
+    /// The token decoded on its own
+    token: String,
+    logprob: f32,
+}
+
+/// Where the chunks of a streamed reply come from
+enum ChunkSource {
+    /// Words of a demo reply
+    Demo(VecDeque<String>),
+    /// Tokens sampled from the model as the stream is polled
+    Model(Box<Sampler>),
+}
+
+/// Progress of a `generate_stream` call
+enum StreamState<'a> {
+    Start(&'a mut ChatSession, String),
+    Streaming {
+        session: &'a mut ChatSession,
+        input: String,
+        source: ChunkSource,
+        text: String,
+        timing: GenerationTiming,
+        stop: StopDetector,
+    },
+}
+
+/// Chat session management
+pub struct ChatSession {
+    pub model: PhiModel,
+    conversation_history: Vec<(String, String)>, // (user, assistant) pairs
+    system_prompt: Option<String>,
+    coding_mode: bool,
+    math_mode: bool,
+    max_history_turns: usize,
+    pub sampling: SamplingConfig,
+    stop_sequences: Vec<String>,
+    metrics: Box<dyn MetricsSink>,
+    timeout: Option<Duration>,
+    filter: Arc<dyn ContentFilter>,
+    seed: Option<u64>,
+    rng: fastrand::Rng,
+    /// Recently sampled tokens, which the repetition settings steer away from
+    generated: Vec<usize>,
+    /// Shared with other sessions, such as the API server's requests for one model
+    inference: Option<Arc<PhiInference>>,
+    /// Prompt tokens the model ran for the last sampled reply
+    prefilled_tokens: Option<usize>,
+}
+
+impl ChatSession {
+    pub fn new(model: PhiModel, system_prompt: Option<String>, coding_mode: bool, math_mode: bool) -> Self {
+        let enhanced_system = if let Some(base) = system_prompt {
+            Some(Self::enhance_system_prompt(base, coding_mode, math_mode))
+        } else {
+            Some(Self::default_system_prompt(coding_mode, math_mode))
+        };
+
+        Self {
+            model,
+            conversation_history: Vec::new(),
+            system_prompt: enhanced_system,
+            coding_mode,
+            math_mode,
+            max_history_turns: DEFAULT_HISTORY_TURNS,
+            sampling: SamplingConfig::default(),
+            stop_sequences: Vec::new(),
+            metrics: Box::new(LogSink),
+            timeout: None,
+            filter: Arc::new(NoopFilter),
+            seed: None,
+            rng: fastrand::Rng::new(),
+            generated: Vec::new(),
+            inference: None,
+            prefilled_tokens: None,
+        }
+    }
+
+    /// Seed the sampling RNG so generations are reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Seed given with [`with_seed`](Self::with_seed), if any
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Set the sink that generation metrics are emitted to
+    pub fn with_metrics(mut self, metrics: Box<dyn MetricsSink>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Set the sampling parameters used for generation
+    pub fn with_sampling(mut self, sampling: SamplingConfig) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Set the strings that end a generation when produced
+    pub fn with_stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.stop_sequences = stop_sequences;
+        self
+    }
+
+    /// Set how many (user, assistant) turns are retained between requests
+    pub fn with_history_turns(mut self, max_history_turns: usize) -> Self {
+        self.max_history_turns = max_history_turns;
+        self
+    }
+
+    /// Fail generations that take longer than `timeout`; without one they run to completion
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Check prompts and replies with `filter`; by default nothing is filtered
+    pub fn with_filter(mut self, filter: Arc<dyn ContentFilter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Generate replies with `inference` when it has a graph and tokenizer to run;
+    /// otherwise the session keeps answering with demo replies
+    pub fn with_inference(mut self, inference: impl Into<Arc<PhiInference>>) -> Self {
+        self.inference = Some(inference.into());
+        self
+    }
+
+    /// Whether replies are sampled from the model rather than demo replies
+    fn samples_from_model(&self) -> bool {
+        self.inference
+            .as_ref()
+            .is_some_and(|inference| inference.can_generate())
+    }
+
+    /// Reply to `input`, rendered as `prompt`, from the model if it can generate and with
+    /// a demo response otherwise, along with each token's logprob
+    async fn reply(&mut self, input: &str, prompt: &str) -> Result<(String, Vec<(String, f32)>)> {
+        if self.samples_from_model() {
+            self.sample_reply(prompt).await
+        } else {
+            Ok(self.generate_demo_response(input).await)
+        }
+    }
+
+    /// Render the prompt for `input`, first dropping the history that no longer fits the
+    /// context window
+    fn prepare_prompt(&mut self, input: &str) -> String {
+        self.trim_history_to_context(input);
+        let prompt = self.build_prompt(&self.enhance_input(input));
+        tracing::trace!(%prompt, "Rendered prompt");
+        prompt
+    }
+
+    /// Forget the model's cached keys and values, so the next turn processes its whole
+    /// prompt
+    pub fn reset_cache(&mut self) {
+        if let Some(inference) = &self.inference {
+            inference.reset_cache();
+        }
+    }
+
+    /// Prompt tokens the model had to run for the last sampled reply; the rest came from
+    /// its key/value cache. `None` until a reply has been sampled from a model.
+    pub fn prefilled_tokens(&self) -> Option<usize> {
+        self.prefilled_tokens
+    }
+
+    /// Sample a reply to `prompt` from the model's logits, with each token's logprob
+    async fn sample_reply(&mut self, prompt: &str) -> Result<(String, Vec<(String, f32)>)> {
+        let mut sampler = self.start_sampling(prompt)?;
+        let mut logprobs = Vec::with_capacity(self.sampling.max_tokens);
+        while let Some(sampled) = self.sample_token(&mut sampler).await? {
+            logprobs.push((sampled.token, sampled.logprob));
+        }
+        self.trim_generated();
+        Ok((sampler.text, logprobs))
+    }
+
+    /// Tokenize `prompt` to start sampling a reply to it
+    ///
+    /// A prompt that leaves too little of the context window for the reply keeps only its
+    /// last tokens.
+    fn start_sampling(&self, prompt: &str) -> Result<Sampler> {
+        let inference = Arc::clone(self.inference.as_ref().context("no model is loaded")?);
+        let tokenizer = inference.tokenizer().context("the model has no tokenizer")?;
+        let eos = tokenizer.eos_token_ids();
+
+        let mut ids = tokenizer.encode(prompt)?;
+        let budget = self
+            .model
+            .context_length()
+            .saturating_sub(self.sampling.max_tokens)
+            .max(1);
+        if ids.len() > budget {
+            tracing::debug!(
+                dropped = ids.len() - budget,
+                "Dropped the start of the prompt to fit the context window"
+            );
+            ids.drain(..ids.len() - budget);
+        }
+        if ids.is_empty() {
+            anyhow::bail!("the prompt encodes to no tokens");
+        }
+
+        Ok(Sampler {
+            inference,
+            eos,
+            prompt_len: ids.len(),
+            ids,
+            text: String::new(),
+        })
+    }
+
+    /// Sample the next token of `sampler`'s reply
+    ///
+    /// Returns `None` once `max_tokens` tokens have been sampled or the model produces an
+    /// end-of-text or end-of-turn token. Each step reuses the key/value cache, so only the
+    /// tokens the cache doesn't hold run through the graph. The forward pass runs on the
+    /// blocking thread pool so it can't stall the runtime, and a timeout cancels the
+    /// generation between tokens.
+    async fn sample_token(&mut self, sampler: &mut Sampler) -> Result<Option<SampledChunk>> {
+        let sampled = sampler.ids.len() - sampler.prompt_len;
+        if sampled >= self.sampling.max_tokens {
+            return Ok(None);
+        }
+
+        let context_length = self.model.context_length();
+        let window = sampler.ids[sampler.ids.len().saturating_sub(context_length)..].to_vec();
+        let model = Arc::clone(&sampler.inference);
+        let logits = tokio::task::spawn_blocking(move || {
+            model.next_token_logits_cached(&window, context_length)
+        })
+        .await
+        .context("the forward pass panicked")??;
+        if sampled == 0 {
+            tracing::debug!(
+                processed = logits.processed_tokens,
+                cached = sampler.ids.len().min(context_length) - logits.processed_tokens,
+                "Prefilled prompt"
+            );
+            self.prefilled_tokens = Some(logits.processed_tokens);
+        }
+
+        let token =
+            sample_next_token(&logits.logits, &self.generated, &self.sampling, self.rng.f32());
+        let id = token.index as u32;
+        if sampler.eos.contains(&id) {
+            return Ok(None);
+        }
+        self.generated.push(token.index);
+        sampler.ids.push(id);
+
+        let tokenizer = sampler.inference.tokenizer().context("the model has no tokenizer")?;
+        let text = tokenizer.decode(&sampler.ids[sampler.prompt_len..])?;
+        let token_text = tokenizer.decode(&[id])?;
+        // Decoding the reply whole keeps the spacing that a lone token decodes without
+        let chunk = match text.strip_prefix(sampler.text.as_str()) {
+            Some(added) => added.to_string(),
+            None => token_text.clone(),
+        };
+        sampler.text = text;
+        Ok(Some(SampledChunk {
+            chunk,
+            token: token_text,
+            logprob: token.logprob,
+        }))
+    }
+
+    /// Keep only the sampled tokens the repetition settings look at
+    fn trim_generated(&mut self) {
+        let excess = self.generated.len().saturating_sub(REPEAT_PENALTY_WINDOW);
+        self.generated.drain(..excess);
+    }
+
+    fn default_system_prompt(coding_mode: bool, math_mode: bool) -> String {
+        let mut prompt = "You are Phi, a helpful AI assistant created by Microsoft.".to_string();
+        
+        if coding_mode {
+            prompt.push_str(" You specialize in helping with programming tasks, code generation, debugging, and software development best practices.");
+        }
+        
+        if math_mode {
+            prompt.push_str(" You excel at mathematical reasoning, problem solving, and explaining complex mathematical concepts clearly.");
+        }
+        
+        prompt.push_str(" You provide accurate, helpful, and concise responses.");
+        prompt
+    }
+
+    fn enhance_system_prompt(base: String, coding_mode: bool, math_mode: bool) -> String {
+        let mut enhanced = base;
+        
+        if coding_mode {
+            enhanced.push_str("\n\nCoding Assistant Mode: Focus on programming tasks, code quality, and best practices.");
+        }
+        
+        if math_mode {
+            enhanced.push_str("\n\nMath Assistant Mode: Emphasize mathematical accuracy and clear step-by-step explanations.");
+        }
+        
+        enhanced
+    }
+
+    /// Generate a reply to `input`, failing with [`GenerationTimeout`] if it runs too long
+    pub async fn generate(&mut self, input: &str) -> Result<Generation> {
+        let Some(limit) = self.timeout else {
+            return self.generate_untimed(input).await;
+        };
+        match tokio::time::timeout(limit, self.generate_untimed(input)).await {
+            Ok(generation) => generation,
+            Err(_) => Err(GenerationTimeout(limit).into()),
+        }
+    }
+
+    /// Generate a reply to a user turn made of text and image parts
+    ///
+    /// Text parts are joined with newlines into one input. Fails with
+    /// [`ImageInputUnsupported`], before anything is generated, if an image is given to a
+    /// model that only reads text.
+    pub async fn generate_messages(&mut self, messages: Vec<Message>) -> Result<Generation> {
+        let mut texts = Vec::with_capacity(messages.len());
+        for message in messages {
+            match message {
+                Message::Text(text) => texts.push(text),
+                Message::Image(_) if !self.model.supports_images() => {
+                    let model = self.model.model_name().to_string();
+                    return Err(ImageInputUnsupported { model }.into());
+                }
+                Message::Image(_) => anyhow::bail!("image input is not implemented yet"),
+            }
+        }
+        self.generate(&texts.join("\n")).await
+    }
+
+    #[tracing::instrument(
+        name = "generate",
+        skip(self, input),
+        fields(
+            model = self.model.model_name(),
+            prompt_chars = input.len(),
+            max_tokens = self.sampling.max_tokens,
+            top_p = self.sampling.top_p,
+            top_k = self.sampling.top_k,
+            tokens = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        )
+    )]
+    async fn generate_untimed(&mut self, input: &str) -> Result<Generation> {
+        let input = &sanitize_input(input, MAX_INPUT_BYTES);
+        self.check_content(input, FilterStage::Input)?;
+        let prompt = self.prepare_prompt(input);
+
+        let start = Instant::now();
+        let (reply, token_logprobs) = self.reply(input, &prompt).await?;
+        let response = stop::truncate_at_stop(&reply, &self.stop_sequences);
+        self.check_content(&response, FilterStage::Output)?;
+
+        let tokens = response.split_whitespace().count();
+        let span = tracing::Span::current();
+        span.record("tokens", tokens);
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+
+        let tags = [("model", self.model.model_name())];
+        self.metrics.timing("phi.inference.latency_ms", start.elapsed(), &tags);
+        self.metrics.increment("phi.inference.requests", 1, &tags);
+        self.metrics.gauge("phi.inference.tokens", tokens as f64, &tags);
+        
+        let logprobs = self
+            .sampling
+            .return_logprobs
+            .then(|| tokens_within(&response, token_logprobs));
+
+        self.record_turn(input, &response);
+
+        Ok(Generation {
+            text: response,
+            logprobs,
+        })
+    }
+
+    /// Stream the reply to `input` as it is generated
+    ///
+    /// A reply sampled from the model is yielded token by token as each is sampled; a demo
+    /// reply is known whole up front and yielded word by word with a short pause between
+    /// words. The turn is added to history only once the stream completes, so a stream
+    /// dropped part-way (e.g. cancelled with Ctrl-C) leaves the conversation unchanged,
+    /// apart from turns that had to be dropped to fit the context window.
+    ///
+    /// With a timeout set, the stream yields a [`GenerationTimeout`] error and ends once the
+    /// time since the call exceeds it.
+    pub fn generate_stream(&mut self, input: &str) -> impl Stream<Item = Result<String>> + '_ {
+        let input = sanitize_input(input, MAX_INPUT_BYTES);
+        let deadline = self
+            .timeout
+            .map(|limit| (tokio::time::Instant::now() + limit, limit));
+
+        futures::stream::unfold(Some(StreamState::Start(self, input)), move |state| async move {
+            let step = advance_stream(state?);
+            let Some((deadline, limit)) = deadline else {
+                return step.await;
+            };
+            match tokio::time::timeout_at(deadline, step).await {
+                Ok(item) => item,
+                Err(_) => Some((Err(GenerationTimeout(limit).into()), None)),
+            }
+        })
+    }
+
+    /// Seed the conversation with earlier (user, assistant) turns
+    pub fn with_history(mut self, history: Vec<(String, String)>) -> Self {
+        self.conversation_history = history;
+        self
+    }
+
+    /// Completed (user, assistant) turns, oldest first
+    pub fn history(&self) -> &[(String, String)] {
+        &self.conversation_history
+    }
+
+    /// Forget the conversation so far
+    pub fn clear_history(&mut self) {
+        self.conversation_history.clear();
+        self.generated.clear();
+        self.reset_cache();
+    }
+
+    /// Render the system prompt and every turn as a Markdown transcript
+    ///
+    /// Each message is a fenced block longer than any run of backticks inside it, so code
+    /// blocks in a reply can't close it early.
+    pub fn to_markdown(&self) -> String {
+        let exported_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let mut markdown = format!(
+            "# Chat with {}\n\nExported {}\n",
+            self.model.model_name(),
+            format_utc(exported_at)
+        );
+        if let Some(system) = &self.system_prompt {
+            push_markdown_section(&mut markdown, "System", system);
+        }
+        for (user, assistant) in &self.conversation_history {
+            push_markdown_section(&mut markdown, "User", user);
+            push_markdown_section(&mut markdown, "Assistant", assistant);
+        }
+        markdown
+    }
+
+    /// Write the [`to_markdown`](Self::to_markdown) transcript to `path`
+    pub fn export_markdown(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_markdown())
+            .with_context(|| format!("Failed to write transcript {:?}", path))
+    }
+
+    /// Append a completed turn, keeping the history within its limits
+    fn record_turn(&mut self, input: &str, response: &str) {
+        self.conversation_history.push((input.to_string(), response.to_string()));
+
+        // Keep conversation history manageable
+        if self.conversation_history.len() > self.max_history_turns {
+            let excess = self.conversation_history.len() - self.max_history_turns;
+            self.conversation_history.drain(..excess);
+        }
+        self.trim_history_to_context("");
+    }
+
+    /// Estimated prompt size and response budget against the model's context window
+    ///
+    /// The prompt is the system prompt plus the retained history; the next input comes on
+    /// top of it.
+    pub fn context_usage(&self) -> ContextFit {
+        ContextFit::new(
+            self.prompt_tokens(""),
+            self.sampling.max_tokens,
+            self.model.context_length(),
+        )
+    }
+
+    /// Prompt for the model's reply to `input`: the system prompt, history and `input`
+    /// rendered with the model's chat template
+    pub fn build_prompt(&self, input: &str) -> String {
+        self.model.chat_template().render(
+            self.system_prompt.as_deref(),
+            &self.conversation_history,
+            input,
+        )
+    }
+
+    /// Number of tokens `text` uses with the session model's tokenizer, estimated when
+    /// no model with a tokenizer is attached
+    pub fn count_tokens(&self, text: &str) -> usize {
+        let tokenizer = self.inference.as_ref().and_then(|inference| inference.tokenizer());
+        crate::count_tokens_with(tokenizer, text)
+    }
+
+    /// Tokens of the prompt built for `input`
+    fn prompt_tokens(&self, input: &str) -> usize {
+        self.count_tokens(&self.build_prompt(input))
+    }
+
+    /// Drop the oldest turns until the prompt for `input` and `max_tokens` fit the window
+    fn trim_history_to_context(&mut self, input: &str) {
+        let budget = self
+            .model
+            .context_length()
+            .saturating_sub(self.sampling.max_tokens);
+        let mut dropped = 0;
+        while !self.conversation_history.is_empty() && self.prompt_tokens(input) > budget {
+            self.conversation_history.remove(0);
+            dropped += 1;
+        }
+        if dropped > 0 {
+            tracing::debug!(dropped, "Dropped history turns to fit the context window");
+        }
+    }
+
+    /// Run `text` past the content filter, failing with `ContentBlocked` if it is blocked
+    fn check_content(&self, text: &str, stage: FilterStage) -> Result<()> {
+        let result = match stage {
+            FilterStage::Input => self.filter.check_input(text),
+            FilterStage::Output => self.filter.check_output(text),
+        };
+        filter::enforce(result, stage)
+    }
+
+    /// Generate a reply and return only its text
+    pub async fn generate_response(&mut self, input: &str) -> Result<String> {
+        Ok(self.generate(input).await?.text)
+    }
+
+    /// Run a short throwaway generation that leaves history, sampling and the RNG untouched
+    pub async fn warmup(&mut self) -> Result<Duration> {
+        let start = Instant::now();
+        let sampling = self.sampling.clone();
+        let rng = self.rng.clone();
+        let generated = self.generated.len();
+        self.sampling.max_tokens = WARMUP_MAX_TOKENS;
+
+        let result = self.generate_response(WARMUP_PROMPT).await;
+        self.sampling = sampling;
+        self.rng = rng;
+        self.generated.truncate(generated);
+        result?;
+        self.conversation_history.pop();
+
+        Ok(start.elapsed())
+    }
+
+    fn enhance_input(&self, input: &str) -> String {
+        let mut enhanced = input.to_string();
+
+        if self.coding_mode && (input.contains("code") || input.contains("function") || input.contains("bug")) {
+            enhanced = format!("[CODING TASK] {}", enhanced);
+        }
+
+        if self.math_mode && (input.contains("solve") || input.contains("calculate") || input.contains("equation")) {
+            enhanced = format!("[MATH PROBLEM] {}", enhanced);
+        }
+
+        enhanced
+    }
+
+    /// Canned reply to `input` followed by a sampled closing sentence, with the logprob of
+    /// each word
+    ///
+    /// The canned part is fixed, so its words have logprob 0. Each closing phrase is one
+    /// sampled token: its first word carries the phrase's logprob and the rest follow with
+    /// certainty.
+    async fn generate_demo_response(&mut self, input: &str) -> (String, Vec<(String, f32)>) {
+        let mut response = self.demo_reply(input).await;
+        let mut logprobs: Vec<(String, f32)> = response
+            .split_whitespace()
+            .map(|word| (word.to_string(), 0.0))
+            .collect();
+        for (phrase, logprob) in self.demo_closing() {
+            response.push(' ');
+            response.push_str(phrase);
+            for (index, word) in phrase.split_whitespace().enumerate() {
+                logprobs.push((word.to_string(), if index == 0 { logprob } else { 0.0 }));
+            }
+        }
+        (response, logprobs)
+    }
+
+    /// Phrases of a closing sentence sampled from [`DEMO_CLOSING`] with the session's
+    /// sampling settings, each with its logprob
+    ///
+    /// Every phrase is a token of one vocabulary spanning the groups, so the repetition
+    /// penalty and n-gram ban steer away from the closings of earlier turns.
+    fn demo_closing(&mut self) -> Vec<(&'static str, f32)> {
+        let vocab_size = DEMO_CLOSING.iter().map(|group| group.len()).sum();
+        let mut offset = 0;
+        let mut phrases = Vec::with_capacity(DEMO_CLOSING.len());
+        for group in DEMO_CLOSING {
+            let mut logits = vec![f32::NEG_INFINITY; vocab_size];
+            for (index, (_, logit)) in group.iter().enumerate() {
+                logits[offset + index] = *logit;
+            }
+            let token = sample_next_token(&logits, &self.generated, &self.sampling, self.rng.f32());
+            phrases.push((group[token.index - offset].0, token.logprob));
+            self.generated.push(token.index);
+            offset += group.len();
+        }
+
+        let excess = self.generated.len().saturating_sub(REPEAT_PENALTY_WINDOW);
+        self.generated.drain(..excess);
+        phrases
+    }
+
+    async fn demo_reply(&self, input: &str) -> String {
+        // Simulate processing time
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        // Generate contextual demo responses based on the model and input
+        match &self.model {
+            PhiModel::Phi2 { .. } => {
+                if input.to_lowercase().contains("code") {
+                    "I'd be happy to help with coding! As Phi-2, I can assist with code generation, explanation, and basic debugging. What specific programming task are you working on?".to_string()
+                } else if input.to_lowercase().contains("math") {
+                    "I can help with mathematical problems! Please share the specific math question or equation you'd like me to work on.".to_string()
+                } else {
+                    format!("Thank you for your question about '{}'. As Phi-2, I'm designed to help with language comprehension and reasoning tasks. How can I assist you further?", input)
+                }
+            }
+            PhiModel::Phi3 { .. } => {
+                if self.coding_mode && input.to_lowercase().contains("code") {
+                    "As Phi-3 in coding mode, I'm optimized for programming tasks! I can help with:\n• Code generation and completion\n• Debugging and error analysis\n• Algorithm design\n• Best practices\n\nWhat would you like to work on?".to_string()
+                } else if self.math_mode && input.to_lowercase().contains("math") {
+                    "Phi-3 excels at mathematical reasoning! I can help with:\n• Problem solving step-by-step\n• Equation solving\n• Mathematical proofs\n• Concept explanation\n\nWhat math problem shall we tackle?".to_string()
+                } else {
+                    format!("I'm Phi-3, designed for coding, math, and reasoning tasks. Regarding '{}', I can provide detailed analysis and solutions. What specific aspect would you like me to focus on?", input)
+                }
+            }
+            PhiModel::Phi4 { .. } => {
+                "As Phi-4, I excel at complex reasoning and mathematical problem solving. I can provide sophisticated analysis with step-by-step reasoning. What challenging problem would you like me to work on?".to_string()
+            }
+            _ => {
+                format!("I understand you're asking about '{}'. How can I help you with this?", input)
+            }
+        }
+    }
+}
+
+/// The leading `tokens` found in order in `text`, dropping those cut off by a stop sequence
+fn tokens_within(text: &str, tokens: Vec<(String, f32)>) -> Vec<(String, f32)> {
+    let mut rest = text;
+    tokens
+        .into_iter()
+        .map_while(|(token, logprob)| {
+            let start = rest.find(token.as_str())?;
+            rest = &rest[start + token.len()..];
+            Some((token, logprob))
+        })
+        .collect()
+}
+
+/// Run the generation on the first call, then emit its chunks one at a time
+async fn advance_stream(state: StreamState<'_>) -> Option<(Result<String>, Option<StreamState<'_>>)> {
+    match state {
+        StreamState::Start(session, input) => {
+            if let Err(e) = session.check_content(&input, FilterStage::Input) {
+                return Some((Err(e), None));
+            }
+            let timing = GenerationTiming::start();
+            let prompt = session.prepare_prompt(&input);
+            let source = if session.samples_from_model() {
+                match session.start_sampling(&prompt) {
+                    Ok(sampler) => ChunkSource::Model(Box::new(sampler)),
+                    Err(e) => return Some((Err(e), None)),
+                }
+            } else {
+                let (response, _) = session.generate_demo_response(&input).await;
+                // The whole demo reply is known up front, so it is checked before any chunk
+                if let Err(e) = session.check_content(&response, FilterStage::Output) {
+                    return Some((Err(e), None));
+                }
+                ChunkSource::Demo(
+                    response
+                        .split_inclusive(char::is_whitespace)
+                        .map(str::to_string)
+                        .collect(),
+                )
+            };
+            let stop = StopDetector::new(&session.stop_sequences);
+            let state = StreamState::Streaming {
+                session,
+                input,
+                source,
+                text: String::new(),
+                timing,
+                stop,
+            };
+            next_chunk(state).await
+        }
+        state => next_chunk(state).await,
+    }
+}
+
+/// Emit the next queued chunk, or finish the stream by recording the turn and its timing
+///
+/// Chunks pass through the stop detector first; once a stop sequence is produced the
+/// remaining chunks are discarded.
+async fn next_chunk(state: StreamState<'_>) -> Option<(Result<String>, Option<StreamState<'_>>)> {
+    let StreamState::Streaming {
+        session,
+        input,
+        mut source,
+        mut text,
+        mut timing,
+        mut stop,
+    } = state
+    else {
+        return None;
+    };
+
+    let chunk = loop {
+        let next = if stop.is_stopped() {
+            None
+        } else {
+            match &mut source {
+                ChunkSource::Demo(chunks) => chunks.pop_front(),
+                ChunkSource::Model(sampler) => match session.sample_token(sampler).await {
+                    Ok(token) => token.map(|token| token.chunk),
+                    Err(e) => return Some((Err(e), None)),
+                },
+            }
+        };
+        let Some(next) = next else {
+            break stop.finish();
+        };
+        let chunk = stop.push(&next);
+        if !chunk.is_empty() {
+            break chunk;
+        }
+    };
+
+    if chunk.is_empty() {
+        let tags = [("model", session.model.model_name())];
+        timing.report(session.metrics.as_ref(), &tags);
+        session.metrics.increment("phi.inference.requests", 1, &tags);
+        if let ChunkSource::Model(_) = source {
+            session.trim_generated();
+        }
+        session.record_turn(&input, &text);
+        return None;
+    }
+
+    match source {
+        // Sampled text is checked as it grows, before each chunk is sent
+        ChunkSource::Model(_) => {
+            let checked = format!("{}{}", text, chunk);
+            if let Err(e) = session.check_content(&checked, FilterStage::Output) {
+                return Some((Err(e), None));
+            }
+        }
+        ChunkSource::Demo(_) if !text.is_empty() => tokio::time::sleep(DEMO_CHUNK_DELAY).await,
+        ChunkSource::Demo(_) => {}
+    }
+    timing.record_chunk();
+    text.push_str(&chunk);
+    let state = StreamState::Streaming {
+        session,
+        input,
+        source,
+        text,
+        timing,
+        stop,
+    };
+    Some((Ok(chunk), Some(state)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_chat_session_creation() {
+        let model = PhiModel::Phi3 {
+            parameters: "3.8B".to_string(),
+            context_length: 4096,
+            specialization: vec!["coding".to_string()],
+        };
+
+        let session = ChatSession::new(model, None, true, false);
+        assert!(session.coding_mode);
+        assert!(!session.math_mode);
+        assert!(session.system_prompt.is_some());
+    }
+
+    #[test]
+    fn test_system_prompt_enhancement() {
+        let base = "You are an AI assistant.".to_string();
+        let enhanced = ChatSession::enhance_system_prompt(base, true, true);
+        
+        assert!(enhanced.contains("Coding Assistant Mode"));
+        assert!(enhanced.contains("Math Assistant Mode"));
+    }
+
+    #[tokio::test]
+    async fn test_history_turns_limit() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false).with_history_turns(3);
+
+        for turn in 0..5 {
+            session.generate_response(&format!("turn {}", turn)).await.unwrap();
+            assert!(session.conversation_history.len() <= 3);
+        }
+        assert_eq!(session.conversation_history[0].0, "turn 2");
+    }
+
+    #[tokio::test]
+    async fn test_long_history_is_trimmed_to_context_window() {
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        assert_eq!(phi2.context_length(), 2048);
+
+        // 100 turns of 2,000 characters: about 200K characters, far beyond 2048 tokens
+        let history: Vec<_> = (0..100)
+            .map(|turn| {
+                let question = format!("question {} {}", turn, "word ".repeat(200));
+                (question, "answer ".repeat(143))
+            })
+            .collect();
+        let history_chars: usize = history.iter().map(|(u, a)| u.len() + a.len()).sum();
+        assert!(history_chars >= 200_000);
+
+        let mut session = ChatSession::new(phi2, None, false, false)
+            .with_history_turns(1000)
+            .with_history(history);
+        assert!(!session.context_usage().fits());
+
+        session.generate_response("hello").await.unwrap();
+
+        let usage = session.context_usage();
+        assert!(usage.fits(), "{} prompt tokens", usage.prompt_tokens);
+        assert_eq!(usage.context_length, 2048);
+        // The newest turns survive and the latest exchange is last
+        assert!(session.history().len() > 1 && session.history().len() < 100);
+        assert_eq!(session.history().last().unwrap().0, "hello");
+        assert!(session.history()[0].0.starts_with("question 9"));
+    }
+
+    /// In-memory sink that records the names of emitted timings
+    struct RecordingSink(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl MetricsSink for RecordingSink {
+        fn gauge(&self, _name: &str, _value: f64, _tags: metrics::Tags) {}
+
+        fn increment(&self, _name: &str, _value: u64, _tags: metrics::Tags) {}
+
+        fn timing(&self, name: &str, _duration: Duration, _tags: metrics::Tags) {
+            self.0.lock().unwrap().push(name.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generation_emits_latency_timing() {
+        let timings = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_metrics(Box::new(RecordingSink(timings.clone())));
+
+        session.generate_response("hello").await.unwrap();
+
+        assert_eq!(*timings.lock().unwrap(), vec!["phi.inference.latency_ms".to_string()]);
+    }
+
+    /// Layer that records the `tokens` field of `generate` spans
+    struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<u64>>>);
+
+    struct TokensVisitor(Option<u64>);
+
+    impl tracing::field::Visit for TokensVisitor {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            if field.name() == "tokens" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let is_generate = ctx.span(id).map_or(false, |span| span.name() == "generate");
+            let mut visitor = TokensVisitor(None);
+            values.record(&mut visitor);
+            if let (true, Some(tokens)) = (is_generate, visitor.0) {
+                self.0.lock().unwrap().push(tokens);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_span_records_tokens() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorded = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanRecorder(recorded.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false);
+        let response = session.generate_response("hello").await.unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0], response.split_whitespace().count() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_words_and_records_turn() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false);
+
+        let chunks: Vec<String> = session
+            .generate_stream("hello")
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert!(chunks.len() > 1);
+        assert_eq!(session.conversation_history.len(), 1);
+        assert_eq!(session.conversation_history[0].1, chunks.concat());
+    }
+
+    #[tokio::test]
+    async fn test_stream_stops_at_stop_sequence() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        // "As Phi" spans two word chunks of the demo response
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_stop_sequences(vec!["As Phi".to_string(), "never produced".to_string()]);
+
+        let text: String = session
+            .generate_stream("hello")
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(text, "Thank you for your question about 'hello'. ");
+        assert_eq!(session.conversation_history[0].1, text);
+        assert_eq!(session.generate_response("hello").await.unwrap(), text);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_leaves_history_untouched() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false);
+
+        {
+            let stream = session.generate_stream("hello");
+            tokio::pin!(stream);
+            assert!(stream.next().await.is_some());
+        }
+
+        assert!(session.conversation_history.is_empty());
+    }
+
+    #[test]
+    fn test_prompt_uses_model_chat_template() {
+        let phi3 = PhiModel::from_short_name("phi3").unwrap();
+        let history = vec![("hi".to_string(), "hello".to_string())];
+        let session = ChatSession::new(phi3, Some("Be brief.".to_string()), false, false)
+            .with_history(history);
+
+        assert_eq!(
+            session.build_prompt("what is rust?"),
+            "<|system|>\nBe brief.<|end|>\n<|user|>\nhi<|end|>\n<|assistant|>\nhello<|end|>\n\
+             <|user|>\nwhat is rust?<|end|>\n<|assistant|>\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_same_seed_reproduces_output() {
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        let seeded = |seed| ChatSession::new(phi2.clone(), None, false, false).with_seed(seed);
+
+        let (mut first, mut second) = (seeded(7), seeded(7));
+        assert_eq!(first.seed(), Some(7));
+        for prompt in ["hello", "what is a lifetime?"] {
+            let reply = first.generate_response(prompt).await.unwrap();
+            assert_eq!(second.generate_response(prompt).await.unwrap(), reply);
+        }
+
+        let reply = seeded(7).generate_response("hello").await.unwrap();
+        let other_reply = seeded(8).generate_response("hello").await.unwrap();
+        assert_ne!(reply, other_reply);
+
+        // Streaming samples the same words
+        let mut session = seeded(8);
+        let streamed: Vec<_> = session.generate_stream("hello").collect().await;
+        let streamed: String = streamed.into_iter().map(Result::unwrap).collect();
+        assert_eq!(streamed, other_reply);
+    }
+
+    #[tokio::test]
+    async fn test_content_filter_blocks_input_and_passes_the_rest() {
+        use crate::filter::{ContentBlocked, RegexDenyFilter};
+
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let filter = RegexDenyFilter::new(&["(?i)secret"]).unwrap();
+        let mut session =
+            ChatSession::new(model, None, false, false).with_filter(Arc::new(filter));
+
+        let error = session.generate_response("Tell me a SECRET").await.unwrap_err();
+        let blocked = error.downcast_ref::<ContentBlocked>().unwrap();
+        assert_eq!(blocked.stage, FilterStage::Input);
+        assert!(session.history().is_empty());
+
+        {
+            let stream = session.generate_stream("the secret plan");
+            tokio::pin!(stream);
+            assert!(stream.next().await.unwrap().is_err());
+            assert!(stream.next().await.is_none());
+        }
+
+        let reply = session.generate_response("Explain ownership").await.unwrap();
+        assert!(reply.contains("Phi-2"));
+        assert_eq!(session.history().len(), 1);
+
+        // Replies are checked too: every Phi-2 demo reply names the model
+        let filter = RegexDenyFilter::new(&["Phi-2"]).unwrap();
+        session = session.with_filter(Arc::new(filter));
+        let error = session.generate_response("hello").await.unwrap_err();
+        let blocked = error.downcast_ref::<ContentBlocked>().unwrap();
+        assert_eq!(blocked.stage, FilterStage::Output);
+        assert_eq!(session.history().len(), 1);
+    }
+
+    /// Cache a graph that predicts `hello phi world` in a cycle after any token, with a
+    /// word-level tokenizer for it, and load both
+    fn next_word_model(dir: &Path) -> PhiInference {
+        cycle_model(dir, "world")
+    }
+
+    /// Like [`next_word_model`], with `third` as the word after `phi`
+    fn cycle_model(dir: &Path, third: &str) -> PhiInference {
+        use crate::onnx::{GraphProto, ModelProto, NodeProto, TensorProto, ValueInfoProto, FLOAT};
+        use prost::Message as _;
+
+        let tensor = |name: &str, dims: &[i64], values: &[f32]| TensorProto {
+            dims: dims.to_vec(),
+            data_type: FLOAT,
+            name: name.to_string(),
+            raw_data: values.iter().flat_map(|value| value.to_le_bytes()).collect(),
+            ..Default::default()
+        };
+        let node = |op_type: &str, input: [&str; 2], output: &str| NodeProto {
+            input: input.iter().map(|name| name.to_string()).collect(),
+            output: vec![output.to_string()],
+            op_type: op_type.to_string(),
+            ..Default::default()
+        };
+        let value = |name: &str| ValueInfoProto {
+            name: name.to_string(),
+        };
+        // One-hot embeddings; row i of the head puts the next word's logit at 5
+        let mut embedding = [0.0; 16];
+        let mut head = [0.0; 16];
+        for (token, next) in [1, 2, 3, 1].into_iter().enumerate() {
+            embedding[token * 4 + token] = 1.0;
+            head[token * 4 + next] = 5.0;
+        }
+        let model = ModelProto {
+            ir_version: 8,
+            graph: Some(GraphProto {
+                node: vec![
+                    node("Gather", ["embedding", "input_ids"], "hidden"),
+                    node("MatMul", ["hidden", "head"], "logits"),
+                ],
+                name: "next-word".to_string(),
+                initializer: vec![
+                    tensor("embedding", &[4, 4], &embedding),
+                    tensor("head", &[4, 4], &head),
+                ],
+                input: vec![value("input_ids")],
+                output: vec![value("logits")],
+            }),
+        };
+
+        let path = dir.join("next-word.onnx");
+        std::fs::write(&path, model.encode_to_vec()).unwrap();
+        std::fs::write(
+            path.with_extension("tokenizer.json"),
+            r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+                "normalizer": null, "pre_tokenizer": {"type": "Whitespace"},
+                "post_processor": null, "decoder": null,
+                "model": {"type": "WordLevel", "unk_token": "[UNK]",
+                          "vocab": {"[UNK]": 0, "hello": 1, "phi": 2, "world": 3}}}"#
+                .replace("\"world\"", &format!("{:?}", third)),
+        )
+        .unwrap();
+        PhiInference::from_onnx(&path, &Default::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replies_are_sampled_from_the_loaded_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_sampling(SamplingConfig {
+                temperature: 0.0,
+                max_tokens: 4,
+                ..SamplingConfig::default()
+            })
+            .with_inference(next_word_model(dir.path()));
+
+        // The rendered prompt ends in an unknown token, which the graph follows with `hello`
+        let reply = session.generate_response("hello").await.unwrap();
+        assert_eq!(reply, "hello phi world hello");
+
+        let streamed: Vec<_> = session.generate_stream("hello").collect().await;
+        let streamed: String = streamed.into_iter().map(Result::unwrap).collect();
+        assert_eq!(streamed, "hello phi world hello");
+
+        // A prompt longer than the context window keeps its last tokens instead of failing
+        let long_prompt = "phi ".repeat(5000);
+        let reply = session.generate_response(&long_prompt).await.unwrap();
+        assert_eq!(reply, "hello phi world hello");
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_tokens_as_they_are_sampled() {
+        let dir = tempfile::tempdir().unwrap();
+        let inference = Arc::new(next_word_model(dir.path()));
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, true, false)
+            .with_sampling(SamplingConfig {
+                temperature: 0.0,
+                max_tokens: 4,
+                ..SamplingConfig::default()
+            })
+            .with_inference(Arc::clone(&inference));
+
+        {
+            let stream = session.generate_stream("write some code");
+            tokio::pin!(stream);
+            assert_eq!(stream.next().await.unwrap().unwrap(), "hello");
+        }
+        // Only the prompt has run through the graph when the first token arrives
+        let prefilled = session.prefilled_tokens().unwrap();
+        assert_eq!(inference.cached_tokens(), prefilled);
+
+        // Streamed and whole replies are sampled from the same prompt
+        session.reset_cache();
+        let chunks: Vec<_> = session.generate_stream("write some code").collect().await;
+        let chunks: Vec<_> = chunks.into_iter().map(Result::unwrap).collect();
+        assert_eq!(chunks, ["hello", " phi", " world", " hello"]);
+        assert_eq!(session.prefilled_tokens(), Some(prefilled));
+        session.clear_history();
+        session.generate_response("write some code").await.unwrap();
+        assert_eq!(session.prefilled_tokens(), Some(prefilled));
+    }
+
+    #[tokio::test]
+    async fn test_sampling_stops_at_the_end_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_sampling(SamplingConfig {
+                temperature: 0.0,
+                max_tokens: 8,
+                ..SamplingConfig::default()
+            })
+            .with_inference(cycle_model(dir.path(), "<|end|>"));
+
+        assert_eq!(session.generate_response("hello").await.unwrap(), "hello phi");
+    }
+
+    #[tokio::test]
+    async fn test_cached_second_turn_processes_fewer_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false)
+            .with_sampling(SamplingConfig {
+                temperature: 0.0,
+                max_tokens: 4,
+                ..SamplingConfig::default()
+            })
+            .with_inference(next_word_model(dir.path()));
+
+        session.generate_response("hello phi").await.unwrap();
+        let cold = session.prefilled_tokens().unwrap();
+        session.generate_response("world").await.unwrap();
+        let warm = session.prefilled_tokens().unwrap();
+        assert!(warm < cold, "second turn ran {} tokens, first ran {}", warm, cold);
+
+        // Clearing the cache makes the next turn process its whole prompt again
+        session.reset_cache();
+        session.generate_response("world").await.unwrap();
+        assert!(session.prefilled_tokens().unwrap() > warm);
+    }
+
+    #[tokio::test]
+    async fn test_logprobs_come_from_the_sampled_tokens() {
+        let sampling = SamplingConfig {
+            temperature: 0.7,
+            return_logprobs: true,
+            ..SamplingConfig::default()
+        };
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut demo = ChatSession::new(model.clone(), None, false, false)
+            .with_sampling(sampling.clone())
+            .with_seed(3);
+        let generation = demo.generate("hello").await.unwrap();
+        let logprobs = generation.logprobs.unwrap();
+        assert_eq!(logprobs.len(), generation.text.split_whitespace().count());
+        assert!(logprobs.iter().all(|(_, logprob)| *logprob <= 0.0));
+        assert!(logprobs.iter().any(|(_, logprob)| *logprob < 0.0));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut sampled = ChatSession::new(model, None, false, false)
+            .with_sampling(SamplingConfig {
+                max_tokens: 4,
+                ..sampling
+            })
+            .with_inference(next_word_model(dir.path()));
+        let generation = sampled.generate("hello").await.unwrap();
+        let logprobs = generation.logprobs.unwrap();
+        assert_eq!(logprobs.len(), 4);
+        assert!(logprobs.iter().all(|(_, logprob)| *logprob < 0.0));
+        let tokens: Vec<_> = logprobs.into_iter().map(|(token, _)| token).collect();
+        assert_eq!(tokens.join(" "), generation.text);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_leaves_session_untouched() {
+        let model = PhiModel::Phi2 {
+            parameters: "2.7B".to_string(),
+            context_length: 2048,
+            specialization: vec!["reasoning".to_string()],
+        };
+        let mut session = ChatSession::new(model, None, false, false);
+        let sampling = session.sampling.clone();
+
+        let elapsed = session.warmup().await.unwrap();
+
+        assert!(elapsed > Duration::ZERO);
+        assert!(session.conversation_history.is_empty());
+        assert_eq!(session.sampling, sampling);
+    }
+
+    #[tokio::test]
+    async fn test_demo_response_generation() {
+        let model = PhiModel::Phi3 {
+            parameters: "3.8B".to_string(),
+            context_length: 4096,
+            specialization: vec!["coding".to_string()],
+        };
+
+        let mut session = ChatSession::new(model, None, true, false);
+        let (response, logprobs) = session.generate_demo_response("help me write code").await;
+        
+        assert!(!response.is_empty());
+        assert_eq!(logprobs.len(), response.split_whitespace().count());
+        assert!(response.to_lowercase().contains("code") || response.to_lowercase().contains("coding"));
+    }
+
+    #[tokio::test]
+    async fn test_generation_times_out() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false).with_timeout(Duration::ZERO);
+
+        let error = session.generate("hello").await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&GenerationTimeout(Duration::ZERO)));
+        assert_eq!(error.to_string(), "generation timed out after 0s");
+        assert!(session.history().is_empty());
+
+        let chunks: Vec<_> = session.generate_stream("hello").collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].as_ref().unwrap_err().is::<GenerationTimeout>());
+        assert!(session.history().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_image_input_is_rejected_by_text_only_models() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false);
+
+        let messages = vec![
+            Message::Text("What is in this picture?".to_string()),
+            Message::Image(vec![0x89, b'P', b'N', b'G']),
+        ];
+        let error = session.generate_messages(messages).await.unwrap_err();
+        let unsupported = error.downcast_ref::<ImageInputUnsupported>().unwrap();
+        assert_eq!(unsupported.model, "microsoft/Phi-3-mini-4k-instruct");
+        assert!(error.to_string().starts_with("this model does not support image input"));
+        assert!(session.history().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_text_messages_are_joined_into_one_turn() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let mut session = ChatSession::new(model, None, false, false).with_seed(7);
+
+        let messages = vec![
+            Message::Text("Explain ownership".to_string()),
+            Message::Text("in Rust".to_string()),
+        ];
+        let generation = session.generate_messages(messages).await.unwrap();
+
+        assert!(!generation.text.is_empty());
+        assert_eq!(session.history().len(), 1);
+        assert_eq!(session.history()[0].0, "Explain ownership\nin Rust");
+    }
+
+    #[test]
+    fn test_markdown_transcript_lists_turns_in_order() {
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let history = vec![
+            ("What is Rust?".to_string(), "A systems language.".to_string()),
+            (
+                "Show me hello world".to_string(),
+                "```rust\nfn main() { println!(\"hi\"); }\n```".to_string(),
+            ),
+        ];
+        let session = ChatSession::new(model, Some("Be brief.".to_string()), false, false)
+            .with_history(history);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chat.md");
+        session.export_markdown(&path).unwrap();
+        let markdown = std::fs::read_to_string(&path).unwrap();
+
+        let mut lines = markdown.lines();
+        assert_eq!(lines.next(), Some("# Chat with microsoft/Phi-3-mini-4k-instruct"));
+        assert_eq!(lines.next(), Some(""));
+        let exported = lines.next().unwrap();
+        assert!(exported.starts_with("Exported 20") && exported.ends_with(" UTC"), "{}", exported);
+
+        let order = [
+            "## System\n\n```\nBe brief.\n```",
+            "## User\n\n```\nWhat is Rust?\n```",
+            "## Assistant\n\n```\nA systems language.\n```",
+            "## User\n\n```\nShow me hello world\n```",
+            "## Assistant\n\n````\n```rust\nfn main() { println!(\"hi\"); }\n```\n````",
+        ];
+        let positions: Vec<_> = order
+            .iter()
+            .map(|section| markdown.find(section).unwrap_or_else(|| panic!("{}", markdown)))
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", markdown);
+
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14 22:13:20 UTC");
+    }
+}
@@ -0,0 +1,38 @@
+/*!
+Layered configuration shared by the binaries
+
+Settings resolve as built-in defaults < config file < environment variables <
+command-line flags through the `config-layers` crate shared with the other Burn
+templates. Phi environment variables use the `PHI_` prefix, e.g. `PHI_MAX_TOKENS`.
+Without `--config` a binary reads its file from `~/.config/vibecode/` if one exists
+there.
+*/
+
+use std::path::PathBuf;
+
+pub use config_layers::ConfigLayers;
+
+/// Environment variable prefix used by the Phi binaries
+pub const ENV_PREFIX: &str = "PHI_";
+
+/// Default config file location, `~/.config/vibecode/<file_name>`
+pub fn user_config_path(file_name: &str) -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config").join("vibecode").join(file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_invalid_phi_values_name_their_variable() {
+        let env = HashMap::from([("PHI_TEMPERATURE".to_string(), "3.0".to_string())]);
+        let layers = ConfigLayers::new(HashMap::new(), env, ENV_PREFIX);
+
+        let err = layers
+            .resolve_with("temperature", None, 0.7, crate::sampling::parse_temperature)
+            .unwrap_err();
+        assert!(err.to_string().contains("PHI_TEMPERATURE"));
+    }
+}
@@ -0,0 +1,135 @@
+/*!
+Text embeddings from Phi models
+
+An embedding is the mean of the model's final hidden states over the tokens its attention
+mask keeps, L2-normalized so cosine similarity is a dot product. The hidden states are
+read from the loaded ONNX graph, at the input of its language-model head, so an
+[`Embedder`] needs a [`PhiInference`] that can run the model.
+
+Only the edge-suitable models (Phi-1 through Phi-3.5, and Phi-4-mini) are served for
+embeddings. Phi-4 is rejected: embedding a document corpus with a 14B model is
+impractically slow on-device, and retrieval quality does not need the extra capacity.
+*/
+
+use anyhow::{bail, Context, Result};
+use std::sync::Arc;
+
+use crate::{PhiInference, PhiModel};
+
+/// Produces fixed-size embeddings with one loaded Phi model
+#[derive(Debug, Clone)]
+pub struct Embedder {
+    model: PhiModel,
+    inference: Arc<PhiInference>,
+}
+
+impl Embedder {
+    /// Create an embedder for `model` running on `inference`, rejecting models that are
+    /// not served for embeddings and inference without a graph and tokenizer
+    pub fn new(model: PhiModel, inference: Arc<PhiInference>) -> Result<Self> {
+        check_model(&model)?;
+        if !inference.can_generate() {
+            bail!(
+                "{} was loaded without its graph or tokenizer, so it cannot embed text",
+                model.model_name()
+            );
+        }
+        Ok(Self { model, inference })
+    }
+
+    /// Mean-pooled, L2-normalized embedding of `text`
+    ///
+    /// Text longer than the model's context window is truncated to its first tokens.
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let tokenizer = self
+            .inference
+            .tokenizer()
+            .context("the model has no tokenizer")?;
+        let (mut ids, mut attention_mask) = tokenizer.encode_with_mask(text)?;
+        if ids.is_empty() {
+            bail!("cannot embed text without any tokens");
+        }
+        ids.truncate(self.model.context_length());
+        attention_mask.truncate(ids.len());
+
+        let states = self.inference.hidden_states(&ids)?;
+        let mut embedding = mean_pool(&states, &attention_mask);
+        l2_normalize(&mut embedding);
+        Ok(embedding)
+    }
+
+    /// Embed each text in order
+    pub fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+}
+
+/// Reject models that are not served for embeddings
+pub fn check_model(model: &PhiModel) -> Result<()> {
+    if !model.is_edge_suitable() {
+        bail!(
+            "{} does not support embeddings; use phi1, phi15, phi2, phi3, phi35 or phi4-mini",
+            model.model_name()
+        );
+    }
+    Ok(())
+}
+
+/// Average the per-token `states` whose `attention_mask` entry is non-zero
+pub fn mean_pool(states: &[Vec<f32>], attention_mask: &[u32]) -> Vec<f32> {
+    let width = states.first().map_or(0, Vec::len);
+    let mut pooled = vec![0.0; width];
+    let mut count = 0;
+    for (state, _) in states.iter().zip(attention_mask).filter(|(_, mask)| **mask != 0) {
+        pooled.iter_mut().zip(state).for_each(|(sum, value)| *sum += value);
+        count += 1;
+    }
+    let count = count.max(1) as f32;
+    pooled.iter_mut().for_each(|value| *value /= count);
+    pooled
+}
+
+/// Scale `vector` to unit length; an all-zero vector is left unchanged
+pub fn l2_normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_pool_skips_masked_tokens() {
+        let states = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![100.0, 100.0]];
+        assert_eq!(mean_pool(&states, &[1, 1, 0]), vec![2.0, 3.0]);
+        assert_eq!(mean_pool(&states, &[0, 0, 0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_l2_normalize() {
+        let mut vector = vec![3.0, 4.0];
+        l2_normalize(&mut vector);
+        assert_eq!(vector, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        l2_normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_inputs_are_rejected() {
+        assert!(check_model(&PhiModel::from_short_name("phi4").unwrap()).is_err());
+        assert!(check_model(&PhiModel::from_short_name("phi2").unwrap()).is_ok());
+
+        // A model opened without its graph cannot embed
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+        std::fs::write(&path, b"not a graph").unwrap();
+        let inference = Arc::new(PhiInference::load(&path).await.unwrap());
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        assert!(Embedder::new(model, inference).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+/*!
+Content filters for chat input and output
+
+A [`ChatSession`](crate::ChatSession) passes every prompt through its filter's
+[`ContentFilter::check_input`] before generating, and every reply through
+[`ContentFilter::check_output`] before returning it. A blocked prompt is never sent to
+the model and a blocked reply is never shown; either way the turn is left out of the
+history and the generation fails with [`ContentBlocked`].
+
+Sessions use [`NoopFilter`] unless given another. [`RegexDenyFilter`] blocks text that
+matches any of a list of patterns, such as those loaded from the chat binary's
+`--deny-file`.
+*/
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fmt;
+use std::path::Path;
+
+/// Verdict of a [`ContentFilter`] on one piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterResult {
+    Allow,
+    /// Blocked, with a reason that can be shown to the user
+    Block(String),
+}
+
+impl FilterResult {
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, Self::Block(_))
+    }
+}
+
+/// Check applied to prompts before generation and to replies after it
+pub trait ContentFilter: fmt::Debug + Send + Sync {
+    fn check_input(&self, input: &str) -> FilterResult;
+
+    fn check_output(&self, output: &str) -> FilterResult;
+}
+
+/// Filter that allows everything
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopFilter;
+
+impl ContentFilter for NoopFilter {
+    fn check_input(&self, _input: &str) -> FilterResult {
+        FilterResult::Allow
+    }
+
+    fn check_output(&self, _output: &str) -> FilterResult {
+        FilterResult::Allow
+    }
+}
+
+/// Filter that blocks input and output matching any of its patterns
+#[derive(Debug, Clone)]
+pub struct RegexDenyFilter {
+    patterns: Vec<Regex>,
+}
+
+impl RegexDenyFilter {
+    /// Compile `patterns`, failing on the first invalid one
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_ref();
+                Regex::new(pattern).with_context(|| format!("Invalid deny pattern '{}'", pattern))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Load one pattern per line from `path`, skipping blank lines and `#` comments
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read deny file {:?}", path))?;
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        Self::new(&patterns).with_context(|| format!("Invalid deny file {:?}", path))
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    fn check(&self, text: &str) -> FilterResult {
+        match self.patterns.iter().find(|pattern| pattern.is_match(text)) {
+            Some(pattern) => FilterResult::Block(format!("matches '{}'", pattern.as_str())),
+            None => FilterResult::Allow,
+        }
+    }
+}
+
+impl ContentFilter for RegexDenyFilter {
+    fn check_input(&self, input: &str) -> FilterResult {
+        self.check(input)
+    }
+
+    fn check_output(&self, output: &str) -> FilterResult {
+        self.check(output)
+    }
+}
+
+/// Which side of a generation a filter blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterStage {
+    Input,
+    Output,
+}
+
+/// Error returned when the session's content filter blocks a prompt or a reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentBlocked {
+    pub stage: FilterStage,
+    pub reason: String,
+}
+
+impl fmt::Display for ContentBlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stage {
+            FilterStage::Input => write!(f, "prompt blocked by content filter: {}", self.reason),
+            FilterStage::Output => write!(f, "response blocked by content filter: {}", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ContentBlocked {}
+
+/// Turn a blocking `result` into a [`ContentBlocked`] error at `stage`
+pub(crate) fn enforce(result: FilterResult, stage: FilterStage) -> Result<()> {
+    match result {
+        FilterResult::Allow => Ok(()),
+        FilterResult::Block(reason) => Err(ContentBlocked { stage, reason }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deny_patterns_block_matching_text() {
+        let filter = RegexDenyFilter::new(&[r"(?i)\bpassword\b", r"\d{3}-\d{2}-\d{4}"]).unwrap();
+        assert_eq!(filter.len(), 2);
+
+        assert!(filter.check_input("What is my PASSWORD?").is_blocked());
+        assert!(filter.check_output("It is 123-45-6789").is_blocked());
+        assert_eq!(filter.check_input("Explain ownership in Rust"), FilterResult::Allow);
+        assert_eq!(NoopFilter.check_input("What is my password?"), FilterResult::Allow);
+
+        assert!(RegexDenyFilter::new(&["("]).is_err());
+    }
+
+    #[test]
+    fn test_deny_file_skips_comments_and_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deny.txt");
+        std::fs::write(&path, "# secrets\n\napi[_-]?key\n  token  \n").unwrap();
+
+        let filter = RegexDenyFilter::from_file(&path).unwrap();
+        assert_eq!(filter.len(), 2);
+        assert!(filter.check_input("my api_key is").is_blocked());
+        assert!(filter.check_input("a token").is_blocked());
+        assert!(!filter.check_input("# secrets").is_blocked());
+
+        assert!(RegexDenyFilter::from_file(&dir.path().join("missing.txt")).is_err());
+    }
+}
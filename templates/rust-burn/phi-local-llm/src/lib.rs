@@ -21,6 +21,8 @@ performance at a fraction of the size, compute requirements, and cost of much la
 
 | Model | Parameters | Context | Specialization | Use Case |
 |-------|------------|---------|----------------|----------|
+| Phi-1 | 1.3B | 2K | Python coding | Lightweight code completion |
+| Phi-1.5 | 1.3B | 2K | Common sense reasoning | Small-model experiments |
 | Phi-2 | 2.7B | 2K | Language, Reasoning | General development |
 | Phi-3 | 3.8B | 4K | Coding, Math | Code assistance |
 | Phi-3.5 | 3.8B | 128K | Multilingual | International teams |
@@ -71,21 +73,171 @@ This template showcases:
 cargo run --bin chat-phi --model phi3 --coding-mode
 ```
 
+Without `--model` (or a `model` in the config), a terminal session opens a numbered menu
+of the models, dimming those that won't fit this machine; otherwise `phi3` is used.
+
+`--seed 42` seeds the sampling RNG, so the same prompts, seed and sampling settings
+reproduce the same replies (in `--api-mode` too, where every request uses the seed).
+
+`--compare phi4-mini` loads a second model and answers every prompt with both, printing
+each reply under its model's name one after the other.
+
+Before loading, `chat-phi` checks the model against this machine's free memory and disk
+and refuses one that won't fit, listing why; `--force` loads it anyway with a warning.
+
+While a reply streams to a terminal, a status line after the text shows the token count,
+tokens/sec and elapsed time; each reply ends with its token count, tokens/sec and
+time-to-first-token. `--quiet` hides both.
+
+`/export [path]` saves the conversation so far as a Markdown transcript, and
+`--export-md chat.md` writes one when the chat ends.
+
+`--dry-run` is a smoke test for deployments: it checks that the model (with `--api-mode`,
+each `--preload` model) fits this machine and is cached, prints a readiness report and
+exits nonzero listing the blocking issues, without downloading or generating anything. An
+API server dry run without `--preload` has no model to check and reports that instead.
+
+`--selftest` is the one-shot readiness check for containers: it checks this machine,
+fetches the smallest model (Phi-1) if it isn't cached and runs one short generation,
+printing ✅/❌ per step and exiting nonzero unless every step passed.
+
+### Configuration
+Settings resolve as defaults < config file < `PHI_*` environment variables < flags:
+```bash
+PHI_TEMPERATURE=0.3 cargo run --bin chat-phi -- --config phi.toml --max-tokens 256
+```
+
+The config file is `~/.config/vibecode/phi-chat.toml` unless `--config` names another
+(a `.json` file is read as JSON). Its keys are the flag names, and unknown keys are
+rejected so typos don't go unnoticed:
+```toml
+model = "phi4-mini"
+temperature = 0.3
+system = "You are a concise senior Rust reviewer."
+coding-mode = true
+```
+
+`--repeat-penalty` (default 1.1) divides the logits of the last 64 generated tokens, and
+`--no-repeat-ngram-size N` rules out any N-token sequence that was already generated, to
+keep small models from looping. Both reshape the logits before temperature, top-k and
+top-p apply; `set repeat-penalty 1.3` changes the penalty mid-chat.
+
 ### Code Assistant
 ```bash
-cargo run --bin code-assistant --model phi4-mini
+cargo run --bin code-assistant -- --model phi4-mini --file src/parser.rs
+cargo run --bin code-assistant -- --file src/parser.rs --explain
 ```
 
+Put a `<CURSOR>` marker in the file where code should be completed; the code before and
+after it becomes a fill-in-the-middle prompt. `--explain` summarizes the file instead.
+
 ### Benchmarking
 ```bash
-cargo run --bin benchmark-phi --backend cuda --model phi3
+cargo run --features cuda --bin benchmark-phi -- --backend cuda --model phi3 --iterations 10
+```
+
+Reports tokens/sec, time-to-first-token and peak memory after `--warmup` unmeasured
+runs; `--json` prints the summary and every sample as JSON.
+
+Streamed generations are timed per chunk with [`timing::GenerationTiming`], so
+time-to-first-token (prompt processing) is reported separately from inter-token
+latency (decoding) and total time.
+
+### Running ONNX Graphs
+`PhiModelManager::load_onnx` fetches a model and decodes its ONNX graph onto Burn's
+`ndarray` backend, and `PhiInference::next_token_logits` runs one forward pass over token
+ids. `burn-import` only generates code from ONNX at build time, so [`onnx`] interprets the
+graph at runtime instead. It supports embedding and projection graphs so far (`Gather`,
+`MatMul`, `Gemm`, `Add`, `Mul`, `Relu`, `Tanh`, `Identity`). No published Phi export
+loads yet: they all use more operators and fail with the list of missing ones, so
+downloaded models still get demo replies.
+
+A `ChatSession` given a model with a graph and tokenizer through `with_inference` samples
+its replies from the graph's logits, keeping the last tokens of a prompt longer than the
+context window. `PhiInference::open`, which `chat-phi` loads models with, falls back to
+the demo replies when the graph can't run yet.
+
+The model keeps a key/value cache across turns, bounded by the model's context length:
+each prompt reuses the tokens it shares with the previous one, so a follow-up only runs
+the new turn. The chat `clear` command (like `clear_history`) empties it.
+
+### Token Counting
+```bash
+cargo run --bin tokens-phi -- --model phi3 --max-tokens 512 --file src/main.rs
 ```
 
 ### Model Download
 ```bash
-cargo run --bin download-phi --model phi4 --cache-dir ./models
+cargo run --bin download-phi -- pull phi4 --cache-dir ./models
+cargo run --bin download-phi -- list --details   # also: size, clean
+cargo run --bin download-phi -- clean --max-cache-size 20GB
+```
+
+Each download saves a `<model>.manifest.json` next to the weights recording the repository,
+the commit the `main` revision resolved to, and every file fetched with its size and
+SHA-256; `list --details` prints it and `PhiModelManager::manifest` reads it back.
+`chat-phi --status` checks the cached files against those hashes.
+
+Without `--cache-dir`, models are cached in `$VIBECODE_PHI_CACHE` when set (e.g. a volume
+mounted into a container), else under the OS cache directory in `vibecode/phi-models`. The
+directory is created if missing, and startup fails with a clear error if it isn't writable.
+
+With `--max-cache-size`, `clean` only deletes the least recently used models until the
+cache fits; loading a model counts as using it.
+
+`--quantization int4` (also on `chat-phi`) sizes the memory checks for int4 weights and
+caches the model as `microsoft_Phi-4_q4.onnx`, apart from other precisions. Quantized
+to int4, even Phi-4 counts as edge-suitable.
+
+Set `HF_ENDPOINT` to download from a Hugging Face mirror. Timeouts, dropped connections
+and 5xx responses are retried with exponential backoff, 3 times unless `--max-retries`
+says otherwise; a missing file fails immediately. Servers that support range requests are
+read over `--connections` parallel ranges (default 4) into a `.part` file, and an interrupted
+download resumes from the bytes already on disk the next time it is pulled.
+
+### System Check
+```bash
+cargo run --bin system-info -- --json
 ```
 
+Prints memory, disk, CPU and GPU details, the recommended backend, and which models fit
+this machine. `--json` emits the same report as one JSON object for scripts.
+
+`chat-phi --list` (or `--list-models`) prints one row per model with its parameters,
+context length, size in the cache (or `no`) and whether it fits this machine.
+
+### REST API
+```bash
+cargo run --bin chat-phi -- --api-mode --port 8080
+curl -s localhost:8080/v1/chat -H 'content-type: application/json' \
+  -d '{"model": "phi3", "messages": [{"role": "user", "content": "Hello"}]}'
+```
+
+`POST /v1/chat/completions` accepts OpenAI-style requests (including `"stream": true`
+for Server-Sent Events), so existing OpenAI clients can point their base URL at the
+server. `POST /v1/generate` streams the continuation of a raw `prompt` as events and stops
+generating as soon as the client disconnects. `POST /v1/embeddings` returns mean-pooled
+hidden states of a preloaded model for RAG pipelines (see [`embeddings`] for the supported
+models). `GET /healthz` answers liveness probes,
+`GET /models` lists the servable models and `GET /metrics` exposes request counts, errors,
+generation latency histograms and tokens generated for Prometheus to scrape.
+
+`POST /v1/sessions` returns a `session_id`; chat requests naming it only send the new
+message, and the server keeps the earlier turns until `DELETE /v1/sessions/{id}` or until
+the session sits idle for 30 minutes.
+
+Generations are cancelled after `--timeout-secs` (120 by default), in the API and the
+interactive chat alike; the API answers a timed-out request with `504 Gateway Timeout`.
+
+`--preload phi3 phi4-mini` downloads, loads and warms up models at startup (`--no-warmup`
+skips the warmup generation); requests for them generate with the loaded model, and
+requests for other models get demo replies. `GET /healthz` and `GET /readyz` answer `503`
+until the preload finishes, so orchestrators only route traffic to a server that is ready;
+`/readyz` then lists the preloaded models.
+
+On SIGTERM the server stops accepting connections and lets in-flight requests, streams
+included, finish for up to `--shutdown-grace-secs` (30 by default) before exiting.
+
 ## Integration with VibeCode
 
 This template integrates seamlessly with the VibeCode platform:
@@ -121,7 +273,7 @@ You can extend this template by:
 2. **Custom Fine-tuning**: Domain-specific model adaptation
 3. **API Integration**: REST/GraphQL endpoints
 4. **Streaming Responses**: Real-time token generation
-5. **Multi-modal Support**: Text, code, and image inputs
+5. **Multi-modal Support**: Text, code, and image inputs, starting from [`Message`]
 
 ## Production Deployment
 
@@ -136,7 +288,7 @@ FROM debian:bookworm-slim
 RUN apt-get update && apt-get install -y ca-certificates
 COPY --from=builder /app/target/release/chat-phi /usr/local/bin/
 EXPOSE 8080
-CMD ["chat-phi", "--api-mode"]
+CMD ["chat-phi", "--api-mode", "--host", "0.0.0.0", "--port", "8080"]
 ```
 
 ### Kubernetes Deployment
@@ -152,9 +304,18 @@ spec:
       app: phi-inference
   template:
     spec:
+      # Longer than --shutdown-grace-secs, so in-flight requests drain before SIGKILL
+      terminationGracePeriodSeconds: 35
       containers:
       - name: phi-inference
         image: vibecode/phi-local-llm:latest
+        args: ["--api-mode", "--shutdown-grace-secs", "30"]
+        env:
+        - name: VIBECODE_PHI_CACHE
+          value: /models
+        volumeMounts:
+        - name: models
+          mountPath: /models
         resources:
           requests:
             memory: "2Gi"
@@ -162,6 +323,10 @@ spec:
           limits:
             memory: "4Gi"
             cpu: "2"
+      volumes:
+      - name: models
+        persistentVolumeClaim:
+          claimName: phi-models
 ```
 
 ## Monitoring Integration
@@ -176,7 +341,9 @@ spec:
 - Custom metrics publishing
 - Performance dashboards
 - Alert configuration
-- Distributed tracing
+- Distributed tracing: model loads, downloads and generations are spans carrying the
+  model, token count and `elapsed_ms`; export them with `--otlp-endpoint`, or log them
+  as JSON lines with `--log-format json`
 
 ## Security Considerations
 
@@ -184,7 +351,8 @@ spec:
 - Model integrity verification
 - Secure model storage
 - Access control and authentication
-- Input validation and sanitization
+- Input validation and sanitization, plus deny-pattern filtering of prompts and replies
+  with `chat-phi --deny-file`
 
 ### Runtime Security
 - Sandboxed execution environment
@@ -196,10 +364,40 @@ This template provides a complete foundation for deploying Microsoft Phi models
 in production environments with the VibeCode platform.
 */
 
+pub mod chat;
+pub mod config;
+pub mod embeddings;
+pub mod filter;
+pub mod metrics;
+pub mod onnx;
 pub mod phi_models;
+pub mod sampling;
+pub mod server;
+pub mod sessions;
+pub mod stop;
+pub mod streaming;
+pub mod telemetry;
+pub mod template;
+pub mod timing;
+
+use serde::{Deserialize, Serialize};
 
 // Re-export main types
-pub use phi_models::{PhiModel, PhiModelManager};
+pub use chat::{ChatSession, GenerationTimeout, ImageInputUnsupported, Message};
+pub use config::ConfigLayers;
+pub use embeddings::Embedder;
+pub use filter::{ContentBlocked, ContentFilter, FilterResult, NoopFilter, RegexDenyFilter};
+pub use phi_models::{
+    CachedModel, Manifest, ManifestFile, ModelStatus, PhiError, PhiModel, PhiModelManager,
+    Quantization, RetryPolicy, Tokenizer,
+};
+pub use metrics::{MetricsBackend, MetricsSink};
+pub use sampling::{Generation, SamplingConfig};
+pub use sessions::SessionStore;
+pub use stop::StopDetector;
+pub use streaming::CancellationToken;
+pub use template::ChatTemplate;
+pub use timing::GenerationTiming;
 
 // Version and metadata
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -208,11 +406,20 @@ pub const DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 
 /// Initialize tracing for the application
 pub fn init_tracing() {
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    init_tracing_with_format(telemetry::LogFormat::Text);
+}
+
+/// Initialize tracing, writing log lines as text or JSON
+pub fn init_tracing_with_format(format: telemetry::LogFormat) {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive("burn_phi_local_llm=info".parse().unwrap())
         )
+        .with(telemetry::fmt_layer(format, std::io::stdout))
         .init();
 }
 
@@ -225,22 +432,223 @@ pub fn print_banner() {
     println!();
 }
 
+/// Whether to print the startup banner
+///
+/// The banner is for people at a terminal; it is skipped when asked to, when output is
+/// machine-readable JSON, or when stdout is piped or redirected.
+pub fn should_show_banner(no_banner: bool, json_output: bool, stdout_is_tty: bool) -> bool {
+    !no_banner && !json_output && stdout_is_tty
+}
+
+/// Default cap on the size of a single user prompt
+pub const MAX_INPUT_BYTES: usize = 32 * 1024;
+
+/// Marker appended when input is cut short
+const TRUNCATION_MARKER: &str = "…";
+
+/// Clean up user input before it reaches the model
+///
+/// Drops control characters other than newlines and tabs, trims surrounding whitespace
+/// and caps the result at `max_bytes`, cutting on a character boundary and appending an
+/// ellipsis when anything was removed.
+pub fn sanitize_input(input: &str, max_bytes: usize) -> String {
+    let cleaned: String = input
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+
+    if cleaned.len() <= max_bytes {
+        return cleaned;
+    }
+
+    // Leave room for the marker unless the limit is too small to hold any text with it
+    if max_bytes <= TRUNCATION_MARKER.len() {
+        return truncate_to_char_boundary(&cleaned, max_bytes).to_string();
+    }
+    let budget = max_bytes - TRUNCATION_MARKER.len();
+    format!("{}{}", truncate_to_char_boundary(&cleaned, budget), TRUNCATION_MARKER)
+}
+
+/// Longest prefix of `s` that is at most `max_bytes` long and ends on a char boundary
+fn truncate_to_char_boundary(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Unit system for [`format_bytes_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytesStyle {
+    /// Powers of 1024, labeled KiB, MiB, GiB
+    Binary,
+    /// Powers of 1000, labeled KB, MB, GB
+    Decimal,
+}
+
 /// Format bytes as human readable string
+///
+/// Uses powers of 1024 labeled KB, MB, GB, as this crate always has; see
+/// [`format_bytes_with`] for correctly labeled units.
 pub fn format_bytes(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    format_scaled(bytes, 1024.0, &["B", "KB", "MB", "GB", "TB"])
+}
+
+/// Format bytes as human readable string in `style`'s units
+pub fn format_bytes_with(bytes: u64, style: BytesStyle) -> String {
+    match style {
+        BytesStyle::Binary => format_scaled(bytes, 1024.0, &["B", "KiB", "MiB", "GiB", "TiB"]),
+        BytesStyle::Decimal => format_scaled(bytes, 1000.0, &["B", "KB", "MB", "GB", "TB"]),
+    }
+}
+
+/// `bytes` in the largest of `units` (each `base` times the last) below `base`
+fn format_scaled(bytes: u64, base: f64, units: &[&str]) -> String {
     let mut size = bytes as f64;
     let mut unit_index = 0;
 
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
+    // Above bytes, sizes that round up to `base` at one decimal move to the next unit
+    // too, so 1023.96 KiB prints as 1.0 MiB rather than 1024.0 KiB
+    while unit_index < units.len() - 1 {
+        let rollover = if unit_index == 0 { base } else { base - 0.05 };
+        if size < rollover {
+            break;
+        }
+        size /= base;
         unit_index += 1;
     }
 
     if unit_index == 0 {
-        format!("{} {}", bytes, UNITS[unit_index])
+        format!("{} {}", bytes, units[unit_index])
     } else {
-        format!("{:.1} {}", size, UNITS[unit_index])
+        format!("{:.1} {}", size, units[unit_index])
+    }
+}
+
+/// Parse a byte size such as `500MB`, `1.5 GiB` or `4096`
+///
+/// KB, MB and GB are powers of 1024, matching [`format_bytes`].
+pub fn parse_bytes(value: &str) -> Result<u64, String> {
+    const UNITS: &[(&str, u64)] = &[
+        ("TIB", 1 << 40),
+        ("GIB", 1 << 30),
+        ("MIB", 1 << 20),
+        ("KIB", 1 << 10),
+        ("TB", 1 << 40),
+        ("GB", 1 << 30),
+        ("MB", 1 << 20),
+        ("KB", 1 << 10),
+        ("B", 1),
+    ];
+    let upper = value.trim().to_uppercase();
+    let (number, scale) = UNITS
+        .iter()
+        .find_map(|&(unit, scale)| Some((upper.strip_suffix(unit)?, scale)))
+        .unwrap_or((upper.as_str(), 1));
+    match number.trim().parse::<f64>() {
+        Ok(number) if number >= 0.0 && number.is_finite() => Ok((number * scale as f64) as u64),
+        _ => Err(format!(
+            "invalid size '{}' (expected a number of bytes, optionally with KB, MB, GB or TB)",
+            value
+        )),
+    }
+}
+
+/// Backends this crate knows about, paired with whether each is compiled into this build
+pub fn compiled_backends() -> Vec<(&'static str, bool)> {
+    vec![
+        ("ndarray", true),
+        ("cuda", cfg!(feature = "cuda")),
+        ("metal", cfg!(feature = "metal")),
+        ("wgpu", cfg!(feature = "wgpu")),
+    ]
+}
+
+/// Render the `--list-backends` report
+pub fn format_backend_list(system: &SystemInfo) -> String {
+    let recommended = system.recommended_backend();
+    let mut output = String::from("Backends:\n");
+
+    for (name, compiled) in compiled_backends() {
+        let status = if !compiled {
+            format!("not compiled (build with --features {})", name)
+        } else if name == recommended {
+            "available (recommended)".to_string()
+        } else {
+            "available".to_string()
+        };
+        output.push_str(&format!("  {:<8} {}\n", name, status));
+    }
+
+    output
+}
+
+/// Render the `--list-models` table of every model, its size in the cache and whether it
+/// fits this system
+pub fn format_model_list(statuses: &[phi_models::ModelStatus]) -> String {
+    let mut output = format!(
+        "{:<36} {:>7} {:>8} {:>10} {:>5}\n",
+        "Model", "Params", "Context", "Cached", "Fits"
+    );
+    for status in statuses {
+        output.push_str(&format_model_list_row(status));
     }
+    output
+}
+
+/// One `--list-models` row; uncached models show "no" instead of a size
+fn format_model_list_row(status: &phi_models::ModelStatus) -> String {
+    let cached = match status.size {
+        Some(bytes) if status.cached => format_bytes(bytes),
+        _ => "no".to_string(),
+    };
+    format!(
+        "{:<36} {:>7} {:>8} {:>10} {:>5}\n",
+        status.model.model_name(),
+        status.model.parameters(),
+        status.model.context_length(),
+        cached,
+        if status.can_run { "yes" } else { "no" }
+    )
+}
+
+/// Render the `--status` table of cache health for every model
+///
+/// Models cached without hashes in their manifest show `header` under Valid, as only the
+/// file header could be checked.
+pub fn format_model_status(statuses: &[phi_models::ModelStatus]) -> String {
+    let mut output = format!(
+        "{:<36} {:>7} {:>10} {:>6} {:>8}\n",
+        "Model", "Cached", "Size", "Valid", "Can run"
+    );
+
+    for status in statuses {
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+        let size = status
+            .size
+            .map(|bytes| format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)))
+            .unwrap_or_else(|| "-".to_string());
+        output.push_str(&format!(
+            "{:<36} {:>7} {:>10} {:>6} {:>8}\n",
+            status.model.model_name(),
+            yes_no(status.cached),
+            size,
+            match (status.cached, status.valid, status.verified) {
+                (false, _, _) => "-",
+                _ if status.verification_error.is_some() => "error",
+                (true, true, false) => "header",
+                (true, valid, _) => yes_no(valid),
+            },
+            yes_no(status.can_run)
+        ));
+    }
+
+    output
 }
 
 /// Check system requirements for Phi model deployment
@@ -278,7 +686,7 @@ pub fn check_system_requirements() -> anyhow::Result<SystemInfo> {
     })
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SystemInfo {
     pub memory: MemoryInfo,
     pub disk: DiskInfo,
@@ -286,36 +694,63 @@ pub struct SystemInfo {
     pub gpu: GpuInfo,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MemoryInfo {
     pub total: u64,      // Total memory in bytes
     pub available: u64,  // Available memory in bytes
 }
 
-#[derive(Debug)]  
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DiskInfo {
     pub total: u64,      // Total disk space in bytes
     pub available: u64,  // Available disk space in bytes
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub has_cuda: bool,
     pub has_metal: bool,
     pub has_vulkan: bool,
     pub device_count: usize,
+    #[serde(alias = "total_vram")]
+    pub vram_total: u64,      // Total VRAM of the primary device in bytes (0 if unknown)
+    #[serde(alias = "available_vram")]
+    pub vram_available: u64,  // Free VRAM of the primary device in bytes (0 if unknown)
 }
 
 impl SystemInfo {
-    /// Check if system can run a specific Phi model
-    pub fn can_run_model(&self, model: &PhiModel) -> (bool, Vec<String>) {
+    /// Check if system can run a specific Phi model with its weights at `quantization`
+    pub fn can_run_model(
+        &self,
+        model: &PhiModel,
+        quantization: Quantization,
+    ) -> (bool, Vec<String>) {
         let mut issues = Vec::new();
         let mut can_run = true;
 
-        // Estimate memory requirements (rough approximation)
-        let estimated_memory = (model.parameter_count() * 2.0 * 1024.0 * 1024.0 * 1024.0) as u64; // 2 bytes per parameter
+        // Estimate memory requirements from the weights alone (rough approximation)
+        let Some(estimated_memory) = model.estimated_memory(quantization) else {
+            issues.push(format!(
+                "Cannot estimate memory for {} at {:?}: size overflows",
+                model.model_name(),
+                quantization
+            ));
+            return (false, issues);
+        };
 
-        if self.memory.available < estimated_memory {
+        // GPU backends hold the weights in VRAM; fall back to system RAM when VRAM is unknown
+        let backend = self.recommended_backend();
+        if backend != "ndarray" && self.gpu.vram_total > 0 {
+            if self.gpu.vram_available < estimated_memory {
+                can_run = false;
+                issues.push(format!(
+                    "Insufficient VRAM for {} backend: need ~{}, have {}",
+                    backend,
+                    format_bytes(estimated_memory),
+                    format_bytes(self.gpu.vram_available)
+                ));
+            }
+        } else if self.memory.available < estimated_memory {
             can_run = false;
             issues.push(format!(
                 "Insufficient memory: need ~{}, have {}",
@@ -325,7 +760,7 @@ impl SystemInfo {
         }
 
         // Check disk space (models + cache)
-        let required_disk = estimated_memory * 2; // Model + cache space
+        let required_disk = estimated_memory.saturating_mul(2); // Model + cache space
         if self.disk.available < required_disk {
             can_run = false;
             issues.push(format!(
@@ -356,6 +791,28 @@ impl SystemInfo {
         }
     }
 
+    /// System information as JSON, with the recommended backend and, for every available
+    /// model, whether it can run at the default quantization
+    pub fn to_json(&self) -> serde_json::Value {
+        let models = PhiModel::available_models()
+            .iter()
+            .map(|model| {
+                let (can_run, issues) = self.can_run_model(model, Quantization::default());
+                serde_json::json!({
+                    "model": model.short_name(),
+                    "name": model.model_name(),
+                    "can_run": can_run,
+                    "issues": issues,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut value = serde_json::to_value(self).expect("SystemInfo serializes to JSON");
+        value["recommended_backend"] = self.recommended_backend().into();
+        value["models"] = models.into();
+        value
+    }
+
     /// Display system information
     pub fn display(&self) {
         println!("💻 System Information:");
@@ -368,6 +825,11 @@ impl SystemInfo {
         println!("  CPU Cores: {}", self.cpu_cores);
         println!("  GPU Support: CUDA={}, Metal={}, Vulkan={}", 
                 self.gpu.has_cuda, self.gpu.has_metal, self.gpu.has_vulkan);
+        if self.gpu.vram_total > 0 {
+            println!("  GPU VRAM: {} total, {} available",
+                    format_bytes(self.gpu.vram_total),
+                    format_bytes(self.gpu.vram_available));
+        }
         println!("  Recommended Backend: {}", self.recommended_backend());
     }
 }
@@ -420,47 +882,398 @@ fn parse_memory_line(line: &str) -> anyhow::Result<u64> {
     }
 }
 
-fn check_disk_space(path: &str) -> anyhow::Result<DiskInfo> {
-    use std::fs;
-    
-    // Simplified disk space check
-    let metadata = fs::metadata(path)?;
-    
-    // This is a placeholder - real implementation would use platform-specific APIs
+/// Total and available space of the filesystem holding `path`
+///
+/// A path that does not exist yet, such as a cache directory about to be created, is
+/// resolved to its nearest existing ancestor.
+fn check_disk_space(path: impl AsRef<std::path::Path>) -> anyhow::Result<DiskInfo> {
+    let path = nearest_existing_ancestor(path.as_ref())?;
+
+    // The filesystem holding the path is the one with the longest matching mount point
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .ok_or_else(|| anyhow::anyhow!("No mounted filesystem contains {}", path.display()))?;
+
     Ok(DiskInfo {
-        total: 100 * 1024 * 1024 * 1024, // Assume 100GB
-        available: 50 * 1024 * 1024 * 1024, // Assume 50GB available
+        total: disk.total_space(),
+        available: disk.available_space(),
     })
 }
 
+/// Canonical form of `path` or, if it does not exist, of its closest existing ancestor
+fn nearest_existing_ancestor(path: &std::path::Path) -> anyhow::Result<std::path::PathBuf> {
+    let absolute = std::path::absolute(path)?;
+    absolute
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .ok_or_else(|| anyhow::anyhow!("No existing ancestor of {}", path.display()))
+}
+
 fn check_gpu_availability() -> GpuInfo {
     // In practice, would check for:
     // - CUDA: nvidia-ml-py, nvidia-smi
     // - Metal: system_profiler on macOS
     // - Vulkan: vulkan-tools, vkcube
+    let (vram_total, vram_available) = check_vram();
     
     GpuInfo {
         has_cuda: cfg!(feature = "cuda"),
         has_metal: cfg!(feature = "metal"),
         has_vulkan: cfg!(feature = "wgpu"), 
         device_count: if cfg!(feature = "cuda") { 1 } else { 0 },
+        vram_total,
+        vram_available,
+    }
+}
+
+/// Query (total, available) VRAM of the primary GPU, or zeros when it can't be determined
+fn check_vram() -> (u64, u64) {
+    #[cfg(feature = "cuda")]
+    if let Some(vram) = check_cuda_vram() {
+        return vram;
     }
+
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    if let Some(vram) = check_metal_vram() {
+        return vram;
+    }
+
+    // wgpu adapters don't report memory sizes, so VRAM stays unknown there
+    (0, 0)
+}
+
+#[cfg(feature = "cuda")]
+fn check_cuda_vram() -> Option<(u64, u64)> {
+    let nvml = nvml_wrapper::Nvml::init().ok()?;
+    let memory = nvml.device_by_index(0).ok()?.memory_info().ok()?;
+    Some((memory.total, memory.free))
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+fn check_metal_vram() -> Option<(u64, u64)> {
+    // Apple Silicon uses unified memory; the working set size is the GPU's usable share
+    let device = metal::Device::system_default()?;
+    let total = device.recommended_max_working_set_size();
+    Some((total, total.saturating_sub(device.current_allocated_size())))
 }
 
-// Placeholder for future Burn integration
+/// Burn backend that ONNX graphs run on
+pub type OnnxBackend = burn_ndarray::NdArray<f32>;
+
+/// A cached Phi model with its tokenizer and, when loaded with [`PhiInference::from_onnx`],
+/// its executable graph
 pub struct PhiInference {
-    // Will contain actual Burn model, tokenizer, etc.
+    model_path: std::path::PathBuf,
+    tokenizer: Option<Tokenizer>,
+    graph: Option<onnx::OnnxModel<OnnxBackend>>,
+    /// Shared by the sessions using this model, which only reuse the prefix they match
+    cache: std::sync::Mutex<onnx::KvCache<OnnxBackend>>,
+}
+
+impl std::fmt::Debug for PhiInference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PhiInference")
+            .field("model_path", &self.model_path)
+            .field("tokenizer", &self.tokenizer.is_some())
+            .field("graph", &self.graph.is_some())
+            .field("cached_tokens", &self.cached_tokens())
+            .finish()
+    }
+}
+
+impl PhiInference {
+    /// Open the model at `path` and its tokenizer, without decoding the graph
+    pub async fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        // Opening the file surfaces missing or unreadable cache entries
+        tokio::fs::File::open(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load model {:?}: {}", path, e))?;
+
+        Ok(Self {
+            model_path: path.to_path_buf(),
+            tokenizer: Self::load_tokenizer(path)?,
+            graph: None,
+            cache: Default::default(),
+        })
+    }
+
+    /// Decode the ONNX graph at `path` onto `device` so it can compute logits
+    ///
+    /// Only graphs built from the operators in [`onnx::SUPPORTED_OPS`] load; see [`onnx`].
+    pub fn from_onnx(
+        path: &std::path::Path,
+        device: &burn_ndarray::NdArrayDevice,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            model_path: path.to_path_buf(),
+            tokenizer: Self::load_tokenizer(path)?,
+            graph: Some(onnx::OnnxModel::load(path, device)?),
+            cache: Default::default(),
+        })
+    }
+
+    /// Load the model at `path` with its graph if [`onnx`] can run it, otherwise open it
+    /// without one so sessions fall back to demo replies
+    pub async fn open(path: &std::path::Path) -> anyhow::Result<Self> {
+        match Self::from_onnx(path, &Default::default()) {
+            Ok(inference) => Ok(inference),
+            Err(e) => {
+                tracing::warn!("Running without the model's graph: {:#}", e);
+                Self::load(path).await
+            }
+        }
+    }
+
+    /// `PhiModelManager` caches the tokenizer next to the weights
+    fn load_tokenizer(path: &std::path::Path) -> anyhow::Result<Option<Tokenizer>> {
+        let tokenizer_path = path.with_extension("tokenizer.json");
+        if tokenizer_path.exists() {
+            Ok(Some(Tokenizer::from_file(&tokenizer_path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Run one forward pass over `token_ids` and return the logits of the next token
+    pub fn next_token_logits(&self, token_ids: &[u32]) -> anyhow::Result<Vec<f32>> {
+        match &self.graph {
+            Some(graph) => graph.next_token_logits(token_ids),
+            None => anyhow::bail!(
+                "{:?} was opened without its graph; load it with PhiInference::from_onnx",
+                self.model_path
+            ),
+        }
+    }
+
+    /// Logits of the token after `token_ids`, reusing the work of earlier calls
+    ///
+    /// Only the tokens after the longest prefix shared with the previous call run through
+    /// the graph, so each turn of a conversation pays for its new tokens alone. The cache
+    /// holds at most `context_length` tokens; longer sequences are an error.
+    pub fn next_token_logits_cached(
+        &self,
+        token_ids: &[u32],
+        context_length: usize,
+    ) -> anyhow::Result<onnx::CachedLogits> {
+        let Some(graph) = &self.graph else {
+            anyhow::bail!(
+                "{:?} was opened without its graph; load it with PhiInference::from_onnx",
+                self.model_path
+            );
+        };
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.capacity() != context_length {
+            *cache = onnx::KvCache::new(context_length);
+        }
+        graph.next_token_logits_cached(token_ids, &mut cache)
+    }
+
+    /// Final hidden states of `token_ids`, one row per token; see
+    /// [`onnx::OnnxModel::hidden_states`]
+    pub fn hidden_states(&self, token_ids: &[u32]) -> anyhow::Result<Vec<Vec<f32>>> {
+        match &self.graph {
+            Some(graph) => graph.hidden_states(token_ids),
+            None => anyhow::bail!(
+                "{:?} was opened without its graph; load it with PhiInference::from_onnx",
+                self.model_path
+            ),
+        }
+    }
+
+    /// Forget the cached keys and values, so the next call processes its whole prompt
+    pub fn reset_cache(&self) {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).reset();
+    }
+
+    /// Tokens currently held in the key/value cache
+    pub fn cached_tokens(&self) -> usize {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Whether the model has both a graph and a tokenizer to generate with
+    pub fn can_generate(&self) -> bool {
+        self.graph.is_some() && self.tokenizer.is_some()
+    }
+
+    /// The model's tokenizer, if one was cached alongside it
+    pub fn tokenizer(&self) -> Option<&Tokenizer> {
+        self.tokenizer.as_ref()
+    }
+
+    /// Path the model was loaded from
+    pub fn model_path(&self) -> &std::path::Path {
+        &self.model_path
+    }
+
+    /// Number of tokens `text` encodes to with the model's tokenizer, or
+    /// [`estimate_tokens`](Self::estimate_tokens) when it has none
+    pub fn count_tokens(&self, text: &str) -> usize {
+        count_tokens_with(self.tokenizer(), text)
+    }
+
+    /// Estimate how many tokens `text` uses with the Phi BPE vocabularies, for when no
+    /// tokenizer is available
+    ///
+    /// This approximates BPE behaviour: runs of letters and digits cost one token per four
+    /// characters, every other symbol and each newline costs one, and other whitespace is
+    /// merged into the following token.
+    pub fn estimate_tokens(text: &str) -> usize {
+        let mut tokens = 0;
+        let mut word_chars: usize = 0;
+
+        for c in text.chars() {
+            if c.is_alphanumeric() {
+                word_chars += 1;
+                continue;
+            }
+            tokens += word_chars.div_ceil(4);
+            word_chars = 0;
+            if !c.is_whitespace() || c == '\n' {
+                tokens += 1;
+            }
+        }
+        tokens + word_chars.div_ceil(4)
+    }
+}
+
+/// Number of tokens `text` encodes to with `tokenizer`, falling back to
+/// [`PhiInference::estimate_tokens`] without one or when encoding fails
+pub fn count_tokens_with(tokenizer: Option<&Tokenizer>, text: &str) -> usize {
+    tokenizer
+        .and_then(|tokenizer| tokenizer.count_tokens(text).ok())
+        .unwrap_or_else(|| PhiInference::estimate_tokens(text))
+}
+
+/// Whether a prompt plus the response budget fits in a model's context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextFit {
+    pub prompt_tokens: usize,
+    pub max_tokens: usize,
+    pub context_length: usize,
+}
+
+impl ContextFit {
+    pub fn new(prompt_tokens: usize, max_tokens: usize, context_length: usize) -> Self {
+        Self {
+            prompt_tokens,
+            max_tokens,
+            context_length,
+        }
+    }
+
+    pub fn fits(&self) -> bool {
+        self.prompt_tokens + self.max_tokens <= self.context_length
+    }
+
+    /// Tokens left over (positive) or missing (negative) once the response budget is reserved
+    pub fn headroom(&self) -> i64 {
+        self.context_length as i64 - (self.prompt_tokens + self.max_tokens) as i64
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_banner_decision() {
+        assert!(should_show_banner(false, false, true));
+        assert!(!should_show_banner(false, true, true));
+        assert!(!should_show_banner(true, false, true));
+        assert!(!should_show_banner(false, false, false));
+    }
+
+    #[test]
+    fn test_sanitize_input_truncates_on_char_boundary() {
+        // "é" is two bytes, so a 9-byte limit minus the 3-byte marker falls mid-character
+        let input = "aéééééééé";
+        let sanitized = sanitize_input(input, 9);
+
+        assert!(sanitized.len() <= 9);
+        assert!(std::str::from_utf8(sanitized.as_bytes()).is_ok());
+        assert_eq!(sanitized, "aéé…");
+
+        assert_eq!(sanitize_input("  short\u{0}  ", 100), "short");
+        assert_eq!(sanitize_input("ééé", 2), "é");
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(PhiInference::estimate_tokens(""), 0);
+        assert_eq!(PhiInference::estimate_tokens("hello world"), 4);
+        assert_eq!(PhiInference::estimate_tokens("fn main() {}\n"), 7);
+        assert_eq!(count_tokens_with(None, "hello world"), 4);
+
+        let fit = ContextFit::new(3000, 512, 4096);
+        assert!(fit.fits());
+        assert_eq!(fit.headroom(), 584);
+        assert!(!ContextFit::new(4000, 512, 4096).fits());
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_with_the_model_tokenizer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+        std::fs::write(&path, b"weights").unwrap();
+        std::fs::write(
+            path.with_extension("tokenizer.json"),
+            r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+                "normalizer": null, "pre_tokenizer": {"type": "Whitespace"},
+                "post_processor": null, "decoder": null,
+                "model": {"type": "WordLevel", "unk_token": "[UNK]",
+                          "vocab": {"[UNK]": 0, "hello": 1, "world": 2}}}"#,
+        )
+        .unwrap();
+
+        let inference = PhiInference::load(&path).await.unwrap();
+        assert_eq!(inference.count_tokens("hello world"), 2);
+        assert_eq!(PhiInference::estimate_tokens("hello world"), 4);
+
+        // Without a tokenizer the estimate is used
+        std::fs::remove_file(path.with_extension("tokenizer.json")).unwrap();
+        let inference = PhiInference::load(&path).await.unwrap();
+        assert_eq!(inference.count_tokens("hello world"), 4);
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(1024), "1.0 KB");
         assert_eq!(format_bytes(1024 * 1024), "1.0 MB");
         assert_eq!(format_bytes(1024 * 1024 * 1024), "1.0 GB");
+        assert_eq!(format_bytes(1024 * 1024 - 1), "1.0 MB");
+    }
+
+    #[test]
+    fn test_format_bytes_with_styles() {
+        let binary = |bytes| format_bytes_with(bytes, BytesStyle::Binary);
+        assert_eq!(binary(999), "999 B");
+        assert_eq!(binary(1000), "1000 B");
+        assert_eq!(binary(1023), "1023 B");
+        assert_eq!(binary(1024), "1.0 KiB");
+        assert_eq!(binary(1024 * 1024 - 1), "1.0 MiB");
+        assert_eq!(binary(1024 * 1024 - 103), "1023.9 KiB");
+
+        let decimal = |bytes| format_bytes_with(bytes, BytesStyle::Decimal);
+        assert_eq!(decimal(999), "999 B");
+        assert_eq!(decimal(1000), "1.0 KB");
+        assert_eq!(decimal(1023), "1.0 KB");
+        assert_eq!(decimal(1024), "1.0 KB");
+        assert_eq!(decimal(999_999), "1.0 MB");
+        assert_eq!(decimal(999_900), "999.9 KB");
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("4096"), Ok(4096));
+        assert_eq!(parse_bytes("500MB"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_bytes("1.5 gb"), Ok(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_bytes("2KiB"), Ok(2048));
+        assert!(parse_bytes("lots").is_err());
+        assert!(parse_bytes("-1GB").is_err());
     }
 
     #[test]
@@ -489,6 +1302,8 @@ mod tests {
                 has_metal: false,
                 has_vulkan: false,
                 device_count: 1,
+                vram_total: 0,
+                vram_available: 0,
             },
         };
 
@@ -498,8 +1313,187 @@ mod tests {
             specialization: vec!["coding".to_string()],
         };
 
-        let (can_run, issues) = system_info.can_run_model(&phi3);
+        let (can_run, issues) = system_info.can_run_model(&phi3, Quantization::F16);
         assert!(can_run);
         assert_eq!(system_info.recommended_backend(), "cuda");
     }
+
+    #[test]
+    fn test_system_info_json_round_trip() {
+        let system_info = check_system_requirements().unwrap();
+        let json = system_info.to_json();
+
+        let parsed: SystemInfo = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed.cpu_cores, system_info.cpu_cores);
+        assert_eq!(json["recommended_backend"], system_info.recommended_backend());
+        assert_eq!(
+            json["models"].as_array().unwrap().len(),
+            PhiModel::available_models().len()
+        );
+        assert!(json["models"][0]["can_run"].is_boolean());
+    }
+
+    #[test]
+    fn test_list_reports() {
+        let system_info = check_system_requirements().unwrap();
+
+        let backends = format_backend_list(&system_info);
+        assert!(backends.contains("ndarray"));
+        assert!(backends.contains("cuda"));
+    }
+
+    #[test]
+    fn test_model_list_rows_show_cache_size_and_fit() {
+        let mut models = PhiModel::available_models().into_iter();
+        let cached = ModelStatus {
+            model: models.next().unwrap(),
+            cached: true,
+            size: Some(1536 * 1024 * 1024),
+            valid: true,
+            verified: true,
+            verification_error: None,
+            can_run: true,
+            issues: Vec::new(),
+        };
+        let uncached = ModelStatus {
+            model: models.next().unwrap(),
+            cached: false,
+            size: None,
+            valid: false,
+            verified: false,
+            verification_error: None,
+            can_run: false,
+            issues: vec!["Insufficient memory".to_string()],
+        };
+
+        let row = format_model_list_row(&cached);
+        let columns: Vec<&str> = row.split_whitespace().rev().take(3).collect();
+        assert_eq!(columns, ["yes", "GB", "1.5"]);
+        assert!(row.starts_with(cached.model.model_name()));
+        assert!(row.contains(&cached.model.context_length().to_string()));
+
+        let row = format_model_list_row(&uncached);
+        let columns: Vec<&str> = row.split_whitespace().rev().take(2).collect();
+        assert_eq!(columns, ["no", "no"]);
+        assert!(row.contains(uncached.model.parameters()));
+
+        let table = format_model_list(&[cached, uncached]);
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.lines().next().unwrap().ends_with("Cached  Fits"));
+    }
+
+    #[test]
+    fn test_model_exceeding_vram_is_rejected() {
+        let mut system_info = SystemInfo {
+            memory: MemoryInfo {
+                total: 64 * 1024 * 1024 * 1024,
+                available: 48 * 1024 * 1024 * 1024,
+            },
+            disk: DiskInfo {
+                total: 100 * 1024 * 1024 * 1024,
+                available: 50 * 1024 * 1024 * 1024,
+            },
+            cpu_cores: 8,
+            gpu: GpuInfo {
+                has_cuda: true,
+                has_metal: false,
+                has_vulkan: false,
+                device_count: 1,
+                vram_total: 4 * 1024 * 1024 * 1024,
+                vram_available: 4 * 1024 * 1024 * 1024,
+            },
+        };
+        let phi3 = PhiModel::from_short_name("phi3").unwrap();
+
+        let (can_run, issues) = system_info.can_run_model(&phi3, Quantization::F16);
+        assert!(!can_run);
+        assert!(issues.iter().any(|issue| issue.contains("VRAM")));
+
+        // Quantized to int4 the weights fit in VRAM
+        let (can_run, _) = system_info.can_run_model(&phi3, Quantization::Int4);
+        assert!(can_run);
+
+        // The same model fits in system RAM on the CPU backend
+        system_info.gpu.has_cuda = false;
+        let (can_run, _) = system_info.can_run_model(&phi3, Quantization::F16);
+        assert!(can_run);
+    }
+
+    #[test]
+    fn test_small_vram_reports_a_vram_issue() {
+        let gib = 1024 * 1024 * 1024;
+        let system_info = SystemInfo {
+            memory: MemoryInfo {
+                total: 64 * gib,
+                available: 48 * gib,
+            },
+            disk: DiskInfo {
+                total: 100 * gib,
+                available: 50 * gib,
+            },
+            cpu_cores: 8,
+            gpu: GpuInfo {
+                has_cuda: true,
+                has_metal: false,
+                has_vulkan: false,
+                device_count: 1,
+                vram_total: 2 * gib,
+                vram_available: gib,
+            },
+        };
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+        let (can_run, issues) = system_info.can_run_model(&phi2, Quantization::F16);
+        assert!(!can_run);
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert!(issues[0].starts_with("Insufficient VRAM for cuda backend"), "{}", issues[0]);
+
+        // Reports saved before the fields were renamed still load
+        let gpu: GpuInfo = serde_json::from_str(
+            r#"{"has_cuda": true, "has_metal": false, "has_vulkan": false, "device_count": 1,
+                "total_vram": 2, "available_vram": 1}"#,
+        )
+        .unwrap();
+        assert_eq!((gpu.vram_total, gpu.vram_available), (2, 1));
+    }
+
+    #[test]
+    fn test_disk_space_of_temp_dir() {
+        let disk = check_disk_space(std::env::temp_dir()).unwrap();
+        assert!(disk.available > 0 && disk.available <= disk.total);
+
+        // A path that doesn't exist yet reports its nearest existing ancestor's filesystem
+        let missing = std::env::temp_dir().join("phi-disk-check/not/created/yet");
+        let disk = check_disk_space(&missing).unwrap();
+        assert!(disk.available > 0 && disk.available <= disk.total);
+    }
+
+    #[test]
+    fn test_memory_estimate_per_quantization() {
+        for model in PhiModel::available_models() {
+            let f32_bytes = model.estimated_memory(Quantization::F32).unwrap();
+            let f16_bytes = model.estimated_memory(Quantization::F16).unwrap();
+            let int4_bytes = model.estimated_memory(Quantization::Int4).unwrap();
+
+            assert_eq!(f16_bytes * 2, f32_bytes);
+            let ratio = int4_bytes as f64 / f32_bytes as f64;
+            assert!((ratio - 0.125).abs() < 1e-6, "{}: {}", model.model_name(), ratio);
+        }
+
+        // 14B parameters at two bytes each, without saturating
+        let phi4 = PhiModel::from_short_name("phi4").unwrap();
+        assert_eq!(phi4.estimated_memory(Quantization::F16), Some(28_000_000_000));
+    }
+
+    #[cfg(feature = "cuda")]
+    #[test]
+    fn test_cuda_vram_reported() {
+        let gpu = check_gpu_availability();
+        if check_cuda_vram().is_none() {
+            eprintln!("Skipping: no CUDA device available");
+            return;
+        }
+        assert!(gpu.vram_total > 0);
+        assert!(gpu.vram_available > 0);
+    }
 }
\ No newline at end of file
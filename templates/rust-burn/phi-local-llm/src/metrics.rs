@@ -0,0 +1,310 @@
+/*!
+Backend-agnostic metrics for Phi inference
+
+Instrumentation code emits through the `MetricsSink` trait so the same call sites can
+report to Datadog (DogStatsD), Prometheus, or plain logs.
+*/
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::debug;
+
+/// Key/value tags attached to a metric
+pub type Tags<'a> = &'a [(&'a str, &'a str)];
+
+/// Destination for gauges, counters and timings
+pub trait MetricsSink: Send + Sync {
+    /// Record the current value of a quantity
+    fn gauge(&self, name: &str, value: f64, tags: Tags);
+
+    /// Add `value` to a monotonically increasing counter
+    fn increment(&self, name: &str, value: u64, tags: Tags);
+
+    /// Record how long an operation took
+    fn timing(&self, name: &str, duration: Duration, tags: Tags);
+}
+
+/// Available metrics backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsBackend {
+    Datadog,
+    Prometheus,
+    Log,
+}
+
+impl FromStr for MetricsBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "datadog" => Ok(MetricsBackend::Datadog),
+            "prometheus" => Ok(MetricsBackend::Prometheus),
+            "log" => Ok(MetricsBackend::Log),
+            _ => Err(format!(
+                "unknown metrics backend '{}' (expected datadog, prometheus or log)",
+                s
+            )),
+        }
+    }
+}
+
+/// Build the sink for the selected backend
+pub fn create_sink(backend: MetricsBackend) -> Result<Box<dyn MetricsSink>> {
+    Ok(match backend {
+        MetricsBackend::Datadog => Box::new(DatadogSink::from_env()?),
+        MetricsBackend::Prometheus => Box::new(PrometheusSink::new()),
+        MetricsBackend::Log => Box::new(LogSink),
+    })
+}
+
+/// Sends metrics to a Datadog agent over DogStatsD (UDP)
+pub struct DatadogSink {
+    socket: UdpSocket,
+}
+
+impl DatadogSink {
+    /// Connect to the agent at `addr` (e.g. `127.0.0.1:8125`)
+    pub fn new(addr: &str) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").context("Failed to bind DogStatsD socket")?;
+        socket
+            .connect(addr)
+            .with_context(|| format!("Failed to resolve DogStatsD address {}", addr))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// Connect using `DD_AGENT_HOST` / `DD_DOGSTATSD_PORT`, defaulting to localhost:8125
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("DD_AGENT_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
+        let port = std::env::var("DD_DOGSTATSD_PORT").unwrap_or_else(|_| "8125".to_string());
+        Self::new(&format!("{}:{}", host, port))
+    }
+
+    fn send(&self, name: &str, value: &str, kind: &str, tags: Tags) {
+        let mut line = format!("{}:{}|{}", name, value, kind);
+        if !tags.is_empty() {
+            let tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+            let _ = write!(line, "|#{}", tags.join(","));
+        }
+        // Metrics are best-effort; a missing agent must never fail inference
+        if let Err(e) = self.socket.send(line.as_bytes()) {
+            debug!("Failed to send DogStatsD metric {}: {}", name, e);
+        }
+    }
+}
+
+impl MetricsSink for DatadogSink {
+    fn gauge(&self, name: &str, value: f64, tags: Tags) {
+        self.send(name, &value.to_string(), "g", tags);
+    }
+
+    fn increment(&self, name: &str, value: u64, tags: Tags) {
+        self.send(name, &value.to_string(), "c", tags);
+    }
+
+    fn timing(&self, name: &str, duration: Duration, tags: Tags) {
+        self.send(name, &duration.as_millis().to_string(), "ms", tags);
+    }
+}
+
+/// Upper bounds in milliseconds of the histogram buckets timings are sorted into, spanning
+/// fast warm generations to long completions on slow hardware
+pub const LATENCY_BUCKETS_MS: [f64; 10] = [
+    100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0, 20_000.0, 30_000.0, 60_000.0,
+];
+
+/// Keeps metrics in memory and renders them in the Prometheus text format
+///
+/// Dots and dashes in names become underscores and counters get the conventional `_total`
+/// suffix, so `phi.api.requests` is exported as `phi_api_requests_total`. Timings become
+/// histograms over [`LATENCY_BUCKETS_MS`].
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    series: Mutex<BTreeMap<String, Series>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Series {
+    Gauge(f64),
+    Counter(u64),
+    Histogram {
+        /// Non-cumulative count of observations per bucket of `LATENCY_BUCKETS_MS`
+        buckets: [u64; LATENCY_BUCKETS_MS.len()],
+        sum: f64,
+        count: u64,
+    },
+}
+
+impl PrometheusSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render all recorded series in the Prometheus exposition format
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap();
+        let mut output = String::new();
+
+        for (key, value) in series.iter() {
+            let (name, labels) = key.split_once('{').map_or((key.as_str(), ""), |(n, l)| (n, l));
+            let labels = if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}", labels)
+            };
+
+            let _ = match value {
+                Series::Gauge(v) => writeln!(output, "{}{} {}", name, labels, v),
+                Series::Counter(v) => writeln!(output, "{}_total{} {}", name, labels, v),
+                Series::Histogram {
+                    buckets,
+                    sum,
+                    count,
+                } => {
+                    let mut cumulative = 0;
+                    for (bound, observed) in LATENCY_BUCKETS_MS.iter().zip(buckets) {
+                        cumulative += observed;
+                        let le = bound.to_string();
+                        let bucket_labels = with_label(&labels, "le", &le);
+                        let _ = writeln!(output, "{}_bucket{} {}", name, bucket_labels, cumulative);
+                    }
+                    let _ = writeln!(
+                        output,
+                        "{}_bucket{} {}",
+                        name,
+                        with_label(&labels, "le", "+Inf"),
+                        count
+                    );
+                    writeln!(
+                        output,
+                        "{name}_sum{labels} {sum}\n{name}_count{labels} {count}",
+                        name = name,
+                        labels = labels,
+                        sum = sum,
+                        count = count
+                    )
+                }
+            };
+        }
+
+        output
+    }
+
+    fn key(name: &str, tags: Tags) -> String {
+        let name = name.replace(['.', '-'], "_");
+        if tags.is_empty() {
+            return name;
+        }
+        let labels: Vec<String> = tags.iter().map(|(k, v)| format!("{}=\"{}\"", k, v)).collect();
+        format!("{}{{{}}}", name, labels.join(","))
+    }
+}
+
+impl MetricsSink for PrometheusSink {
+    fn gauge(&self, name: &str, value: f64, tags: Tags) {
+        let mut series = self.series.lock().unwrap();
+        series.insert(Self::key(name, tags), Series::Gauge(value));
+    }
+
+    fn increment(&self, name: &str, value: u64, tags: Tags) {
+        let mut series = self.series.lock().unwrap();
+        let entry = series.entry(Self::key(name, tags)).or_insert(Series::Counter(0));
+        if let Series::Counter(total) = entry {
+            *total += value;
+        }
+    }
+
+    fn timing(&self, name: &str, duration: Duration, tags: Tags) {
+        let mut series = self.series.lock().unwrap();
+        let entry = series.entry(Self::key(name, tags)).or_insert(Series::Histogram {
+            buckets: [0; LATENCY_BUCKETS_MS.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        if let Series::Histogram {
+            buckets,
+            sum,
+            count,
+        } = entry
+        {
+            let ms = duration.as_secs_f64() * 1000.0;
+            if let Some(bucket) = LATENCY_BUCKETS_MS.iter().position(|bound| ms <= *bound) {
+                buckets[bucket] += 1;
+            }
+            *sum += ms;
+            *count += 1;
+        }
+    }
+}
+
+/// Add `name="value"` to a rendered label set such as `{model="phi-3"}`, which may be empty
+fn with_label(labels: &str, name: &str, value: &str) -> String {
+    match labels.strip_suffix('}') {
+        Some(existing) => format!("{},{}=\"{}\"}}", existing, name, value),
+        None => format!("{{{}=\"{}\"}}", name, value),
+    }
+}
+
+/// Writes metrics to the tracing log at debug level
+pub struct LogSink;
+
+impl MetricsSink for LogSink {
+    fn gauge(&self, name: &str, value: f64, tags: Tags) {
+        debug!(metric = name, value, ?tags, "gauge");
+    }
+
+    fn increment(&self, name: &str, value: u64, tags: Tags) {
+        debug!(metric = name, value, ?tags, "increment");
+    }
+
+    fn timing(&self, name: &str, duration: Duration, tags: Tags) {
+        debug!(metric = name, duration_ms = duration.as_millis() as u64, ?tags, "timing");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_backend_parsing() {
+        assert_eq!("datadog".parse(), Ok(MetricsBackend::Datadog));
+        assert_eq!("Prometheus".parse(), Ok(MetricsBackend::Prometheus));
+        assert!("statsd".parse::<MetricsBackend>().is_err());
+    }
+
+    #[test]
+    fn test_prometheus_render() {
+        let sink = PrometheusSink::new();
+        sink.increment("phi.inference.requests", 1, &[("model", "phi-3")]);
+        sink.increment("phi.inference.requests", 2, &[("model", "phi-3")]);
+        sink.timing("phi.inference.latency_ms", Duration::from_millis(250), &[]);
+
+        let output = sink.render();
+        assert!(output.contains("phi_inference_requests_total{model=\"phi-3\"} 3"));
+        assert!(output.contains("phi_inference_latency_ms_sum 250"));
+        assert!(output.contains("phi_inference_latency_ms_count 1"));
+    }
+
+    #[test]
+    fn test_prometheus_latency_histogram() {
+        let sink = PrometheusSink::new();
+        let tags: Tags = &[("model", "phi-3")];
+        sink.timing("phi.generation_ms", Duration::from_millis(80), tags);
+        sink.timing("phi.generation_ms", Duration::from_millis(3_000), tags);
+        sink.timing("phi.generation_ms", Duration::from_secs(90), tags);
+
+        let output = sink.render();
+        assert!(output.contains("phi_generation_ms_bucket{model=\"phi-3\",le=\"100\"} 1"));
+        assert!(output.contains("phi_generation_ms_bucket{model=\"phi-3\",le=\"2500\"} 1"));
+        assert!(output.contains("phi_generation_ms_bucket{model=\"phi-3\",le=\"5000\"} 2"));
+        assert!(output.contains("phi_generation_ms_bucket{model=\"phi-3\",le=\"60000\"} 2"));
+        assert!(output.contains("phi_generation_ms_bucket{model=\"phi-3\",le=\"+Inf\"} 3"));
+        assert!(output.contains("phi_generation_ms_count{model=\"phi-3\"} 3"));
+    }
+}
@@ -0,0 +1,689 @@
+/*!
+Running ONNX language-model graphs on Burn tensors
+
+`burn-import` turns an ONNX file into Rust source at build time, so it can't load a model
+that [`PhiModelManager`](crate::PhiModelManager) downloads at runtime. [`OnnxModel`]
+instead decodes the `ModelProto` directly (only the messages and fields read here are
+declared; their tags match `onnx.proto`) and interprets the graph node by node on the
+backend's tensors.
+
+Only a small set of operators is supported so far: `Gather` (token embeddings),
+`MatMul`, `Gemm`, `Add`, `Mul`, `Relu`, `Tanh` and `Identity`, on float tensors of rank
+one or two, with the token ids as the single graph input. That covers embedding plus
+projection graphs that return next-token logits; loading any other graph fails up front,
+listing the operators it would need. None of the published Phi exports load yet: their
+normalization and attention layers use operators such as `SimplifiedLayerNormalization`
+and `com.microsoft.GroupQueryAttention`.
+
+[`OnnxModel::next_token_logits_cached`] keeps each node's per-token outputs in a
+[`KvCache`], so a prompt that extends an earlier one only runs its new tokens. Those rows
+are what attention nodes will read as past keys and values; the operators supported so far
+work on each token independently and only need the new rows.
+*/
+
+use anyhow::{bail, Context, Result};
+use burn::tensor::activation::relu;
+use burn::tensor::backend::Backend;
+use burn::tensor::{Int, Tensor, TensorData};
+use prost::Message;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Operators [`OnnxModel`] can execute
+pub const SUPPORTED_OPS: &[&str] = &[
+    "Gather", "MatMul", "Gemm", "Add", "Mul", "Relu", "Tanh", "Identity",
+];
+
+/// `TensorProto.DataType.FLOAT`
+pub const FLOAT: i32 = 1;
+
+/// `TensorProto.DataLocation.EXTERNAL`
+const EXTERNAL: i32 = 1;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ModelProto {
+    #[prost(int64, tag = "1")]
+    pub ir_version: i64,
+    #[prost(message, optional, tag = "7")]
+    pub graph: Option<GraphProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    pub node: Vec<NodeProto>,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, repeated, tag = "5")]
+    pub initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    pub input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    pub output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    pub input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub output: Vec<String>,
+    #[prost(string, tag = "3")]
+    pub name: String,
+    #[prost(string, tag = "4")]
+    pub op_type: String,
+    #[prost(message, repeated, tag = "5")]
+    pub attribute: Vec<AttributeProto>,
+    #[prost(string, tag = "7")]
+    pub domain: String,
+}
+
+/// `AttributeProto` restricted to scalar float and int values
+#[derive(Clone, PartialEq, Message)]
+pub struct AttributeProto {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(float, tag = "2")]
+    pub f: f32,
+    #[prost(int64, tag = "3")]
+    pub i: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    pub dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    pub data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    pub float_data: Vec<f32>,
+    #[prost(string, tag = "8")]
+    pub name: String,
+    /// Little-endian element bytes, used instead of `float_data` by most exporters
+    #[prost(bytes = "vec", tag = "9")]
+    pub raw_data: Vec<u8>,
+    #[prost(int32, tag = "14")]
+    pub data_location: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    pub name: String,
+}
+
+/// A value flowing through the graph
+#[derive(Debug, Clone)]
+enum Value<B: Backend> {
+    /// Float tensor; rank-one initializers are stored as a single row
+    Float(Tensor<B, 2>),
+    /// Token ids fed to the graph
+    Ids(Tensor<B, 1, Int>),
+}
+
+/// Per-token node outputs of the tokens a model has already processed
+#[derive(Debug)]
+pub struct KvCache<B: Backend> {
+    tokens: Vec<u32>,
+    rows: HashMap<String, Tensor<B, 2>>,
+    capacity: usize,
+}
+
+impl<B: Backend> KvCache<B> {
+    /// An empty cache holding at most `capacity` tokens, normally the context length
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            tokens: Vec::new(),
+            rows: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Most tokens the cache holds
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Tokens cached so far
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Whether no tokens are cached
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Forget every cached token
+    pub fn reset(&mut self) {
+        self.tokens.clear();
+        self.rows.clear();
+    }
+
+    /// Keep only the first `len` tokens
+    fn truncate(&mut self, len: usize) {
+        if len == 0 {
+            self.reset();
+            return;
+        }
+        self.tokens.truncate(len);
+        for rows in self.rows.values_mut() {
+            let [_, cols] = rows.dims();
+            *rows = rows.clone().slice([0..len, 0..cols]);
+        }
+    }
+}
+
+impl<B: Backend> Default for KvCache<B> {
+    /// An empty cache without a size limit
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+/// Result of [`OnnxModel::next_token_logits_cached`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedLogits {
+    /// Logits for the next token, one per vocabulary entry
+    pub logits: Vec<f32>,
+    /// Tokens run through the graph; the rest came from the cache
+    pub processed_tokens: usize,
+}
+
+/// ONNX graph loaded onto a Burn device, ready to compute next-token logits
+#[derive(Debug)]
+pub struct OnnxModel<B: Backend> {
+    nodes: Vec<NodeProto>,
+    initializers: HashMap<String, Tensor<B, 2>>,
+    input: String,
+    output: String,
+    device: B::Device,
+}
+
+impl<B: Backend> OnnxModel<B> {
+    /// Decode the ONNX file at `path` and load its weights onto `device`
+    pub fn load(path: &Path, device: &B::Device) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read ONNX model {:?}", path))?;
+        let model = ModelProto::decode(bytes.as_slice())
+            .with_context(|| format!("{:?} is not an ONNX model", path))?;
+        Self::from_proto(model, device).with_context(|| format!("Can't run ONNX model {:?}", path))
+    }
+
+    /// Load a decoded model onto `device`
+    pub fn from_proto(model: ModelProto, device: &B::Device) -> Result<Self> {
+        let graph = model.graph.context("the model has no graph")?;
+
+        let unsupported = graph
+            .node
+            .iter()
+            .filter(|node| {
+                !node.domain.is_empty() || !SUPPORTED_OPS.contains(&node.op_type.as_str())
+            })
+            .map(|node| match node.domain.as_str() {
+                "" => node.op_type.clone(),
+                domain => format!("{}.{}", domain, node.op_type),
+            })
+            .collect::<BTreeSet<_>>();
+        if !unsupported.is_empty() {
+            bail!(
+                "unsupported operators {} (supported: {})",
+                unsupported.into_iter().collect::<Vec<_>>().join(", "),
+                SUPPORTED_OPS.join(", ")
+            );
+        }
+
+        let initializers = graph
+            .initializer
+            .iter()
+            .map(|tensor| Ok((tensor.name.clone(), float_initializer(tensor, device)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        // Older exporters also list initializers as graph inputs
+        let inputs = graph
+            .input
+            .iter()
+            .map(|input| input.name.as_str())
+            .filter(|name| !initializers.contains_key(*name))
+            .collect::<Vec<_>>();
+        let input = match inputs.as_slice() {
+            [input] => input.to_string(),
+            inputs => bail!(
+                "expected the token ids as the only input, found [{}]",
+                inputs.join(", ")
+            ),
+        };
+        let output = graph
+            .output
+            .first()
+            .map(|output| output.name.clone())
+            .context("the graph has no outputs")?;
+
+        Ok(Self {
+            nodes: graph.node,
+            initializers,
+            input,
+            output,
+            device: device.clone(),
+        })
+    }
+
+    /// Logits for the token following `token_ids`, one per vocabulary entry
+    pub fn next_token_logits(&self, token_ids: &[u32]) -> Result<Vec<f32>> {
+        if token_ids.is_empty() {
+            bail!("at least one token id is needed");
+        }
+        let outputs = self.forward(token_ids)?;
+        self.last_logits(&outputs)
+    }
+
+    /// Like [`next_token_logits`](Self::next_token_logits), but only runs the tokens after
+    /// the longest prefix of `token_ids` that `cache` already holds
+    ///
+    /// The cache is left holding `token_ids`. The last token always runs, so its logits can
+    /// be returned; sequences longer than the cache's capacity are an error.
+    pub fn next_token_logits_cached(
+        &self,
+        token_ids: &[u32],
+        cache: &mut KvCache<B>,
+    ) -> Result<CachedLogits> {
+        if token_ids.is_empty() {
+            bail!("at least one token id is needed");
+        }
+        if token_ids.len() > cache.capacity {
+            bail!(
+                "{} tokens exceed the {} token context",
+                token_ids.len(),
+                cache.capacity
+            );
+        }
+
+        let reused = cache
+            .tokens
+            .iter()
+            .zip(token_ids)
+            .take_while(|(cached, id)| cached == id)
+            .count()
+            .min(token_ids.len() - 1);
+        cache.truncate(reused);
+
+        let new_tokens = &token_ids[reused..];
+        let outputs = self.forward(new_tokens)?;
+        let logits = self.last_logits(&outputs)?;
+
+        for (name, value) in outputs {
+            // Only outputs with a row per token extend the cache
+            let Value::Float(rows) = value else { continue };
+            if rows.dims()[0] != new_tokens.len() {
+                continue;
+            }
+            let rows = match cache.rows.remove(&name) {
+                Some(cached) => Tensor::cat(vec![cached, rows], 0),
+                None => rows,
+            };
+            cache.rows.insert(name, rows);
+        }
+        cache.tokens.extend_from_slice(new_tokens);
+
+        Ok(CachedLogits {
+            logits,
+            processed_tokens: new_tokens.len(),
+        })
+    }
+
+    /// Final hidden states of `token_ids`, one row per token
+    ///
+    /// These are the input of the projection (`MatMul` or `Gemm`) the logits are computed
+    /// from, found by walking back from the graph output past any bias or scaling.
+    pub fn hidden_states(&self, token_ids: &[u32]) -> Result<Vec<Vec<f32>>> {
+        if token_ids.is_empty() {
+            bail!("at least one token id is needed");
+        }
+        let name = self.hidden_state_name()?;
+        let outputs = self.forward(token_ids)?;
+        let Some(Value::Float(states)) = outputs.get(name) else {
+            bail!("hidden states '{}' are not a float tensor computed by the graph", name);
+        };
+        let [_, width] = states.dims();
+        let values = states
+            .clone()
+            .into_data()
+            .to_vec::<f32>()
+            .map_err(|e| anyhow::anyhow!("Failed to read hidden states: {:?}", e))?;
+        Ok(values.chunks(width).map(<[f32]>::to_vec).collect())
+    }
+
+    /// Name of the value [`hidden_states`](Self::hidden_states) reads
+    fn hidden_state_name(&self) -> Result<&str> {
+        let mut name = self.output.as_str();
+        loop {
+            let node = self
+                .nodes
+                .iter()
+                .find(|node| node.output.first().is_some_and(|output| output == name))
+                .with_context(|| format!("'{}' is not computed by any node", name))?;
+            let input = node
+                .input
+                .first()
+                .with_context(|| format!("node '{}' has no inputs", node.name))?;
+            match node.op_type.as_str() {
+                "MatMul" | "Gemm" => return Ok(input),
+                "Add" | "Mul" | "Identity" => name = input,
+                op => bail!(
+                    "the logits come from a {} node instead of a projection of hidden states",
+                    op
+                ),
+            }
+        }
+    }
+
+    /// Run the graph over `token_ids`, returning the output of every node
+    fn forward(&self, token_ids: &[u32]) -> Result<HashMap<String, Value<B>>> {
+        let ids = token_ids.iter().map(|&id| id as i64).collect::<Vec<_>>();
+        let ids =
+            Tensor::<B, 1, Int>::from_data(TensorData::new(ids, [token_ids.len()]), &self.device);
+
+        let mut values: HashMap<&str, Value<B>> = self
+            .initializers
+            .iter()
+            .map(|(name, tensor)| (name.as_str(), Value::Float(tensor.clone())))
+            .collect();
+        values.insert(self.input.as_str(), Value::Ids(ids));
+
+        let mut outputs = HashMap::new();
+        for node in &self.nodes {
+            let result = self
+                .run_node(node, &values)
+                .with_context(|| format!("{} node '{}' failed", node.op_type, node.name))?;
+            let output = node.output.first().context("node has no output")?;
+            values.insert(output.as_str(), result.clone());
+            outputs.insert(output.clone(), result);
+        }
+        Ok(outputs)
+    }
+
+    /// Last row of the graph output among `outputs`
+    fn last_logits(&self, outputs: &HashMap<String, Value<B>>) -> Result<Vec<f32>> {
+        let logits = match outputs.get(&self.output) {
+            Some(Value::Float(logits)) => logits.clone(),
+            Some(Value::Ids(_)) => bail!("graph output '{}' is not a float tensor", self.output),
+            None => bail!("graph output '{}' was never computed", self.output),
+        };
+        let [rows, vocab] = logits.dims();
+        logits
+            .slice([rows - 1..rows, 0..vocab])
+            .into_data()
+            .to_vec::<f32>()
+            .map_err(|e| anyhow::anyhow!("Failed to read logits: {:?}", e))
+    }
+
+    fn run_node(&self, node: &NodeProto, values: &HashMap<&str, Value<B>>) -> Result<Value<B>> {
+        let input = |index: usize| -> Result<&Value<B>> {
+            let name = node
+                .input
+                .get(index)
+                .with_context(|| format!("missing input {}", index))?;
+            values
+                .get(name.as_str())
+                .with_context(|| format!("input '{}' is not computed before this node", name))
+        };
+        let float = |index: usize| -> Result<Tensor<B, 2>> {
+            match input(index)? {
+                Value::Float(tensor) => Ok(tensor.clone()),
+                Value::Ids(_) => bail!("input {} must be a float tensor", index),
+            }
+        };
+
+        Ok(Value::Float(match node.op_type.as_str() {
+            "Gather" => {
+                if attribute(node, "axis").is_some_and(|axis| axis.i != 0) {
+                    bail!("only axis 0 is supported");
+                }
+                match input(1)? {
+                    Value::Ids(ids) => float(0)?.select(0, ids.clone()),
+                    Value::Float(_) => bail!("indices must be the token ids"),
+                }
+            }
+            "MatMul" => float(0)?.matmul(float(1)?),
+            "Gemm" => {
+                let scale = |name| attribute(node, name).map_or(1.0, |attribute| attribute.f);
+                let transposed =
+                    |name| attribute(node, name).is_some_and(|attribute| attribute.i != 0);
+                let (a, b) = (float(0)?, float(1)?);
+                let a = if transposed("transA") {
+                    a.transpose()
+                } else {
+                    a
+                };
+                let b = if transposed("transB") {
+                    b.transpose()
+                } else {
+                    b
+                };
+                let product = a.matmul(b).mul_scalar(scale("alpha"));
+                match node.input.get(2).filter(|name| !name.is_empty()) {
+                    Some(_) => product + float(2)?.mul_scalar(scale("beta")),
+                    None => product,
+                }
+            }
+            "Add" => float(0)? + float(1)?,
+            "Mul" => float(0)? * float(1)?,
+            "Relu" => relu(float(0)?),
+            "Tanh" => float(0)?.tanh(),
+            "Identity" => return input(0).cloned(),
+            op => bail!("unsupported operator {}", op),
+        }))
+    }
+}
+
+fn attribute<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute
+        .iter()
+        .find(|attribute| attribute.name == name)
+}
+
+/// Load a rank one or two float initializer as a 2-D tensor
+fn float_initializer<B: Backend>(tensor: &TensorProto, device: &B::Device) -> Result<Tensor<B, 2>> {
+    let name = &tensor.name;
+    if tensor.data_location == EXTERNAL {
+        bail!(
+            "initializer '{}' is stored in an external data file, which is not supported",
+            name
+        );
+    }
+    if tensor.data_type != FLOAT {
+        bail!(
+            "initializer '{}' has data type {}; only float32 (1) is supported",
+            name,
+            tensor.data_type
+        );
+    }
+    let dim = |dim: i64| {
+        usize::try_from(dim)
+            .with_context(|| format!("initializer '{}' has negative dimension {}", name, dim))
+    };
+    let shape = match tensor.dims.as_slice() {
+        &[len] => [1, dim(len)?],
+        &[rows, cols] => [dim(rows)?, dim(cols)?],
+        dims => bail!(
+            "initializer '{}' has rank {}; only ranks 1 and 2 are supported",
+            name,
+            dims.len()
+        ),
+    };
+
+    let values = if tensor.raw_data.is_empty() {
+        tensor.float_data.clone()
+    } else {
+        tensor
+            .raw_data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    };
+    let len = shape[0]
+        .checked_mul(shape[1])
+        .with_context(|| format!("initializer '{}' has too many values: {:?}", name, tensor.dims))?;
+    if values.len() != len {
+        bail!(
+            "initializer '{}' holds {} values for shape {:?}",
+            name,
+            values.len(),
+            tensor.dims
+        );
+    }
+    Ok(Tensor::from_data(TensorData::new(values, shape), device))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_ndarray::{NdArray, NdArrayDevice};
+
+    type TestBackend = NdArray<f32>;
+
+    fn float_tensor(name: &str, dims: &[i64], values: &[f32]) -> TensorProto {
+        TensorProto {
+            dims: dims.to_vec(),
+            data_type: FLOAT,
+            name: name.to_string(),
+            raw_data: values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn node(op_type: &str, input: &[&str], output: &str) -> NodeProto {
+        NodeProto {
+            input: input.iter().map(|name| name.to_string()).collect(),
+            output: vec![output.to_string()],
+            name: output.to_string(),
+            op_type: op_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn value(name: &str) -> ValueInfoProto {
+        ValueInfoProto {
+            name: name.to_string(),
+        }
+    }
+
+    /// Embed 4 tokens in 2 dimensions and project back onto the vocabulary plus a bias
+    fn tiny_language_model() -> ModelProto {
+        ModelProto {
+            ir_version: 8,
+            graph: Some(GraphProto {
+                node: vec![
+                    node("Gather", &["embedding", "input_ids"], "hidden"),
+                    node("MatMul", &["hidden", "lm_head"], "projected"),
+                    node("Add", &["projected", "bias"], "logits"),
+                ],
+                name: "tiny".to_string(),
+                initializer: vec![
+                    float_tensor("embedding", &[4, 2], &[1., 0., 0., 1., 1., 1., 2., 0.]),
+                    float_tensor("lm_head", &[2, 4], &[1., 0., 2., 0., 0., 1., 0., 3.]),
+                    float_tensor("bias", &[4], &[0.5, 0., 0., -1.]),
+                ],
+                input: vec![value("input_ids")],
+                output: vec![value("logits")],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_tiny_graph_returns_logits_of_the_last_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiny.onnx");
+        std::fs::write(&path, tiny_language_model().encode_to_vec()).unwrap();
+
+        let model = OnnxModel::<TestBackend>::load(&path, &NdArrayDevice::Cpu).unwrap();
+        // Token 2 embeds as [1, 1]: [1 + 0, 0 + 1, 2 + 0, 0 + 3] plus the bias
+        let logits = model.next_token_logits(&[0, 2]).unwrap();
+        assert_eq!(logits, vec![1.5, 1.0, 2.0, 2.0]);
+
+        assert!(model.next_token_logits(&[]).is_err());
+    }
+
+    #[test]
+    fn test_hidden_states_are_the_input_of_the_lm_head() {
+        let model =
+            OnnxModel::<TestBackend>::from_proto(tiny_language_model(), &NdArrayDevice::Cpu)
+                .unwrap();
+        let states = model.hidden_states(&[0, 2]).unwrap();
+        assert_eq!(states, vec![vec![1.0, 0.0], vec![1.0, 1.0]]);
+        assert!(model.hidden_states(&[]).is_err());
+
+        // Without a projection there are no hidden states to read
+        let mut graph = tiny_language_model();
+        let nodes = &mut graph.graph.as_mut().unwrap().node;
+        nodes.truncate(1);
+        nodes[0].output = vec!["logits".to_string()];
+        let model = OnnxModel::<TestBackend>::from_proto(graph, &NdArrayDevice::Cpu).unwrap();
+        assert!(model.hidden_states(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_cached_turn_only_processes_new_tokens() {
+        let model =
+            OnnxModel::<TestBackend>::from_proto(tiny_language_model(), &NdArrayDevice::Cpu)
+                .unwrap();
+        let mut cache = KvCache::new(6);
+
+        let cold = model.next_token_logits_cached(&[0, 2, 1], &mut cache).unwrap();
+        assert_eq!(cold.processed_tokens, 3);
+        let warm = model.next_token_logits_cached(&[0, 2, 1, 3, 2], &mut cache).unwrap();
+        assert_eq!(warm.processed_tokens, 2);
+        assert_eq!(warm.logits, model.next_token_logits(&[0, 2, 1, 3, 2]).unwrap());
+        assert_eq!(cache.len(), 5);
+
+        // A diverging prompt reuses only the shared prefix
+        let edited = model.next_token_logits_cached(&[0, 3], &mut cache).unwrap();
+        assert_eq!(edited.processed_tokens, 1);
+        assert_eq!(cache.len(), 2);
+
+        assert!(model.next_token_logits_cached(&[0; 7], &mut cache).is_err());
+        cache.reset();
+        let reset = model.next_token_logits_cached(&[0, 3], &mut cache).unwrap();
+        assert_eq!(reset.processed_tokens, 2);
+    }
+
+    #[test]
+    fn test_unsupported_graphs_are_rejected_at_load() {
+        let mut model = tiny_language_model();
+        let graph = model.graph.as_mut().unwrap();
+        graph
+            .node
+            .push(node("LayerNormalization", &["logits"], "normed"));
+        graph.node.push(NodeProto {
+            domain: "com.microsoft".to_string(),
+            ..node("GroupQueryAttention", &["normed"], "attended")
+        });
+
+        let error = OnnxModel::<TestBackend>::from_proto(model, &NdArrayDevice::Cpu).unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "unsupported operators LayerNormalization, com.microsoft.GroupQueryAttention"
+            ),
+            "{}",
+            error
+        );
+
+        let mut model = tiny_language_model();
+        model.graph.as_mut().unwrap().initializer[0].data_type = 10;
+        let error = OnnxModel::<TestBackend>::from_proto(model, &NdArrayDevice::Cpu).unwrap_err();
+        assert!(error.to_string().contains("only float32"), "{}", error);
+
+        let mut model = tiny_language_model();
+        model.graph.as_mut().unwrap().initializer[0].dims = vec![-3, 2];
+        let error = OnnxModel::<TestBackend>::from_proto(model, &NdArrayDevice::Cpu).unwrap_err();
+        assert!(error.to_string().contains("negative dimension -3"), "{}", error);
+
+        let mut model = tiny_language_model();
+        model.graph.as_mut().unwrap().initializer[0].dims = vec![i64::MAX, i64::MAX];
+        let error = OnnxModel::<TestBackend>::from_proto(model, &NdArrayDevice::Cpu).unwrap_err();
+        assert!(error.to_string().contains("too many values"), "{}", error);
+    }
+}
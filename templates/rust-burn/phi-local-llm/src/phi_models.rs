@@ -7,14 +7,102 @@ for efficient on-device AI capabilities in the VibeCode platform.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
+use reqwest::header::{CONTENT_RANGE, ETAG, RANGE};
+use reqwest::StatusCode;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tracing::{info, warn};
 
+use crate::template::ChatTemplate;
+use crate::SystemInfo;
+
+pub mod tokenizer;
+
+pub use tokenizer::Tokenizer;
+
+/// Numeric precision the model weights are held in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Quantization {
+    F32,
+    /// Half precision, two bytes per weight
+    #[default]
+    F16,
+    Int8,
+    Int4,
+}
+
+impl Quantization {
+    /// Every quantization, from widest to narrowest
+    pub const ALL: [Quantization; 4] = [
+        Quantization::F32,
+        Quantization::F16,
+        Quantization::Int8,
+        Quantization::Int4,
+    ];
+
+    /// Storage per weight in bits
+    pub fn bits(&self) -> u64 {
+        match self {
+            Quantization::F32 => 32,
+            Quantization::F16 => 16,
+            Quantization::Int8 => 8,
+            Quantization::Int4 => 4,
+        }
+    }
+
+    /// Suffix of the cached model file name; the default F16 has none so existing caches
+    /// stay valid
+    pub fn file_suffix(&self) -> &'static str {
+        match self {
+            Quantization::F32 => "_f32",
+            Quantization::F16 => "",
+            Quantization::Int8 => "_q8",
+            Quantization::Int4 => "_q4",
+        }
+    }
+}
+
+impl std::str::FromStr for Quantization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "f32" => Ok(Quantization::F32),
+            "f16" => Ok(Quantization::F16),
+            "int8" | "q8" => Ok(Quantization::Int8),
+            "int4" | "q4" => Ok(Quantization::Int4),
+            _ => Err(format!(
+                "unknown quantization '{}' (expected f32, f16, int8 or int4)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Quantization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Quantization::F32 => "f32",
+            Quantization::F16 => "f16",
+            Quantization::Int8 => "int8",
+            Quantization::Int4 => "int4",
+        })
+    }
+}
+
+/// Most weight memory a model may need to count as edge-suitable: 4B parameters at F16
+const EDGE_MEMORY_BUDGET: u64 = 8_000_000_000;
+
 /// Microsoft Phi model variants with their specifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PhiModel {
-    /// Phi-1: ~1B parameters, Python coding focus
+    /// Phi-1: 1.3B parameters, Python coding focus
     Phi1 {
         parameters: String,
         context_length: usize,
@@ -62,6 +150,20 @@ impl PhiModel {
     /// Get all available Phi models with their specifications
     pub fn available_models() -> Vec<Self> {
         vec![
+            PhiModel::Phi1 {
+                parameters: "1.3B".to_string(),
+                context_length: 2048,
+                specialization: vec!["Python coding".to_string(), "code generation".to_string()],
+            },
+            PhiModel::Phi1_5 {
+                parameters: "1.3B".to_string(),
+                context_length: 2048,
+                specialization: vec![
+                    "common sense reasoning".to_string(),
+                    "language understanding".to_string(),
+                    "code generation".to_string(),
+                ],
+            },
             PhiModel::Phi2 {
                 parameters: "2.7B".to_string(),
                 context_length: 2048,
@@ -112,6 +214,28 @@ impl PhiModel {
         ]
     }
 
+    /// Look up a model by its CLI name (`phi1`, `phi15`, `phi2`, `phi3`, `phi35`, `phi4`,
+    /// `phi4-mini`)
+    pub fn from_short_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::available_models()
+            .into_iter()
+            .find(|model| model.short_name() == name)
+    }
+
+    /// Short CLI name, the inverse of `from_short_name` for the available models
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            PhiModel::Phi1 { .. } => "phi1",
+            PhiModel::Phi1_5 { .. } => "phi15",
+            PhiModel::Phi2 { .. } => "phi2",
+            PhiModel::Phi3 { .. } => "phi3",
+            PhiModel::Phi3_5 { .. } => "phi35",
+            PhiModel::Phi4 { .. } => "phi4",
+            PhiModel::Phi4Mini { .. } => "phi4-mini",
+        }
+    }
+
     /// Get the model name for downloading
     pub fn model_name(&self) -> &'static str {
         match self {
@@ -125,22 +249,58 @@ impl PhiModel {
         }
     }
 
+    /// Conversation format the model was tuned on
+    pub fn chat_template(&self) -> ChatTemplate {
+        match self {
+            PhiModel::Phi1 { .. } | PhiModel::Phi1_5 { .. } | PhiModel::Phi2 { .. } => {
+                ChatTemplate::InstructOutput
+            }
+            PhiModel::Phi3 { .. } | PhiModel::Phi3_5 { .. } | PhiModel::Phi4Mini { .. } => {
+                ChatTemplate::Phi3
+            }
+            PhiModel::Phi4 { .. } => ChatTemplate::Phi4,
+        }
+    }
+
     /// Get Hugging Face model repository
     pub fn hf_repo(&self) -> &'static str {
         match self {
+            PhiModel::Phi1 { .. } => "microsoft/phi-1",
+            PhiModel::Phi1_5 { .. } => "microsoft/phi-1_5",
             PhiModel::Phi2 { .. } => "microsoft/phi-2",
             PhiModel::Phi3 { .. } => "microsoft/Phi-3-mini-4k-instruct-onnx",
             PhiModel::Phi3_5 { .. } => "microsoft/Phi-3.5-mini-instruct-onnx", 
             PhiModel::Phi4 { .. } => "microsoft/Phi-4-onnx",
             PhiModel::Phi4Mini { .. } => "microsoft/Phi-4-mini-onnx",
-            _ => "microsoft/phi-2", // Default fallback
+        }
+    }
+
+    /// Path of the ONNX weights inside the Hugging Face repository
+    ///
+    /// Large exports keep their tensors in a sidecar `<file>.data` next to this file.
+    /// Phi-1, Phi-1.5 and Phi-2 are only published as PyTorch weights, so they have no
+    /// ONNX file to download; one exported locally can be copied into the cache instead.
+    pub fn onnx_file(&self) -> Option<&'static str> {
+        match self {
+            PhiModel::Phi1 { .. } | PhiModel::Phi1_5 { .. } | PhiModel::Phi2 { .. } => None,
+            PhiModel::Phi3 { .. } => Some("cpu_and_mobile/cpu-int4-rtn-block-32-acc-level-4/phi3-mini-4k-instruct-cpu-int4-rtn-block-32-acc-level-4.onnx"),
+            PhiModel::Phi3_5 { .. } => Some("cpu_and_mobile/cpu-int4-awq-block-128-acc-level-4/phi-3.5-mini-instruct-cpu-int4-awq-block-128-acc-level-4.onnx"),
+            PhiModel::Phi4 { .. } | PhiModel::Phi4Mini { .. } => Some("cpu_and_mobile/cpu-int4-rtn-block-32-acc-level-4/model.onnx"),
+        }
+    }
+
+    /// Path of `tokenizer.json` inside the Hugging Face repository, next to the weights
+    pub fn tokenizer_file(&self) -> String {
+        match self.onnx_file().and_then(|file| file.rsplit_once('/')) {
+            Some((dir, _)) => format!("{}/tokenizer.json", dir),
+            None => "tokenizer.json".to_string(),
         }
     }
 
     /// Get parameter count as number
     pub fn parameter_count(&self) -> f32 {
         match self {
-            PhiModel::Phi1 { .. } => 1.0,
+            PhiModel::Phi1 { .. } => 1.3,
             PhiModel::Phi1_5 { .. } => 1.3,
             PhiModel::Phi2 { .. } => 2.7,
             PhiModel::Phi3 { .. } => 3.8,
@@ -150,6 +310,16 @@ impl PhiModel {
         }
     }
 
+    /// Bytes needed to hold the weights at `quantization`, or `None` if that overflows
+    pub fn estimated_memory(&self, quantization: Quantization) -> Option<u64> {
+        // Parameter counts are in billions with one decimal place, so whole millions are exact
+        let millions = (self.parameter_count() * 1000.0).round() as u64;
+        millions
+            .checked_mul(1_000_000)?
+            .checked_mul(quantization.bits())
+            .map(|bits| bits / 8)
+    }
+
     /// Get context length
     pub fn context_length(&self) -> usize {
         match self {
@@ -163,6 +333,29 @@ impl PhiModel {
         }
     }
 
+    /// Width of the transformer's hidden states
+    pub fn hidden_size(&self) -> usize {
+        match self {
+            PhiModel::Phi1 { .. } | PhiModel::Phi1_5 { .. } => 2048,
+            PhiModel::Phi2 { .. } => 2560,
+            PhiModel::Phi3 { .. } | PhiModel::Phi3_5 { .. } | PhiModel::Phi4Mini { .. } => 3072,
+            PhiModel::Phi4 { .. } => 5120,
+        }
+    }
+
+    /// Get the human readable parameter size (e.g. "3.8B")
+    pub fn parameters(&self) -> &str {
+        match self {
+            PhiModel::Phi1 { parameters, .. } => parameters,
+            PhiModel::Phi1_5 { parameters, .. } => parameters,
+            PhiModel::Phi2 { parameters, .. } => parameters,
+            PhiModel::Phi3 { parameters, .. } => parameters,
+            PhiModel::Phi3_5 { parameters, .. } => parameters,
+            PhiModel::Phi4 { parameters, .. } => parameters,
+            PhiModel::Phi4Mini { parameters, .. } => parameters,
+        }
+    }
+
     /// Get specializations
     pub fn specializations(&self) -> &Vec<String> {
         match self {
@@ -176,9 +369,17 @@ impl PhiModel {
         }
     }
 
-    /// Check if model is suitable for edge/on-device deployment
+    /// Check if model is suitable for edge/on-device deployment at the default quantization
     pub fn is_edge_suitable(&self) -> bool {
-        self.parameter_count() <= 4.0 // Models <= 4B parameters
+        self.is_edge_suitable_at(Quantization::default())
+    }
+
+    /// Check if the model's weights at `quantization` fit an edge device's memory budget
+    ///
+    /// At F16 this admits models up to 4B parameters; quantized to int4 even Phi-4 fits.
+    pub fn is_edge_suitable_at(&self, quantization: Quantization) -> bool {
+        self.estimated_memory(quantization)
+            .is_some_and(|bytes| bytes <= EDGE_MEMORY_BUDGET)
     }
 
     /// Check if model supports coding tasks
@@ -188,6 +389,14 @@ impl PhiModel {
             .any(|s| s.contains("coding") || s.contains("code"))
     }
 
+    /// Check if model takes image input
+    ///
+    /// None of the supported Phi models do yet; vision variants such as Phi-3.5-vision
+    /// would.
+    pub fn supports_images(&self) -> bool {
+        false
+    }
+
     /// Check if model supports mathematical reasoning
     pub fn supports_math(&self) -> bool {
         self.specializations()
@@ -198,6 +407,16 @@ impl PhiModel {
     /// Get recommended use cases for this model
     pub fn recommended_use_cases(&self) -> Vec<&'static str> {
         match self {
+            PhiModel::Phi1 { .. } => vec![
+                "Python code completion",
+                "Small coding tools",
+                "Prototyping"
+            ],
+            PhiModel::Phi1_5 { .. } => vec![
+                "Common sense reasoning",
+                "Text completion",
+                "Research on small models"
+            ],
             PhiModel::Phi2 { .. } => vec![
                 "Code completion",
                 "Text generation", 
@@ -230,7 +449,6 @@ impl PhiModel {
                 "Natural conversation",
                 "Efficient deployment"
             ],
-            _ => vec!["General language tasks", "Prototyping"],
         }
     }
 
@@ -239,15 +457,7 @@ impl PhiModel {
         format!(
             "🤖 {} ({} parameters)\n📏 Context: {} tokens\n🎯 Specializations: {}\n💡 Use cases: {}",
             self.model_name(),
-            match self {
-                PhiModel::Phi1 { parameters, .. } => parameters,
-                PhiModel::Phi1_5 { parameters, .. } => parameters,
-                PhiModel::Phi2 { parameters, .. } => parameters,
-                PhiModel::Phi3 { parameters, .. } => parameters,
-                PhiModel::Phi3_5 { parameters, .. } => parameters,
-                PhiModel::Phi4 { parameters, .. } => parameters,
-                PhiModel::Phi4Mini { parameters, .. } => parameters,
-            },
+            self.parameters(),
             self.context_length(),
             self.specializations().join(", "),
             self.recommended_use_cases().join(", ")
@@ -255,27 +465,309 @@ impl PhiModel {
     }
 }
 
+/// Stub contents written by older template builds in place of real weights
+const PLACEHOLDER_MODEL_BYTES: &[u8] = b"placeholder-model-file";
+
+/// Smallest file size accepted as an ONNX model
+const MIN_MODEL_FILE_SIZE: u64 = 1024;
+
+/// Protobuf tag of `ModelProto.ir_version` (field 1, varint), the first byte of an ONNX file
+const ONNX_IR_VERSION_TAG: u8 = 0x08;
+
+/// Error from fetching, checking or managing cached models
+#[derive(Debug, thiserror::Error)]
+pub enum PhiError {
+    /// The model has not been downloaded into the cache
+    #[error("model file not found: {path:?}")]
+    ModelNotCached { path: PathBuf },
+    /// A file could not be fetched from the Hugging Face hub
+    #[error("failed to download {url}")]
+    DownloadFailed {
+        url: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// A cached file is not the model (or manifest) it should be
+    #[error("{path:?} {reason}")]
+    IntegrityMismatch { path: PathBuf, reason: String },
+    /// The requested inference backend cannot be used
+    #[error("backend '{backend}' is unavailable: {reason}")]
+    BackendUnavailable { backend: String, reason: String },
+    /// The machine lacks the memory, VRAM or disk a model needs
+    #[error(
+        "{model} won't fit on this machine at {quantization}:\n{}",
+        issues.iter().map(|issue| format!("  - {}", issue)).collect::<Vec<_>>().join("\n")
+    )]
+    InsufficientResources {
+        model: String,
+        quantization: Quantization,
+        issues: Vec<String>,
+    },
+    /// The cache directory can't be created or written to
+    #[error("cache directory {path:?} is not writable (set VIBECODE_PHI_CACHE to use another)")]
+    CacheDirUnwritable {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The model is not published as ONNX, so there is nothing to download
+    #[error(
+        "{model} has no ONNX export on the Hugging Face hub; export it with \
+         `optimum-cli export onnx` and copy the model to {path:?}"
+    )]
+    NoOnnxExport { model: String, path: PathBuf },
+    /// A cached model uses parts of ONNX that the Burn runtime does not execute yet
+    #[error("{path:?} can't run on Burn: {reason}")]
+    UnsupportedGraph { path: PathBuf, reason: String },
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Cache directory named by `env`, the value of [`CACHE_DIR_ENV`], or the OS cache
+/// directory's `vibecode/phi-models` when it is unset or empty
+pub fn default_cache_dir(env: Option<std::ffi::OsString>) -> PathBuf {
+    match env {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("vibecode")
+            .join("phi-models"),
+    }
+}
+
+/// Create `dir` if needed and check that files can be written in it
+fn check_writable(dir: &Path) -> Result<(), PhiError> {
+    let unwritable = |source| PhiError::CacheDirUnwritable {
+        path: dir.to_path_buf(),
+        source,
+    };
+    std::fs::create_dir_all(dir).map_err(unwritable)?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"").map_err(unwritable)?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Map an I/O error to [`PhiError::Io`] described by `context`
+fn io_error(context: impl Into<String>) -> impl FnOnce(std::io::Error) -> PhiError {
+    let context = context.into();
+    move |source| PhiError::Io { context, source }
+}
+
+/// Classify a failure to load the graph at `path`: I/O errors stay [`PhiError::Io`], and
+/// anything else means the graph can't run
+fn graph_error(path: PathBuf, error: anyhow::Error) -> PhiError {
+    let reason = format!("{:#}", error);
+    match error.downcast::<std::io::Error>() {
+        Ok(source) => PhiError::Io {
+            context: reason,
+            source,
+        },
+        Err(_) => PhiError::UnsupportedGraph { path, reason },
+    }
+}
+
+/// Cache health of one model, as reported by `PhiModelManager::status`
+#[derive(Debug, Clone)]
+pub struct ModelStatus {
+    pub model: PhiModel,
+    pub cached: bool,
+    /// Size of the cached file in bytes, if cached
+    pub size: Option<u64>,
+    /// Whether the cached file passes `validate_model_file` and its files match the hashes
+    /// in the manifest
+    pub valid: bool,
+    /// Whether `valid` includes a hash check; models cached without hashes in a manifest
+    /// only get the header check
+    pub verified: bool,
+    /// Why the hash check could not run, such as a cached file that can't be read; the
+    /// model is then neither valid nor verified
+    pub verification_error: Option<String>,
+    /// Whether this system can run the model, with the reasons if not
+    pub can_run: bool,
+    pub issues: Vec<String>,
+}
+
+/// Environment variable naming the cache directory [`PhiModelManager::default`] uses
+pub const CACHE_DIR_ENV: &str = "VIBECODE_PHI_CACHE";
+
+/// Hugging Face hub used when `HF_ENDPOINT` is not set
+const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Repository revision that models are downloaded from
+const HF_REVISION: &str = "main";
+
+/// Header in which the hub reports the commit a revision resolved to
+const REPO_COMMIT_HEADER: &str = "x-repo-commit";
+
+/// Record of a model download, saved next to the weights as `<model>.manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Hugging Face repository the files came from, e.g. `microsoft/phi-2`
+    pub repo: String,
+    /// Revision that was requested
+    pub revision: String,
+    /// Commit the revision resolved to, when the hub reported one
+    pub commit: Option<String>,
+    pub quantization: Quantization,
+    pub files: Vec<ManifestFile>,
+    /// When the download finished, in seconds since the Unix epoch
+    pub downloaded_at: u64,
+}
+
+/// One downloaded file of a [`Manifest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path of the file in the repository
+    pub name: String,
+    pub size: u64,
+    /// SHA-256 of the file as cached, in lowercase hex; manifests written before hashes
+    /// were recorded have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl Manifest {
+    /// Combined size of every file in bytes
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+impl std::fmt::Display for Manifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.repo, self.revision)?;
+        if let Some(commit) = &self.commit {
+            write!(f, " ({})", &commit[..commit.len().min(12)])?;
+        }
+        write!(
+            f,
+            ", {} files, {}, downloaded at {}",
+            self.files.len(),
+            crate::format_bytes(self.total_size()),
+            self.downloaded_at
+        )
+    }
+}
+
+/// A cached model and the manifest of its download, if one was saved
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedModel {
+    pub label: String,
+    pub manifest: Option<Manifest>,
+}
+
+/// How often a download is retried after a transient failure
+///
+/// Timeouts, dropped connections and 5xx responses are retried after `base_delay`,
+/// doubling after each attempt; a missing file (404) or any other error fails at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (starting at 0)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// Parallel range requests used for a download unless `with_connections` says otherwise
+const DEFAULT_CONNECTIONS: u32 = 4;
+
+/// Smallest byte range worth its own connection
+const MIN_RANGE_SIZE: u64 = 1024 * 1024;
+
+/// Bytes downloaded between saves of a ranged download's progress
+const PROGRESS_SAVE_INTERVAL: u64 = 16 * 1024 * 1024;
+
 /// Model download and cache management
 pub struct PhiModelManager {
     cache_dir: PathBuf,
+    endpoint: String,
+    quantization: Quantization,
+    retry: RetryPolicy,
+    connections: u32,
+    /// One lock per model path, held while that model is checked and downloaded
+    downloads: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
 }
 
 impl PhiModelManager {
     /// Create a new model manager with specified cache directory
+    ///
+    /// Models are fetched from `HF_ENDPOINT` (a mirror) when set, else from huggingface.co.
     pub fn new<P: AsRef<Path>>(cache_dir: P) -> Self {
+        let endpoint =
+            std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_string());
+        Self::with_endpoint(cache_dir, endpoint)
+    }
+
+    /// Create a model manager that downloads from a specific Hugging Face endpoint
+    pub fn with_endpoint<P: AsRef<Path>>(cache_dir: P, endpoint: impl Into<String>) -> Self {
         Self {
             cache_dir: cache_dir.as_ref().to_path_buf(),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            quantization: Quantization::default(),
+            retry: RetryPolicy::default(),
+            connections: DEFAULT_CONNECTIONS,
+            downloads: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Get default model manager with standard cache location
-    pub fn default() -> Self {
-        let cache_dir = dirs::cache_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("vibecode")
-            .join("phi-models");
-        
-        Self::new(cache_dir)
+    /// Set how transient download failures are retried
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Download models over up to `connections` parallel range requests
+    ///
+    /// Servers without range support are always read over a single connection.
+    pub fn with_connections(mut self, connections: u32) -> Self {
+        self.connections = connections.max(1);
+        self
+    }
+
+    /// Keep models at `quantization`, cached apart from other quantizations of the same model
+    pub fn with_quantization(mut self, quantization: Quantization) -> Self {
+        self.quantization = quantization;
+        self
+    }
+
+    /// Quantization of the models this manager caches
+    pub fn quantization(&self) -> Quantization {
+        self.quantization
+    }
+
+    /// Model manager caching in `VIBECODE_PHI_CACHE` when set, else in the OS cache
+    /// directory under `vibecode/phi-models`
+    ///
+    /// Fails with [`PhiError::CacheDirUnwritable`] if the directory can't be created or
+    /// written to, rather than on the first download.
+    pub fn default() -> Result<Self, PhiError> {
+        Self::writable(default_cache_dir(std::env::var_os(CACHE_DIR_ENV)))
+    }
+
+    /// Model manager caching in `cache_dir`, once it is known to be writable
+    fn writable(cache_dir: PathBuf) -> Result<Self, PhiError> {
+        check_writable(&cache_dir)?;
+        Ok(Self::new(cache_dir))
     }
 
     /// Check if a model is cached locally
@@ -284,91 +776,559 @@ impl PhiModelManager {
         model_path.exists() && tokio::fs::metadata(&model_path).await.is_ok()
     }
 
-    /// Get the local path for a model
+    /// Get the local path for a model at this manager's quantization
     pub fn model_path(&self, model: &PhiModel) -> PathBuf {
-        self.cache_dir.join(format!("{}.onnx", model.model_name().replace("/", "_")))
+        self.cache_dir.join(cache_file_name(model, self.quantization))
+    }
+
+    /// Check that the cached file looks like a real ONNX model rather than a stub
+    ///
+    /// ONNX files are serialized `ModelProto` messages, which start with the `ir_version`
+    /// field tag. Files that are too small or are the placeholder written by older builds
+    /// are rejected.
+    pub async fn validate_model_file(&self, model: &PhiModel) -> Result<(), PhiError> {
+        let model_path = self.model_path(model);
+        let metadata = match fs::metadata(&model_path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(PhiError::ModelNotCached { path: model_path });
+            }
+            Err(e) => return Err(io_error("Failed to read model file metadata")(e)),
+        };
+
+        let mut header = [0u8; PLACEHOLDER_MODEL_BYTES.len()];
+        let mut file = fs::File::open(&model_path).await
+            .map_err(io_error("Failed to open model file"))?;
+        let read = file.read(&mut header).await
+            .map_err(io_error("Failed to read model file"))?;
+
+        let mismatch = |reason: String| PhiError::IntegrityMismatch {
+            path: model_path.clone(),
+            reason,
+        };
+        if header[..read] == PLACEHOLDER_MODEL_BYTES[..] {
+            return Err(mismatch("is a placeholder, not a downloaded model".to_string()));
+        }
+        if metadata.len() < MIN_MODEL_FILE_SIZE {
+            return Err(mismatch(format!(
+                "is only {} bytes, too small to be an ONNX model",
+                metadata.len()
+            )));
+        }
+        if header[0] != ONNX_IR_VERSION_TAG {
+            return Err(mismatch("does not start with an ONNX model header".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Check the cached files against the SHA-256 hashes in the model's manifest
+    ///
+    /// Returns whether any hash was compared: a model cached without a manifest, or with
+    /// one written before hashes were recorded, has nothing to check against. A file that
+    /// doesn't match is reported as an [`PhiError::IntegrityMismatch`].
+    pub async fn verify_model_hashes(&self, model: &PhiModel) -> Result<bool, PhiError> {
+        let Some(manifest) = self.manifest(model).await? else {
+            return Ok(false);
+        };
+
+        let mut verified = false;
+        for file in &manifest.files {
+            let (Some(expected), Some(path)) =
+                (&file.sha256, self.cached_file_path(model, &file.name))
+            else {
+                continue;
+            };
+            let actual = sha256_file(&path).await
+                .map_err(io_error(format!("Failed to hash {:?}", path)))?;
+            if actual != *expected {
+                return Err(PhiError::IntegrityMismatch {
+                    path,
+                    reason: format!("has SHA-256 {} but its manifest records {}", actual, expected),
+                });
+            }
+            verified = true;
+        }
+        Ok(verified)
+    }
+
+    /// Local path of the file a manifest lists as `name`
+    fn cached_file_path(&self, model: &PhiModel, name: &str) -> Option<PathBuf> {
+        let onnx_file = model.onnx_file()?;
+        if name == onnx_file {
+            Some(self.model_path(model))
+        } else if name == format!("{}.data", onnx_file) {
+            Some(self.sidecar_path(model))
+        } else if name == model.tokenizer_file() {
+            Some(self.tokenizer_path(model))
+        } else {
+            None
+        }
+    }
+
+    /// Get the local path of a model's `tokenizer.json`
+    pub fn tokenizer_path(&self, model: &PhiModel) -> PathBuf {
+        self.model_path(model).with_extension("tokenizer.json")
+    }
+
+    /// Get the local path of a model's external-data sidecar
+    pub fn sidecar_path(&self, model: &PhiModel) -> PathBuf {
+        self.model_path(model).with_extension("onnx.data")
+    }
+
+    /// Get the local path of the manifest describing a model's download
+    pub fn manifest_path(&self, model: &PhiModel) -> PathBuf {
+        self.model_path(model).with_extension("manifest.json")
+    }
+
+    /// Manifest of the cached model's download, or `None` if none was saved
+    ///
+    /// Models cached before manifests were written have none.
+    pub async fn manifest(&self, model: &PhiModel) -> Result<Option<Manifest>, PhiError> {
+        read_manifest(&self.manifest_path(model)).await
+    }
+
+    /// Lock serializing downloads of the model cached at `model_path`
+    fn download_lock(&self, model_path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+        let mut downloads = self.downloads.lock().unwrap_or_else(|e| e.into_inner());
+        downloads.entry(model_path.to_path_buf()).or_default().clone()
+    }
+
+    /// Get the temporary path a model is written to while downloading
+    fn download_path(&self, model: &PhiModel) -> PathBuf {
+        self.model_path(model).with_extension("onnx.part")
     }
 
     /// Download a model if not cached
-    pub async fn ensure_model(&self, model: &PhiModel) -> Result<PathBuf> {
+    pub async fn ensure_model(&self, model: &PhiModel) -> Result<PathBuf, PhiError> {
+        self.ensure_model_with_progress(model, |_, _| {}).await
+    }
+
+    /// Download a model if not cached, reporting `(bytes downloaded, total bytes)`
+    ///
+    /// The total is `None` when the server does not send a content length. Concurrent
+    /// calls for the same model wait for a single download; other models download in
+    /// parallel.
+    #[tracing::instrument(
+        skip(self, model, progress),
+        fields(model = model.model_name(), cached = tracing::field::Empty)
+    )]
+    pub async fn ensure_model_with_progress(
+        &self,
+        model: &PhiModel,
+        progress: impl FnMut(u64, Option<u64>) + Send,
+    ) -> Result<PathBuf, PhiError> {
         let model_path = self.model_path(model);
-        
+        let lock = self.download_lock(&model_path);
+        let _downloading = lock.lock().await;
+
         if self.is_cached(model).await {
-            info!("Model {} already cached at {:?}", model.model_name(), model_path);
-            return Ok(model_path);
+            match self.validate_model_file(model).await {
+                Ok(()) => {
+                    info!("Model {} already cached at {:?}", model.model_name(), model_path);
+                    tracing::Span::current().record("cached", true);
+                    touch(&model_path).await;
+                    self.ensure_tokenizer(model).await?;
+                    return Ok(model_path);
+                }
+                Err(e) => {
+                    warn!("Cached model {:?} is invalid, re-downloading: {}", model_path, e);
+                    fs::remove_file(&model_path).await
+                        .map_err(io_error("Failed to remove invalid cached model"))?;
+                }
+            }
         }
 
         info!("Downloading model {} to {:?}", model.model_name(), model_path);
-        self.download_model(model).await
+        tracing::Span::current().record("cached", false);
+        let mut manifest = self.download_model(model, progress).await?;
+        let tokenizer_path = self.ensure_tokenizer(model).await?;
+        manifest.files.push(ManifestFile {
+            name: model.tokenizer_file(),
+            size: fs::metadata(&tokenizer_path).await.map(|m| m.len()).unwrap_or(0),
+            sha256: None,
+        });
+        for file in &mut manifest.files {
+            if let Some(path) = self.cached_file_path(model, &file.name) {
+                let hash = sha256_file(&path).await
+                    .map_err(io_error(format!("Failed to hash {:?}", path)))?;
+                file.sha256 = Some(hash);
+            }
+        }
+
+        // The model is usable without its manifest, so failing to write one is not fatal
+        let manifest_path = self.manifest_path(model);
+        let written = async {
+            fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+            anyhow::Ok(())
+        };
+        if let Err(e) = written.await {
+            warn!("Failed to write model manifest {:?}: {:#}", manifest_path, e);
+        }
+        Ok(model_path)
     }
 
-    /// Download a model from Hugging Face
-    async fn download_model(&self, model: &PhiModel) -> Result<PathBuf> {
-        // Create cache directory
+    /// Download the model's `tokenizer.json` if not cached
+    pub async fn ensure_tokenizer(&self, model: &PhiModel) -> Result<PathBuf, PhiError> {
+        let tokenizer_path = self.tokenizer_path(model);
+        if tokenizer_path.exists() {
+            return Ok(tokenizer_path);
+        }
+
         fs::create_dir_all(&self.cache_dir).await
-            .context("Failed to create cache directory")?;
+            .map_err(io_error("Failed to create cache directory"))?;
+
+        let url = self.file_url(model.hf_repo(), &model.tokenizer_file());
+        let partial = PartialDownload::new(tokenizer_path.with_extension("json.part"));
+        let fetched = fetch_with_retry(
+            &reqwest::Client::new(),
+            &url,
+            partial.path(),
+            &mut |_, _| {},
+            &self.retry,
+            1,
+        )
+        .await;
+        download_result(&url, fetched)?;
+
+        fs::rename(partial.path(), &tokenizer_path).await
+            .map_err(io_error("Failed to move downloaded tokenizer into the cache"))?;
+        partial.commit();
+
+        info!("Tokenizer cached at {:?}", tokenizer_path);
+        Ok(tokenizer_path)
+    }
 
+    /// Ensure the model is cached and load its ONNX graph onto `device` for forward passes
+    ///
+    /// Unlike [`Self::load_with_repair`], a graph that fails to load is not fetched again:
+    /// the usual cause is an operator the runtime does not support yet, which a fresh copy
+    /// would not fix. That is currently the case for every published Phi export, which all
+    /// fail with [`PhiError::UnsupportedGraph`]; a file that can't be read is reported as
+    /// [`PhiError::Io`].
+    pub async fn load_onnx(
+        &self,
+        model: &PhiModel,
+        device: &burn_ndarray::NdArrayDevice,
+    ) -> Result<crate::PhiInference, PhiError> {
+        let path = self.ensure_model(model).await?;
+        crate::PhiInference::from_onnx(&path, device).map_err(|e| graph_error(path, e))
+    }
+
+    /// Ensure the model is cached and load it, repairing the cache once if loading fails
+    ///
+    /// A cached file can pass validation and still fail to load (truncated by a crash,
+    /// corrupted on disk). In that case the entry is deleted, the model is fetched again
+    /// and `load` is retried a single time. A model that still fails to load is reported
+    /// as an [`PhiError::IntegrityMismatch`].
+    #[tracing::instrument(
+        name = "load_model",
+        skip(self, model, load),
+        fields(model = model.model_name(), elapsed_ms = tracing::field::Empty)
+    )]
+    pub async fn load_with_repair<T, F, Fut>(
+        &self,
+        model: &PhiModel,
+        mut load: F,
+    ) -> Result<T, PhiError>
+    where
+        F: FnMut(PathBuf) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let start = Instant::now();
+        let span = tracing::Span::current();
+        let record_elapsed = || {
+            span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        };
+
+        let model_path = self.ensure_model(model).await?;
+        let first_error = match load(model_path.clone()).await {
+            Ok(loaded) => {
+                record_elapsed();
+                return Ok(loaded);
+            }
+            Err(e) => e,
+        };
+
+        warn!(
+            "Failed to load cached model {:?}, re-downloading: {:#}",
+            model_path, first_error
+        );
+        if let Err(e) = fs::remove_file(&model_path).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(io_error("Failed to remove unloadable cached model")(e));
+            }
+        }
+
+        let model_path = self.ensure_model(model).await?;
+        let loaded = load(model_path.clone()).await.map_err(|e| PhiError::IntegrityMismatch {
+            path: model_path,
+            reason: format!("failed to load again after re-downloading: {:#}", e),
+        })?;
+        record_elapsed();
+        Ok(loaded)
+    }
+
+    /// URL of a file in a Hugging Face repository
+    fn file_url(&self, repo: &str, file: &str) -> String {
+        format!("{}/{}/resolve/{}/{}", self.endpoint, repo, HF_REVISION, file)
+    }
+
+    /// Download a model from Hugging Face, returning the manifest of what was fetched
+    ///
+    /// Each file streams into a `.part` path that is renamed into the cache only once
+    /// complete; see [`fetch_to_file`] for how an interrupted download resumes. The
+    /// `.onnx` file is moved last, so `is_cached` never sees a model whose sidecar is
+    /// still missing.
+    #[tracing::instrument(
+        skip(self, model, progress),
+        fields(
+            model = model.model_name(),
+            repo = model.hf_repo(),
+            bytes = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        )
+    )]
+    async fn download_model(
+        &self,
+        model: &PhiModel,
+        mut progress: impl FnMut(u64, Option<u64>) + Send,
+    ) -> Result<Manifest, PhiError> {
+        let start = Instant::now();
         let model_path = self.model_path(model);
-        
-        // This is a simplified download - in practice, you'd use the hf-hub crate
-        // or implement proper Hugging Face API integration
-        warn!("Model download not implemented - this is a template");
-        warn!("In production, integrate with hf-hub or Hugging Face API");
-        warn!("For now, manually download {} to {:?}", model.hf_repo(), model_path);
+        let Some(onnx_file) = model.onnx_file() else {
+            return Err(PhiError::NoOnnxExport {
+                model: model.model_name().to_string(),
+                path: model_path,
+            });
+        };
+        // Create cache directory
+        fs::create_dir_all(&self.cache_dir).await
+            .map_err(io_error("Failed to create cache directory"))?;
+
+        let client = reqwest::Client::new();
+        let onnx_url = self.file_url(model.hf_repo(), onnx_file);
+
+        // Anything written before the download completes is removed on failure or panic
+        let partial = PartialDownload::new(self.download_path(model));
+        let partial_sidecar =
+            PartialDownload::new(self.sidecar_path(model).with_extension("data.part"));
+
+        let onnx = fetch_with_retry(
+            &client,
+            &onnx_url,
+            partial.path(),
+            &mut progress,
+            &self.retry,
+            self.connections,
+        )
+        .await;
+        let onnx = download_result(&onnx_url, onnx)?;
+        let mut files = vec![ManifestFile {
+            name: onnx_file.to_string(),
+            size: onnx.size,
+            sha256: None,
+        }];
+
+        // Only larger exports have external data, so a missing sidecar is not an error
+        let sidecar_url = format!("{}.data", onnx_url);
+        let sidecar = fetch_with_retry(
+            &client,
+            &sidecar_url,
+            partial_sidecar.path(),
+            &mut progress,
+            &self.retry,
+            self.connections,
+        )
+        .await
+        .map_err(|source| PhiError::DownloadFailed { url: sidecar_url, source })?;
+        if let Some(sidecar) = sidecar {
+            fs::rename(partial_sidecar.path(), self.sidecar_path(model)).await
+                .map_err(io_error("Failed to move downloaded model data into the cache"))?;
+            partial_sidecar.commit();
+            files.push(ManifestFile {
+                name: format!("{}.data", onnx_file),
+                size: sidecar.size,
+                sha256: None,
+            });
+        }
 
-        // Create a placeholder file for demonstration
-        fs::write(&model_path, b"placeholder-model-file").await
-            .context("Failed to create placeholder model file")?;
+        fs::rename(partial.path(), &model_path).await
+            .map_err(io_error("Failed to move downloaded model into the cache"))?;
+        partial.commit();
+
+        let bytes = fs::metadata(&model_path).await.map(|m| m.len()).unwrap_or(0);
+        let span = tracing::Span::current();
+        span.record("bytes", bytes);
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
 
         info!("Model download completed: {:?}", model_path);
-        Ok(model_path)
+        Ok(Manifest {
+            repo: model.hf_repo().to_string(),
+            revision: HF_REVISION.to_string(),
+            commit: onnx.commit,
+            quantization: self.quantization,
+            files,
+            downloaded_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+        })
+    }
+
+    /// Cache and compatibility status of every available model
+    pub async fn status(&self, system: &SystemInfo) -> Vec<ModelStatus> {
+        let mut statuses = Vec::new();
+        for model in PhiModel::available_models() {
+            let size = fs::metadata(self.model_path(&model)).await.ok().map(|m| m.len());
+            let header_valid = size.is_some() && self.validate_model_file(&model).await.is_ok();
+            let (valid, verified, verification_error) = match header_valid {
+                true => match self.verify_model_hashes(&model).await {
+                    Ok(verified) => (true, verified, None),
+                    Err(e @ PhiError::IntegrityMismatch { .. }) => {
+                        warn!("Cached model {} failed verification: {}", model.model_name(), e);
+                        (false, true, None)
+                    }
+                    Err(e) => {
+                        warn!("Could not verify cached model {}: {}", model.model_name(), e);
+                        (false, false, Some(e.to_string()))
+                    }
+                },
+                false => (false, false, None),
+            };
+            let (can_run, issues) = system.can_run_model(&model, self.quantization);
+
+            statuses.push(ModelStatus {
+                cached: size.is_some(),
+                size,
+                valid,
+                verified,
+                verification_error,
+                can_run,
+                issues,
+                model,
+            });
+        }
+        statuses
     }
 
-    /// List all cached models
-    pub async fn list_cached_models(&self) -> Result<Vec<String>> {
+    /// List all cached models, with the quantization of any not cached at F16
+    pub async fn list_cached_models(&self) -> Result<Vec<String>, PhiError> {
+        let models = self.list_cached_models_with_manifests().await?;
+        Ok(models.into_iter().map(|model| model.label).collect())
+    }
+
+    /// Cached models together with the manifest of each one's download
+    pub async fn list_cached_models_with_manifests(&self) -> Result<Vec<CachedModel>, PhiError> {
         if !self.cache_dir.exists() {
             return Ok(vec![]);
         }
 
         let mut entries = fs::read_dir(&self.cache_dir).await
-            .context("Failed to read cache directory")?;
+            .map_err(io_error("Failed to read cache directory"))?;
 
         let mut models = vec![];
         while let Some(entry) = entries.next_entry().await
-            .context("Failed to read directory entry")? {
-            
-            if let Some(name) = entry.file_name().to_str() {
-                if name.ends_with(".onnx") {
-                    models.push(name.replace(".onnx", "").replace("_", "/"));
-                }
+            .map_err(io_error("Failed to read directory entry"))? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.ends_with(".onnx") {
+                continue;
             }
+            let manifest_path = entry.path().with_extension("manifest.json");
+            let manifest = match read_manifest(&manifest_path).await {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    warn!("{}", e);
+                    None
+                }
+            };
+            models.push(CachedModel {
+                label: cached_model_label(&name),
+                manifest,
+            });
         }
 
         Ok(models)
     }
 
     /// Clear model cache
-    pub async fn clear_cache(&self) -> Result<()> {
+    pub async fn clear_cache(&self) -> Result<(), PhiError> {
         if self.cache_dir.exists() {
             fs::remove_dir_all(&self.cache_dir).await
-                .context("Failed to clear cache directory")?;
+                .map_err(io_error("Failed to clear cache directory"))?;
             info!("Model cache cleared");
         }
         Ok(())
     }
 
+    /// Delete least recently used models until the cache holds at most `max_bytes`
+    ///
+    /// Models are ordered by the modification time of their `.onnx` file, which
+    /// `ensure_model` refreshes on every use, and each goes together with its sidecar,
+    /// tokenizer and manifest. Returns the evicted models, least recently used first.
+    pub async fn evict_to(&self, max_bytes: u64) -> Result<Vec<String>, PhiError> {
+        let mut size = self.cache_size().await?;
+        if size <= max_bytes {
+            return Ok(vec![]);
+        }
+
+        let mut models = vec![];
+        let mut entries = fs::read_dir(&self.cache_dir).await
+            .map_err(io_error("Failed to read cache directory"))?;
+        while let Some(entry) = entries.next_entry().await
+            .map_err(io_error("Failed to read directory entry"))? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.ends_with(".onnx") {
+                continue;
+            }
+            let last_used = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            models.push((last_used, entry.path(), name));
+        }
+        models.sort();
+
+        let mut evicted = vec![];
+        for (_, path, name) in models {
+            if size <= max_bytes {
+                break;
+            }
+            for file in [
+                path.with_extension("onnx.data"),
+                path.with_extension("tokenizer.json"),
+                path.with_extension("manifest.json"),
+                path,
+            ] {
+                let Ok(metadata) = fs::metadata(&file).await else {
+                    continue;
+                };
+                fs::remove_file(&file).await
+                    .map_err(io_error(format!("Failed to evict {:?}", file)))?;
+                size = size.saturating_sub(metadata.len());
+            }
+
+            let label = cached_model_label(&name);
+            info!("Evicted {} from the model cache", label);
+            evicted.push(label);
+        }
+
+        Ok(evicted)
+    }
+
     /// Get cache size in bytes
-    pub async fn cache_size(&self) -> Result<u64> {
+    pub async fn cache_size(&self) -> Result<u64, PhiError> {
         if !self.cache_dir.exists() {
             return Ok(0);
         }
 
         let mut total_size = 0;
         let mut entries = fs::read_dir(&self.cache_dir).await
-            .context("Failed to read cache directory")?;
+            .map_err(io_error("Failed to read cache directory"))?;
 
         while let Some(entry) = entries.next_entry().await
-            .context("Failed to read directory entry")? {
+            .map_err(io_error("Failed to read directory entry"))? {
             
             if let Ok(metadata) = entry.metadata().await {
                 total_size += metadata.len();
@@ -379,17 +1339,496 @@ impl PhiModelManager {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// File name a model is cached under, e.g. `microsoft_phi-2_q4.onnx` at int4
+pub fn cache_file_name(model: &PhiModel, quantization: Quantization) -> String {
+    format!(
+        "{}{}.onnx",
+        model.model_name().replace('/', "_"),
+        quantization.file_suffix()
+    )
+}
 
-    #[test]
-    fn test_phi_model_info() {
-        let phi3 = PhiModel::Phi3 {
-            parameters: "3.8B".to_string(),
-            context_length: 4096,
-            specialization: vec!["coding".to_string(), "math".to_string()],
-        };
+/// Model name (and non-default quantization) of a cached `.onnx` file name
+fn cached_model_label(file_name: &str) -> String {
+    for model in PhiModel::available_models() {
+        for quantization in Quantization::ALL {
+            if cache_file_name(&model, quantization) != file_name {
+                continue;
+            }
+            return if quantization == Quantization::default() {
+                model.model_name().to_string()
+            } else {
+                format!("{} ({})", model.model_name(), quantization)
+            };
+        }
+    }
+    file_name.trim_end_matches(".onnx").replace('_', "/")
+}
+
+/// SHA-256 of the file at `path` in lowercase hex
+async fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Manifest saved at `path`, or `None` if there is no such file
+async fn read_manifest(path: &Path) -> Result<Option<Manifest>, PhiError> {
+    let json = match fs::read(path).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error(format!("Failed to read manifest {:?}", path))(e)),
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| PhiError::IntegrityMismatch {
+            path: path.to_path_buf(),
+            reason: format!("is not a valid manifest: {}", e),
+        })
+}
+
+/// Mark a cached file as just used, so `evict_to` keeps it longest
+async fn touch(path: &Path) {
+    let touched = async {
+        let file = fs::OpenOptions::new().append(true).open(path).await?;
+        file.into_std().await.set_modified(SystemTime::now())
+    };
+    if let Err(e) = touched.await {
+        warn!("Failed to record use of {:?}: {}", path, e);
+    }
+}
+
+/// [`fetch_to_file`], retrying transient failures as `retry` allows
+async fn fetch_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    progress: &mut (impl FnMut(u64, Option<u64>) + Send),
+    retry: &RetryPolicy,
+    connections: u32,
+) -> Result<Option<FetchedFile>> {
+    let mut attempt = 0;
+    loop {
+        let error = match fetch_to_file(client, url, dest, progress, connections).await {
+            Ok(found) => return Ok(found),
+            Err(e) => e,
+        };
+        attempt += 1;
+        if !is_transient(&error) {
+            return Err(error);
+        }
+        if attempt > retry.max_retries {
+            let attempts = if attempt == 1 { "attempt" } else { "attempts" };
+            return Err(error.context(format!("Giving up after {} {}", attempt, attempts)));
+        }
+
+        let delay = retry.delay(attempt - 1);
+        warn!(
+            "Download attempt {} of {} failed, retrying in {:?}: {:#}",
+            attempt,
+            retry.max_retries + 1,
+            delay,
+            error
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// The file [`fetch_with_retry`] fetched from `url`, treating a missing file as a failure
+fn download_result(
+    url: &str,
+    fetched: Result<Option<FetchedFile>>,
+) -> Result<FetchedFile, PhiError> {
+    match fetched {
+        Ok(Some(file)) => Ok(file),
+        Ok(None) => Err(PhiError::DownloadFailed {
+            url: url.to_string(),
+            source: anyhow::anyhow!("not found on the Hugging Face hub"),
+        }),
+        Err(source) => Err(PhiError::DownloadFailed { url: url.to_string(), source }),
+    }
+}
+
+/// Whether a download error is worth retrying: timeouts, connection failures and 5xx
+fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| match e.status() {
+            Some(status) => status.is_server_error(),
+            None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+        })
+}
+
+/// A file written by [`fetch_to_file`]
+struct FetchedFile {
+    size: u64,
+    /// Commit the hub resolved the requested revision to
+    commit: Option<String>,
+}
+
+/// Stream `url` into `dest`, returning `None` if the server has no such file
+///
+/// When the server supports range requests the file is fetched as up to `connections`
+/// byte ranges in parallel, and the ranges' progress is saved next to `dest` so an
+/// interrupted download resumes where it stopped. Otherwise it is read in one request.
+async fn fetch_to_file(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    progress: &mut (impl FnMut(u64, Option<u64>) + Send),
+    connections: u32,
+) -> Result<Option<FetchedFile>> {
+    let probe = client
+        .get(url)
+        .header(RANGE, "bytes=0-0")
+        .send()
+        .await
+        .with_context(|| format!("Failed to request {}", url))?;
+    let response = match probe.status() {
+        StatusCode::NOT_FOUND => return Ok(None),
+        StatusCode::PARTIAL_CONTENT => {
+            if let Some(total) = content_range_total(&probe) {
+                let etag = probe
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string);
+                let commit = repo_commit(&probe);
+                fetch_ranges(client, url, dest, progress, total, etag, connections).await?;
+                return Ok(Some(FetchedFile { size: total, commit }));
+            }
+            None
+        }
+        // An empty file has no byte 0 to probe
+        StatusCode::RANGE_NOT_SATISFIABLE => None,
+        _ => Some(probe),
+    };
+    let response = match response {
+        Some(response) => response,
+        None => client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to request {}", url))?,
+    };
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("Download of {} failed", url))?;
+
+    let commit = repo_commit(&response);
+    let size = fetch_stream(response, url, dest, progress).await?;
+    Ok(Some(FetchedFile { size, commit }))
+}
+
+/// Commit reported in the hub's `X-Repo-Commit` header
+fn repo_commit(response: &reqwest::Response) -> Option<String> {
+    let commit = response.headers().get(REPO_COMMIT_HEADER)?.to_str().ok()?;
+    Some(commit.to_string())
+}
+
+/// Size of the whole file from a `Content-Range: bytes 0-0/<total>` header
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Write the body of `response` to `dest` from the start, returning its size
+async fn fetch_stream(
+    mut response: reqwest::Response,
+    url: &str,
+    dest: &Path,
+    progress: &mut (impl FnMut(u64, Option<u64>) + Send),
+) -> Result<u64> {
+    // Progress saved by an earlier ranged attempt does not describe this file anymore
+    if let Err(e) = fs::remove_file(progress_path(dest)).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(e).context("Failed to remove stale download progress");
+        }
+    }
+
+    let total = response.content_length();
+    let mut file = fs::File::create(dest).await
+        .with_context(|| format!("Failed to create {:?}", dest))?;
+    let mut downloaded = 0u64;
+
+    while let Some(chunk) = response.chunk().await
+        .with_context(|| format!("Connection lost while downloading {}", url))? {
+        file.write_all(&chunk).await
+            .with_context(|| format!("Failed to write {:?}", dest))?;
+        downloaded += chunk.len() as u64;
+        progress(downloaded, total);
+    }
+
+    file.sync_all().await
+        .with_context(|| format!("Failed to flush {:?}", dest))?;
+    if let Some(total) = total.filter(|&total| total != downloaded) {
+        anyhow::bail!("Downloaded {} of {} bytes from {}", downloaded, total, url);
+    }
+    Ok(downloaded)
+}
+
+/// Fetch the `total` bytes of `url` into `dest` as parallel range requests
+///
+/// Progress saved by an earlier attempt is reused when the server still reports the
+/// same size and ETag; otherwise the download starts over.
+async fn fetch_ranges(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    progress: &mut (impl FnMut(u64, Option<u64>) + Send),
+    total: u64,
+    etag: Option<String>,
+    connections: u32,
+) -> Result<()> {
+    let state_path = progress_path(dest);
+    let on_disk = fs::metadata(dest).await.map(|m| m.len()).ok();
+    let state = match RangeProgress::load(&state_path).await {
+        Some(saved) if saved.total == total && saved.etag == etag && on_disk == Some(total) => {
+            info!(
+                "Resuming download of {} at {} of {} bytes",
+                url,
+                saved.downloaded(),
+                total
+            );
+            saved
+        }
+        _ => {
+            let fresh = RangeProgress::new(total, etag, connections);
+            let file = fs::File::create(dest).await
+                .with_context(|| format!("Failed to create {:?}", dest))?;
+            file.set_len(total).await
+                .with_context(|| format!("Failed to allocate {:?}", dest))?;
+            fresh.save(&state_path).await?;
+            fresh
+        }
+    };
+
+    let mut downloaded = state.downloaded();
+    progress(downloaded, Some(total));
+
+    let pending: Vec<usize> = (0..state.ranges.len())
+        .filter(|&index| !state.ranges[index].is_complete())
+        .collect();
+    let state = std::sync::Mutex::new(state);
+    let (sent, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let workers = pending
+        .into_iter()
+        .map(|index| fetch_range(client, url, dest, &state, index, sent.clone()))
+        .collect::<Vec<_>>();
+    drop(sent);
+
+    let report = async {
+        let mut unsaved = 0;
+        while let Some(bytes) = received.recv().await {
+            downloaded += bytes;
+            progress(downloaded, Some(total));
+            unsaved += bytes;
+            if unsaved >= PROGRESS_SAVE_INTERVAL {
+                unsaved = 0;
+                let snapshot = state.lock().unwrap().clone();
+                if let Err(e) = snapshot.save(&state_path).await {
+                    warn!("{:#}", e);
+                }
+            }
+        }
+    };
+    let (fetched, ()) = tokio::join!(futures::future::try_join_all(workers), report);
+
+    let state = state.into_inner().unwrap();
+    if let Err(e) = fetched {
+        if let Err(save_error) = state.save(&state_path).await {
+            warn!("{:#}", save_error);
+        }
+        return Err(e);
+    }
+
+    // Without its progress file a size mismatch is cleaned up and restarted next time
+    fs::remove_file(&state_path).await
+        .context("Failed to remove download progress")?;
+    let written = fs::metadata(dest).await
+        .with_context(|| format!("Failed to read {:?}", dest))?
+        .len();
+    if written != total || state.downloaded() != total {
+        anyhow::bail!(
+            "Download of {} ended at {} of {} bytes",
+            url,
+            state.downloaded(),
+            total
+        );
+    }
+    Ok(())
+}
+
+/// Fetch what is missing of range `index` into its place in `dest`
+async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    state: &std::sync::Mutex<RangeProgress>,
+    index: usize,
+    sent: tokio::sync::mpsc::UnboundedSender<u64>,
+) -> Result<()> {
+    let range = state.lock().unwrap().ranges[index];
+    let start = range.start + range.done;
+    let mut response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", start, range.end - 1))
+        .send()
+        .await
+        .with_context(|| format!("Failed to request {}", url))?
+        .error_for_status()
+        .with_context(|| format!("Download of {} failed", url))?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        anyhow::bail!("{} ignored the request for bytes {}-{}", url, start, range.end - 1);
+    }
+
+    let mut file = fs::OpenOptions::new().write(true).open(dest).await
+        .with_context(|| format!("Failed to open {:?}", dest))?;
+    file.seek(std::io::SeekFrom::Start(start)).await
+        .with_context(|| format!("Failed to seek in {:?}", dest))?;
+
+    let mut remaining = range.end - start;
+    while let Some(chunk) = response.chunk().await
+        .with_context(|| format!("Connection lost while downloading {}", url))? {
+        let len = chunk.len() as u64;
+        if len > remaining {
+            anyhow::bail!("{} sent more than the requested bytes {}-{}", url, start, range.end - 1);
+        }
+        file.write_all(&chunk).await
+            .with_context(|| format!("Failed to write {:?}", dest))?;
+        remaining -= len;
+        state.lock().unwrap().ranges[index].done += len;
+        let _ = sent.send(len);
+    }
+
+    file.sync_all().await
+        .with_context(|| format!("Failed to flush {:?}", dest))?;
+    if remaining > 0 {
+        anyhow::bail!("{} closed the connection {} bytes short", url, remaining);
+    }
+    Ok(())
+}
+
+/// Where the progress of a ranged download into `part` is saved
+fn progress_path(part: &Path) -> PathBuf {
+    let mut path = part.as_os_str().to_owned();
+    path.push(".progress");
+    PathBuf::from(path)
+}
+
+/// Byte ranges of a ranged download and how much of each is on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RangeProgress {
+    total: u64,
+    etag: Option<String>,
+    ranges: Vec<ByteRange>,
+}
+
+/// `start..end` of the file, of which the first `done` bytes are written
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct ByteRange {
+    start: u64,
+    end: u64,
+    done: u64,
+}
+
+impl ByteRange {
+    fn is_complete(&self) -> bool {
+        self.start + self.done >= self.end
+    }
+}
+
+impl RangeProgress {
+    /// Split `total` bytes into up to `connections` ranges of at least `MIN_RANGE_SIZE`
+    fn new(total: u64, etag: Option<String>, connections: u32) -> Self {
+        let count = total.div_ceil(MIN_RANGE_SIZE).clamp(1, connections.max(1) as u64);
+        let size = total.div_ceil(count).max(1);
+        let ranges = (0..count)
+            .map(|i| ByteRange {
+                start: i * size,
+                end: ((i + 1) * size).min(total),
+                done: 0,
+            })
+            .filter(|range| range.start < range.end)
+            .collect();
+        Self {
+            total,
+            etag,
+            ranges,
+        }
+    }
+
+    fn downloaded(&self) -> u64 {
+        self.ranges.iter().map(|range| range.done).sum()
+    }
+
+    /// Progress saved at `path`, if any can be read
+    async fn load(path: &Path) -> Option<Self> {
+        serde_json::from_slice(&fs::read(path).await.ok()?).ok()
+    }
+
+    async fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec(self)?).await
+            .with_context(|| format!("Failed to save download progress to {:?}", path))
+    }
+}
+
+/// RAII guard for an in-progress download
+///
+/// The file at `path` is deleted when the guard is dropped unless `commit()` was called
+/// or a ranged download saved its progress next to it, so an error or panic mid-download
+/// never leaves a file that looks like a cached model or that cannot be resumed.
+struct PartialDownload {
+    path: PathBuf,
+    committed: bool,
+}
+
+impl PartialDownload {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            committed: false,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keep the downloaded file; call once it has been moved into place
+    fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for PartialDownload {
+    fn drop(&mut self) {
+        // A ranged download keeps its file and progress so the next attempt resumes it
+        if !self.committed && self.path.exists() && !progress_path(&self.path).exists() {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("Failed to remove partial download {:?}: {}", self.path, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phi_model_info() {
+        let phi3 = PhiModel::Phi3 {
+            parameters: "3.8B".to_string(),
+            context_length: 4096,
+            specialization: vec!["coding".to_string(), "math".to_string()],
+        };
 
         assert_eq!(phi3.parameter_count(), 3.8);
         assert_eq!(phi3.context_length(), 4096);
@@ -398,6 +1837,19 @@ mod tests {
         assert!(phi3.is_edge_suitable());
     }
 
+    #[test]
+    fn test_from_short_name() {
+        let phi4_mini = PhiModel::from_short_name("phi4-mini").unwrap();
+        assert_eq!(phi4_mini.model_name(), "microsoft/Phi-4-mini");
+        assert_eq!(PhiModel::from_short_name("PHI3").unwrap().context_length(), 4096);
+        assert!(PhiModel::from_short_name("phi5").is_none());
+
+        for model in PhiModel::available_models() {
+            let round_trip = PhiModel::from_short_name(model.short_name()).unwrap();
+            assert_eq!(round_trip.model_name(), model.model_name());
+        }
+    }
+
     #[test]
     fn test_available_models() {
         let models = PhiModel::available_models();
@@ -410,6 +1862,92 @@ mod tests {
         assert!(has_large);
     }
 
+    #[test]
+    fn test_quantization_adjusts_edge_suitability() {
+        let phi3 = PhiModel::from_short_name("phi3").unwrap();
+        let phi4 = PhiModel::from_short_name("phi4").unwrap();
+
+        assert_eq!(phi4.estimated_memory(Quantization::Int4), Some(7_000_000_000));
+        assert!(!phi4.is_edge_suitable());
+        assert!(phi4.is_edge_suitable_at(Quantization::Int4));
+        assert!(phi3.is_edge_suitable());
+        assert!(!phi3.is_edge_suitable_at(Quantization::F32));
+
+        assert_eq!("q4".parse::<Quantization>(), Ok(Quantization::Int4));
+        for quantization in Quantization::ALL {
+            assert_eq!(quantization.to_string().parse(), Ok(quantization));
+        }
+    }
+
+    #[test]
+    fn test_default_cache_dir_comes_from_the_environment() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path().join("volume");
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+        assert_eq!(default_cache_dir(Some(cache_dir.clone().into())), cache_dir);
+        let os_default = default_cache_dir(None);
+        assert!(os_default.ends_with("vibecode/phi-models"));
+        assert_eq!(default_cache_dir(Some("".into())), os_default);
+
+        let manager = PhiModelManager::writable(cache_dir.clone());
+        // A file where the directory should be can't hold a cache
+        let file = temp_dir.path().join("not-a-dir");
+        std::fs::write(&file, b"").unwrap();
+        let unwritable = PhiModelManager::writable(file.clone());
+
+        assert!(manager.unwrap().model_path(&phi2).starts_with(&cache_dir));
+        assert!(cache_dir.is_dir());
+        match unwritable {
+            Err(PhiError::CacheDirUnwritable { path, .. }) => assert_eq!(path, file),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("a file was accepted as the cache directory"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quantized_cache_file_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        let phi15 = PhiModel::from_short_name("phi15").unwrap();
+
+        let manager = PhiModelManager::new(temp_dir.path());
+        assert_eq!(manager.model_path(&phi2), temp_dir.path().join("microsoft_phi-2.onnx"));
+
+        let manager = manager.with_quantization(Quantization::Int4);
+        let q4_path = manager.model_path(&phi2);
+        assert_eq!(q4_path, temp_dir.path().join("microsoft_phi-2_q4.onnx"));
+        assert_eq!(
+            manager.tokenizer_path(&phi2),
+            temp_dir.path().join("microsoft_phi-2_q4.tokenizer.json")
+        );
+
+        fs::write(&q4_path, b"weights").await.unwrap();
+        fs::write(temp_dir.path().join(cache_file_name(&phi15, Quantization::F16)), b"weights")
+            .await
+            .unwrap();
+        let mut cached = manager.list_cached_models().await.unwrap();
+        cached.sort();
+        assert_eq!(cached, ["microsoft/phi-1_5", "microsoft/phi-2 (int4)"]);
+    }
+
+    #[test]
+    fn test_available_models_cover_every_variant() {
+        let short_names: Vec<_> = PhiModel::available_models()
+            .iter()
+            .map(|model| model.short_name())
+            .collect();
+        assert_eq!(
+            short_names,
+            ["phi1", "phi15", "phi2", "phi3", "phi35", "phi4", "phi4-mini"]
+        );
+
+        let phi15 = PhiModel::from_short_name("phi15").unwrap();
+        assert_eq!(phi15.parameters(), "1.3B");
+        assert_eq!(phi15.hf_repo(), "microsoft/phi-1_5");
+        assert_eq!(PhiModel::from_short_name("phi1").unwrap().hf_repo(), "microsoft/phi-1");
+    }
+
     #[tokio::test]
     async fn test_model_manager() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -424,4 +1962,626 @@ mod tests {
         assert!(!manager.is_cached(&phi2).await);
         assert_eq!(manager.cache_size().await.unwrap(), 0);
     }
+
+    #[tokio::test]
+    async fn test_validate_model_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        let model_path = manager.model_path(&phi2);
+
+        fs::write(&model_path, b"placeholder-model-file").await.unwrap();
+        assert!(manager.is_cached(&phi2).await);
+        assert!(manager.validate_model_file(&phi2).await.is_err());
+
+        let mut onnx = vec![ONNX_IR_VERSION_TAG, 0x07];
+        onnx.resize(MIN_MODEL_FILE_SIZE as usize, 0);
+        fs::write(&model_path, &onnx).await.unwrap();
+        assert!(manager.validate_model_file(&phi2).await.is_ok());
+
+        fs::write(&model_path, vec![0u8; MIN_MODEL_FILE_SIZE as usize]).await.unwrap();
+        assert!(manager.validate_model_file(&phi2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_uncached_model_is_reported_as_not_cached() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+        let error = manager.validate_model_file(&phi2).await.unwrap_err();
+        match error {
+            PhiError::ModelNotCached { path } => assert_eq!(path, manager.model_path(&phi2)),
+            other => panic!("expected ModelNotCached, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_cache_entries_are_integrity_mismatches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+        fs::write(manager.model_path(&phi2), vec![0u8; MIN_MODEL_FILE_SIZE as usize])
+            .await
+            .unwrap();
+        let error = manager.validate_model_file(&phi2).await.unwrap_err();
+        match error {
+            PhiError::IntegrityMismatch { path, reason } => {
+                assert_eq!(path, manager.model_path(&phi2));
+                assert!(reason.contains("ONNX model header"), "{}", reason);
+            }
+            other => panic!("expected IntegrityMismatch, got {:?}", other),
+        }
+
+        fs::write(manager.manifest_path(&phi2), b"{ not json").await.unwrap();
+        let error = manager.manifest(&phi2).await.unwrap_err();
+        assert!(matches!(error, PhiError::IntegrityMismatch { .. }), "{:?}", error);
+    }
+
+    /// Bytes that pass `validate_model_file`
+    fn valid_looking_onnx() -> Vec<u8> {
+        let mut onnx = vec![ONNX_IR_VERSION_TAG, 0x07];
+        onnx.resize(MIN_MODEL_FILE_SIZE as usize, 0);
+        onnx
+    }
+
+    /// Commit `mock_hub` reports for every file
+    const MOCK_COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    /// Local stand-in for the Hugging Face hub serving `body` for every path ending in
+    /// one of `suffixes`, 404 for anything else
+    async fn mock_hub(body: Vec<u8>, suffixes: &'static [&'static str]) -> String {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+                    let served = suffixes.iter().any(|suffix| path.ends_with(suffix));
+                    let mut response = if served {
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nX-Repo-Commit: {}\r\n\
+                             Connection: close\r\n\r\n",
+                            body.len(),
+                            MOCK_COMMIT
+                        )
+                        .into_bytes()
+                    } else {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    };
+                    if served {
+                        response.extend(&body);
+                    }
+                    let _ = socket.write_all(&response).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Hub whose first `failures` model requests answer 503, counting model requests
+    async fn flaky_hub(
+        failures: usize,
+        requests: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) -> String {
+        use std::sync::atomic::Ordering;
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+
+                    let (status, body) = if path.ends_with(".onnx") {
+                        if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                            ("503 Service Unavailable", Vec::new())
+                        } else {
+                            ("200 OK", valid_looking_onnx())
+                        }
+                    } else if path.ends_with("tokenizer.json") {
+                        ("200 OK", b"{}".to_vec())
+                    } else {
+                        ("404 Not Found", Vec::new())
+                    };
+                    let mut response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend(&body);
+                    let _ = socket.write_all(&response).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Hub answering range requests for `.onnx` files with slices of `body`, recording the
+    /// requested ranges; the first request past the `bytes=0-0` probe is cut off halfway
+    async fn range_hub(
+        body: Vec<u8>,
+        requested: std::sync::Arc<std::sync::Mutex<Vec<(u64, u64)>>>,
+    ) -> String {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                let requested = requested.clone();
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+                    if path.ends_with("tokenizer.json") {
+                        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+                        let _ = socket.write_all(response.as_bytes()).await;
+                        return;
+                    }
+                    if !path.ends_with(".onnx") {
+                        let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+                        let _ = socket.write_all(response.as_bytes()).await;
+                        return;
+                    }
+
+                    let (start, end) = request
+                        .lines()
+                        .find_map(|line| line.to_lowercase().strip_prefix("range: bytes=")?
+                            .split_once('-')
+                            .map(|(start, end)| (start.parse().unwrap(), end.parse().unwrap())))
+                        .unwrap_or((0, body.len() as u64 - 1));
+                    let first_real_request = {
+                        let mut requested = requested.lock().unwrap();
+                        requested.push((start, end));
+                        requested.iter().filter(|&&range| range != (0, 0)).count() == 1
+                    };
+
+                    let slice = &body[start as usize..=end as usize];
+                    let sent = if first_real_request && (start, end) != (0, 0) {
+                        &slice[..slice.len() / 2]
+                    } else {
+                        slice
+                    };
+                    let mut response = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\
+                         Content-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                        slice.len(),
+                        start,
+                        end,
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend(sent);
+                    let _ = socket.write_all(&response).await;
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_ranged_download_resumes() {
+        use std::sync::{Arc, Mutex};
+
+        let mut body = valid_looking_onnx();
+        body.extend((0..4 * MIN_RANGE_SIZE).map(|i| (i % 251) as u8));
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let endpoint = range_hub(body.clone(), requested.clone()).await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let no_retry = RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        };
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint)
+            .with_retry(no_retry)
+            .with_connections(4);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let part = manager.download_path(&model);
+
+        // The cut-off range fails the download but its progress is kept
+        assert!(manager.ensure_model(&model).await.is_err());
+        assert!(part.exists());
+        assert!(progress_path(&part).exists());
+
+        let path = manager.ensure_model(&model).await.unwrap();
+        assert_eq!(fs::read(&path).await.unwrap(), body);
+        assert!(!part.exists());
+        assert!(!progress_path(&part).exists());
+
+        // Ranges ran in parallel, and the cut-off one continued from the byte it stopped at
+        let requested = requested.lock().unwrap();
+        let range_size = (body.len() as u64).div_ceil(4);
+        let starts: Vec<u64> = requested
+            .iter()
+            .filter(|&&range| range != (0, 0))
+            .map(|&(start, _)| start)
+            .collect();
+        assert!(starts.contains(&range_size));
+        assert!(starts.iter().any(|start| start % range_size != 0));
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_transient_failures() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let retry = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let model = PhiModel::from_short_name("phi3").unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let endpoint = flaky_hub(2, requests.clone()).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint).with_retry(retry);
+
+        let path = manager.ensure_model(&model).await.unwrap();
+        assert_eq!(fs::read(&path).await.unwrap(), valid_looking_onnx());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let endpoint = flaky_hub(usize::MAX, requests.clone()).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint).with_retry(retry);
+
+        let error = manager.ensure_model(&model).await.unwrap_err();
+        let PhiError::DownloadFailed { source, .. } = error else {
+            panic!("expected a download failure, got {:?}", error);
+        };
+        assert!(format!("{:#}", source).contains("Giving up after 4 attempts"));
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        assert!(!manager.is_cached(&model).await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_downloads_of_one_model_share_a_fetch() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let endpoint = flaky_hub(0, requests.clone()).await;
+        let manager = Arc::new(PhiModelManager::with_endpoint(temp_dir.path(), endpoint));
+        let model = PhiModel::from_short_name("phi3").unwrap();
+
+        let calls = (0..5).map(|_| {
+            let manager = manager.clone();
+            let model = model.clone();
+            tokio::spawn(async move { manager.ensure_model(&model).await })
+        });
+        for path in futures::future::join_all(calls).await {
+            let path = path.unwrap().unwrap();
+            assert_eq!(fs::read(&path).await.unwrap(), valid_looking_onnx());
+        }
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(!manager.download_path(&model).exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_streams_into_cache() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(valid_looking_onnx(), &[".onnx", ".onnx.data", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+
+        let mut last_progress = None;
+        let path = manager
+            .ensure_model_with_progress(&model, |done, total| last_progress = Some((done, total)))
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read(&path).await.unwrap(), valid_looking_onnx());
+        assert!(manager.sidecar_path(&model).exists());
+        assert!(manager.tokenizer_path(&model).exists());
+        assert!(!manager.download_path(&model).exists());
+        assert_eq!(last_progress, Some((MIN_MODEL_FILE_SIZE, Some(MIN_MODEL_FILE_SIZE))));
+        assert!(manager.validate_model_file(&model).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_models_without_onnx_export_are_not_downloaded() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), "http://127.0.0.1:9");
+        for name in ["phi1", "phi15", "phi2"] {
+            let model = PhiModel::from_short_name(name).unwrap();
+            assert_eq!(model.onnx_file(), None);
+            assert_eq!(model.tokenizer_file(), "tokenizer.json");
+
+            let error = manager.ensure_model(&model).await.unwrap_err();
+            assert!(matches!(error, PhiError::NoOnnxExport { .. }), "{:?}", error);
+            assert!(error.to_string().contains("optimum-cli"), "{}", error);
+        }
+
+        // A model exported locally and copied into the cache is used as is
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+        write_valid_looking_model(&manager, &phi2).await;
+        fs::write(manager.tokenizer_path(&phi2), "{}").await.unwrap();
+        assert_eq!(manager.ensure_model(&phi2).await.unwrap(), manager.model_path(&phi2));
+    }
+
+    #[tokio::test]
+    async fn test_download_writes_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(valid_looking_onnx(), &[".onnx", ".onnx.data", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        assert_eq!(manager.manifest(&model).await.unwrap(), None);
+
+        manager.ensure_model(&model).await.unwrap();
+
+        assert!(manager.manifest_path(&model).exists());
+        let manifest = manager.manifest(&model).await.unwrap().unwrap();
+        assert_eq!(manifest.repo, model.hf_repo());
+        assert_eq!(manifest.revision, "main");
+        assert_eq!(manifest.commit.as_deref(), Some(MOCK_COMMIT));
+        assert_eq!(manifest.quantization, Quantization::F16);
+        let names = manifest.files.iter().map(|file| file.name.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                model.onnx_file().unwrap().to_string(),
+                format!("{}.data", model.onnx_file().unwrap()),
+                model.tokenizer_file(),
+            ]
+        );
+        assert!(manifest.files.iter().all(|file| file.size == MIN_MODEL_FILE_SIZE));
+        let hash = format!("{:x}", Sha256::digest(valid_looking_onnx()));
+        assert!(manifest.files.iter().all(|file| file.sha256.as_ref() == Some(&hash)));
+        assert!(manifest.downloaded_at > 0);
+
+        let listed = manager.list_cached_models_with_manifests().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].manifest.as_ref(), Some(&manifest));
+    }
+
+    #[tokio::test]
+    async fn test_download_of_missing_model_leaves_no_cache_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(Vec::new(), &[]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+
+        assert!(manager.ensure_model(&model).await.is_err());
+        assert!(!manager.is_cached(&model).await);
+        assert!(!manager.download_path(&model).exists());
+    }
+
+    /// Write a cached file that passes validation so `ensure_model` keeps it
+    async fn write_valid_looking_model(manager: &PhiModelManager, model: &PhiModel) -> PathBuf {
+        let path = manager.model_path(model);
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(&path, valid_looking_onnx()).await.unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_cached_models() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let cached = PhiModel::from_short_name("phi2").unwrap();
+        write_valid_looking_model(&manager, &cached).await;
+
+        let system = crate::check_system_requirements().unwrap();
+        let statuses = manager.status(&system).await;
+        assert_eq!(statuses.len(), PhiModel::available_models().len());
+
+        for status in statuses {
+            if status.model.model_name() == cached.model_name() {
+                assert!(status.cached);
+                assert!(status.valid);
+                // Without a manifest only the header could be checked
+                assert!(!status.verified);
+                assert_eq!(status.size, Some(MIN_MODEL_FILE_SIZE));
+            } else {
+                assert!(!status.cached);
+                assert!(!status.valid);
+                assert_eq!(status.size, None);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_checks_manifest_hashes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(valid_looking_onnx(), &[".onnx", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let path = manager.ensure_model(&model).await.unwrap();
+        let system = crate::check_system_requirements().unwrap();
+        let status_of = |statuses: Vec<ModelStatus>| {
+            statuses
+                .into_iter()
+                .find(|status| status.model.model_name() == model.model_name())
+                .unwrap()
+        };
+
+        let status = status_of(manager.status(&system).await);
+        assert!(status.valid && status.verified);
+        assert!(manager.verify_model_hashes(&model).await.unwrap());
+
+        // Still has an ONNX header, but not the bytes that were downloaded
+        let mut corrupted = valid_looking_onnx();
+        corrupted[100] = 0xff;
+        fs::write(&path, corrupted).await.unwrap();
+        assert!(manager.validate_model_file(&model).await.is_ok());
+        let status = status_of(manager.status(&system).await);
+        assert!(!status.valid && status.verified);
+        assert!(status.verification_error.is_none());
+        let error = manager.verify_model_hashes(&model).await.unwrap_err();
+        assert!(matches!(error, PhiError::IntegrityMismatch { .. }), "{:?}", error);
+
+        // A file that can't be read was not verified at all
+        fs::write(&path, valid_looking_onnx()).await.unwrap();
+        let tokenizer = manager.tokenizer_path(&model);
+        fs::remove_file(&tokenizer).await.unwrap();
+        fs::create_dir(&tokenizer).await.unwrap();
+        let status = status_of(manager.status(&system).await);
+        assert!(!status.valid && !status.verified);
+        let error = status.verification_error.unwrap();
+        assert!(error.contains("Failed to hash"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_load_with_repair_redownloads_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut fresh = valid_looking_onnx();
+        fresh[1] = 0x09;
+        let endpoint = mock_hub(fresh.clone(), &[".onnx", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        let path = write_valid_looking_model(&manager, &model).await;
+
+        let mut attempts = 0;
+        let loaded = manager
+            .load_with_repair(&model, |path| {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt == 1 {
+                        anyhow::bail!("corrupted weights in {:?}", path);
+                    }
+                    Ok(fs::read(&path).await?)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        // The corrupted entry was replaced by a fresh download
+        assert_eq!(loaded, fresh);
+        assert_eq!(fs::read(&path).await.unwrap(), fresh);
+    }
+
+    #[tokio::test]
+    async fn test_load_with_repair_gives_up_after_one_retry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(valid_looking_onnx(), &[".onnx", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi3").unwrap();
+        write_valid_looking_model(&manager, &model).await;
+
+        let mut attempts = 0;
+        let result: Result<(), PhiError> = manager
+            .load_with_repair(&model, |_| {
+                attempts += 1;
+                async { anyhow::bail!("still broken") }
+            })
+            .await;
+
+        assert!(matches!(result, Err(PhiError::IntegrityMismatch { .. })));
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_load_onnx_separates_io_errors_from_unsupported_graphs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let endpoint = mock_hub(valid_looking_onnx(), &[".onnx", "tokenizer.json"]).await;
+        let manager = PhiModelManager::with_endpoint(temp_dir.path(), endpoint);
+        let model = PhiModel::from_short_name("phi2").unwrap();
+        let path = write_valid_looking_model(&manager, &model).await;
+
+        let result = manager.load_onnx(&model, &Default::default()).await;
+        assert!(
+            matches!(&result, Err(PhiError::UnsupportedGraph { path: p, .. }) if *p == path),
+            "{:?}",
+            result
+        );
+
+        let missing = temp_dir.path().join("missing.onnx");
+        let error = crate::PhiInference::from_onnx(&missing, &Default::default()).unwrap_err();
+        match graph_error(missing, error) {
+            PhiError::Io { context, source } => {
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+                assert!(context.contains("Failed to read ONNX model"), "{}", context);
+            }
+            other => panic!("expected an I/O error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evict_to_removes_least_recently_used_first() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let [oldest, middle, newest] = ["phi2", "phi3", "phi35"]
+            .map(|name| PhiModel::from_short_name(name).unwrap());
+
+        let now = SystemTime::now();
+        for (age_hours, model) in [(3, &oldest), (2, &middle), (1, &newest)] {
+            let path = write_valid_looking_model(&manager, model).await;
+            std::fs::File::options()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_hours * 3600))
+                .unwrap();
+        }
+        fs::write(manager.sidecar_path(&oldest), b"weights").await.unwrap();
+        fs::write(manager.tokenizer_path(&middle), b"{}").await.unwrap();
+
+        assert!(manager.evict_to(u64::MAX).await.unwrap().is_empty());
+
+        let evicted = manager.evict_to(2 * MIN_MODEL_FILE_SIZE + 2).await.unwrap();
+        assert_eq!(evicted, vec![oldest.model_name().to_string()]);
+        assert!(!manager.is_cached(&oldest).await);
+        assert!(!manager.sidecar_path(&oldest).exists());
+        assert!(manager.cache_size().await.unwrap() <= 2 * MIN_MODEL_FILE_SIZE + 2);
+
+        // Using a model makes it the most recently used
+        manager.ensure_model(&middle).await.unwrap();
+        let evicted = manager.evict_to(MIN_MODEL_FILE_SIZE + 2).await.unwrap();
+        assert_eq!(evicted, vec![newest.model_name().to_string()]);
+        assert!(manager.is_cached(&middle).await);
+    }
+
+    #[tokio::test]
+    async fn test_partial_download_cleanup_on_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let manager = PhiModelManager::new(temp_dir.path());
+        let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+        // Simulate a download that fails after writing some bytes
+        let simulated: Result<()> = async {
+            let partial = PartialDownload::new(manager.download_path(&phi2));
+            fs::write(partial.path(), b"half-a-model").await?;
+            anyhow::bail!("connection reset mid-download");
+        }
+        .await;
+
+        assert!(simulated.is_err());
+        assert!(!manager.download_path(&phi2).exists());
+        assert!(!manager.is_cached(&phi2).await);
+    }
+
+    #[tokio::test]
+    async fn test_partial_download_commit_keeps_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("model.onnx.part");
+
+        let partial = PartialDownload::new(path.clone());
+        fs::write(partial.path(), b"complete").await.unwrap();
+        partial.commit();
+
+        assert!(path.exists());
+    }
 }
\ No newline at end of file
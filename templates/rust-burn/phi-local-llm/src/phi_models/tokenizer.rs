@@ -0,0 +1,112 @@
+/*!
+Phi tokenizer
+
+Wraps the Hugging Face `tokenizers` crate around the `tokenizer.json` shipped in each
+model repository, which `PhiModelManager::ensure_model` downloads next to the weights.
+*/
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+/// Tokens that end a Phi generation: the end of text, and the end of a turn in the Phi-3
+/// and ChatML templates
+const END_TOKENS: &[&str] = &["<|endoftext|>", "<|end|>", "<|im_end|>"];
+
+/// Text <-> token id conversion for a Phi model
+pub struct Tokenizer {
+    inner: tokenizers::Tokenizer,
+}
+
+impl Tokenizer {
+    /// Load a `tokenizer.json` file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let inner = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| anyhow!("Failed to load tokenizer {:?}: {}", path, e))?;
+        Ok(Self { inner })
+    }
+
+    /// Token ids for `text`, without special tokens such as BOS
+    pub fn encode(&self, text: &str) -> Result<Vec<u32>> {
+        let encoding = self
+            .inner
+            .encode(text, false)
+            .map_err(|e| anyhow!("Failed to encode text: {}", e))?;
+        Ok(encoding.get_ids().to_vec())
+    }
+
+    /// Token ids for `text` with their attention mask, which is 0 for padding
+    pub fn encode_with_mask(&self, text: &str) -> Result<(Vec<u32>, Vec<u32>)> {
+        let encoding = self
+            .inner
+            .encode(text, false)
+            .map_err(|e| anyhow!("Failed to encode text: {}", e))?;
+        Ok((
+            encoding.get_ids().to_vec(),
+            encoding.get_attention_mask().to_vec(),
+        ))
+    }
+
+    /// Text for `ids`, skipping special tokens
+    pub fn decode(&self, ids: &[u32]) -> Result<String> {
+        self.inner
+            .decode(ids, true)
+            .map_err(|e| anyhow!("Failed to decode tokens: {}", e))
+    }
+
+    /// Ids of the vocabulary's end-of-text and end-of-turn tokens
+    pub fn eos_token_ids(&self) -> Vec<u32> {
+        END_TOKENS
+            .iter()
+            .filter_map(|token| self.inner.token_to_id(token))
+            .collect()
+    }
+
+    /// Number of tokens `text` encodes to
+    pub fn count_tokens(&self, text: &str) -> Result<usize> {
+        Ok(self.encode(text)?.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal word-level tokenizer in the `tokenizer.json` format
+    const WORD_LEVEL_TOKENIZER: &str = r#"{
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": { "[UNK]": 0, "hello": 1, "phi": 2, "world": 3, "<|end|>": 4 },
+            "unk_token": "[UNK]"
+        }
+    }"#;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.json");
+        std::fs::write(&path, WORD_LEVEL_TOKENIZER).unwrap();
+
+        let tokenizer = Tokenizer::from_file(&path).unwrap();
+        let ids = tokenizer.encode("hello phi world").unwrap();
+
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(tokenizer.decode(&ids).unwrap(), "hello phi world");
+        assert_eq!(tokenizer.count_tokens("hello unknown").unwrap(), 2);
+        assert_eq!(tokenizer.eos_token_ids(), vec![4]);
+        let (ids, mask) = tokenizer.encode_with_mask("hello phi").unwrap();
+        assert_eq!((ids, mask), (vec![1, 2], vec![1, 1]));
+    }
+
+    #[test]
+    fn test_missing_file_is_an_error() {
+        assert!(Tokenizer::from_file(Path::new("/nonexistent/tokenizer.json")).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+/*!
+Sampling configuration for Phi text generation
+
+Holds the user-tunable knobs that control decoding, together with the bounds checks
+shared by the CLI flags and the runtime `set` commands, and the token selection that
+applies them to a logit distribution.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Sampling parameters for a generation request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Softmax temperature; lower is more deterministic
+    pub temperature: f32,
+    /// Nucleus sampling cutoff in (0, 1]
+    pub top_p: f32,
+    /// Only the `top_k` most likely tokens are considered; at least 1
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// Maximum number of tokens to generate
+    pub max_tokens: usize,
+    /// Report the log-probability of every generated token
+    #[serde(default)]
+    pub return_logprobs: bool,
+    /// Divisor for the logits of recently generated tokens; 1.0 disables it
+    #[serde(default = "default_repeat_penalty")]
+    pub repeat_penalty: f32,
+    /// Never generate an n-gram of this many tokens twice; 0 disables it
+    #[serde(default)]
+    pub no_repeat_ngram_size: usize,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.7,
+            top_p: 0.9,
+            top_k: default_top_k(),
+            max_tokens: 512,
+            return_logprobs: false,
+            repeat_penalty: default_repeat_penalty(),
+            no_repeat_ngram_size: 0,
+        }
+    }
+}
+
+fn default_top_k() -> usize {
+    40
+}
+
+fn default_repeat_penalty() -> f32 {
+    1.1
+}
+
+/// Generated tokens that [`SamplingConfig::repeat_penalty`] applies to
+pub const REPEAT_PENALTY_WINDOW: usize = 64;
+
+impl SamplingConfig {
+    /// Update a single parameter by name, validating the value first
+    ///
+    /// The config is left untouched when the name is unknown or the value is out of range.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "temperature" | "temp" => self.temperature = parse_temperature(value)?,
+            "top-p" | "top_p" => self.top_p = parse_top_p(value)?,
+            "top-k" | "top_k" => self.top_k = parse_top_k(value)?,
+            "max-tokens" | "max_tokens" => self.max_tokens = parse_max_tokens(value)?,
+            "repeat-penalty" | "repeat_penalty" => {
+                self.repeat_penalty = parse_repeat_penalty(value)?
+            }
+            "no-repeat-ngram-size" | "no_repeat_ngram_size" => {
+                self.no_repeat_ngram_size = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a valid n-gram size", value))?
+            }
+            "logprobs" => {
+                self.return_logprobs = value
+                    .parse()
+                    .map_err(|_| format!("logprobs must be true or false, got {}", value))?
+            }
+            _ => {
+                return Err(format!(
+                    "unknown parameter '{}' (expected temperature, top-p, top-k, max-tokens, \
+                     repeat-penalty, no-repeat-ngram-size or logprobs)",
+                    name
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SamplingConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "temperature={:.2}, top-p={:.2}, top-k={}, max-tokens={}, repeat-penalty={:.2}, \
+             no-repeat-ngram-size={}, logprobs={}",
+            self.temperature,
+            self.top_p,
+            self.top_k,
+            self.max_tokens,
+            self.repeat_penalty,
+            self.no_repeat_ngram_size,
+            self.return_logprobs
+        )
+    }
+}
+
+/// Generated text, with per-token log-probabilities when they were requested
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Generation {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<(String, f32)>>,
+}
+
+/// Token chosen from a logit distribution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledToken {
+    pub index: usize,
+    /// Log-probability of the token under the model's unscaled distribution
+    pub logprob: f32,
+}
+
+/// Numerically stable log-softmax of `logits`
+pub fn log_softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let log_sum = logits.iter().map(|l| (l - max).exp()).sum::<f32>().ln() + max;
+    logits.iter().map(|l| l - log_sum).collect()
+}
+
+/// Pick the next token from `logits` using temperature, top-k and nucleus (top-p) sampling
+///
+/// `draw` is a uniform random number in [0, 1) supplied by the caller so selection is
+/// reproducible. A temperature of 0 always picks the most likely token. The reported
+/// logprob is taken from the unscaled distribution so it reflects model confidence
+/// independently of the sampling knobs.
+pub fn sample_token(logits: &[f32], config: &SamplingConfig, draw: f32) -> SampledToken {
+    let logprobs = log_softmax(logits);
+
+    let index = if config.temperature == 0.0 {
+        argmax(&logprobs)
+    } else {
+        let scaled: Vec<f32> = logits.iter().map(|l| l / config.temperature).collect();
+        let mut candidates: Vec<(usize, f32)> = log_softmax(&scaled)
+            .into_iter()
+            .map(f32::exp)
+            .enumerate()
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        candidates.truncate(config.top_k.max(1));
+
+        // Keep the smallest prefix whose cumulative probability reaches top-p
+        let mut cumulative = 0.0;
+        let cutoff = candidates
+            .iter()
+            .position(|(_, p)| {
+                cumulative += p;
+                cumulative >= config.top_p
+            })
+            .map_or(candidates.len(), |i| i + 1);
+        candidates.truncate(cutoff);
+
+        let total: f32 = candidates.iter().map(|(_, p)| p).sum();
+        let mut target = draw.clamp(0.0, 1.0) * total;
+        candidates
+            .iter()
+            .find(|(_, p)| {
+                target -= p;
+                target < 0.0
+            })
+            .or(candidates.last())
+            .map_or(0, |(i, _)| *i)
+    };
+
+    SampledToken {
+        index,
+        logprob: logprobs[index],
+    }
+}
+
+/// Pick the token after `history` from `logits`, discouraging repetition first
+///
+/// The repetition penalty and n-gram ban reshape the logits before temperature, top-k and
+/// top-p are applied, so a penalized token is also less likely to make the nucleus and a
+/// banned one never does. The reported logprob is still the model's own, as in
+/// [`sample_token`].
+pub fn sample_next_token(
+    logits: &[f32],
+    history: &[usize],
+    config: &SamplingConfig,
+    draw: f32,
+) -> SampledToken {
+    let mut penalized = logits.to_vec();
+    let window = &history[history.len().saturating_sub(REPEAT_PENALTY_WINDOW)..];
+    apply_repeat_penalty(&mut penalized, window, config.repeat_penalty);
+    ban_repeated_ngrams(&mut penalized, history, config.no_repeat_ngram_size);
+    // Repetition is better than nothing when every token is banned
+    if penalized.iter().all(|logit| *logit == f32::NEG_INFINITY) {
+        penalized.copy_from_slice(logits);
+    }
+
+    let index = sample_token(&penalized, config, draw).index;
+    SampledToken {
+        index,
+        logprob: log_softmax(logits)[index],
+    }
+}
+
+/// Make each token in `recent` less likely by `penalty`, once however often it appears
+///
+/// Positive logits are divided by the penalty and negative ones multiplied, so a penalty
+/// above 1.0 always lowers the token's logit. Tokens outside `logits` are ignored.
+pub fn apply_repeat_penalty(logits: &mut [f32], recent: &[usize], penalty: f32) {
+    if penalty == 1.0 {
+        return;
+    }
+    let mut seen = std::collections::HashSet::new();
+    for &token in recent {
+        let Some(logit) = logits.get_mut(token) else {
+            continue;
+        };
+        if seen.insert(token) {
+            *logit = if *logit > 0.0 { *logit / penalty } else { *logit * penalty };
+        }
+    }
+}
+
+/// Rule out every token that would repeat an n-gram of `size` tokens already in `history`
+pub fn ban_repeated_ngrams(logits: &mut [f32], history: &[usize], size: usize) {
+    if size == 0 || history.len() < size {
+        return;
+    }
+    let prefix = &history[history.len() + 1 - size..];
+    for ngram in history.windows(size) {
+        if ngram[..size - 1] == *prefix {
+            if let Some(logit) = logits.get_mut(ngram[size - 1]) {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
+    }
+}
+
+fn argmax(values: &[f32]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Highest accepted temperature, as in common hosted LLM APIs
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Parse and validate a temperature in [0.0, [`MAX_TEMPERATURE`]]
+pub fn parse_temperature(value: &str) -> Result<f32, String> {
+    let temperature: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number", value))?;
+
+    if !temperature.is_finite() {
+        return Err(format!("temperature must be a finite number, got {}", value));
+    }
+    if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+        return Err(format!(
+            "temperature must be between 0.0 and {:.1}, got {}",
+            MAX_TEMPERATURE, value
+        ));
+    }
+    Ok(temperature)
+}
+
+/// Parse and validate a top-p value in (0.0, 1.0]
+pub fn parse_top_p(value: &str) -> Result<f32, String> {
+    let top_p: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number", value))?;
+
+    if !(top_p > 0.0 && top_p <= 1.0) {
+        return Err(format!("top-p must be in (0.0, 1.0], got {}", value));
+    }
+    Ok(top_p)
+}
+
+/// Parse and validate a top-k value of at least 1
+pub fn parse_top_k(value: &str) -> Result<usize, String> {
+    let top_k: usize = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid token count", value))?;
+
+    if top_k == 0 {
+        return Err("top-k must be at least 1".to_string());
+    }
+    Ok(top_k)
+}
+
+/// Parse and validate a repetition penalty of at least 1.0
+pub fn parse_repeat_penalty(value: &str) -> Result<f32, String> {
+    let penalty: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number", value))?;
+
+    if !penalty.is_finite() || penalty < 1.0 {
+        return Err(format!(
+            "repeat-penalty must be at least 1.0 (1.0 disables it), got {}",
+            value
+        ));
+    }
+    Ok(penalty)
+}
+
+/// Parse and validate a token budget of at least 1
+pub fn parse_max_tokens(value: &str) -> Result<usize, String> {
+    let max_tokens: usize = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid token count", value))?;
+
+    if max_tokens == 0 {
+        return Err("max-tokens must be at least 1".to_string());
+    }
+    Ok(max_tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(parse_temperature("0.7"), Ok(0.7));
+        assert_eq!(parse_temperature("1.5"), Ok(1.5));
+        assert!(parse_temperature("-0.5").is_err());
+        assert!(parse_temperature("3.0").is_err());
+        assert!(parse_temperature("NaN").unwrap_err().contains("finite"));
+        assert!(parse_temperature("inf").unwrap_err().contains("finite"));
+
+        assert_eq!(parse_top_p("1.0"), Ok(1.0));
+        assert!(parse_top_p("0").is_err());
+        assert!(parse_top_p("1.1").is_err());
+
+        assert_eq!(parse_top_k("40"), Ok(40));
+        assert!(parse_top_k("0").is_err());
+        assert!(parse_top_k("-1").is_err());
+
+        assert_eq!(parse_repeat_penalty("1.1"), Ok(1.1));
+        assert!(parse_repeat_penalty("0.9").is_err());
+        assert!(parse_repeat_penalty("NaN").is_err());
+
+        assert_eq!(parse_max_tokens("128"), Ok(128));
+        assert!(parse_max_tokens("0").is_err());
+    }
+
+    #[test]
+    fn test_set_updates_config() {
+        let mut config = SamplingConfig::default();
+        config.set("temperature", "0.2").unwrap();
+        config.set("top-p", "0.5").unwrap();
+
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.top_p, 0.5);
+    }
+
+    #[test]
+    fn test_sampled_logprob_matches_log_softmax() {
+        let logits = [1.0f32, 2.0, 3.0];
+        let expected = 3.0 - (1.0f32.exp() + 2.0f32.exp() + 3.0f32.exp()).ln();
+
+        let greedy = SamplingConfig {
+            temperature: 0.0,
+            ..SamplingConfig::default()
+        };
+        let token = sample_token(&logits, &greedy, 0.5);
+        assert_eq!(token.index, 2);
+        assert!((token.logprob - expected).abs() < 1e-6);
+
+        // A draw of zero lands on the most likely token for any temperature
+        let token = sample_token(&logits, &SamplingConfig::default(), 0.0);
+        assert_eq!(token.index, 2);
+        assert!((token.logprob - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_top_p_excludes_unlikely_tokens() {
+        let config = SamplingConfig {
+            temperature: 1.0,
+            top_p: 0.5,
+            ..SamplingConfig::default()
+        };
+        // The first token alone covers the nucleus, so every draw selects it
+        let logits = [5.0f32, 0.0, 0.0];
+        for draw in [0.0, 0.5, 0.999] {
+            assert_eq!(sample_token(&logits, &config, draw).index, 0);
+        }
+    }
+
+    #[test]
+    fn test_top_k_limits_candidates() {
+        let config = SamplingConfig {
+            temperature: 1.0,
+            top_p: 1.0,
+            top_k: 1,
+            ..SamplingConfig::default()
+        };
+        // Nearly uniform logits would spread draws across every token without top-k
+        let logits = [1.0f32, 1.1, 1.0, 1.0];
+        for draw in [0.0, 0.5, 0.999] {
+            assert_eq!(sample_token(&logits, &config, draw).index, 1);
+        }
+    }
+
+    #[test]
+    fn test_repeat_penalty_lowers_emitted_tokens() {
+        let mut logits = [2.0f32, -1.0, 2.0, 0.5];
+        apply_repeat_penalty(&mut logits, &[0, 1, 0, 9], 2.0);
+        assert_eq!(logits, [1.0, -2.0, 2.0, 0.5]);
+
+        // After "0 1" once, "1" can't follow "0" again
+        let mut logits = [0.0f32; 3];
+        ban_repeated_ngrams(&mut logits, &[0, 1, 2, 0], 2);
+        assert_eq!(logits, [0.0, f32::NEG_INFINITY, 0.0]);
+
+        let greedy = SamplingConfig {
+            temperature: 0.0,
+            repeat_penalty: 2.0,
+            ..SamplingConfig::default()
+        };
+        let logits = [3.0f32, 2.0];
+        assert_eq!(sample_next_token(&logits, &[], &greedy, 0.0).index, 0);
+        let token = sample_next_token(&logits, &[0], &greedy, 0.0);
+        assert_eq!(token.index, 1);
+        assert_eq!(token.logprob, log_softmax(&logits)[1]);
+
+        // When everything is banned, the model's choice stands
+        let banned = SamplingConfig {
+            no_repeat_ngram_size: 1,
+            ..greedy
+        };
+        assert_eq!(sample_next_token(&logits, &[0, 1], &banned, 0.0).index, 0);
+    }
+
+    #[test]
+    fn test_set_rejects_invalid_values() {
+        let mut config = SamplingConfig::default();
+        let original = config.clone();
+
+        assert!(config.set("temperature", "3.0").is_err());
+        assert!(config.set("top-p", "abc").is_err());
+        assert!(config.set("frequency", "0.1").is_err());
+        assert_eq!(config, original);
+    }
+}
@@ -0,0 +1,1006 @@
+/*!
+REST API server for Phi models
+
+`chat-phi --api-mode` serves the models over HTTP for the VibeCode platform and
+container deployments:
+
+- `POST /v1/chat` generates a reply to a list of `{role, content}` messages, continuing
+  a server-side conversation when the request names a `session_id`
+- `POST /v1/sessions` starts a server-side conversation and `DELETE /v1/sessions/{id}`
+  frees it; idle ones are evicted after the store's TTL
+- `POST /v1/chat/completions` does the same following the OpenAI chat completions
+  schema, streaming Server-Sent Events when `stream` is true and cancelling the generation
+  as soon as the client disconnects
+- `POST /v1/generate` streams the continuation of a raw prompt as Server-Sent Events,
+  cancelling the generation as soon as the client disconnects
+- `POST /v1/embeddings` embeds one text or a batch of texts with a preloaded model,
+  answering `503` for models that are not loaded
+- `GET /healthz` is a liveness probe for orchestrators, answering `503` while
+  [`preload`] is still loading models
+- `GET /readyz` is the readiness probe: `503` until [`preload`] has loaded and warmed up
+  its models, then `200` with the preloaded model names
+- `GET /models` lists the available Phi models
+- `GET /metrics` reports request, error, latency and token counts in the Prometheus
+  text format
+
+Every chat request builds its own [`ChatSession`], so requests share no conversation
+state beyond what [`ApiState::sessions`] records and can be served concurrently. Requests
+for a model loaded by [`preload`] generate with it; others get demo replies. A
+generation that outlives [`ApiState::timeout`] is cancelled and answered with
+`504 Gateway Timeout`.
+
+[`serve_with_shutdown`] drains the server for rolling deploys: once its shutdown future
+(such as [`shutdown_signal`] for SIGTERM) resolves, new connections are refused while
+in-flight requests, streamed ones included, get a grace period to finish.
+*/
+
+use anyhow::{Context, Result};
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::{Future, IntoFuture};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::embeddings::{self, Embedder};
+use crate::filter::{ContentBlocked, ContentFilter};
+use crate::metrics::{MetricsSink, PrometheusSink};
+use crate::sessions::SessionStore;
+use crate::streaming::{self, CancellationToken};
+use crate::{
+    ChatSession, GenerationTimeout, PhiInference, PhiModel, PhiModelManager, SamplingConfig,
+};
+
+/// Settings applied to every request unless the request overrides them
+#[derive(Debug, Clone, Default)]
+pub struct ApiState {
+    pub sampling: SamplingConfig,
+    pub stop_sequences: Vec<String>,
+    /// Longest a generation may run; `None` lets it run to completion
+    pub timeout: Option<Duration>,
+    /// Request metrics served at `GET /metrics`
+    pub metrics: Arc<PrometheusSink>,
+    /// Filter for prompts and replies; blocked ones are answered with 422
+    pub filter: Option<Arc<dyn ContentFilter>>,
+    /// Seed every request's sampling RNG with this, making replies reproducible
+    pub seed: Option<u64>,
+    /// Set while [`preload`] runs; `/healthz` and `/readyz` answer 503 until it clears
+    pub preloading: Arc<AtomicBool>,
+    /// Skip the throwaway generation [`preload`] runs after loading each model
+    pub skip_warmup: bool,
+    /// Models loaded by [`preload`] by short name, shared by every request for them
+    pub preloaded: Arc<RwLock<HashMap<String, Arc<PhiInference>>>>,
+    /// Conversations continued through `session_id` on `POST /v1/chat`
+    pub sessions: Arc<SessionStore>,
+}
+
+/// One message of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// `system`, `user` or `assistant`
+    pub role: String,
+    pub content: String,
+}
+
+/// Body of `POST /v1/chat`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRequest {
+    /// Short model name, e.g. `phi3`
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Session from `POST /v1/sessions` whose earlier turns precede `messages`
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// Body returned by `POST /v1/chat`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub model: String,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// Body returned by `POST /v1/sessions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCreated {
+    pub session_id: String,
+}
+
+/// Body of `POST /v1/chat/completions`, a subset of the OpenAI request schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    /// Model name such as `phi-3`, `phi3` or `microsoft/Phi-3-mini-4k-instruct`
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    /// Stream the reply as Server-Sent Events
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// Body of `POST /v1/generate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateRequest {
+    /// Short model name, e.g. `phi3`
+    pub model: String,
+    /// Prompt to continue, sent to the model without any conversation around it
+    pub prompt: String,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// One Server-Sent Event of `POST /v1/generate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateChunk {
+    pub text: String,
+}
+
+/// Non-streamed `POST /v1/chat/completions` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Completion {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<CompletionChoice>,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionChoice {
+    pub index: usize,
+    pub message: ChatMessage,
+    pub finish_reason: String,
+}
+
+/// Estimated token counts of a completion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+}
+
+/// One Server-Sent Event of a streamed completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkChoice {
+    pub index: usize,
+    pub delta: Delta,
+    pub finish_reason: Option<String>,
+}
+
+/// Incremental part of the assistant message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Delta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Body of `POST /v1/embeddings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+}
+
+/// A single text or a batch of texts to embed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+/// Response of `POST /v1/embeddings`: `embedding` for a single input, `embeddings` in
+/// input order for a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub model: String,
+    pub dimensions: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embeddings: Option<Vec<Vec<f32>>>,
+}
+
+/// Entry of the `GET /models` listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub name: String,
+    pub parameters: String,
+    pub context_length: usize,
+    pub specializations: Vec<String>,
+}
+
+impl From<&PhiModel> for ModelInfo {
+    fn from(model: &PhiModel) -> Self {
+        Self {
+            id: model.short_name().to_string(),
+            name: model.model_name().to_string(),
+            parameters: model.parameters().to_string(),
+            context_length: model.context_length(),
+            specializations: model.specializations().clone(),
+        }
+    }
+}
+
+/// Error rendered as `{"error": message}` with the given status
+#[derive(Debug)]
+pub struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self(StatusCode::BAD_REQUEST, message.into())
+    }
+
+    fn not_loaded(model: &str) -> Self {
+        Self(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!(
+                "model '{}' is not loaded; start the server with --preload {}",
+                model, model
+            ),
+        )
+    }
+
+    fn unknown_session(id: &str) -> Self {
+        Self(
+            StatusCode::NOT_FOUND,
+            format!("unknown or expired session '{}'", id),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let status = if error.is::<GenerationTimeout>() {
+            StatusCode::GATEWAY_TIMEOUT
+        } else if error.is::<ContentBlocked>() {
+            StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        Self(status, error.to_string())
+    }
+}
+
+/// Build the API routes
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/v1/chat", post(chat))
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/generate", post(generate))
+        .route_layer(middleware::from_fn_with_state(state.clone(), track_requests))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/v1/embeddings", post(embeddings))
+        .route("/v1/sessions", post(create_session))
+        .route("/v1/sessions/{id}", delete(delete_session))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/models", get(models))
+        .with_state(state)
+}
+
+/// Serve the API on `listener` until the task is cancelled or the server fails
+pub async fn serve(listener: TcpListener, state: ApiState) -> Result<()> {
+    tracing::info!("API server listening on {}", listener.local_addr()?);
+    let eviction = spawn_session_eviction(state.sessions.clone());
+    let result = axum::serve(listener, router(state)).await;
+    eviction.abort();
+    Ok(result?)
+}
+
+/// Serve the API on `listener` until `shutdown` resolves, then drain it
+///
+/// After `shutdown` the listener stops accepting connections and the server returns once
+/// every in-flight request has finished, or after `grace`, dropping the ones still running.
+pub async fn serve_with_shutdown(
+    listener: TcpListener,
+    state: ApiState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    grace: Duration,
+) -> Result<()> {
+    tracing::info!("API server listening on {}", listener.local_addr()?);
+    let eviction = spawn_session_eviction(state.sessions.clone());
+    let (draining_tx, draining_rx) = oneshot::channel();
+    let signal = async move {
+        shutdown.await;
+        tracing::info!("Shutting down, waiting up to {:?} for in-flight requests", grace);
+        let _ = draining_tx.send(());
+    };
+    let server = axum::serve(listener, router(state))
+        .with_graceful_shutdown(signal)
+        .into_future();
+
+    let result = tokio::select! {
+        result = server => result,
+        _ = async {
+            // The sender only goes away without sending once the server has returned
+            if draining_rx.await.is_err() {
+                std::future::pending::<()>().await;
+            }
+            tokio::time::sleep(grace).await;
+        } => {
+            tracing::warn!("Shutdown grace period of {:?} elapsed, dropping open requests", grace);
+            Ok(())
+        }
+    };
+    eviction.abort();
+    Ok(result?)
+}
+
+/// Evict idle sessions from `sessions` in the background, checking a few times per TTL
+fn spawn_session_eviction(sessions: Arc<SessionStore>) -> tokio::task::JoinHandle<()> {
+    let period = (sessions.ttl() / 4).max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            let evicted = sessions.evict_idle();
+            if evicted > 0 {
+                tracing::debug!("Evicted {} idle sessions", evicted);
+            }
+        }
+    })
+}
+
+/// Resolve on SIGTERM, as sent by orchestrators stopping a container, or on Ctrl-C
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Download, load and warm up `models` into [`ApiState::preloaded`], so requests for them
+/// generate with the loaded model and the first ones are served hot
+///
+/// Each model runs one short generation after loading, timed as `phi.api.warmup_ms`,
+/// unless [`ApiState::skip_warmup`] is set. `/healthz` and `/readyz` answer 503 from the
+/// start of the preload until it finishes, whether every model is ready or one failed.
+/// Set [`ApiState::preloading`] before serving to cover the time until this is called.
+pub async fn preload(
+    state: &ApiState,
+    manager: &PhiModelManager,
+    models: &[PhiModel],
+) -> Result<()> {
+    state.preloading.store(true, Ordering::SeqCst);
+    let result = preload_models(state, manager, models).await;
+    state.preloading.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn preload_models(
+    state: &ApiState,
+    manager: &PhiModelManager,
+    models: &[PhiModel],
+) -> Result<()> {
+    for model in models {
+        let inference = manager
+            .load_with_repair(model, |path| async move { PhiInference::open(&path).await })
+            .await
+            .with_context(|| format!("Failed to preload {}", model.model_name()))?;
+        let inference = Arc::new(inference);
+
+        if state.skip_warmup {
+            tracing::info!("Preloaded {} from {:?}", model.model_name(), inference.model_path());
+        } else {
+            let mut session = ChatSession::new(model.clone(), None, false, false)
+                .with_sampling(state.sampling.clone())
+                .with_inference(inference.clone());
+            let elapsed = session
+                .warmup()
+                .await
+                .with_context(|| format!("Warmup of {} failed", model.model_name()))?;
+            let tags = [("model", model.short_name())];
+            state.metrics.timing("phi.api.warmup_ms", elapsed, &tags);
+            tracing::info!(
+                "Preloaded {} from {:?}, warmed up in {:?}",
+                model.model_name(),
+                inference.model_path(),
+                elapsed
+            );
+        }
+        state
+            .preloaded
+            .write()
+            .unwrap()
+            .insert(model.short_name().to_string(), inference);
+    }
+    Ok(())
+}
+
+/// Count chat requests, and those that failed, by endpoint
+async fn track_requests(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let endpoint = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    state
+        .metrics
+        .increment("phi.api.requests", 1, &[("endpoint", &endpoint)]);
+    let status = response.status();
+    if !status.is_success() {
+        let tags = [("endpoint", endpoint.as_str()), ("status", status.as_str())];
+        state.metrics.increment("phi.api.errors", 1, &tags);
+    }
+    response
+}
+
+async fn prometheus_metrics(State(state): State<ApiState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+async fn healthz(State(state): State<ApiState>) -> Response {
+    if state.preloading.load(Ordering::SeqCst) {
+        let body = Json(serde_json::json!({ "status": "preloading" }));
+        return (StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+    }
+    Json(serde_json::json!({ "status": "ok" })).into_response()
+}
+
+async fn readyz(State(state): State<ApiState>) -> Response {
+    if state.preloading.load(Ordering::SeqCst) {
+        let body = Json(serde_json::json!({ "status": "warming up" }));
+        return (StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+    }
+    let mut models: Vec<String> = state.preloaded.read().unwrap().keys().cloned().collect();
+    models.sort();
+    Json(serde_json::json!({ "status": "ready", "models": models })).into_response()
+}
+
+async fn create_session(State(state): State<ApiState>) -> (StatusCode, Json<SessionCreated>) {
+    let session_id = state.sessions.create();
+    (StatusCode::CREATED, Json(SessionCreated { session_id }))
+}
+
+async fn delete_session(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.sessions.delete(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::unknown_session(&id))
+    }
+}
+
+async fn models() -> Json<Vec<ModelInfo>> {
+    Json(
+        PhiModel::available_models()
+            .iter()
+            .map(ModelInfo::from)
+            .collect(),
+    )
+}
+
+async fn chat(
+    State(state): State<ApiState>,
+    Json(request): Json<ChatRequest>,
+) -> Result<Json<ChatResponse>, ApiError> {
+    let stored = match &request.session_id {
+        Some(id) => Some(
+            state
+                .sessions
+                .history(id)
+                .ok_or_else(|| ApiError::unknown_session(id))?,
+        ),
+        None => None,
+    };
+    let (mut session, input) = build_session(
+        &state,
+        &request.model,
+        &request.messages,
+        request.max_tokens,
+        request.temperature,
+    )?;
+    if let Some(mut history) = stored {
+        history.extend_from_slice(session.history());
+        session = session.with_history(history);
+    }
+    let content = timed_reply(&state, &mut session, &input).await?;
+
+    if let Some(id) = &request.session_id {
+        // The session may have expired while generating; the reply is still returned
+        if let Err(e) = state.sessions.record_turn(id, &input, &content) {
+            tracing::warn!("{}", e);
+        }
+    }
+    Ok(Json(ChatResponse {
+        model: request.model,
+        content,
+        session_id: request.session_id,
+    }))
+}
+
+async fn chat_completions(
+    State(state): State<ApiState>,
+    Json(request): Json<CompletionRequest>,
+) -> Result<Response, ApiError> {
+    let (mut session, input) = build_session(
+        &state,
+        &request.model,
+        &request.messages,
+        request.max_tokens,
+        request.temperature,
+    )?;
+    let header = ChunkHeader::new(request.model);
+
+    if request.stream {
+        let events = completion_events(state.metrics.clone(), session, input, header);
+        return Ok(Sse::new(events).into_response());
+    }
+
+    let content = timed_reply(&state, &mut session, &input).await?;
+    let prompt_tokens = request
+        .messages
+        .iter()
+        .map(|message| session.count_tokens(&message.content))
+        .sum();
+    let completion_tokens = session.count_tokens(&content);
+
+    Ok(Json(Completion {
+        id: header.id,
+        object: "chat.completion".to_string(),
+        created: header.created,
+        model: header.model,
+        choices: vec![CompletionChoice {
+            index: 0,
+            message: ChatMessage {
+                role: "assistant".to_string(),
+                content,
+            },
+            finish_reason: "stop".to_string(),
+        }],
+        usage: Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        },
+    })
+    .into_response())
+}
+
+async fn generate(
+    State(state): State<ApiState>,
+    Json(request): Json<GenerateRequest>,
+) -> Result<Response, ApiError> {
+    let prompt = [ChatMessage {
+        role: "user".to_string(),
+        content: request.prompt,
+    }];
+    let (session, prompt) = build_session(
+        &state,
+        &request.model,
+        &prompt,
+        request.max_tokens,
+        request.temperature,
+    )?;
+    Ok(Sse::new(generate_events(state.metrics.clone(), session, prompt)).into_response())
+}
+
+/// Generate the whole reply to `input`, recording its latency and length
+///
+/// Streamed replies are recorded the same way by [`spawn_generation`].
+async fn timed_reply(state: &ApiState, session: &mut ChatSession, input: &str) -> Result<String> {
+    let start = Instant::now();
+    let content = session.generate_response(input).await?;
+    record_generation(&state.metrics, session, start.elapsed(), &content);
+    Ok(content)
+}
+
+/// Record a finished generation's latency as `phi.api.generation_latency_ms` and its
+/// length as `phi.api.tokens_generated`
+fn record_generation(
+    metrics: &PrometheusSink,
+    session: &ChatSession,
+    elapsed: Duration,
+    content: &str,
+) {
+    let tags = [("model", session.model.short_name())];
+    let tokens = session.count_tokens(content) as u64;
+    metrics.timing("phi.api.generation_latency_ms", elapsed, &tags);
+    metrics.increment("phi.api.tokens_generated", tokens, &tags);
+}
+
+async fn embeddings(
+    State(state): State<ApiState>,
+    Json(request): Json<EmbeddingRequest>,
+) -> Result<Json<EmbeddingResponse>, ApiError> {
+    let model = lookup_model(&request.model)?;
+    embeddings::check_model(&model).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let short_name = model.short_name();
+    let inference = state.preloaded.read().unwrap().get(short_name).cloned();
+    let embedder = inference
+        .and_then(|inference| Embedder::new(model, inference).ok())
+        .ok_or_else(|| ApiError::not_loaded(short_name))?;
+
+    // Each embedding runs a forward pass, which would stall the async workers
+    let (embedding, embeddings) = tokio::task::spawn_blocking(move || match request.input {
+        EmbeddingInput::Single(text) => embedder.embed(&text).map(|one| (Some(one), None)),
+        EmbeddingInput::Batch(texts) => embedder.embed_batch(&texts).map(|all| (None, Some(all))),
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("embedding task failed: {}", e))?
+    .map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let dimensions = embedding
+        .as_ref()
+        .or_else(|| embeddings.as_ref().and_then(|batch| batch.first()))
+        .map_or(0, Vec::len);
+    Ok(Json(EmbeddingResponse {
+        model: request.model,
+        dimensions,
+        embedding,
+        embeddings,
+    }))
+}
+
+/// Fields shared by every chunk of one streamed completion
+#[derive(Debug, Clone)]
+struct ChunkHeader {
+    id: String,
+    created: u64,
+    model: String,
+}
+
+impl ChunkHeader {
+    fn new(model: String) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            id: format!("chatcmpl-{:x}", now.as_nanos()),
+            created: now.as_secs(),
+            model,
+        }
+    }
+
+    fn event(&self, delta: Delta, finish_reason: Option<&str>) -> Result<Event, axum::Error> {
+        Event::default().json_data(CompletionChunk {
+            id: self.id.clone(),
+            object: "chat.completion.chunk".to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta,
+                finish_reason: finish_reason.map(str::to_string),
+            }],
+        })
+    }
+}
+
+/// Stream a completion as OpenAI-style events: the assistant role, one event per
+/// generated chunk, a final event with the finish reason, then `[DONE]`
+///
+/// Generation runs in its own task and stops as soon as the client disconnects; see
+/// [`spawn_generation`]. A failed generation ends the stream early, after logging.
+fn completion_events(
+    metrics: Arc<PrometheusSink>,
+    session: ChatSession,
+    input: String,
+    header: ChunkHeader,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    let rx = spawn_generation(metrics, session, input);
+
+    let role = header.event(
+        Delta {
+            role: Some("assistant".to_string()),
+            content: None,
+        },
+        None,
+    );
+    let finish = header.event(Delta::default(), Some("stop"));
+    let content = futures::stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await? {
+            Ok(chunk) => Some((chunk, rx)),
+            Err(error) => {
+                tracing::warn!("streamed generation failed: {}", error);
+                None
+            }
+        }
+    })
+    .map(move |chunk| {
+        let delta = Delta {
+            role: None,
+            content: Some(chunk),
+        };
+        header.event(delta, None)
+    });
+
+    futures::stream::once(async { role })
+        .chain(content)
+        .chain(futures::stream::iter([
+            finish,
+            Ok(Event::default().data("[DONE]")),
+        ]))
+}
+
+/// Stream the continuation of `prompt` as one `{"text": ...}` event per generated chunk,
+/// then `[DONE]`; a failed generation ends with an `error` event instead
+///
+/// Generation runs in its own task and stops as soon as the client disconnects; see
+/// [`spawn_generation`].
+fn generate_events(
+    metrics: Arc<PrometheusSink>,
+    session: ChatSession,
+    prompt: String,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    let rx = spawn_generation(metrics, session, prompt);
+
+    futures::stream::unfold(Some(rx), |rx| async move {
+        let mut rx = rx?;
+        let event = match rx.recv().await {
+            Some(Ok(text)) => Event::default().json_data(GenerateChunk { text }),
+            Some(Err(error)) => {
+                let error = serde_json::json!({ "error": error.to_string() });
+                return Some((Event::default().event("error").json_data(error), None));
+            }
+            None => return Some((Ok(Event::default().data("[DONE]")), None)),
+        };
+        Some((event, Some(rx)))
+    })
+}
+
+/// Generate the reply to `input` in its own task, returning the channel its chunks arrive on
+///
+/// The connection dropping the response body drops the receiver, which
+/// [`streaming::forward_chunks`] notices even between chunks and cancels the generation.
+/// Each cancellation is counted as `phi.api.generations_cancelled`; a stream that runs to
+/// the end is timed from its start to its last chunk, like [`timed_reply`] times a reply.
+fn spawn_generation(
+    metrics: Arc<PrometheusSink>,
+    mut session: ChatSession,
+    input: String,
+) -> mpsc::Receiver<Result<String>> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let cancel = CancellationToken::new();
+        let start = Instant::now();
+        let mut content = String::new();
+        let mut failed = false;
+        let chunks = session.generate_stream(&input).inspect(|chunk| match chunk {
+            Ok(chunk) => content.push_str(chunk),
+            Err(_) => failed = true,
+        });
+        streaming::forward_chunks(tx, &cancel, chunks).await;
+
+        if cancel.is_cancelled() {
+            let tags = [("model", session.model.short_name())];
+            metrics.increment("phi.api.generations_cancelled", 1, &tags);
+        } else if !failed {
+            record_generation(&metrics, &session, start.elapsed(), &content);
+        }
+    });
+    rx
+}
+
+/// Build a session for one request from the server defaults and the request's overrides,
+/// returning it with the message to answer
+fn build_session(
+    state: &ApiState,
+    model: &str,
+    messages: &[ChatMessage],
+    max_tokens: Option<usize>,
+    temperature: Option<f32>,
+) -> Result<(ChatSession, String), ApiError> {
+    let model = lookup_model(model)?;
+    let conversation = split_messages(messages).map_err(ApiError::bad_request)?;
+    let inference = state.preloaded.read().unwrap().get(model.short_name()).cloned();
+
+    let mut sampling = state.sampling.clone();
+    if let Some(max_tokens) = max_tokens {
+        sampling
+            .set("max-tokens", &max_tokens.to_string())
+            .map_err(ApiError::bad_request)?;
+    }
+    if let Some(temperature) = temperature {
+        sampling
+            .set("temperature", &temperature.to_string())
+            .map_err(ApiError::bad_request)?;
+    }
+
+    let mut session = ChatSession::new(model, conversation.system_prompt, false, false)
+        .with_sampling(sampling)
+        .with_stop_sequences(state.stop_sequences.clone())
+        .with_history(conversation.history);
+    if let Some(timeout) = state.timeout {
+        session = session.with_timeout(timeout);
+    }
+    if let Some(filter) = &state.filter {
+        session = session.with_filter(filter.clone());
+    }
+    if let Some(seed) = state.seed {
+        session = session.with_seed(seed);
+    }
+    if let Some(inference) = inference {
+        session = session.with_inference(inference);
+    }
+    Ok((session, conversation.input))
+}
+
+/// Resolve a requested model name, answering 400 for unknown models
+fn lookup_model(name: &str) -> Result<PhiModel, ApiError> {
+    resolve_model(name).ok_or_else(|| ApiError::bad_request(format!("unknown model '{}'", name)))
+}
+
+/// Map a requested model name to a Phi model
+///
+/// Matches the short CLI name or the Hugging Face repository name, ignoring case,
+/// punctuation and the `microsoft/` prefix, so `phi-3`, `phi3` and
+/// `microsoft/Phi-3-mini-4k-instruct` all select Phi-3.
+fn resolve_model(name: &str) -> Option<PhiModel> {
+    fn normalize(name: &str) -> String {
+        let name = name.to_lowercase();
+        let name = name.strip_prefix("microsoft/").unwrap_or(&name);
+        name.chars().filter(|c| c.is_ascii_alphanumeric()).collect()
+    }
+
+    let wanted = normalize(name);
+    PhiModel::available_models().into_iter().find(|model| {
+        normalize(model.short_name()) == wanted || normalize(model.model_name()) == wanted
+    })
+}
+
+/// A request's messages, split into what `ChatSession` needs
+#[derive(Debug, PartialEq)]
+struct Conversation {
+    system_prompt: Option<String>,
+    /// Earlier (user, assistant) turns
+    history: Vec<(String, String)>,
+    /// Final user message to answer
+    input: String,
+}
+
+/// Split a message list into the system prompt, earlier turns and the message to answer
+fn split_messages(messages: &[ChatMessage]) -> Result<Conversation, String> {
+    let (last, earlier) = messages
+        .split_last()
+        .ok_or_else(|| "messages must not be empty".to_string())?;
+    if last.role != "user" {
+        return Err("the last message must have role 'user'".to_string());
+    }
+
+    let mut system_prompt = None;
+    let mut history = Vec::new();
+    let mut pending_user: Option<&str> = None;
+    for message in earlier {
+        match (message.role.as_str(), pending_user) {
+            ("system", _) => system_prompt = Some(message.content.clone()),
+            ("user", None) => pending_user = Some(&message.content),
+            ("assistant", Some(user)) => {
+                history.push((user.to_string(), message.content.clone()));
+                pending_user = None;
+            }
+            (role, _) => return Err(format!("unexpected '{}' message in conversation", role)),
+        }
+    }
+    if pending_user.is_some() {
+        return Err(
+            "every earlier user message must be followed by an assistant reply".to_string(),
+        );
+    }
+
+    Ok(Conversation {
+        system_prompt,
+        history,
+        input: last.content.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_messages() {
+        let messages = [
+            message("system", "Be brief."),
+            message("user", "hi"),
+            message("assistant", "hello"),
+            message("user", "what is rust?"),
+        ];
+        let conversation = split_messages(&messages).unwrap();
+
+        assert_eq!(conversation.system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(
+            conversation.history,
+            vec![("hi".to_string(), "hello".to_string())]
+        );
+        assert_eq!(conversation.input, "what is rust?");
+    }
+
+    #[test]
+    fn test_resolve_model_names() {
+        for name in ["phi-3", "phi3", "PHI-3", "microsoft/Phi-3-mini-4k-instruct"] {
+            let model = resolve_model(name).unwrap();
+            assert_eq!(model.short_name(), "phi3", "{}", name);
+        }
+        assert_eq!(resolve_model("phi-3.5").unwrap().short_name(), "phi35");
+        assert_eq!(
+            resolve_model("phi-4-mini").unwrap().short_name(),
+            "phi4-mini"
+        );
+        assert!(resolve_model("gpt-4").is_none());
+    }
+
+    #[test]
+    fn test_split_messages_rejects_malformed_conversations() {
+        assert!(split_messages(&[]).is_err());
+        assert!(split_messages(&[message("assistant", "hello")]).is_err());
+        assert!(split_messages(&[message("user", "a"), message("user", "b")]).is_err());
+        assert!(split_messages(&[message("tool", "x"), message("user", "b")]).is_err());
+    }
+}
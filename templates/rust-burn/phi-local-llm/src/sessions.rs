@@ -0,0 +1,199 @@
+/*!
+Server-side conversation state
+
+Keeps the (user, assistant) history of each conversation keyed by a session id so that
+clients only send the new prompt on every turn. Sessions that have not been used for
+longer than the configured TTL are evicted.
+*/
+
+use anyhow::{anyhow, Result};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default idle time after which a session is dropped
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// One (user, assistant) exchange
+pub type Turn = (String, String);
+
+#[derive(Debug)]
+struct Session {
+    history: Vec<Turn>,
+    last_used: Instant,
+}
+
+/// Thread-safe store of conversation histories
+///
+/// The lock is never held across generation, so callers fetch the history, generate,
+/// then record the turn.
+#[derive(Debug)]
+pub struct SessionStore {
+    sessions: Mutex<HashMap<String, Session>>,
+    ttl: Duration,
+    max_turns: usize,
+    next_id: AtomicU64,
+    id_hasher: RandomState,
+}
+
+impl SessionStore {
+    /// Create a store that evicts sessions idle for `ttl` and keeps `max_turns` per session
+    pub fn new(ttl: Duration, max_turns: usize) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            ttl,
+            max_turns,
+            next_id: AtomicU64::new(0),
+            id_hasher: RandomState::new(),
+        }
+    }
+
+    /// Start a new empty session and return its id
+    pub fn create(&self) -> String {
+        // Hashing a counter with a randomly keyed hasher gives ids that are unique
+        // within the process and not guessable from one another
+        let counter = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let id = format!("sess-{:016x}", self.id_hasher.hash_one(counter));
+
+        self.sessions.lock().unwrap().insert(
+            id.clone(),
+            Session {
+                history: Vec::new(),
+                last_used: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// Conversation so far, or `None` if the session does not exist or has expired
+    pub fn history(&self, id: &str) -> Option<Vec<Turn>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(id)?;
+        if session.last_used.elapsed() > self.ttl {
+            sessions.remove(id);
+            return None;
+        }
+        session.last_used = Instant::now();
+        Some(session.history.clone())
+    }
+
+    /// Append a completed turn, trimming the oldest turns beyond the limit
+    pub fn record_turn(&self, id: &str, user: &str, assistant: &str) -> Result<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("Unknown or expired session: {}", id))?;
+
+        session.history.push((user.to_string(), assistant.to_string()));
+        if session.history.len() > self.max_turns {
+            let excess = session.history.len() - self.max_turns;
+            session.history.drain(..excess);
+        }
+        session.last_used = Instant::now();
+        Ok(())
+    }
+
+    /// Free a session; returns whether it existed
+    pub fn delete(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Drop every session idle for longer than the TTL and return how many were removed
+    pub fn evict_idle(&self) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, session| session.last_used.elapsed() <= self.ttl);
+        before - sessions.len()
+    }
+
+    /// Idle time after which a session is evicted
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Number of live sessions
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL, 10)
+    }
+}
+
+/// Render the history and the new prompt as a plain-text conversation
+pub fn format_context(history: &[Turn], prompt: &str) -> String {
+    let mut context = String::new();
+    for (user, assistant) in history {
+        context.push_str(&format!("User: {}\nAssistant: {}\n", user, assistant));
+    }
+    context.push_str(&format!("User: {}\nAssistant:", prompt));
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in generator that answers with the context it was given
+    fn reply(store: &SessionStore, id: &str, prompt: &str) -> String {
+        let history = store.history(id).unwrap();
+        let response = format!("seen: {}", format_context(&history, prompt));
+        store.record_turn(id, prompt, &response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_second_turn_sees_first() {
+        let store = SessionStore::default();
+        let id = store.create();
+
+        let first = reply(&store, &id, "my name is Ada");
+        assert!(!first.contains("Assistant: seen"));
+
+        let second = reply(&store, &id, "what is my name?");
+        assert!(second.contains("User: my name is Ada"));
+        assert!(second.contains("User: what is my name?"));
+
+        // Other sessions do not share history
+        let other = store.create();
+        assert_ne!(other, id);
+        assert!(!reply(&store, &other, "hi").contains("Ada"));
+    }
+
+    #[test]
+    fn test_delete_and_idle_eviction() {
+        let store = SessionStore::new(Duration::ZERO, 10);
+        let id = store.create();
+        assert!(store.delete(&id));
+        assert!(!store.delete(&id));
+        assert!(store.record_turn(&id, "a", "b").is_err());
+
+        store.create();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(store.evict_idle(), 1);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_history_is_trimmed() {
+        let store = SessionStore::new(DEFAULT_SESSION_TTL, 2);
+        let id = store.create();
+        for turn in 0..5 {
+            store.record_turn(&id, &turn.to_string(), "ok").unwrap();
+        }
+
+        let history = store.history(&id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].0, "3");
+    }
+}
@@ -0,0 +1,178 @@
+/*!
+Stop sequences for Phi text generation
+
+Generation halts as soon as any stop string (e.g. "```" or a blank line) appears in the
+output, and the text is cut at that boundary. Streamed chunks rarely line up with a stop
+sequence, so [`StopDetector`] holds back any tail that could be the start of one until
+the next chunk settles it.
+*/
+
+/// Incremental stop-sequence matcher for streamed output
+#[derive(Debug, Clone, Default)]
+pub struct StopDetector {
+    stops: Vec<String>,
+    pending: String,
+    stopped: bool,
+}
+
+impl StopDetector {
+    pub fn new(stops: &[String]) -> Self {
+        Self {
+            stops: stops
+                .iter()
+                .filter(|stop| !stop.is_empty())
+                .cloned()
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Feed the next generated chunk and return the text that is safe to emit
+    ///
+    /// Once a stop sequence is seen, the text before it is returned and every later
+    /// chunk is ignored.
+    pub fn push(&mut self, chunk: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.pending.push_str(chunk);
+
+        if let Some(end) = find_stop(&self.pending, &self.stops) {
+            self.stopped = true;
+            self.pending.truncate(end);
+            return std::mem::take(&mut self.pending);
+        }
+
+        let held = self.partial_match_len();
+        let tail = self.pending.split_off(self.pending.len() - held);
+        std::mem::replace(&mut self.pending, tail)
+    }
+
+    /// Flush the held-back tail once generation has ended without hitting a stop
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Whether a stop sequence has been produced
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Length of the longest suffix of the pending text that begins some stop sequence
+    fn partial_match_len(&self) -> usize {
+        let longest = self.stops.iter().map(String::len).max().unwrap_or(0);
+        (1..longest.min(self.pending.len() + 1))
+            .rev()
+            .find(|&len| {
+                let start = self.pending.len() - len;
+                self.pending.is_char_boundary(start)
+                    && self
+                        .stops
+                        .iter()
+                        .any(|stop| stop.starts_with(&self.pending[start..]))
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Byte offset of the earliest stop sequence in `text`, if any
+pub fn find_stop(text: &str, stops: &[String]) -> Option<usize> {
+    stops
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| text.find(stop.as_str()))
+        .min()
+}
+
+/// Cut `text` at the earliest stop sequence
+pub fn truncate_at_stop(text: &str, stops: &[String]) -> String {
+    match find_stop(text, stops) {
+        Some(end) => text[..end].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Parse a `--stop` value, expanding `\n`, `\t` and `\\` escapes
+pub fn parse_stop_sequence(value: &str) -> Result<String, String> {
+    let mut sequence = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            sequence.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => sequence.push('\n'),
+            Some('t') => sequence.push('\t'),
+            Some('\\') => sequence.push('\\'),
+            Some(other) => {
+                sequence.push('\\');
+                sequence.push(other);
+            }
+            None => sequence.push('\\'),
+        }
+    }
+
+    if sequence.is_empty() {
+        return Err("stop sequence must not be empty".to_string());
+    }
+    Ok(sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn run(detector: &mut StopDetector, chunks: &[&str]) -> String {
+        let mut output: String = chunks.iter().map(|chunk| detector.push(chunk)).collect();
+        output.push_str(&detector.finish());
+        output
+    }
+
+    #[test]
+    fn test_stop_split_across_chunks() {
+        let mut detector = StopDetector::new(&stops(&["```"]));
+        let output = run(&mut detector, &["fn main() {}\n`", "``\nmore", " text"]);
+
+        assert!(detector.is_stopped());
+        assert_eq!(output, "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_earliest_of_multiple_stops_wins() {
+        let sequences = stops(&["\n\n", "END"]);
+
+        let mut detector = StopDetector::new(&sequences);
+        assert_eq!(
+            run(&mut detector, &["first line\n", "\nEND second"]),
+            "first line"
+        );
+
+        let mut detector = StopDetector::new(&sequences);
+        assert_eq!(run(&mut detector, &["a EN", "D b\n", "\nc"]), "a ");
+        assert!(detector.is_stopped());
+
+        assert_eq!(truncate_at_stop("a END b\n\nc", &sequences), "a ");
+    }
+
+    #[test]
+    fn test_held_back_partial_match_is_flushed() {
+        let mut detector = StopDetector::new(&stops(&["```"]));
+
+        assert_eq!(detector.push("inline `code"), "inline `code");
+        assert_eq!(detector.push(" ends with `"), " ends with ");
+        assert_eq!(detector.finish(), "`");
+        assert!(!detector.is_stopped());
+    }
+
+    #[test]
+    fn test_parse_stop_sequence_escapes() {
+        assert_eq!(parse_stop_sequence(r"\n\n"), Ok("\n\n".to_string()));
+        assert_eq!(parse_stop_sequence("```"), Ok("```".to_string()));
+        assert!(parse_stop_sequence("").is_err());
+    }
+}
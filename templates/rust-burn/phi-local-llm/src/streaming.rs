@@ -0,0 +1,130 @@
+/*!
+Streaming generation with client-disconnect cancellation
+
+A streamed response pushes chunks into a channel that the HTTP layer drains into the
+connection. When the client goes away the receiving half is dropped, closing the channel;
+[`forward_chunks`] notices at once, even while the generator is between chunks, and stops
+polling it instead of running to `max_tokens` for nobody.
+*/
+
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Cooperative cancellation flag shared between a request and its generator
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the generator to stop at its next chunk
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Send the chunks of `chunks` to `tx` until the stream ends, yields an error (which is
+/// sent too), or the receiver is dropped
+///
+/// A dropped receiver means the client disconnected: the stream is dropped unfinished,
+/// which cancels the generation, and `cancel` is cancelled so anything else watching it
+/// stops too. Returns the number of chunks sent.
+pub async fn forward_chunks(
+    tx: mpsc::Sender<Result<String>>,
+    cancel: &CancellationToken,
+    chunks: impl Stream<Item = Result<String>>,
+) -> usize {
+    futures::pin_mut!(chunks);
+    let mut sent = 0;
+    while !cancel.is_cancelled() {
+        let chunk = tokio::select! {
+            chunk = chunks.next() => chunk,
+            _ = tx.closed() => None,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let failed = chunk.is_err();
+        if tx.send(chunk).await.is_err() || failed {
+            break;
+        }
+        sent += 1;
+    }
+    if tx.is_closed() {
+        tracing::debug!(sent, "client disconnected, cancelling generation");
+        cancel.cancel();
+    }
+    sent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Endless generation counting the chunks it was polled for
+    fn counting_stream(generated: Arc<AtomicUsize>) -> impl Stream<Item = Result<String>> {
+        futures::stream::repeat_with(move || {
+            let count = generated.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(format!("token{} ", count))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_dropped_receiver_cancels_generation() {
+        let (tx, mut rx) = mpsc::channel::<Result<String>>(1);
+        let client = tokio::spawn(async move {
+            // Read two chunks, then disconnect
+            rx.recv().await.unwrap().unwrap();
+            rx.recv().await.unwrap().unwrap();
+        });
+
+        let cancel = CancellationToken::new();
+        let generated = Arc::new(AtomicUsize::new(0));
+        let sent = forward_chunks(tx, &cancel, counting_stream(generated.clone())).await;
+        client.await.unwrap();
+
+        let generated = generated.load(Ordering::SeqCst);
+        assert!(cancel.is_cancelled());
+        assert!(sent >= 2);
+        assert!(generated < 10, "generated {} chunks after disconnect", generated);
+    }
+
+    #[tokio::test]
+    async fn test_forwarding_stops_at_end_of_generation_or_error() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let chunks = futures::stream::iter(["a", "b"].map(|chunk| Ok(chunk.to_string())));
+
+        let cancel = CancellationToken::new();
+        let sent = forward_chunks(tx, &cancel, chunks).await;
+
+        assert_eq!(sent, 2);
+        assert!(!cancel.is_cancelled());
+        assert_eq!(rx.recv().await.unwrap().unwrap(), "a");
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let chunks = futures::stream::iter([
+            Ok("a".to_string()),
+            Err(anyhow::anyhow!("failed")),
+            Ok("b".to_string()),
+        ]);
+        let sent = forward_chunks(tx, &cancel, chunks).await;
+
+        assert_eq!(sent, 1);
+        assert!(rx.recv().await.unwrap().is_ok());
+        assert!(rx.recv().await.unwrap().is_err());
+        assert!(rx.recv().await.is_none());
+        assert!(!cancel.is_cancelled());
+    }
+}
@@ -0,0 +1,185 @@
+/*!
+Tracing setup with optional OpenTelemetry export
+
+Spans created with `#[tracing::instrument]` across the crate are always logged locally;
+when an OTLP endpoint is configured they are also shipped to a collector for
+distributed tracing.
+
+With [`LogFormat::Json`] log lines are written as one JSON object each, and every span
+also emits an event when it closes, carrying its fields (model, tokens, `elapsed_ms`) for
+log pipelines that ingest spans without an OTLP collector.
+*/
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use std::fmt;
+use std::str::FromStr;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, including span close events
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format '{}' (expected text or json)", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// Formatting layer for `format`, writing to `writer`
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_span_events(FmtSpan::CLOSE)
+            .boxed(),
+    }
+}
+
+/// Flushes pending spans to the collector when dropped
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber, exporting spans over OTLP/HTTP when `otlp_endpoint` is set
+///
+/// `default_filter` is used when `RUST_LOG` is not set. Keep the returned guard alive
+/// for the lifetime of the program so buffered spans are flushed on exit.
+pub fn init(
+    default_filter: &str,
+    otlp_endpoint: Option<&str>,
+    format: LogFormat,
+) -> Result<TelemetryGuard> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_filter));
+
+    let provider = otlp_endpoint
+        .map(|endpoint| {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .build()
+                .with_context(|| format!("Failed to create OTLP exporter for {}", endpoint))?;
+
+            Ok::<_, anyhow::Error>(
+                SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(Resource::builder().with_service_name(crate::NAME).build())
+                    .build(),
+            )
+        })
+        .transpose()?;
+
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(crate::NAME)));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(format, std::io::stdout))
+        .with(otel_layer)
+        .try_init()
+        .context("Failed to install tracing subscriber")?;
+
+    Ok(TelemetryGuard { provider })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects everything written by the formatting layer
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Captured {
+        type Writer = Captured;
+
+        fn make_writer(&'w self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+        assert_eq!("text".parse(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_json_format_logs_generation_span() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(fmt_layer(LogFormat::Json, captured.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let model = crate::PhiModel::from_short_name("phi3").unwrap();
+        let mut session = crate::ChatSession::new(model, None, false, false);
+        session.generate_response("hello").await.unwrap();
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let close = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|line| line["span"]["name"] == "generate" && line["fields"]["message"] == "close")
+            .expect("generate span close event");
+        assert_eq!(close["span"]["model"], "microsoft/Phi-3-mini-4k-instruct");
+        assert!(close["span"]["tokens"].as_u64().unwrap() > 0);
+        assert!(close["span"]["elapsed_ms"].is_u64());
+    }
+}
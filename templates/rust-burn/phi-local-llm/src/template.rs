@@ -0,0 +1,122 @@
+/*!
+Chat prompt templates for the Phi model families
+
+Each Phi generation was tuned on its own conversation format, and a model prompted in
+another family's format answers noticeably worse. [`ChatTemplate`] renders a system
+prompt, the earlier turns and the new user message into the single prompt string the
+model is given, ending with the marker that opens the assistant's reply.
+*/
+
+/// Conversation format a Phi model was tuned on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTemplate {
+    /// Phi-1, Phi-1.5 and Phi-2, which have no chat tokens: `Instruct: ...\nOutput: ...`
+    InstructOutput,
+    /// Phi-3, Phi-3.5 and Phi-4-mini: `<|user|>\n...<|end|>\n<|assistant|>\n...`
+    Phi3,
+    /// Phi-4: `<|im_start|>user<|im_sep|>...<|im_end|><|im_start|>assistant<|im_sep|>...`
+    Phi4,
+}
+
+impl ChatTemplate {
+    /// Render the conversation into a prompt that asks the model for the next reply
+    ///
+    /// `history` holds earlier (user, assistant) turns, oldest first.
+    pub fn render(
+        &self,
+        system_prompt: Option<&str>,
+        history: &[(String, String)],
+        input: &str,
+    ) -> String {
+        let mut prompt = String::new();
+        if let Some(system) = system_prompt.filter(|system| !system.is_empty()) {
+            self.push_message(&mut prompt, "system", system);
+        }
+        for (user, assistant) in history {
+            self.push_message(&mut prompt, "user", user);
+            self.push_message(&mut prompt, "assistant", assistant);
+        }
+        self.push_message(&mut prompt, "user", input);
+        prompt.push_str(&self.open("assistant"));
+        prompt
+    }
+
+    /// Text that starts a `role` message
+    fn open(&self, role: &str) -> String {
+        match (self, role) {
+            (Self::InstructOutput, "user") => "Instruct: ".to_string(),
+            (Self::InstructOutput, "assistant") => "Output:".to_string(),
+            (Self::InstructOutput, _) => String::new(),
+            (Self::Phi3, role) => format!("<|{}|>\n", role),
+            (Self::Phi4, role) => format!("<|im_start|>{}<|im_sep|>", role),
+        }
+    }
+
+    fn push_message(&self, prompt: &mut String, role: &str, content: &str) {
+        prompt.push_str(&self.open(role));
+        match (self, role) {
+            (Self::InstructOutput, "assistant") => {
+                prompt.push(' ');
+                prompt.push_str(content);
+                prompt.push('\n');
+            }
+            (Self::InstructOutput, "system") => {
+                prompt.push_str(content);
+                prompt.push_str("\n\n");
+            }
+            (Self::InstructOutput, _) => {
+                prompt.push_str(content);
+                prompt.push('\n');
+            }
+            (Self::Phi3, _) => {
+                prompt.push_str(content);
+                prompt.push_str("<|end|>\n");
+            }
+            (Self::Phi4, _) => {
+                prompt.push_str(content);
+                prompt.push_str("<|im_end|>");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_turns() -> Vec<(String, String)> {
+        vec![("What is Rust?".to_string(), "A systems language.".to_string())]
+    }
+
+    #[test]
+    fn test_phi3_two_turn_prompt() {
+        let prompt = ChatTemplate::Phi3.render(Some("Be brief."), &two_turns(), "Is it fast?");
+        assert_eq!(
+            prompt,
+            "<|system|>\nBe brief.<|end|>\n\
+             <|user|>\nWhat is Rust?<|end|>\n\
+             <|assistant|>\nA systems language.<|end|>\n\
+             <|user|>\nIs it fast?<|end|>\n\
+             <|assistant|>\n"
+        );
+    }
+
+    #[test]
+    fn test_phi4_and_instruct_output_prompts() {
+        let prompt = ChatTemplate::Phi4.render(Some("Be brief."), &two_turns(), "Is it fast?");
+        assert_eq!(
+            prompt,
+            "<|im_start|>system<|im_sep|>Be brief.<|im_end|>\
+             <|im_start|>user<|im_sep|>What is Rust?<|im_end|>\
+             <|im_start|>assistant<|im_sep|>A systems language.<|im_end|>\
+             <|im_start|>user<|im_sep|>Is it fast?<|im_end|>\
+             <|im_start|>assistant<|im_sep|>"
+        );
+
+        let prompt = ChatTemplate::InstructOutput.render(None, &two_turns(), "Is it fast?");
+        assert_eq!(
+            prompt,
+            "Instruct: What is Rust?\nOutput: A systems language.\nInstruct: Is it fast?\nOutput:"
+        );
+    }
+}
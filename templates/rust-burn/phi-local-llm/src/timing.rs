@@ -0,0 +1,144 @@
+/*!
+Per-chunk timing of streamed generation
+
+Separates time-to-first-token (prompt processing) from inter-token latency (decoding)
+so benchmarks can report the two costs independently of total generation time.
+*/
+
+use std::time::{Duration, Instant};
+
+use crate::metrics::{MetricsSink, Tags};
+
+/// Timestamps of every chunk emitted by one streamed generation
+#[derive(Debug, Clone)]
+pub struct GenerationTiming {
+    start: Instant,
+    chunks: Vec<Instant>,
+}
+
+impl GenerationTiming {
+    /// Start timing a request now
+    pub fn start() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    /// Start timing a request that was received at `start`
+    pub fn started_at(start: Instant) -> Self {
+        Self {
+            start,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Record that a chunk was emitted now
+    pub fn record_chunk(&mut self) {
+        self.record_chunk_at(Instant::now());
+    }
+
+    /// Record that a chunk was emitted at `at`
+    pub fn record_chunk_at(&mut self, at: Instant) {
+        self.chunks.push(at);
+    }
+
+    /// Number of chunks emitted so far
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Time from the request to the first emitted chunk
+    pub fn ttft(&self) -> Option<Duration> {
+        self.chunks
+            .first()
+            .map(|first| first.saturating_duration_since(self.start))
+    }
+
+    /// Mean gap between consecutive chunks after the first
+    pub fn inter_token_latency(&self) -> Option<Duration> {
+        let (first, last) = (self.chunks.first()?, self.chunks.last()?);
+        let gaps = self.chunks.len().checked_sub(1).filter(|&gaps| gaps > 0)?;
+        Some(last.saturating_duration_since(*first) / gaps as u32)
+    }
+
+    /// Time from the request to `at`
+    pub fn elapsed_at(&self, at: Instant) -> Duration {
+        at.saturating_duration_since(self.start)
+    }
+
+    /// Chunks per second from the request to `at`, or `None` if no time has passed
+    pub fn tokens_per_second_at(&self, at: Instant) -> Option<f64> {
+        let elapsed = self.elapsed_at(at).as_secs_f64();
+        (elapsed > 0.0).then(|| self.chunks.len() as f64 / elapsed)
+    }
+
+    /// Chunks per second from the request to the last emitted chunk
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        self.tokens_per_second_at(*self.chunks.last()?)
+    }
+
+    /// Time from the request to the last emitted chunk
+    pub fn total(&self) -> Option<Duration> {
+        self.chunks
+            .last()
+            .map(|last| last.saturating_duration_since(self.start))
+    }
+
+    /// Emit TTFT, inter-token latency and total time through a metrics sink
+    pub fn report(&self, sink: &dyn MetricsSink, tags: Tags) {
+        if let Some(ttft) = self.ttft() {
+            sink.timing("phi.generation.ttft", ttft, tags);
+        }
+        if let Some(itl) = self.inter_token_latency() {
+            sink.timing("phi.generation.inter_token_latency", itl, tags);
+        }
+        if let Some(total) = self.total() {
+            sink.timing("phi.generation.total", total, tags);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttft_is_first_chunk_timestamp() {
+        let start = Instant::now();
+        let mut timing = GenerationTiming::started_at(start);
+        assert_eq!(timing.ttft(), None);
+
+        for ms in [30, 40, 50] {
+            timing.record_chunk_at(start + Duration::from_millis(ms));
+        }
+
+        assert_eq!(timing.chunks(), 3);
+        assert_eq!(timing.ttft(), Some(Duration::from_millis(30)));
+        assert_eq!(timing.inter_token_latency(), Some(Duration::from_millis(10)));
+        assert_eq!(timing.total(), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn test_single_chunk_has_no_inter_token_latency() {
+        let start = Instant::now();
+        let mut timing = GenerationTiming::started_at(start);
+        timing.record_chunk_at(start + Duration::from_millis(5));
+
+        assert_eq!(timing.ttft(), timing.total());
+        assert_eq!(timing.inter_token_latency(), None);
+    }
+
+    #[test]
+    fn test_tokens_per_second_counts_chunks_up_to_the_given_time() {
+        let start = Instant::now();
+        let mut timing = GenerationTiming::started_at(start);
+        assert_eq!(timing.tokens_per_second_at(start), None);
+        assert_eq!(timing.tokens_per_second_at(start + Duration::from_millis(500)), Some(0.0));
+
+        for ms in [250, 300, 350, 400, 450, 500] {
+            timing.record_chunk_at(start + Duration::from_millis(ms));
+        }
+        assert_eq!(timing.tokens_per_second_at(start + Duration::from_millis(500)), Some(12.0));
+        assert_eq!(timing.tokens_per_second(), Some(12.0));
+        // The rate keeps falling while no new chunk arrives
+        assert_eq!(timing.tokens_per_second_at(start + Duration::from_secs(1)), Some(6.0));
+    }
+}
@@ -0,0 +1,662 @@
+use burn_phi_local_llm::server::{
+    self, ApiState, ChatResponse, Completion, CompletionChunk, EmbeddingResponse, GenerateChunk,
+    ModelInfo, SessionCreated,
+};
+use burn_phi_local_llm::{PhiModel, PhiModelManager, RegexDenyFilter, RetryPolicy};
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Boot the API server on an ephemeral port and return its address
+async fn start_server() -> SocketAddr {
+    start_server_with(ApiState::default()).await
+}
+
+/// Boot the API server with the given settings on an ephemeral port
+async fn start_server_with(state: ApiState) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(server::serve(listener, state));
+    addr
+}
+
+async fn post_chat(addr: SocketAddr, body: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/chat", addr))
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_healthz() {
+    let addr = start_server().await;
+    let response = reqwest::get(format!("http://{}/healthz", addr))
+        .await
+        .unwrap();
+
+    assert!(response.status().is_success());
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn test_models_lists_available_models() {
+    let addr = start_server().await;
+    let response = reqwest::get(format!("http://{}/models", addr))
+        .await
+        .unwrap();
+
+    assert!(response.status().is_success());
+    let models: Vec<ModelInfo> = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+    let expected: Vec<&str> = PhiModel::available_models()
+        .iter()
+        .map(PhiModel::short_name)
+        .collect();
+    assert_eq!(ids, expected);
+}
+
+#[tokio::test]
+async fn test_chat_generates_reply() {
+    let addr = start_server().await;
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi2", "messages": [{"role": "user", "content": "hello"}], "max_tokens": 64}"#,
+    )
+    .await;
+
+    assert!(response.status().is_success());
+    let reply: ChatResponse = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(reply.model, "phi2");
+    assert!(reply.content.contains("hello"));
+}
+
+#[tokio::test]
+async fn test_chat_rejects_unknown_model() {
+    let addr = start_server().await;
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi9", "messages": [{"role": "user", "content": "hello"}]}"#,
+    )
+    .await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("phi9"));
+}
+
+const COMPLETION_BODY: &str =
+    r#"{"model": "phi-3", "messages": [{"role": "user", "content": "hello"}], "stream": STREAM}"#;
+
+async fn post_completion(addr: SocketAddr, body: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/chat/completions", addr))
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_chat_completion() {
+    let addr = start_server().await;
+    let response = post_completion(addr, &COMPLETION_BODY.replace("STREAM", "false")).await;
+
+    assert!(response.status().is_success());
+    let completion: Completion = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(completion.object, "chat.completion");
+    assert_eq!(completion.model, "phi-3");
+    assert_eq!(completion.choices[0].message.role, "assistant");
+    assert!(completion.choices[0].message.content.contains("Phi-3"));
+    assert!(completion.usage.prompt_tokens > 0);
+    assert!(completion.usage.completion_tokens > 0);
+    assert_eq!(
+        completion.usage.total_tokens,
+        completion.usage.prompt_tokens + completion.usage.completion_tokens
+    );
+}
+
+#[tokio::test]
+async fn test_streamed_chat_completion() {
+    let addr = start_server().await;
+    let response = post_completion(addr, &COMPLETION_BODY.replace("STREAM", "true")).await;
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let body = response.text().await.unwrap();
+    let events: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .collect();
+    assert_eq!(events.last(), Some(&"[DONE]"));
+
+    let chunks: Vec<CompletionChunk> = events[..events.len() - 1]
+        .iter()
+        .map(|event| serde_json::from_str(event).unwrap())
+        .collect();
+    assert_eq!(
+        chunks[0].choices[0].delta.role.as_deref(),
+        Some("assistant")
+    );
+    assert_eq!(
+        chunks.last().unwrap().choices[0].finish_reason.as_deref(),
+        Some("stop")
+    );
+    let content: String = chunks
+        .iter()
+        .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+        .collect();
+    assert!(content.contains("Phi-3"));
+    assert!(chunks.len() > 3);
+}
+
+#[tokio::test]
+async fn test_streamed_completion_records_latency() {
+    let state = ApiState::default();
+    let addr = start_server_with(state.clone()).await;
+    let response = post_completion(addr, &COMPLETION_BODY.replace("STREAM", "true")).await;
+    assert!(response.status().is_success());
+    response.text().await.unwrap();
+
+    // The generation task records the stream once it has sent the last chunk
+    let latency = "phi_api_generation_latency_ms_count{model=\"phi3\"} 1";
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while !state.metrics.render().contains(latency) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "streamed generation was not timed:\n{}",
+            state.metrics.render()
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(state.metrics.render().contains("phi_api_tokens_generated_total{model=\"phi3\"}"));
+}
+
+#[tokio::test]
+async fn test_chat_completion_rejects_unknown_model() {
+    let addr = start_server().await;
+    let body = COMPLETION_BODY
+        .replace("STREAM", "false")
+        .replace("phi-3", "gpt-4");
+    let response = post_completion(addr, &body).await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("gpt-4"));
+}
+
+#[tokio::test]
+async fn test_chat_session_keeps_history_until_deleted() {
+    let state = ApiState::default();
+    let sessions = state.sessions.clone();
+    let addr = start_server_with(state).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("http://{}/v1/sessions", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    let id = serde_json::from_str::<SessionCreated>(&response.text().await.unwrap())
+        .unwrap()
+        .session_id;
+
+    for prompt in ["hello", "what did I just say?"] {
+        let body = serde_json::json!({
+            "model": "phi3",
+            "session_id": id,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        let response = post_chat(addr, &body.to_string()).await;
+        assert!(response.status().is_success());
+        let reply: ChatResponse = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(reply.session_id.as_deref(), Some(id.as_str()));
+    }
+    let history = sessions.history(&id).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].0, "hello");
+    assert_eq!(history[1].0, "what did I just say?");
+
+    let session_url = format!("http://{}/v1/sessions/{}", addr, id);
+    let response = client.delete(&session_url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+    let response = client.delete(&session_url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let body = serde_json::json!({
+        "model": "phi3",
+        "session_id": id,
+        "messages": [{"role": "user", "content": "still there?"}],
+    });
+    let response = post_chat(addr, &body.to_string()).await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    assert!(sessions.is_empty());
+}
+
+async fn post_embeddings(addr: SocketAddr, body: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/embeddings", addr))
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|v| v * v).sum::<f32>().sqrt()
+}
+
+/// Boot the API server with `model` preloaded from the graph [`seed_cache_with_graph`]
+/// caches, whose hidden state for each token is a one-hot vector of width 4
+async fn start_server_with_embedder(model: &str) -> SocketAddr {
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9");
+    let model = PhiModel::from_short_name(model).unwrap();
+    seed_cache_with_graph(&manager, &model);
+
+    let state = ApiState {
+        skip_warmup: true,
+        ..ApiState::default()
+    };
+    server::preload(&state, &manager, &[model]).await.unwrap();
+    start_server_with(state).await
+}
+
+#[tokio::test]
+async fn test_embeddings_single_input() {
+    let addr = start_server_with_embedder("phi3").await;
+    let response = post_embeddings(addr, r#"{"model": "phi3", "input": "hello phi"}"#).await;
+
+    assert!(response.status().is_success());
+    let reply: EmbeddingResponse = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let embedding = reply.embedding.unwrap();
+    assert_eq!(reply.dimensions, 4);
+    assert_eq!(embedding.len(), reply.dimensions);
+    assert!((norm(&embedding) - 1.0).abs() < 1e-5);
+    // The mean of the one-hot hidden states of `hello` and `phi`
+    let expected = [0.0, 1.0, 1.0, 0.0].map(|v: f32| v * std::f32::consts::FRAC_1_SQRT_2);
+    assert!(embedding.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-5));
+    assert!(reply.embeddings.is_none());
+}
+
+#[tokio::test]
+async fn test_embeddings_batch_input() {
+    let addr = start_server_with_embedder("phi2").await;
+    let response = post_embeddings(
+        addr,
+        r#"{"model": "phi2", "input": ["hello world", "phi world", "hello"]}"#,
+    )
+    .await;
+
+    assert!(response.status().is_success());
+    let reply: EmbeddingResponse = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    let embeddings = reply.embeddings.unwrap();
+    assert_eq!(embeddings.len(), 3);
+    assert!(embeddings.iter().all(|embedding| embedding.len() == 4));
+    assert_ne!(embeddings[0], embeddings[1]);
+    assert!(reply.embedding.is_none());
+}
+
+#[tokio::test]
+async fn test_embeddings_require_a_loaded_model() {
+    let addr = start_server().await;
+    let response = post_embeddings(addr, r#"{"model": "phi3", "input": "hello"}"#).await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("--preload phi3"));
+}
+
+#[tokio::test]
+async fn test_embeddings_reject_unsupported_model() {
+    let addr = start_server().await;
+    let response = post_embeddings(addr, r#"{"model": "phi4", "input": "hello"}"#).await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("embeddings"));
+}
+
+#[tokio::test]
+async fn test_chat_timeout_returns_gateway_timeout() {
+    let addr = start_server_with(ApiState {
+        timeout: Some(Duration::ZERO),
+        ..ApiState::default()
+    })
+    .await;
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi3", "messages": [{"role": "user", "content": "hello"}]}"#,
+    )
+    .await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["error"], "generation timed out after 0s");
+}
+
+#[tokio::test]
+async fn test_chat_blocked_by_filter_returns_unprocessable_entity() {
+    let filter = RegexDenyFilter::new(&["(?i)password"]).unwrap();
+    let addr = start_server_with(ApiState {
+        filter: Some(Arc::new(filter)),
+        ..ApiState::default()
+    })
+    .await;
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi3", "messages": [{"role": "user", "content": "my Password is"}]}"#,
+    )
+    .await;
+
+    assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["error"], "prompt blocked by content filter: matches '(?i)password'");
+}
+
+#[tokio::test]
+async fn test_metrics_count_chat_requests() {
+    let addr = start_server().await;
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi3", "messages": [{"role": "user", "content": "hello"}]}"#,
+    )
+    .await;
+    assert!(response.status().is_success());
+
+    let response = reqwest::get(format!("http://{}/metrics", addr))
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let body = response.text().await.unwrap();
+    assert!(body.contains("phi_api_requests_total{endpoint=\"/v1/chat\"} 1\n"), "{}", body);
+    assert!(body.contains("phi_api_generation_latency_ms_count{model=\"phi3\"} 1"));
+    assert!(body.contains("phi_api_tokens_generated_total{model=\"phi3\"}"));
+    assert!(!body.contains("phi_api_errors"));
+}
+
+async fn post_generate(addr: SocketAddr, prompt: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{}/v1/generate", addr))
+        .header("content-type", "application/json")
+        .body(serde_json::json!({ "model": "phi2", "prompt": prompt }).to_string())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_generate_streams_text_events() {
+    let addr = start_server().await;
+    let response = post_generate(addr, "the borrow checker").await;
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let body = response.text().await.unwrap();
+    let events: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .collect();
+    assert_eq!(events.last(), Some(&"[DONE]"));
+
+    let text: String = events[..events.len() - 1]
+        .iter()
+        .map(|event| serde_json::from_str::<GenerateChunk>(event).unwrap().text)
+        .collect();
+    assert!(text.contains("the borrow checker"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_shutdown_lets_in_flight_stream_finish() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let serving = tokio::spawn(server::serve_with_shutdown(
+        listener,
+        ApiState::default(),
+        async {
+            let _ = shutdown_rx.await;
+        },
+        Duration::from_secs(10),
+    ));
+
+    let prompt = "word ".repeat(40);
+    let mut response = post_generate(addr, &prompt).await;
+    assert!(response.status().is_success());
+    let mut body = String::from_utf8(response.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+
+    shutdown_tx.send(()).unwrap();
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        body.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    assert!(body.contains("data: [DONE]"), "{}", body);
+
+    tokio::time::timeout(Duration::from_secs(5), serving)
+        .await
+        .expect("server should exit once the stream is done")
+        .unwrap()
+        .unwrap();
+    assert!(reqwest::get(format!("http://{}/healthz", addr)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_generate_is_cancelled_when_client_disconnects() {
+    let state = ApiState::default();
+    let metrics = state.metrics.clone();
+    let addr = start_server_with(state).await;
+
+    // The demo reply echoes the prompt, so this one streams for several seconds
+    let prompt = "word ".repeat(300);
+    let mut response = post_generate(addr, &prompt).await;
+    assert!(response.status().is_success());
+    assert!(response.chunk().await.unwrap().is_some());
+    drop(response);
+
+    let cancelled = "phi_api_generations_cancelled_total{model=\"phi2\"} 1";
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while !metrics.render().contains(cancelled) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "generation was not cancelled:\n{}",
+            metrics.render()
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_streamed_completion_is_cancelled_when_client_disconnects() {
+    let state = ApiState::default();
+    let metrics = state.metrics.clone();
+    let addr = start_server_with(state).await;
+
+    // The Phi-3 demo reply quotes the prompt, so this one streams for several seconds
+    let body = COMPLETION_BODY
+        .replace("STREAM", "true")
+        .replace("\"hello\"", &format!("\"{}\"", "word ".repeat(300)));
+    let mut response = post_completion(addr, &body).await;
+    assert!(response.status().is_success());
+    assert!(response.chunk().await.unwrap().is_some());
+    drop(response);
+
+    let cancelled = "phi_api_generations_cancelled_total{model=\"phi3\"} 1";
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+    while !metrics.render().contains(cancelled) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "generation was not cancelled:\n{}",
+            metrics.render()
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Cache a tiny graph for `model` that predicts `hello phi world` in a cycle after any
+/// token, with its word-level tokenizer
+fn seed_cache_with_graph(manager: &PhiModelManager, model: &PhiModel) {
+    use burn_phi_local_llm::onnx::{
+        GraphProto, ModelProto, NodeProto, TensorProto, ValueInfoProto, FLOAT,
+    };
+    use prost::Message;
+
+    let tensor = |name: &str, dims: &[i64], values: &[f32]| TensorProto {
+        dims: dims.to_vec(),
+        data_type: FLOAT,
+        name: name.to_string(),
+        raw_data: values.iter().flat_map(|value| value.to_le_bytes()).collect(),
+        ..Default::default()
+    };
+    let node = |op_type: &str, input: [&str; 2], output: &str| NodeProto {
+        input: input.iter().map(|name| name.to_string()).collect(),
+        output: vec![output.to_string()],
+        op_type: op_type.to_string(),
+        ..Default::default()
+    };
+    let value = |name: &str| ValueInfoProto {
+        name: name.to_string(),
+    };
+    let mut embedding = [0.0; 16];
+    let mut head = [0.0; 16];
+    for (token, next) in [1, 2, 3, 1].into_iter().enumerate() {
+        embedding[token * 4 + token] = 1.0;
+        head[token * 4 + next] = 5.0;
+    }
+    let graph = ModelProto {
+        ir_version: 8,
+        graph: Some(GraphProto {
+            node: vec![
+                node("Gather", ["embedding", "input_ids"], "hidden"),
+                node("MatMul", ["hidden", "head"], "logits"),
+            ],
+            name: "next-word".to_string(),
+            initializer: vec![
+                tensor("embedding", &[4, 4], &embedding),
+                tensor("head", &[4, 4], &head),
+                // Unused, pads the file past the cache's minimum model size
+                tensor("padding", &[256], &[0.0; 256]),
+            ],
+            input: vec![value("input_ids")],
+            output: vec![value("logits")],
+        }),
+    };
+
+    std::fs::write(manager.model_path(model), graph.encode_to_vec()).unwrap();
+    std::fs::write(
+        manager.tokenizer_path(model),
+        r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+            "normalizer": null, "pre_tokenizer": {"type": "Whitespace"},
+            "post_processor": null, "decoder": null,
+            "model": {"type": "WordLevel", "unk_token": "[UNK]",
+                      "vocab": {"[UNK]": 0, "hello": 1, "phi": 2, "world": 3}}}"#,
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_healthz_is_unavailable_until_preload_completes() {
+    // Seed the cache so the preload loads from disk instead of downloading
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9");
+    let model = PhiModel::from_short_name("phi3").unwrap();
+    seed_cache_with_graph(&manager, &model);
+
+    let state = ApiState::default();
+    state.preloading.store(true, Ordering::SeqCst);
+    let addr = start_server_with(state.clone()).await;
+    let healthz = format!("http://{}/healthz", addr);
+
+    let response = reqwest::get(&healthz).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    server::preload(&state, &manager, &[model]).await.unwrap();
+
+    let response = reqwest::get(&healthz).await.unwrap();
+    assert!(response.status().is_success());
+
+    // Requests for the preloaded model generate with its graph instead of a demo reply
+    let response = post_chat(
+        addr,
+        r#"{"model": "phi3", "max_tokens": 4, "temperature": 0.0,
+            "messages": [{"role": "user", "content": "hello"}]}"#,
+    )
+    .await;
+    let reply: ChatResponse = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(reply.content, "hello phi world hello");
+}
+
+#[tokio::test]
+async fn test_readyz_waits_for_the_warmup_generation() {
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9");
+    let model = PhiModel::from_short_name("phi3").unwrap();
+    seed_cache_with_graph(&manager, &model);
+
+    let state = ApiState::default();
+    state.preloading.store(true, Ordering::SeqCst);
+    let addr = start_server_with(state.clone()).await;
+    let readyz = format!("http://{}/readyz", addr);
+
+    let response = reqwest::get(&readyz).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!state.metrics.render().contains("phi_api_warmup_ms"));
+
+    server::preload(&state, &manager, &[model]).await.unwrap();
+
+    let response = reqwest::get(&readyz).await.unwrap();
+    assert!(response.status().is_success());
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["models"], serde_json::json!(["phi3"]));
+    let metrics = state.metrics.render();
+    assert!(metrics.contains("phi_api_warmup_ms_count{model=\"phi3\"} 1"), "{}", metrics);
+}
+
+#[tokio::test]
+async fn test_preload_skips_warmup_when_disabled() {
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9");
+    let model = PhiModel::from_short_name("phi3").unwrap();
+    seed_cache_with_graph(&manager, &model);
+
+    let state = ApiState {
+        skip_warmup: true,
+        ..ApiState::default()
+    };
+    server::preload(&state, &manager, &[model]).await.unwrap();
+
+    assert!(state.preloaded.read().unwrap().contains_key("phi3"));
+    assert!(!state.metrics.render().contains("phi_api_warmup_ms"));
+}
+
+#[tokio::test]
+async fn test_failed_preload_clears_the_preloading_flag() {
+    // Nothing is cached and the hub is unreachable, so the download fails
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::with_endpoint(cache.path(), "http://127.0.0.1:9")
+        .with_retry(RetryPolicy {
+            max_retries: 0,
+            base_delay: Duration::ZERO,
+        });
+    let model = PhiModel::from_short_name("phi3").unwrap();
+
+    let state = ApiState::default();
+    let addr = start_server_with(state.clone()).await;
+    let error = server::preload(&state, &manager, &[model]).await.unwrap_err();
+
+    assert!(format!("{:#}", error).contains("Failed to preload"), "{:#}", error);
+    assert!(state.preloaded.read().unwrap().is_empty());
+    let response = reqwest::get(format!("http://{}/healthz", addr)).await.unwrap();
+    assert!(response.status().is_success());
+}
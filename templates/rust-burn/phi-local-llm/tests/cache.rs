@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run_download(cache_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_download-phi"))
+        .args(args)
+        .arg("--cache-dir")
+        .arg(cache_dir)
+        .output()
+        .expect("failed to start download-phi")
+}
+
+#[test]
+fn test_list_and_size_of_seeded_cache() {
+    let cache = tempfile::tempdir().unwrap();
+
+    let output = run_download(cache.path(), &["list"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "No models cached\n"
+    );
+
+    std::fs::write(
+        cache.path().join("microsoft_Phi-3-mini-4k-instruct.onnx"),
+        vec![0u8; 2048],
+    )
+    .unwrap();
+
+    let output = run_download(cache.path(), &["list"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "microsoft/Phi-3-mini-4k-instruct\n"
+    );
+
+    let output = run_download(cache.path(), &["list", "--details"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "microsoft/Phi-3-mini-4k-instruct\n  no manifest\n"
+    );
+
+    let output = run_download(cache.path(), &["size"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2.0 KB\n");
+}
+
+#[test]
+fn test_clean_removes_cache_dir() {
+    let cache = tempfile::tempdir().unwrap();
+    let cache_dir = cache.path().join("models");
+    std::fs::create_dir(&cache_dir).unwrap();
+    std::fs::write(cache_dir.join("model.onnx"), b"stub").unwrap();
+
+    let output = run_download(&cache_dir, &["clean"]);
+    assert!(output.status.success());
+    assert!(!cache_dir.exists());
+}
+
+#[test]
+fn test_clean_with_max_cache_size_evicts_least_recently_used() {
+    let cache = tempfile::tempdir().unwrap();
+    let now = std::time::SystemTime::now();
+    for (name, age_hours) in [
+        ("microsoft_phi-2.onnx", 2),
+        ("microsoft_Phi-3-mini-4k-instruct.onnx", 1),
+    ] {
+        let path = cache.path().join(name);
+        std::fs::write(&path, vec![0u8; 2048]).unwrap();
+        std::fs::File::options()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(now - std::time::Duration::from_secs(age_hours * 3600))
+            .unwrap();
+    }
+
+    let output = run_download(cache.path(), &["clean", "--max-cache-size", "3KB"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "🧹 Evicted microsoft/phi-2\nCache size: 2.0 KB\n"
+    );
+    assert!(cache.path().join("microsoft_Phi-3-mini-4k-instruct.onnx").exists());
+}
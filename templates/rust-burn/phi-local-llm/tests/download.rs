@@ -0,0 +1,23 @@
+//! Downloads a real model from Hugging Face; run with `cargo test --features network-tests`
+#![cfg(feature = "network-tests")]
+
+use burn_phi_local_llm::{PhiModel, PhiModelManager};
+
+#[tokio::test]
+async fn test_download_smallest_model() {
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::new(cache.path());
+    // Phi-3 mini is the smallest model with an ONNX export
+    let model = PhiModel::from_short_name("phi3").unwrap();
+
+    let mut reported = 0;
+    let path = manager
+        .ensure_model_with_progress(&model, |done, _| reported = done)
+        .await
+        .unwrap();
+
+    assert!(reported > 0);
+    assert!(manager.is_cached(&model).await);
+    manager.validate_model_file(&model).await.unwrap();
+    assert_eq!(path, manager.model_path(&model));
+}
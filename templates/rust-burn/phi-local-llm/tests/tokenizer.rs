@@ -0,0 +1,19 @@
+//! Fetches Phi-2's tokenizer from Hugging Face; run with `cargo test --features network-tests`
+#![cfg(feature = "network-tests")]
+
+use burn_phi_local_llm::{PhiModel, PhiModelManager, Tokenizer};
+
+#[tokio::test]
+async fn test_phi2_tokenizer_round_trip() {
+    let cache = tempfile::tempdir().unwrap();
+    let manager = PhiModelManager::new(cache.path());
+    let phi2 = PhiModel::from_short_name("phi2").unwrap();
+
+    let path = manager.ensure_tokenizer(&phi2).await.unwrap();
+    let tokenizer = Tokenizer::from_file(&path).unwrap();
+
+    let ids = tokenizer.encode("Hello world").unwrap();
+    // Phi-2 uses the CodeGen BPE vocabulary, which shares GPT-2's ids for plain words
+    assert_eq!(ids, vec![15496, 995]);
+    assert_eq!(tokenizer.decode(&ids).unwrap(), "Hello world");
+}
@@ -0,0 +1,80 @@
+use burn_phi_local_llm::{PhiModel, PhiModelManager, Quantization};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run_tokens(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tokens-phi"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start tokens-phi");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_counts_stdin_and_reports_fit() {
+    // Nothing is cached, so the count is estimated
+    let cache = tempfile::tempdir().unwrap();
+    let cache_dir = cache.path().to_str().unwrap();
+    let output = run_tokens(
+        &["--model", "phi3", "--max-tokens", "100", "--cache-dir", cache_dir],
+        "hello world",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains("Tokens: ~4 (estimated"), "{}", stdout);
+    assert!(stdout.contains("Verdict: fits (3992 tokens of headroom)"));
+}
+
+#[test]
+fn test_reports_when_text_does_not_fit() {
+    // 2000 four-letter words are 2000 tokens, which plus the budget overflows phi2's 2048
+    let cache = tempfile::tempdir().unwrap();
+    let cache_dir = cache.path().to_str().unwrap();
+    let text = "word ".repeat(2000);
+    let output = run_tokens(
+        &["--model", "phi2", "--max-tokens", "100", "--cache-dir", cache_dir],
+        &text,
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("Tokens: ~2000"));
+    assert!(stdout.contains("Verdict: does not fit (52 tokens over)"));
+}
+
+#[test]
+fn test_counts_with_the_cached_tokenizer() {
+    let cache = tempfile::tempdir().unwrap();
+    let model = PhiModel::from_short_name("phi3").unwrap();
+    let manager = PhiModelManager::new(cache.path()).with_quantization(Quantization::Int4);
+    std::fs::write(
+        manager.tokenizer_path(&model),
+        r#"{"version": "1.0", "truncation": null, "padding": null, "added_tokens": [],
+            "normalizer": null, "pre_tokenizer": {"type": "Whitespace"},
+            "post_processor": null, "decoder": null,
+            "model": {"type": "WordLevel", "unk_token": "[UNK]",
+                      "vocab": {"[UNK]": 0, "hello": 1, "world": 2}}}"#,
+    )
+    .unwrap();
+
+    let cache_dir = cache.path().to_str().unwrap();
+    let output = run_tokens(
+        &["--model", "phi3", "--max-tokens", "100", "--cache-dir", cache_dir],
+        "hello world",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains("Tokens: 2\n"), "{}", stdout);
+    assert!(stdout.contains("Verdict: fits (3994 tokens of headroom)"));
+}